@@ -1,15 +1,19 @@
 use async_trait::async_trait;
 use aws_sdk_bedrockruntime::types::{ContentBlock, ConversationRole, Message};
-use bedrock_client::{BedrockClient, ToolDefinition};
-use bedrock_config::AgentConfig;
-use bedrock_conversation::{ConversationManager, TokenUsageStats};
+use bedrock_client::rate_limit::estimate_request_tokens;
+use bedrock_client::{confirm_tool_execution, print_text, BedrockClient, ConverseApi, ConverseResponse};
+use bedrock_config::{AgentConfig, MixedResponsePolicy, ModelPricing, Permission as ConfigPermission};
+use bedrock_conversation::{assemble_context, transcript, trim_history, ConversationManager, TokenUsageStats};
 use bedrock_core::{
     Agent as AgentTrait, BedrockError, CostDetails, Result, StreamResult,
     Task, TaskResult, TaskStatus, TokenStatistics,
 };
 use bedrock_mcp::McpManager;
-use bedrock_task::TaskExecutor;
-use bedrock_tools::ToolRegistry;
+use bedrock_metrics::MetricsCollector;
+use bedrock_task::{build_tool_definitions, fit_request_tokens, resolve_mixed_response, resolve_tool_policy, ModelComparison, Priority, ReplayBundle, ReplayReport, TaskEstimate, TaskEvent, TaskExecutor};
+use bedrock_tools::{GitTool, HttpRequestTool, Permission, PermissionManager, PermissionPolicy, PollTool, ToolRegistry};
+use std::io::IsTerminal;
+use std::time::Duration;
 use std::sync::Arc;
 use tracing::{debug, info, instrument, warn};
 use uuid::Uuid;
@@ -17,19 +21,178 @@ use uuid::Uuid;
 pub struct Agent {
     config: Arc<AgentConfig>,
     bedrock_client: Arc<BedrockClient>,
+    /// Model called by chats and tasks; `bedrock_client` unless the builder was given one
+    model: Arc<dyn ConverseApi>,
     tool_registry: Arc<ToolRegistry>,
     task_executor: Arc<TaskExecutor>,
     mcp_manager: Option<Arc<tokio::sync::RwLock<McpManager>>>,
+    permissions: Arc<PermissionManager>,
+    /// Messages of earlier `chat`/`chat_stream` turns, re-sent with each new turn
+    chat_history: tokio::sync::Mutex<Vec<Message>>,
+    /// Model and system prompt of `chat`/`chat_stream`, changeable mid-session
+    chat_settings: std::sync::RwLock<ChatSettings>,
+}
+
+/// What a chat session sends with each turn; starts from the configuration
+#[derive(Debug, Clone)]
+struct ChatSettings {
+    model: String,
+    system_prompt: String,
+}
+
+/// Build the tool permission manager from `tools.permissions`.
+///
+/// Tools without an entry stay allowed. `ask` prompts on the terminal when stdin
+/// is interactive; otherwise it is treated as deny.
+fn build_permission_manager(config: &AgentConfig) -> PermissionManager {
+    let mut manager = PermissionManager::new().with_default(Permission::Allow);
+    let permission_of = |tool_name: &str| {
+        config.tools.permissions.get(tool_name).map(|tool_permission| match tool_permission.permission {
+            ConfigPermission::Allow => Permission::Allow,
+            ConfigPermission::Ask => Permission::Ask,
+            ConfigPermission::Deny => Permission::Deny,
+        })
+    };
+    for tool_name in config.tools.permissions.keys() {
+        manager.add_policy(PermissionPolicy {
+            tool_name: tool_name.clone(),
+            permission: permission_of(tool_name).unwrap_or(Permission::Allow),
+        });
+    }
+    // apply_patch writes files too, so it is held to fs_write's rule when that is stricter
+    if let Some(fs_write) = permission_of("fs_write") {
+        let own = permission_of("apply_patch").unwrap_or(Permission::Allow);
+        let strictness = |permission: &Permission| match permission {
+            Permission::Allow => 0,
+            Permission::Ask => 1,
+            Permission::Deny => 2,
+        };
+        manager.add_policy(PermissionPolicy {
+            tool_name: "apply_patch".to_string(),
+            permission: if strictness(&fs_write) > strictness(&own) { fs_write } else { own },
+        });
+    }
+
+    if std::io::stdin().is_terminal() {
+        manager = manager.with_ask_handler(Arc::new(confirm_tool_execution));
+    }
+    manager
+}
+
+/// Fluent construction of an [`Agent`].
+///
+/// Configuration comes from `with_config`, then `with_config_file`, then the
+/// defaults. A registry passed to `with_tool_registry` replaces the default tools,
+/// so custom tools can be registered before the task executor is wired up.
+#[derive(Default)]
+pub struct AgentBuilder {
+    config: Option<AgentConfig>,
+    config_file: Option<std::path::PathBuf>,
+    tool_registry: Option<Arc<ToolRegistry>>,
+    model: Option<Arc<dyn ConverseApi>>,
+    mcp_enabled: Option<bool>,
+}
+
+impl AgentBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the configuration from a YAML file when building
+    pub fn with_config_file(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
+
+    /// Use this configuration; takes precedence over `with_config_file`
+    pub fn with_config(mut self, config: AgentConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Use this registry instead of the default tools
+    pub fn with_tool_registry(mut self, tool_registry: Arc<ToolRegistry>) -> Self {
+        self.tool_registry = Some(tool_registry);
+        self
+    }
+
+    /// Call this model instead of Bedrock, for chats and tasks alike
+    pub fn with_model(mut self, model: Arc<dyn ConverseApi>) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Override `mcp.enabled` from the configuration
+    pub fn with_mcp_enabled(mut self, enabled: bool) -> Self {
+        self.mcp_enabled = Some(enabled);
+        self
+    }
+
+    pub async fn build(self) -> Result<Agent> {
+        let mut config = match (self.config, self.config_file) {
+            (Some(config), _) => config,
+            (None, Some(path)) => AgentConfig::from_file(path)?,
+            (None, None) => AgentConfig::default(),
+        };
+        if let Some(enabled) = self.mcp_enabled {
+            config.mcp.enabled = enabled;
+        }
+
+        let tool_registry = self.tool_registry.unwrap_or_else(|| default_tool_registry(&config));
+        Agent::with_tool_registry(config, tool_registry, self.model).await
+    }
+}
+
+/// Default tools plus the poll tool. Poll checks other tools through the
+/// registry, so it's added once the registry exists.
+///
+/// Poll's own calls are held to `tools.allowed` and `tools.permissions` like the
+/// model's, through a permission manager built from the same configuration.
+pub fn default_tool_registry(config: &AgentConfig) -> Arc<ToolRegistry> {
+    let tool_registry = Arc::new(ToolRegistry::with_default_tools(&config.paths.workspace_dir));
+    let poll = &config.tools.poll;
+    let tools = config.tools.clone();
+    let permissions = build_permission_manager(config);
+    let poll_tool = PollTool::new(Arc::downgrade(&tool_registry))
+        .with_gate(Arc::new(move |tool: &str, input: &serde_json::Value| {
+            tools.is_allowed(tool) && permissions.authorize(tool, input)
+        }))
+        .with_interval(Duration::from_secs(poll.interval_secs))
+        .with_max_attempts(poll.max_attempts)
+        .with_timeout(Duration::from_secs(poll.timeout_secs));
+    if let Err(e) = tool_registry.register(poll_tool) {
+        warn!("Failed to register poll tool: {}", e);
+    }
+    // Network access and repository changes are opt-in: only offered when `allowed` names them
+    let opted_in = |tool: &str| config.tools.allowed.iter().any(|name| name == tool);
+    if opted_in("http_request") {
+        let constraint = config.tools.permissions.get("http_request").and_then(|p| p.constraint.as_deref());
+        if let Err(e) = HttpRequestTool::from_constraint(constraint).and_then(|tool| tool_registry.register(tool)) {
+            warn!("Failed to register http_request tool: {}", e);
+        }
+    }
+    if opted_in("git") {
+        if let Err(e) = tool_registry.register(GitTool::new(&config.paths.workspace_dir)) {
+            warn!("Failed to register git tool: {}", e);
+        }
+    }
+    tool_registry
 }
 
 impl Agent {
     pub async fn new(config: AgentConfig) -> Result<Self> {
-        let bedrock_client = Arc::new(BedrockClient::new(config.clone()).await?);
-        
         // Initialize tool registry with default tools
-        let tool_registry = Arc::new(
-            ToolRegistry::with_default_tools(&config.paths.workspace_dir)
-        );
+        let tool_registry = default_tool_registry(&config);
+        Self::with_tool_registry(config, tool_registry, None).await
+    }
+
+    async fn with_tool_registry(
+        config: AgentConfig,
+        tool_registry: Arc<ToolRegistry>,
+        model: Option<Arc<dyn ConverseApi>>,
+    ) -> Result<Self> {
+        let bedrock_client = Arc::new(BedrockClient::new(config.clone()).await?);
+        let model: Arc<dyn ConverseApi> = model.unwrap_or_else(|| bedrock_client.clone());
         
         // Initialize MCP manager if enabled
         let mcp_manager = if config.mcp.enabled {
@@ -47,12 +210,12 @@ impl Agent {
             if !config.mcp.inline_servers.is_empty() {
                 let mut mcp_servers = std::collections::HashMap::new();
                 for (name, value) in &config.mcp.inline_servers {
-                    match serde_json::from_value::<bedrock_mcp::McpServerConfig>(value.clone()) {
+                    match bedrock_mcp::McpServerConfig::from_value(name, value.clone()) {
                         Ok(server_config) => {
                             mcp_servers.insert(name.clone(), server_config);
                         }
                         Err(e) => {
-                            warn!("Failed to parse inline MCP server '{}': {}", name, e);
+                            warn!("Skipping inline MCP server: {}", e);
                         }
                     }
                 }
@@ -74,26 +237,51 @@ impl Agent {
             None
         };
         
-        let task_executor = Arc::new(TaskExecutor::new(
-            Arc::clone(&bedrock_client),
-            Arc::clone(&tool_registry),
-            Arc::new(config.clone()),
-        )?);
+        let permissions = Arc::new(build_permission_manager(&config));
+        
+        let task_executor = Arc::new(
+            TaskExecutor::new(
+                Arc::clone(&bedrock_client),
+                Arc::clone(&tool_registry),
+                Arc::new(config.clone()),
+            )?
+            .with_permissions(Arc::clone(&permissions))
+            .with_model(Arc::clone(&model)),
+        );
         
         Ok(Self {
-            config: Arc::new(config),
             bedrock_client,
+            model,
             tool_registry,
             task_executor,
             mcp_manager,
+            permissions,
+            chat_history: tokio::sync::Mutex::new(Vec::new()),
+            chat_settings: std::sync::RwLock::new(ChatSettings {
+                model: config.agent.model.clone(),
+                system_prompt: config.agent.get_system_prompt(),
+            }),
+            config: Arc::new(config),
         })
     }
 
     pub async fn from_config_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
-        let config = AgentConfig::from_yaml(path)?;
+        let config = AgentConfig::from_file(path)?;
         Self::new(config).await
     }
 
+    /// Load `AgentConfig::default_config_path()` if it exists, otherwise use the defaults
+    pub async fn from_default_config() -> Result<Self> {
+        let path = AgentConfig::default_config_path();
+        if path.exists() {
+            info!("Loading configuration from {}", path.display());
+            Self::from_config_file(path).await
+        } else {
+            debug!("No configuration at {}, using defaults", path.display());
+            Self::new(AgentConfig::default()).await
+        }
+    }
+
     pub fn get_tool_registry(&self) -> Arc<ToolRegistry> {
         Arc::clone(&self.tool_registry)
     }
@@ -102,35 +290,106 @@ impl Agent {
         Arc::clone(&self.bedrock_client)
     }
 
+    /// Tool executions recorded since the agent started
+    pub fn tool_metrics(&self) -> MetricsCollector {
+        self.bedrock_client.tool_metrics()
+    }
+
+    pub fn get_config(&self) -> Arc<AgentConfig> {
+        Arc::clone(&self.config)
+    }
+
+    /// Run a task against several models concurrently (see `TaskExecutor::compare_models`)
+    pub async fn compare_models(&self, task: Task, models: &[String]) -> Vec<ModelComparison> {
+        self.task_executor.compare_models(task, models).await
+    }
+
+    /// Replay a conversation bundle and check its expectations (see `TaskExecutor::replay`)
+    pub async fn replay(&self, bundle: &ReplayBundle) -> Result<ReplayReport> {
+        self.task_executor.replay(bundle).await
+    }
+
+    /// Execute and save a task like `execute_task`, sending progress to `events`
+    pub async fn execute_task_with_events(
+        &self,
+        task: Task,
+        events: tokio::sync::mpsc::Sender<TaskEvent>,
+    ) -> Result<TaskResult> {
+        let result = self.task_executor.execute_task_with_events(task, Some(events)).await?;
+        self.task_executor.save_result(&result).await?;
+        Ok(result)
+    }
+
+    /// Execute and save a task like `execute_task`, passing the model's text to
+    /// `callback` as it streams in
+    pub async fn execute_task_stream(
+        &self,
+        task: Task,
+        mut callback: impl FnMut(&str) + Send,
+    ) -> Result<TaskResult> {
+        info!("Executing streaming task: {}", task.task_id);
+        let result = self.task_executor.execute_task_stream(task, &mut callback).await?;
+        self.task_executor.save_result(&result).await?;
+        Ok(result)
+    }
+
+    /// Queue `tasks` and run the queue to completion, sending each final result on
+    /// `results` as it finishes. Tasks already in the queue run too.
+    pub async fn run_batch(&self, tasks: Vec<Task>, results: tokio::sync::mpsc::Sender<TaskResult>) -> Result<()> {
+        for task in tasks {
+            self.task_executor.queue_task(task, Priority::Normal).await?;
+        }
+        self.task_executor.drain_queue(results).await;
+        Ok(())
+    }
+
+    /// Estimated cost of the task's first request, without calling Bedrock
+    pub fn estimate(&self, task: &Task) -> Result<CostDetails> {
+        Ok(self.estimate_breakdown(task)?.cost)
+    }
+
+    /// Token count and tool schema size behind `estimate` (see `TaskExecutor::estimate`)
+    pub fn estimate_breakdown(&self, task: &Task) -> Result<TaskEstimate> {
+        self.task_executor.estimate(task)
+    }
+
+    /// Whether `agent.stream_mode` streams this task's answer
+    pub fn should_stream(&self, task: &Task) -> bool {
+        self.config.agent.stream_mode.streams(task)
+    }
+
+    /// Add a previous task's result to the task context (see `TaskExecutor::chain_task`)
+    pub async fn chain_task(&self, task: Task, after: Option<Uuid>) -> Result<Task> {
+        self.task_executor.chain_task(task, after).await
+    }
+
     #[instrument(skip(self, prompt))]
     pub async fn chat(&self, prompt: &str) -> Result<String> {
+        Ok(self.chat_with_stats(prompt).await?.response)
+    }
+
+    /// `chat`, also reporting the turn's token usage and cost
+    pub async fn chat_with_stats(&self, prompt: &str) -> Result<StreamResult> {
         info!("Processing chat prompt");
-        
-        // Initialize conversation manager for non-streaming
-        let mut conv_manager = ConversationManager::new()?;
-        let conversation_id = conv_manager.start_conversation(
-            self.config.agent.model.clone(),
-            Some(self.config.agent.get_system_prompt()),
-        )?;
-        debug!("Started conversation {} for non-streaming chat", conversation_id);
+        let settings = self.chat_settings();
         
         // Build tool definitions if tools are available
-        let tool_definitions = if !self.tool_registry.list().is_empty() {
-            Some(
-                self.tool_registry
-                    .get_all()
-                    .into_iter()
-                    .map(|tool| ToolDefinition {
-                        name: tool.name().to_string(),
-                        description: tool.description().to_string(),
-                        input_schema: tool.schema(),
-                    })
-                    .collect()
-            )
+        let policy = resolve_tool_policy(&self.config.tools, None);
+        let tools = self.tool_registry.select(&policy);
+        let tool_definitions = if !tools.is_empty() {
+            Some(build_tool_definitions(&self.config, tools)?)
         } else {
             None
         };
 
+        // Initialize conversation manager for non-streaming
+        let mut conv_manager = ConversationManager::new()?.with_settings(&self.config.conversation);
+        let conversation_id = conv_manager.start_conversation(
+            settings.model.clone(),
+            Some(settings.system_prompt.clone()),
+        )?;
+        debug!("Started conversation {} for non-streaming chat", conversation_id);
+        
         // Create user message
         let user_message = Message::builder()
             .role(ConversationRole::User)
@@ -143,33 +402,54 @@ impl Agent {
         conv_manager.save_bedrock_message(&user_message, None)?;
         debug!("User message saved successfully");
 
-        let mut conversation = vec![user_message];
+        // Held for the whole turn so concurrent chats don't interleave their messages
+        let mut history = self.chat_history.lock().await;
+        let mut conversation = history.clone();
+        conversation.push(user_message);
+        self.compact_history(&settings, &conv_manager, &mut conversation).await;
         let mut iterations = 0;
-        let mut total_input_tokens = 0usize;
-        let mut total_output_tokens = 0usize;
-        const MAX_ITERATIONS: usize = 10;
+        let mut total_tokens = TokenStatistics::default();
+        let mut tool_cost = 0.0;
+        let max_iterations = self.config.limits.max_tool_iterations;
 
         loop {
             iterations += 1;
-            if iterations > MAX_ITERATIONS {
-                warn!("Maximum iterations reached");
-                return Ok("I apologize, but I couldn't complete the task within the allowed iterations.".to_string());
+            if iterations > max_iterations {
+                warn!("Maximum iterations reached ({})", max_iterations);
+                return Ok(self.chat_result(
+                    &settings,
+                    format!(
+                        "I apologize, but I couldn't complete the task within the allowed {max_iterations} iterations. \
+                        Increase limits.max_tool_iterations to allow more."
+                    ),
+                    total_tokens,
+                    tool_cost,
+                ));
             }
 
-            // Call the model
-            let response = self.bedrock_client
+            // Call the model with the context view of the conversation
+            let system_prompt = settings.system_prompt.clone();
+            let context = fit_request_tokens(
+                &self.config,
+                assemble_context(&conversation, &self.config.conversation)?,
+                Some(&system_prompt),
+                tool_definitions.as_deref(),
+            )?;
+            let mut response = self.model
                 .converse(
-                    &self.config.agent.model,
-                    conversation.clone(),
-                    Some(self.config.agent.get_system_prompt()),
+                    &settings.model,
+                    context,
+                    Some(system_prompt),
                     tool_definitions.clone(),
                 )
                 .await?;
+            if resolve_mixed_response(&self.config.tools, &response, false) == Some(MixedResponsePolicy::PreferAnswer) {
+                response = response.into_answer()?;
+            }
 
-            // Track token usage
-            if let Some(usage) = &response.usage {
-                total_input_tokens += usage.input_tokens() as usize;
-                total_output_tokens += usage.output_tokens() as usize;
+            // Track token usage, cache reads and writes included
+            if let Some(stats) = response.token_statistics() {
+                total_tokens.accumulate(&stats);
             }
 
             // Add assistant response to conversation
@@ -178,16 +458,7 @@ impl Agent {
             // Check if we need to handle tool calls
             if response.has_tool_use() {
                 // Save the assistant message with tool use
-                let response_tokens = if let Some(usage) = &response.usage {
-                    Some(TokenUsageStats {
-                        input_tokens: usage.input_tokens() as u32,
-                        output_tokens: usage.output_tokens() as u32,
-                        total_tokens: usage.total_tokens() as u32,
-                        total_cost: None,
-                    })
-                } else {
-                    None
-                };
+                let response_tokens = self.usage_stats(&settings.model, &response);
                 
                 conv_manager.save_bedrock_message(&response.message, response_tokens)?;
                 debug!("Saved assistant message with tool use");
@@ -198,9 +469,10 @@ impl Agent {
                     debug!("Processing {} tool calls", tool_uses.len());
                     
                     // Execute tools
-                    let tool_results = self.bedrock_client
-                        .execute_tools(&tool_uses, &self.tool_registry)
+                    let (tool_results, cost) = self.bedrock_client
+                        .execute_tools_with_cost(&tool_uses, &self.tool_registry, &self.permissions, &policy)
                         .await?;
+                    tool_cost += cost;
                     
                     // Create tool result message
                     let tool_result_message = Message::builder()
@@ -226,16 +498,7 @@ impl Agent {
             }
 
             // No more tool calls, save final assistant message and return
-            let final_tokens = if let Some(usage) = &response.usage {
-                Some(TokenUsageStats {
-                    input_tokens: usage.input_tokens() as u32,
-                    output_tokens: usage.output_tokens() as u32,
-                    total_tokens: usage.total_tokens() as u32,
-                    total_cost: Some(self.calculate_cost(total_input_tokens, total_output_tokens).total_cost),
-                })
-            } else {
-                None
-            };
+            let final_tokens = self.usage_stats(&settings.model, &response);
             
             if !response.has_tool_use() {
                 conv_manager.save_bedrock_message(&response.message, final_tokens)?;
@@ -245,30 +508,59 @@ impl Agent {
             info!("Saved non-streaming conversation {} with {} messages", 
                   conversation_id, conv_manager.get_message_count());
             
-            return Ok(response.get_text_content());
+            *history = conversation;
+            trim_history(&mut history, self.config.conversation.max_chat_messages);
+            
+            return Ok(self.chat_result(&settings, response.get_text_content(), total_tokens, tool_cost));
         }
     }
 
-    fn calculate_cost(&self, input_tokens: usize, output_tokens: usize) -> CostDetails {
-        let pricing = self.config.pricing.get(&self.config.agent.model);
-        
-        let (input_cost, output_cost, currency) = if let Some(pricing) = pricing {
-            let input_cost = (input_tokens as f64 / 1000.0) * pricing.input_per_1k;
-            let output_cost = (output_tokens as f64 / 1000.0) * pricing.output_per_1k;
-            (input_cost, output_cost, pricing.currency.clone())
-        } else {
-            // Default pricing if model not in config
-            let input_cost = (input_tokens as f64 / 1000.0) * 0.003;
-            let output_cost = (output_tokens as f64 / 1000.0) * 0.015;
-            (input_cost, output_cost, "USD".to_string())
-        };
+    fn chat_result(
+        &self,
+        settings: &ChatSettings,
+        response: String,
+        token_stats: TokenStatistics,
+        tool_cost: f64,
+    ) -> StreamResult {
+        StreamResult {
+            response,
+            cost: self.calculate_cost(&settings.model, &token_stats, tool_cost),
+            token_stats,
+        }
+    }
+
+    /// Token usage of one response and what it cost, as saved with its message
+    fn usage_stats(&self, model: &str, response: &ConverseResponse) -> Option<TokenUsageStats> {
+        let usage = response.usage.as_ref()?;
+        let stats = response.token_statistics()?;
+        Some(TokenUsageStats {
+            input_tokens: usage.input_tokens() as u32,
+            output_tokens: usage.output_tokens() as u32,
+            total_tokens: usage.total_tokens() as u32,
+            total_cost: Some(self.calculate_cost(model, &stats, 0.0).total_cost),
+        })
+    }
+
+    /// Cost of a chat turn's model calls plus `tool_cost`, what its tool calls cost
+    fn calculate_cost(&self, model: &str, tokens: &TokenStatistics, tool_cost: f64) -> CostDetails {
+        // Default pricing if model not in config
+        let pricing = self.config.pricing.get(model).cloned().unwrap_or_else(|| ModelPricing {
+            input_per_1k: 0.003,
+            output_per_1k: 0.015,
+            currency: "USD".to_string(),
+            cache_read_per_1k: None,
+            cache_write_per_1k: None,
+        });
+        let input_cost = pricing.input_cost(tokens);
+        let output_cost = (tokens.output_tokens as f64 / 1000.0) * pricing.output_per_1k;
         
         CostDetails {
             input_cost,
             output_cost,
-            total_cost: input_cost + output_cost,
-            currency,
-            model: self.config.agent.model.clone(),
+            tool_cost,
+            total_cost: input_cost + output_cost + tool_cost,
+            currency: pricing.currency,
+            model: model.to_string(),
         }
     }
 
@@ -278,32 +570,25 @@ impl Agent {
         mut callback: impl FnMut(&str) + Send,
     ) -> Result<StreamResult> {
         info!("Processing streaming chat prompt");
-        
-        // Initialize conversation manager for streaming
-        let mut conv_manager = ConversationManager::new()?;
-        let conversation_id = conv_manager.start_conversation(
-            self.config.agent.model.clone(),
-            Some(self.config.agent.get_system_prompt()),
-        )?;
-        debug!("Started conversation {} for streaming", conversation_id);
+        let settings = self.chat_settings();
         
         // Build tool definitions if tools are available
-        let tool_definitions = if !self.tool_registry.list().is_empty() {
-            Some(
-                self.tool_registry
-                    .get_all()
-                    .into_iter()
-                    .map(|tool| ToolDefinition {
-                        name: tool.name().to_string(),
-                        description: tool.description().to_string(),
-                        input_schema: tool.schema(),
-                    })
-                    .collect()
-            )
+        let policy = resolve_tool_policy(&self.config.tools, None);
+        let tools = self.tool_registry.select(&policy);
+        let tool_definitions = if !tools.is_empty() {
+            Some(build_tool_definitions(&self.config, tools)?)
         } else {
             None
         };
 
+        // Initialize conversation manager for streaming
+        let mut conv_manager = ConversationManager::new()?.with_settings(&self.config.conversation);
+        let conversation_id = conv_manager.start_conversation(
+            settings.model.clone(),
+            Some(settings.system_prompt.clone()),
+        )?;
+        debug!("Started conversation {} for streaming", conversation_id);
+        
         // Create user message
         let user_message = Message::builder()
             .role(ConversationRole::User)
@@ -316,37 +601,55 @@ impl Agent {
         conv_manager.save_bedrock_message(&user_message, None)?;
         debug!("User message saved successfully");
         
-        let mut conversation = vec![user_message];
+        // Held for the whole turn so concurrent chats don't interleave their messages
+        let mut history = self.chat_history.lock().await;
+        let mut conversation = history.clone();
+        conversation.push(user_message);
+        self.compact_history(&settings, &conv_manager, &mut conversation).await;
         let mut iterations = 0;
-        let mut total_input_tokens = 0usize;
-        let mut total_output_tokens = 0usize;
+        let mut total_tokens = TokenStatistics::default();
+        let mut tool_cost = 0.0;
         let final_response;
-        const MAX_ITERATIONS: usize = 10;
+        let max_iterations = self.config.limits.max_tool_iterations;
 
         loop {
             iterations += 1;
-            if iterations > MAX_ITERATIONS {
-                warn!("Maximum iterations reached");
-                let msg = "I apologize, but I couldn't complete the task within the allowed iterations.";
-                callback(msg);
-                final_response = msg.to_string();
+            if iterations > max_iterations {
+                warn!("Maximum iterations reached ({})", max_iterations);
+                let msg = format!(
+                    "I apologize, but I couldn't complete the task within the allowed {max_iterations} iterations. \
+                    Increase limits.max_tool_iterations to allow more."
+                );
+                callback(&msg);
+                final_response = msg;
                 break;
             }
 
             // Get streaming response - this now returns a ConverseResponse with the full message
-            let response = self.bedrock_client
-                .converse_stream(
-                    &self.config.agent.model,
-                    conversation.clone(),
-                    Some(self.config.agent.get_system_prompt()),
+            let system_prompt = settings.system_prompt.clone();
+            let context = fit_request_tokens(
+                &self.config,
+                assemble_context(&conversation, &self.config.conversation)?,
+                Some(&system_prompt),
+                tool_definitions.as_deref(),
+            )?;
+            let mut response = self.model
+                .converse_stream_with_metadata(
+                    &settings.model,
+                    context,
+                    Some(system_prompt),
                     tool_definitions.clone(),
+                    &std::collections::HashMap::new(),
+                    &mut print_text,
                 )
                 .await?;
+            if resolve_mixed_response(&self.config.tools, &response, false) == Some(MixedResponsePolicy::PreferAnswer) {
+                response = response.into_answer()?;
+            }
 
-            // Track token usage
-            if let Some(usage) = &response.usage {
-                total_input_tokens += usage.input_tokens() as usize;
-                total_output_tokens += usage.output_tokens() as usize;
+            // Track token usage, cache reads and writes included
+            if let Some(stats) = response.token_statistics() {
+                total_tokens.accumulate(&stats);
             }
 
             // Add assistant response to conversation
@@ -356,16 +659,7 @@ impl Agent {
             if response.has_tool_use() {
                 // Save the assistant message with tool use to conversation
                 // Calculate token usage for this response
-                let response_tokens = if let Some(usage) = &response.usage {
-                    Some(TokenUsageStats {
-                        input_tokens: usage.input_tokens() as u32,
-                        output_tokens: usage.output_tokens() as u32,
-                        total_tokens: usage.total_tokens() as u32,
-                        total_cost: None, // Will be calculated at the end
-                    })
-                } else {
-                    None
-                };
+                let response_tokens = self.usage_stats(&settings.model, &response);
                 
                 // Save assistant message with tool use
                 conv_manager.save_bedrock_message(&response.message, response_tokens)?;
@@ -377,9 +671,10 @@ impl Agent {
                     debug!("Processing {} tool calls", tool_uses.len());
                     
                     // Execute tools
-                    let tool_results = self.bedrock_client
-                        .execute_tools(&tool_uses, &self.tool_registry)
+                    let (tool_results, cost) = self.bedrock_client
+                        .execute_tools_with_cost(&tool_uses, &self.tool_registry, &self.permissions, &policy)
                         .await?;
+                    tool_cost += cost;
                     
                     // Create tool result message
                     let tool_result_message = Message::builder()
@@ -409,47 +704,98 @@ impl Agent {
             
             // Save final assistant message if it doesn't have tool use
             if !response.has_tool_use() {
-                let final_tokens = if let Some(usage) = &response.usage {
-                    Some(TokenUsageStats {
-                        input_tokens: usage.input_tokens() as u32,
-                        output_tokens: usage.output_tokens() as u32,
-                        total_tokens: usage.total_tokens() as u32,
-                        total_cost: None,
-                    })
-                } else {
-                    None
-                };
+                let final_tokens = self.usage_stats(&settings.model, &response);
                 
                 conv_manager.save_bedrock_message(&response.message, final_tokens)?;
                 debug!("Saved final assistant message");
             }
             
+            // Only a finished turn is remembered; an unanswered tool round would leave
+            // the history ending mid-exchange
+            *history = conversation;
+            trim_history(&mut history, self.config.conversation.max_chat_messages);
             break;
         }
 
-        // Calculate final cost and prepare result
-        let token_stats = TokenStatistics {
-            input_tokens: total_input_tokens,
-            output_tokens: total_output_tokens,
-            total_tokens: total_input_tokens + total_output_tokens,
-            cache_hits: 0,
-        };
-
-        let cost = self.calculate_cost(total_input_tokens, total_output_tokens);
-        
         info!("Saved streaming conversation {} with {} messages", 
               conversation_id, conv_manager.get_message_count());
 
-        Ok(StreamResult {
-            response: final_response,
-            token_stats,
-            cost,
-        })
+        Ok(self.chat_result(&settings, final_response, total_tokens, tool_cost))
+    }
+    
+    /// Summarize older chat turns with the model once the history passes
+    /// `conversation.compact_at_tokens`. A failed summary leaves the history as it was.
+    async fn compact_history(&self, settings: &ChatSettings, conv_manager: &ConversationManager, conversation: &mut Vec<Message>) {
+        let Some(threshold) = self.config.conversation.compact_at_tokens else {
+            return;
+        };
+        let measure = |messages: &[Message]| estimate_request_tokens(&settings.model, messages, Some(&settings.system_prompt));
+        let summarize = |messages: Vec<Message>| self.summarize_messages(&settings.model, messages);
+        if let Err(e) = conv_manager.compact(conversation, threshold, measure, summarize).await {
+            warn!("Failed to compact the conversation: {}", e);
+        }
+    }
+    
+    /// Ask the model for a summary to stand in for `messages`
+    async fn summarize_messages(&self, model: &str, messages: Vec<Message>) -> Result<String> {
+        let request = Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::Text(format!(
+                "Summarize the conversation below. The summary replaces it as context for the rest \
+                 of the session, so keep facts, decisions, file names, tool results and open questions \
+                 that may still matter, and leave out pleasantries.\n\n{}",
+                transcript(&messages)
+            )))
+            .build()
+            .map_err(|e| BedrockError::Unknown(e.to_string()))?;
+        let response = self.model
+            .converse(model, vec![request], None, None)
+            .await?;
+        Ok(response.get_text_content())
+    }
+    
+    /// Forget the messages of earlier chat turns
+    pub async fn reset_conversation(&self) {
+        self.chat_history.lock().await.clear();
+    }
+    
+    fn chat_settings(&self) -> ChatSettings {
+        self.chat_settings.read().unwrap().clone()
+    }
+
+    /// Model used by later chat turns
+    pub fn chat_model(&self) -> String {
+        self.chat_settings().model
+    }
+
+    /// Switch the model of later chat turns; tasks keep the configured model
+    pub fn set_chat_model(&self, model: &str) {
+        self.chat_settings.write().unwrap().model = model.to_string();
+    }
+
+    /// Replace the system prompt of later chat turns
+    pub fn set_chat_system_prompt(&self, system_prompt: &str) {
+        self.chat_settings.write().unwrap().system_prompt = system_prompt.to_string();
+    }
+
+    /// The remembered chat turns as a plain-text transcript
+    pub async fn chat_transcript(&self) -> String {
+        transcript(&self.chat_history.lock().await)
+    }
+
+    /// Number of messages remembered from earlier chat turns
+    pub async fn conversation_len(&self) -> usize {
+        self.chat_history.lock().await.len()
     }
     
     /// Shutdown the agent and cleanup resources
     pub async fn shutdown(&mut self) -> Result<()> {
         info!("Shutting down agent");
+
+        // Let running tasks finish before the MCP servers their tools use go away
+        if let Err(e) = self.task_executor.shutdown().await {
+            warn!("Error persisting the task queue: {}", e);
+        }
         
         // Stop all MCP servers if initialized
         if let Some(mcp_manager) = &self.mcp_manager {
@@ -462,6 +808,21 @@ impl Agent {
         Ok(())
     }
     
+    /// Add the contents of an MCP resource to a task's context
+    pub async fn attach_mcp_resource(&self, mut task: Task, server: &str, uri: &str) -> Result<Task> {
+        let mcp_manager = self.mcp_manager.as_ref()
+            .ok_or_else(|| BedrockError::McpError("MCP is not enabled".into()))?;
+        let content = mcp_manager.read().await.read_resource_text(server, uri).await?;
+        
+        let section = format!("Resource {uri} from MCP server '{server}':\n{content}");
+        task.context = if task.context.is_empty() {
+            section
+        } else {
+            format!("{}\n\n{}", task.context, section)
+        };
+        Ok(task)
+    }
+    
     /// Get list of connected MCP servers
     pub async fn list_mcp_servers(&self) -> Vec<String> {
         if let Some(mcp_manager) = &self.mcp_manager {
@@ -502,4 +863,389 @@ impl AgentTrait for Agent {
             Err(_) => Ok(TaskStatus::Pending),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_bedrockruntime::types::{StopReason, ToolUseBlock};
+    use bedrock_client::ToolDefinition;
+    use bedrock_config::ToolPermission;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_opt_in_tools_need_allowed() {
+        let mut config = AgentConfig::default();
+        let registry = default_tool_registry(&config);
+        assert!(registry.get("http_request").is_none());
+        assert!(registry.get("git").is_none());
+
+        config.tools.allowed = vec!["fs_read".to_string(), "http_request".to_string(), "git".to_string()];
+        let registry = default_tool_registry(&config);
+        assert!(registry.get("http_request").is_some());
+        assert!(registry.get("git").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_poll_calls_follow_allowed_and_permissions() {
+        let mut config = AgentConfig::default();
+        config.tools.allowed = vec!["poll".to_string(), "fs_list".to_string()];
+        config.tools.permissions.insert(
+            "fs_list".to_string(),
+            ToolPermission {
+                permission: ConfigPermission::Deny,
+                constraint: None,
+                timeout_secs: None,
+                strict: false,
+                cost_per_call: None,
+                cost_per_unit: None,
+                cost_unit_path: None,
+            },
+        );
+        let registry = default_tool_registry(&config);
+        let poll = registry.get("poll").unwrap();
+
+        for tool in ["fs_read", "fs_list"] {
+            let err = poll.execute(json!({"tool": tool})).await.unwrap_err();
+            assert!(err.to_string().contains("Permission denied"), "{tool}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_permission_manager_from_config() {
+        let mut config = AgentConfig::default();
+        config.tools.permissions.insert(
+            "execute_bash".to_string(),
+            ToolPermission {
+                permission: ConfigPermission::Deny,
+                constraint: None,
+                timeout_secs: None,
+                strict: false,
+                cost_per_call: None,
+                cost_per_unit: None,
+                cost_unit_path: None,
+            },
+        );
+
+        let manager = build_permission_manager(&config);
+        assert!(!manager.authorize("execute_bash", &json!({"command": "rm -rf /"})));
+        assert!(manager.authorize("fs_read", &json!({"path": "README.md"})));
+        assert!(manager.authorize("apply_patch", &json!({"path": "README.md"})));
+    }
+
+    #[test]
+    fn test_apply_patch_follows_fs_write_permission() {
+        let mut config = AgentConfig::default();
+        let permission = |permission| ToolPermission {
+            permission,
+            constraint: None,
+            timeout_secs: None,
+            strict: false,
+            cost_per_call: None,
+            cost_per_unit: None,
+            cost_unit_path: None,
+        };
+        config.tools.permissions.insert("fs_write".to_string(), permission(ConfigPermission::Deny));
+        config.tools.permissions.insert("apply_patch".to_string(), permission(ConfigPermission::Allow));
+
+        let manager = build_permission_manager(&config);
+        assert!(!manager.authorize("apply_patch", &json!({"path": "README.md"})));
+
+        // A stricter rule of its own still holds
+        config.tools.permissions.insert("fs_write".to_string(), permission(ConfigPermission::Allow));
+        config.tools.permissions.insert("apply_patch".to_string(), permission(ConfigPermission::Deny));
+        let manager = build_permission_manager(&config);
+        assert!(!manager.authorize("apply_patch", &json!({"path": "README.md"})));
+    }
+
+    struct EchoTool;
+
+    /// `echo`, counting its runs
+    struct CountingEchoTool {
+        runs: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl bedrock_tools::Tool for CountingEchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "echoes its input"
+        }
+
+        fn schema(&self) -> serde_json::Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, args: serde_json::Value) -> Result<serde_json::Value> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            Ok(args)
+        }
+    }
+
+    #[async_trait]
+    impl bedrock_tools::Tool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "echoes its input"
+        }
+
+        fn schema(&self) -> serde_json::Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, args: serde_json::Value) -> Result<serde_json::Value> {
+            Ok(args)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builder_uses_custom_tool_registry() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = dir.path().to_path_buf();
+        config.mcp.enabled = true;
+
+        let registry = Arc::new(ToolRegistry::new());
+        registry.register(EchoTool).unwrap();
+
+        let agent = AgentBuilder::new()
+            .with_config(config)
+            .with_tool_registry(registry)
+            .with_mcp_enabled(false)
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(agent.get_tool_registry().list(), vec!["echo"]);
+        assert!(agent.mcp_manager.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chat_applies_max_tools() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = dir.path().to_path_buf();
+        config.mcp.max_tools = 0;
+        config.mcp.on_tool_overflow = bedrock_config::ToolOverflow::Error;
+
+        let registry = Arc::new(ToolRegistry::new());
+        registry.register(EchoTool).unwrap();
+        let agent = AgentBuilder::new()
+            .with_config(config)
+            .with_tool_registry(registry)
+            .with_mcp_enabled(false)
+            .build()
+            .await
+            .unwrap();
+
+        // Refused before the model is called, in both chat paths
+        let err = agent.chat_with_stats("hi").await.unwrap_err();
+        assert!(err.to_string().contains("mcp.max_tools"), "{err}");
+        let err = agent.chat_stream("hi", |_| {}).await.unwrap_err();
+        assert!(err.to_string().contains("mcp.max_tools"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_chat_model_and_system_prompt_switch_mid_session() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = dir.path().to_path_buf();
+        let configured_model = config.agent.model.clone();
+        let agent = AgentBuilder::new().with_config(config).with_mcp_enabled(false).build().await.unwrap();
+        assert_eq!(agent.chat_model(), configured_model);
+
+        agent.set_chat_model("anthropic.claude-3-haiku-20240307-v1:0");
+        agent.set_chat_system_prompt("Answer in French");
+
+        let settings = agent.chat_settings();
+        assert_eq!(settings.model, "anthropic.claude-3-haiku-20240307-v1:0");
+        assert_eq!(settings.system_prompt, "Answer in French");
+        // Tasks keep using the configured model
+        assert_eq!(agent.get_config().agent.model, configured_model);
+        let tokens = TokenStatistics { input_tokens: 1000, total_tokens: 1000, ..Default::default() };
+        let cost = agent.chat_result(&settings, "Bonjour".into(), tokens, 0.0).cost;
+        assert_eq!(cost.model, "anthropic.claude-3-haiku-20240307-v1:0");
+    }
+
+    #[tokio::test]
+    async fn test_chat_cost_includes_cache_reads() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = dir.path().to_path_buf();
+        config.agent.model = "unpriced.model-v1".to_string();
+        let agent = AgentBuilder::new().with_config(config).with_mcp_enabled(false).build().await.unwrap();
+
+        let tokens = TokenStatistics { input_tokens: 1000, cache_hits: 10_000, total_tokens: 11_000, ..Default::default() };
+        let result = agent.chat_result(&agent.chat_settings(), "ok".into(), tokens, 0.0);
+        // 1k uncached at the default $0.003 plus 10k cache reads at a tenth of that
+        assert!((result.cost.input_cost - 0.006).abs() < 1e-9, "{}", result.cost.input_cost);
+        assert_eq!(result.token_stats.cache_hits, 10_000);
+    }
+
+    #[tokio::test]
+    async fn test_saved_chat_messages_carry_their_cost() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = dir.path().to_path_buf();
+        config.agent.model = "unpriced.model-v1".to_string();
+        let agent = AgentBuilder::new().with_config(config).with_mcp_enabled(false).build().await.unwrap();
+
+        let mut response = text_response("ok");
+        response.usage = Some(
+            aws_sdk_bedrockruntime::types::TokenUsage::builder()
+                .input_tokens(1000)
+                .output_tokens(1000)
+                .total_tokens(2000)
+                .build()
+                .unwrap(),
+        );
+        let stats = agent.usage_stats("unpriced.model-v1", &response).unwrap();
+        assert_eq!(stats.total_tokens, 2000);
+        // $0.003 in plus $0.015 out at the default pricing
+        assert!((stats.total_cost.unwrap() - 0.018).abs() < 1e-9, "{:?}", stats.total_cost);
+        assert!(agent.usage_stats("unpriced.model-v1", &text_response("ok")).is_none());
+    }
+
+    /// Replies with the scripted responses in order, recording each request
+    struct ScriptedModel {
+        responses: std::sync::Mutex<Vec<ConverseResponse>>,
+        requests: std::sync::Mutex<Vec<Vec<Message>>>,
+    }
+
+    #[async_trait]
+    impl ConverseApi for ScriptedModel {
+        async fn converse(
+            &self,
+            _model_id: &str,
+            messages: Vec<Message>,
+            _system_prompt: Option<String>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ConverseResponse> {
+            self.requests.lock().unwrap().push(messages);
+            let mut responses = self.responses.lock().unwrap();
+            if responses.is_empty() {
+                return Err(BedrockError::Unknown("script exhausted".into()));
+            }
+            Ok(responses.remove(0))
+        }
+    }
+
+    /// Replies with `responses` in order
+    fn scripted(responses: Vec<ConverseResponse>) -> Arc<ScriptedModel> {
+        Arc::new(ScriptedModel {
+            responses: std::sync::Mutex::new(responses),
+            requests: std::sync::Mutex::new(Vec::new()),
+        })
+    }
+
+    fn text_response(text: &str) -> ConverseResponse {
+        ConverseResponse {
+            message: Message::builder()
+                .role(ConversationRole::Assistant)
+                .content(ContentBlock::Text(text.to_string()))
+                .build()
+                .unwrap(),
+            stop_reason: StopReason::EndTurn,
+            usage: None,
+            stop_sequence: None,
+            guardrail_action: None,
+        }
+    }
+
+    /// A call of the echo tool, with `text` alongside it
+    fn echo_call(text: &str) -> ConverseResponse {
+        let tool_use = ToolUseBlock::builder()
+            .tool_use_id("call-1")
+            .name("echo")
+            .input(BedrockClient::json_to_document(&json!({})).unwrap())
+            .build()
+            .unwrap();
+        ConverseResponse {
+            message: Message::builder()
+                .role(ConversationRole::Assistant)
+                .content(ContentBlock::Text(text.to_string()))
+                .content(ContentBlock::ToolUse(tool_use))
+                .build()
+                .unwrap(),
+            stop_reason: StopReason::ToolUse,
+            usage: None,
+            stop_sequence: None,
+            guardrail_action: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_mixed_response_prefer_answer_skips_tools() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("HOME_DIR", dir.path());
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = dir.path().to_path_buf();
+        config.tools.mixed_response = MixedResponsePolicy::PreferAnswer;
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let registry = Arc::new(ToolRegistry::new());
+        registry.register(CountingEchoTool { runs: Arc::clone(&runs) }).unwrap();
+        let model = scripted(vec![echo_call("It's 4"), echo_call("It's 5")]);
+        let agent = AgentBuilder::new()
+            .with_config(config)
+            .with_tool_registry(registry)
+            .with_model(model.clone())
+            .with_mcp_enabled(false)
+            .build()
+            .await
+            .unwrap();
+
+        assert_eq!(agent.chat_with_stats("2 + 2?").await.unwrap().response, "It's 4");
+        assert_eq!(agent.chat_stream("2 + 3?", |_| {}).await.unwrap().response, "It's 5");
+
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        // The skipped calls aren't left in the history for the next turn
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].iter().flat_map(|m| m.content()).all(|block| !block.is_tool_use()));
+    }
+
+    #[tokio::test]
+    async fn test_chat_cost_includes_tool_calls() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::env::set_var("HOME_DIR", dir.path());
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = dir.path().to_path_buf();
+        config.tools.permissions.insert(
+            "echo".to_string(),
+            ToolPermission {
+                permission: ConfigPermission::Allow,
+                constraint: None,
+                timeout_secs: None,
+                strict: false,
+                cost_per_call: Some(0.25),
+                cost_per_unit: None,
+                cost_unit_path: None,
+            },
+        );
+
+        let registry = Arc::new(ToolRegistry::new());
+        registry.register(EchoTool).unwrap();
+        let agent = AgentBuilder::new()
+            .with_config(config)
+            .with_tool_registry(registry)
+            .with_model(scripted(vec![echo_call(""), text_response("done"), echo_call(""), text_response("done")]))
+            .with_mcp_enabled(false)
+            .build()
+            .await
+            .unwrap();
+
+        for result in [agent.chat_with_stats("echo").await.unwrap(), agent.chat_stream("echo", |_| {}).await.unwrap()] {
+            assert!((result.cost.tool_cost - 0.25).abs() < 1e-9, "{}", result.cost.tool_cost);
+            let model_cost = result.cost.input_cost + result.cost.output_cost;
+            assert!((result.cost.total_cost - model_cost - 0.25).abs() < 1e-9);
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use anyhow::Result;
-use bedrock_agent::Agent;
+use bedrock_agent::{Agent, AgentBuilder};
 use bedrock_core::{Agent as AgentTrait, Task};
 use std::env;
 use tracing::{error, info};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -14,15 +14,14 @@ async fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
     
     let agent = if args.len() > 1 {
-        Agent::from_config_file(&args[1]).await?
+        AgentBuilder::new().with_config_file(&args[1]).build().await?
     } else {
-        // Use default config file or create with default config
-        match Agent::from_config_file("config.yaml").await {
+        // Use config.yaml from the working directory, else the default config location
+        match AgentBuilder::new().with_config_file("config.yaml").build().await {
             Ok(agent) => agent,
             Err(_) => {
                 info!("No config.yaml found, using default configuration");
-                use bedrock_config::AgentConfig;
-                Agent::new(AgentConfig::default()).await?
+                Agent::from_default_config().await?
             }
         }
     };
@@ -68,19 +67,25 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// `LOG_FORMAT=json` writes one JSON object per line, for log aggregators
 fn initialize_logging() {
     let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
     
+    let json = env::var("LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json"));
     let fmt_layer = tracing_subscriber::fmt::layer()
-        .with_target(false)
         .with_thread_ids(false)
         .with_thread_names(false)
         .with_file(false)
         .with_line_number(false);
+    let fmt_layer = if json {
+        fmt_layer.json().with_current_span(true).with_span_list(true).boxed()
+    } else {
+        fmt_layer.with_target(false).boxed()
+    };
     
     tracing_subscriber::registry()
         .with(env_filter)
         .with(fmt_layer)
         .init();
-}
\ No newline at end of file
+}
@@ -0,0 +1,272 @@
+//! Circuit breaker around model calls
+//!
+//! After `failure_threshold` consecutive failed calls the circuit opens and every
+//! call fails fast with `BedrockError::CircuitOpen`. Once `reset_timeout` has passed,
+//! a single probe call is let through: success closes the circuit, failure opens it
+//! again, and so does a probe that never finishes because its call was dropped.
+//! Only failures that point at the service (5xx answers, requests that got no
+//! answer, timeouts) count; rejected requests and throttling don't.
+
+use bedrock_config::CircuitBreakerSettings;
+use bedrock_core::{BedrockError, Result};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls go through
+    Closed,
+    /// Calls fail fast until the reset timeout has passed
+    Open,
+    /// A probe call is deciding whether to close the circuit
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Shared by every clone, like the request limiter
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    circuit: Arc<Mutex<Circuit>>,
+    enabled: bool,
+    failure_threshold: u32,
+    reset_timeout: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(settings: &CircuitBreakerSettings) -> Self {
+        Self {
+            circuit: Arc::new(Mutex::new(Circuit {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+            })),
+            // A threshold of zero would open the circuit before the first call
+            enabled: settings.enabled && settings.failure_threshold > 0,
+            failure_threshold: settings.failure_threshold,
+            reset_timeout: Duration::from_secs(settings.reset_timeout_secs),
+        }
+    }
+
+    /// Current state; an open circuit whose timeout has passed still reports `Open`
+    /// until the next call probes it
+    pub fn state(&self) -> CircuitState {
+        self.circuit.lock().unwrap().state
+    }
+
+    /// Whether a call may go ahead. Moves an expired open circuit to half-open and
+    /// lets the caller make the probe call.
+    pub fn check(&self) -> Result<CircuitPermit> {
+        let permit = |probe| CircuitPermit { breaker: self.clone(), probe, recorded: false };
+        if !self.enabled {
+            return Ok(permit(false));
+        }
+        let mut circuit = self.circuit.lock().unwrap();
+        match circuit.state {
+            CircuitState::Closed => Ok(permit(false)),
+            CircuitState::Open => {
+                let opened_at = circuit.opened_at.unwrap_or_else(Instant::now);
+                let elapsed = opened_at.elapsed();
+                if elapsed >= self.reset_timeout {
+                    circuit.state = CircuitState::HalfOpen;
+                    Ok(permit(true))
+                } else {
+                    Err(BedrockError::CircuitOpen(format!(
+                        "{} consecutive model calls failed; retrying in {}s",
+                        circuit.consecutive_failures,
+                        (self.reset_timeout - elapsed).as_secs().max(1)
+                    )))
+                }
+            }
+            CircuitState::HalfOpen => Err(BedrockError::CircuitOpen(
+                "waiting for a probe call to finish".to_string(),
+            )),
+        }
+    }
+
+    /// Update the circuit with the outcome of a call that `check` let through
+    fn record<T>(&self, result: &Result<T>) {
+        if !self.enabled {
+            return;
+        }
+        let mut circuit = self.circuit.lock().unwrap();
+        match result {
+            Ok(_) => {
+                if circuit.state != CircuitState::Closed {
+                    info!("Model calls succeed again; closing the circuit");
+                }
+                circuit.state = CircuitState::Closed;
+                circuit.consecutive_failures = 0;
+                circuit.opened_at = None;
+            }
+            Err(e) if counts_as_failure(e) => {
+                circuit.consecutive_failures += 1;
+                let reopen = circuit.state == CircuitState::HalfOpen;
+                if reopen || circuit.consecutive_failures >= self.failure_threshold {
+                    if circuit.state != CircuitState::Open {
+                        warn!(
+                            "Opening the circuit after {} failed model calls: {}",
+                            circuit.consecutive_failures,
+                            e
+                        );
+                    }
+                    circuit.state = CircuitState::Open;
+                    circuit.opened_at = Some(Instant::now());
+                }
+            }
+            // The service answered; a half-open probe that was rejected still proves it's up
+            Err(_) => {
+                if circuit.state == CircuitState::HalfOpen {
+                    circuit.state = CircuitState::Closed;
+                    circuit.consecutive_failures = 0;
+                    circuit.opened_at = None;
+                }
+            }
+        }
+    }
+
+    /// Open a half-open circuit again after its probe went away without an outcome
+    fn abandon_probe(&self) {
+        let mut circuit = self.circuit.lock().unwrap();
+        if circuit.state == CircuitState::HalfOpen {
+            warn!("Probe call was dropped before it finished; reopening the circuit");
+            circuit.state = CircuitState::Open;
+            circuit.opened_at = Some(Instant::now());
+        }
+    }
+}
+
+/// A call let through by [`CircuitBreaker::check`]; pass its outcome to `record`.
+///
+/// Dropping the permit unrecorded, e.g. when a timeout cancels the call, reopens the
+/// circuit if the call was its probe, so the next probe is let through after
+/// another `reset_timeout`.
+#[derive(Debug)]
+#[must_use = "record the call's outcome on the permit"]
+pub struct CircuitPermit {
+    breaker: CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl CircuitPermit {
+    pub fn record<T>(mut self, result: &Result<T>) {
+        self.recorded = true;
+        self.breaker.record(result);
+    }
+}
+
+impl Drop for CircuitPermit {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            self.breaker.abandon_probe();
+        }
+    }
+}
+
+/// Errors that suggest Bedrock itself is unavailable
+fn counts_as_failure(error: &BedrockError) -> bool {
+    matches!(error, BedrockError::ServiceUnavailable(_) | BedrockError::Timeout(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(failure_threshold: u32) -> CircuitBreaker {
+        CircuitBreaker::new(&CircuitBreakerSettings { enabled: true, failure_threshold, reset_timeout_secs: 30 })
+    }
+
+    fn outage() -> Result<()> {
+        Err(BedrockError::ServiceUnavailable("ServiceUnavailableException".into()))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_opens_after_threshold_and_closes_after_probe() {
+        let breaker = breaker(3);
+        for _ in 0..2 {
+            breaker.check().unwrap().record(&outage());
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        breaker.check().unwrap().record(&outage());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        let err = breaker.check().unwrap_err();
+        assert!(matches!(err, BedrockError::CircuitOpen(_)));
+        assert!(err.is_retryable());
+
+        // After the reset timeout exactly one probe goes through
+        tokio::time::advance(Duration::from_secs(30)).await;
+        let probe = breaker.check().unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.check().is_err());
+
+        probe.record(&Ok(()));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.check().unwrap().record(&Ok(()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_probe_reopens() {
+        let breaker = breaker(1);
+        breaker.record(&outage());
+        tokio::time::advance(Duration::from_secs(30)).await;
+
+        breaker.check().unwrap().record(&outage());
+
+        assert_eq!(breaker.state(), CircuitState::Open);
+        tokio::time::advance(Duration::from_secs(29)).await;
+        assert!(breaker.check().is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_dropped_probe_reopens() {
+        let breaker = breaker(1);
+        breaker.record(&outage());
+        tokio::time::advance(Duration::from_secs(30)).await;
+
+        // The probe's call hangs until a timeout drops it
+        let probe = async {
+            let permit = breaker.check()?;
+            std::future::pending::<()>().await;
+            permit.record(&Ok(()));
+            Ok::<_, BedrockError>(())
+        };
+        assert!(tokio::time::timeout(Duration::from_secs(5), probe).await.is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.check().is_err());
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        breaker.check().unwrap().record(&Ok(()));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_only_service_failures_count() {
+        let breaker = breaker(2);
+        breaker.record(&outage());
+        breaker.record(&Err::<(), _>(BedrockError::RateLimitError("ThrottlingException".into())));
+        breaker.record(&Err::<(), _>(BedrockError::ConfigError("ValidationException".into())));
+        breaker.record(&Err::<(), _>(BedrockError::AuthError("AccessDeniedException".into())));
+        breaker.record(&Err::<(), _>(BedrockError::Unknown("No message in response".into())));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record(&Ok(()));
+        breaker.record(&outage());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        let disabled = CircuitBreaker::new(&CircuitBreakerSettings { enabled: false, ..Default::default() });
+        for _ in 0..10 {
+            disabled.record(&outage());
+        }
+        assert!(disabled.check().is_ok());
+    }
+}
@@ -0,0 +1,167 @@
+//! Connectivity diagnostics: one small Converse call that tells whether the
+//! configured credentials are accepted and the model answers.
+
+use aws_sdk_bedrockruntime::types::{ContentBlock, ConversationRole, Message};
+use bedrock_core::{BedrockError, TokenStatistics};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::{BedrockClient, ConverseApi};
+
+/// Prompt sent by the probe
+const PROBE_PROMPT: &str = "Hello, can you hear me?";
+
+/// Outcome of a connectivity probe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectivityReport {
+    /// Bedrock accepted the request's credentials
+    pub credentials_ok: bool,
+    /// The model answered
+    pub model_reachable: bool,
+    pub region: String,
+    pub model: String,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_usage: Option<TokenStatistics>,
+    /// The model's reply to the probe
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ConnectivityReport {
+    pub fn is_ok(&self) -> bool {
+        self.credentials_ok && self.model_reachable
+    }
+}
+
+/// Send the probe prompt to `model_id` and report what happened.
+///
+/// Errors never escape: they are classified into the report's flags and
+/// recorded in `error`.
+pub async fn probe_connectivity(api: &dyn ConverseApi, model_id: &str, region: &str) -> ConnectivityReport {
+    let message = Message::builder()
+        .role(ConversationRole::User)
+        .content(ContentBlock::Text(PROBE_PROMPT.to_string()))
+        .build()
+        .expect("role and content are set");
+
+    let started = Instant::now();
+    let outcome = api.converse(model_id, vec![message], None, None).await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let mut report = ConnectivityReport {
+        credentials_ok: false,
+        model_reachable: false,
+        region: region.to_string(),
+        model: model_id.to_string(),
+        latency_ms,
+        token_usage: None,
+        response: None,
+        error: None,
+    };
+
+    match outcome {
+        Ok(response) => {
+            report.credentials_ok = true;
+            report.model_reachable = true;
+            report.token_usage = response.token_statistics();
+            let text: Vec<&str> = response
+                .message
+                .content()
+                .iter()
+                .filter_map(|block| block.as_text().ok().map(String::as_str))
+                .collect();
+            report.response = Some(text.join("\n"));
+        }
+        Err(e) => {
+            // Only errors Bedrock itself returned show the credentials were accepted
+            let (credentials_ok, model_reachable) = match &e {
+                BedrockError::RateLimitError(_) => (true, true),
+                BedrockError::NotFound(_) | BedrockError::ConfigError(_) => (true, false),
+                _ => (false, false),
+            };
+            report.credentials_ok = credentials_ok;
+            report.model_reachable = model_reachable;
+            report.error = Some(e.to_string());
+        }
+    }
+    report
+}
+
+impl BedrockClient {
+    /// Probe the configured model in this client's region
+    pub async fn probe_connectivity(&self) -> ConnectivityReport {
+        probe_connectivity(self, &self.config.agent.model, self.get_region()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ConverseResponse;
+    use aws_sdk_bedrockruntime::types::{StopReason, TokenUsage};
+
+    struct MockModel {
+        outcome: fn() -> bedrock_core::Result<ConverseResponse>,
+    }
+
+    #[async_trait::async_trait]
+    impl ConverseApi for MockModel {
+        async fn converse(
+            &self,
+            _model_id: &str,
+            _messages: Vec<Message>,
+            _system_prompt: Option<String>,
+            _tools: Option<Vec<crate::ToolDefinition>>,
+        ) -> bedrock_core::Result<ConverseResponse> {
+            (self.outcome)()
+        }
+    }
+
+    fn greeting() -> bedrock_core::Result<ConverseResponse> {
+        Ok(ConverseResponse {
+            message: Message::builder()
+                .role(ConversationRole::Assistant)
+                .content(ContentBlock::Text("Loud and clear".to_string()))
+                .build()
+                .unwrap(),
+            stop_reason: StopReason::EndTurn,
+            usage: Some(TokenUsage::builder().input_tokens(12).output_tokens(5).total_tokens(17).build().unwrap()),
+            stop_sequence: None,
+            guardrail_action: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_successful_probe() {
+        let report = probe_connectivity(&MockModel { outcome: greeting }, "test-model", "us-east-1").await;
+
+        assert!(report.credentials_ok);
+        assert!(report.model_reachable);
+        assert!(report.is_ok());
+        assert_eq!(report.region, "us-east-1");
+        assert_eq!(report.response.as_deref(), Some("Loud and clear"));
+        assert_eq!(report.token_usage.unwrap().total_tokens, 17);
+        assert!(report.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe_records_error() {
+        let denied = MockModel { outcome: || Err(BedrockError::AuthError("AccessDeniedException".into())) };
+        let report = probe_connectivity(&denied, "test-model", "us-east-1").await;
+
+        assert!(!report.credentials_ok);
+        assert!(!report.model_reachable);
+        assert!(report.error.unwrap().contains("AccessDeniedException"));
+        assert!(report.token_usage.is_none());
+
+        // Bedrock answered, so the credentials were fine
+        let missing = MockModel { outcome: || Err(BedrockError::NotFound("no such model".into())) };
+        let report = probe_connectivity(&missing, "test-model", "us-east-1").await;
+        assert!(report.credentials_ok);
+        assert!(!report.model_reachable);
+        assert!(report.error.is_some());
+    }
+}
@@ -0,0 +1,138 @@
+//! Helpers for non-text content blocks (images and documents)
+
+use aws_sdk_bedrockruntime::primitives::Blob;
+use aws_sdk_bedrockruntime::types::{
+    ContentBlock, DocumentBlock, DocumentFormat, DocumentSource, ImageBlock, ImageFormat,
+    ImageSource,
+};
+use bedrock_core::{BedrockError, Result};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Read a local image file and wrap it in an `ImageBlock`.
+/// The SDK takes care of base64 encoding the bytes on the wire.
+pub fn image_block_from_path(path: impl AsRef<Path>) -> Result<ContentBlock> {
+    let path = path.as_ref();
+    let format = match extension(path).as_str() {
+        "png" => ImageFormat::Png,
+        "jpg" | "jpeg" => ImageFormat::Jpeg,
+        "gif" => ImageFormat::Gif,
+        "webp" => ImageFormat::Webp,
+        other => {
+            return Err(BedrockError::ConfigError(format!(
+                "Unsupported image format '{other}' for {}",
+                path.display()
+            )))
+        }
+    };
+
+    let bytes = std::fs::read(path)?;
+    let image = ImageBlock::builder()
+        .format(format)
+        .source(ImageSource::Bytes(Blob::new(bytes)))
+        .build()
+        .map_err(|e| BedrockError::Unknown(format!("Failed to build image block: {e}")))?;
+
+    Ok(ContentBlock::Image(image))
+}
+
+/// Read a local document (pdf, csv, txt, ...) and wrap it in a `DocumentBlock`.
+pub fn document_block_from_path(path: impl AsRef<Path>) -> Result<ContentBlock> {
+    let path = path.as_ref();
+    let format = match extension(path).as_str() {
+        "pdf" => DocumentFormat::Pdf,
+        "csv" => DocumentFormat::Csv,
+        "doc" => DocumentFormat::Doc,
+        "docx" => DocumentFormat::Docx,
+        "xls" => DocumentFormat::Xls,
+        "xlsx" => DocumentFormat::Xlsx,
+        "html" | "htm" => DocumentFormat::Html,
+        "txt" => DocumentFormat::Txt,
+        "md" => DocumentFormat::Md,
+        other => {
+            return Err(BedrockError::ConfigError(format!(
+                "Unsupported document format '{other}' for {}",
+                path.display()
+            )))
+        }
+    };
+
+    // Bedrock only allows alphanumerics, whitespace, hyphens, parentheses and brackets in names
+    let name: String = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document".to_string())
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '(' | ')' | '[' | ']') { c } else { '-' })
+        .collect();
+
+    let bytes = std::fs::read(path)?;
+    let document = DocumentBlock::builder()
+        .format(format)
+        .name(name)
+        .source(DocumentSource::Bytes(Blob::new(bytes)))
+        .build()
+        .map_err(|e| BedrockError::Unknown(format!("Failed to build document block: {e}")))?;
+
+    Ok(ContentBlock::Document(document))
+}
+
+/// Describe an image or document block as a small JSON placeholder for storage and display.
+/// Returns `None` for every other kind of block.
+pub fn media_placeholder(block: &ContentBlock) -> Option<Value> {
+    match block {
+        ContentBlock::Image(image) => Some(json!({
+            "type": "image",
+            "format": image.format().as_str(),
+        })),
+        ContentBlock::Document(document) => Some(json!({
+            "type": "document",
+            "format": document.format().as_str(),
+            "name": document.name(),
+        })),
+        _ => None,
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_image_block_from_png() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("diagram.png");
+        std::fs::write(&path, [0x89, b'P', b'N', b'G']).unwrap();
+
+        let block = image_block_from_path(&path).unwrap();
+        let image = block.as_image().unwrap();
+        assert_eq!(image.format(), &ImageFormat::Png);
+        match image.source() {
+            Some(ImageSource::Bytes(blob)) => assert_eq!(blob.clone().into_inner(), vec![0x89, b'P', b'N', b'G']),
+            other => panic!("unexpected image source: {other:?}"),
+        }
+
+        assert_eq!(media_placeholder(&block), Some(json!({"type": "image", "format": "png"})));
+    }
+
+    #[test]
+    fn test_unsupported_image_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("image.bmp");
+        std::fs::write(&path, b"BM").unwrap();
+
+        assert!(image_block_from_path(&path).is_err());
+    }
+
+    #[test]
+    fn test_text_has_no_placeholder() {
+        assert_eq!(media_placeholder(&ContentBlock::Text("hi".into())), None);
+    }
+}
@@ -0,0 +1,293 @@
+//! Text embeddings with retry, backoff and a fallback model
+//!
+//! Embedding calls tend to come in large batches, so throttling is expected rather
+//! than exceptional. Throttled or unavailable models are retried with exponential
+//! backoff; if the primary model still fails, the configured fallback model is tried.
+//! Every embedding records the model that produced it so token and cost accounting
+//! is charged to the right model.
+//!
+//! Requests use the Titan embeddings body format (`inputText` in, `embedding` out).
+
+use async_trait::async_trait;
+use aws_sdk_bedrockruntime::primitives::Blob;
+use bedrock_config::EmbeddingSettings;
+use bedrock_core::{BedrockError, RetryPolicy};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::{BedrockClient, RateLimiter};
+
+/// Why an embedding request failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingErrorKind {
+    /// The model is rate limiting us; worth retrying later
+    Throttled,
+    /// The model is temporarily unavailable or timed out; worth retrying
+    Unavailable,
+    /// The input was rejected (too long, empty, ...); retrying won't help
+    InvalidInput,
+    /// Missing model access or an unknown model id
+    AccessDenied,
+    Other,
+}
+
+impl EmbeddingErrorKind {
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Throttled | Self::Unavailable)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingError {
+    pub kind: EmbeddingErrorKind,
+    pub model_id: String,
+    pub message: String,
+}
+
+impl From<EmbeddingError> for BedrockError {
+    fn from(e: EmbeddingError) -> Self {
+        let message = format!("Embedding with {} failed: {}", e.model_id, e.message);
+        match e.kind {
+            EmbeddingErrorKind::Throttled => BedrockError::RateLimitError(message),
+            EmbeddingErrorKind::AccessDenied => BedrockError::AuthError(message),
+            EmbeddingErrorKind::InvalidInput => BedrockError::ConfigError(message),
+            EmbeddingErrorKind::Unavailable | EmbeddingErrorKind::Other => BedrockError::Unknown(message),
+        }
+    }
+}
+
+/// Raw output of a single embedding call
+#[derive(Debug, Clone)]
+pub struct EmbeddingOutput {
+    pub vector: Vec<f32>,
+    pub input_tokens: usize,
+}
+
+/// An embedding together with the model that actually produced it
+#[derive(Debug, Clone)]
+pub struct Embedding {
+    pub vector: Vec<f32>,
+    pub model_id: String,
+    pub input_tokens: usize,
+}
+
+/// A single embedding call against one model, without retries
+#[async_trait]
+pub trait EmbeddingApi: Send + Sync {
+    async fn invoke_embedding(&self, model_id: &str, text: &str) -> Result<EmbeddingOutput, EmbeddingError>;
+}
+
+#[async_trait]
+impl EmbeddingApi for BedrockClient {
+    async fn invoke_embedding(&self, model_id: &str, text: &str) -> Result<EmbeddingOutput, EmbeddingError> {
+        let error = |kind, message: String| EmbeddingError {
+            kind,
+            model_id: model_id.to_string(),
+            message,
+        };
+
+        let body = serde_json::to_vec(&json!({ "inputText": text }))
+            .map_err(|e| error(EmbeddingErrorKind::InvalidInput, e.to_string()))?;
+
+        let output = self.client
+            .invoke_model()
+            .model_id(model_id)
+            .content_type("application/json")
+            .accept("application/json")
+            .body(Blob::new(body))
+            .send()
+            .await
+            .map_err(|e| {
+                let kind = match e.as_service_error() {
+                    Some(se) if se.is_throttling_exception() => EmbeddingErrorKind::Throttled,
+                    Some(se) if se.is_service_unavailable_exception()
+                        || se.is_model_not_ready_exception()
+                        || se.is_model_timeout_exception()
+                        || se.is_internal_server_exception() => EmbeddingErrorKind::Unavailable,
+                    Some(se) if se.is_validation_exception() => EmbeddingErrorKind::InvalidInput,
+                    Some(se) if se.is_access_denied_exception()
+                        || se.is_resource_not_found_exception() => EmbeddingErrorKind::AccessDenied,
+                    _ => EmbeddingErrorKind::Other,
+                };
+                error(kind, e.to_string())
+            })?;
+
+        let response: Value = serde_json::from_slice(output.body().as_ref())
+            .map_err(|e| error(EmbeddingErrorKind::Other, format!("Invalid embedding response: {e}")))?;
+        let vector = response["embedding"]
+            .as_array()
+            .ok_or_else(|| error(EmbeddingErrorKind::Other, "Embedding response has no 'embedding' field".into()))?
+            .iter()
+            .filter_map(|v| v.as_f64().map(|f| f as f32))
+            .collect();
+        let input_tokens = response["inputTextTokenCount"]
+            .as_u64()
+            .map(|n| n as usize)
+            .unwrap_or_else(|| bedrock_metrics::estimate_tokens(text, model_id));
+
+        Ok(EmbeddingOutput { vector, input_tokens })
+    }
+}
+
+/// Embed one text, retrying throttled calls and falling back to the secondary model
+pub async fn embed_with_fallback(
+    api: &dyn EmbeddingApi,
+    settings: &EmbeddingSettings,
+    rate_limiter: Option<&RateLimiter>,
+    text: &str,
+) -> bedrock_core::Result<Embedding> {
+    let primary = embed_with_retries(api, settings, rate_limiter, &settings.model, text).await;
+
+    match (primary, &settings.fallback_model) {
+        (Ok(embedding), _) => Ok(embedding),
+        (Err(e), Some(fallback)) if e.kind.is_retryable() => {
+            warn!("Embedding model {} failed ({}), falling back to {}", e.model_id, e.message, fallback);
+            Ok(embed_with_retries(api, settings, rate_limiter, fallback, text).await?)
+        }
+        (Err(e), _) => Err(e.into()),
+    }
+}
+
+async fn embed_with_retries(
+    api: &dyn EmbeddingApi,
+    settings: &EmbeddingSettings,
+    rate_limiter: Option<&RateLimiter>,
+    model_id: &str,
+    text: &str,
+) -> Result<Embedding, EmbeddingError> {
+    let retry = RetryPolicy::new(settings.max_retries + 1, Duration::from_millis(settings.initial_backoff_ms))
+        .with_max_delay(Duration::from_millis(settings.max_backoff_ms));
+    let mut attempt = 0;
+    loop {
+        if let Some(limiter) = rate_limiter {
+            limiter.acquire(bedrock_metrics::estimate_tokens(text, model_id)).await;
+        }
+
+        match api.invoke_embedding(model_id, text).await {
+            Ok(output) => {
+                return Ok(Embedding {
+                    vector: output.vector,
+                    model_id: model_id.to_string(),
+                    input_tokens: output.input_tokens,
+                })
+            }
+            Err(e) if e.kind.is_retryable() && retry.allows_retry(attempt + 1) => {
+                let backoff = retry.next_delay(attempt);
+                attempt += 1;
+                debug!(
+                    "Embedding with {} failed ({:?}), retry {}/{} in {:?}",
+                    model_id, e.kind, attempt, settings.max_retries, backoff
+                );
+                tokio::time::sleep(backoff).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl BedrockClient {
+    /// Embed a text with the configured embedding model
+    pub async fn embed(&self, text: &str) -> bedrock_core::Result<Embedding> {
+        embed_with_fallback(self, &self.config.embeddings, Some(&self.rate_limiter), text).await
+    }
+
+    /// Embed several texts in order; each text falls back independently
+    pub async fn embed_many(&self, texts: &[String]) -> bedrock_core::Result<Vec<Embedding>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            embeddings.push(self.embed(text).await?);
+        }
+        Ok(embeddings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bedrock_config::{AgentConfig, ModelPricing};
+    use bedrock_core::TokenStatistics;
+    use bedrock_metrics::CostCalculator;
+    use std::sync::Mutex;
+
+    /// Throttles every call to the primary model; the fallback always succeeds
+    struct ThrottledPrimary {
+        calls: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl EmbeddingApi for ThrottledPrimary {
+        async fn invoke_embedding(&self, model_id: &str, text: &str) -> Result<EmbeddingOutput, EmbeddingError> {
+            self.calls.lock().unwrap().push(model_id.to_string());
+            if model_id == "primary" {
+                return Err(EmbeddingError {
+                    kind: EmbeddingErrorKind::Throttled,
+                    model_id: model_id.to_string(),
+                    message: "ThrottlingException".to_string(),
+                });
+            }
+            Ok(EmbeddingOutput { vector: vec![text.len() as f32, 1.0], input_tokens: 1000 })
+        }
+    }
+
+    fn settings(fallback: Option<&str>) -> EmbeddingSettings {
+        EmbeddingSettings {
+            model: "primary".to_string(),
+            fallback_model: fallback.map(String::from),
+            max_retries: 2,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 30_000,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttled_primary_falls_back() {
+        let api = ThrottledPrimary { calls: Mutex::new(Vec::new()) };
+        let settings = settings(Some("fallback"));
+
+        let mut embeddings = Vec::new();
+        for text in ["first", "second!"] {
+            embeddings.push(embed_with_fallback(&api, &settings, None, text).await.unwrap());
+        }
+
+        assert_eq!(embeddings[0].vector, vec![5.0, 1.0]);
+        assert_eq!(embeddings[1].vector, vec![7.0, 1.0]);
+        assert!(embeddings.iter().all(|e| e.model_id == "fallback"));
+
+        // The primary is retried max_retries times before each fallback
+        let calls = api.calls.lock().unwrap().clone();
+        assert_eq!(calls, ["primary", "primary", "primary", "fallback"].repeat(2));
+
+        // Cost is charged at the fallback model's price
+        let mut config = AgentConfig::default();
+        config.pricing.insert(
+            "fallback".to_string(),
+            ModelPricing { input_per_1k: 0.0001, output_per_1k: 0.0, currency: "USD".to_string(), cache_read_per_1k: None, cache_write_per_1k: None },
+        );
+        let costs = CostCalculator::from_config(&config);
+        for embedding in &embeddings {
+            costs.calculate(
+                &TokenStatistics { input_tokens: embedding.input_tokens, ..Default::default() },
+                &embedding.model_id,
+            );
+        }
+        assert!((costs.get_total_cost() - 0.0002).abs() < 1e-12);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttled_without_fallback_is_rate_limit_error() {
+        let api = ThrottledPrimary { calls: Mutex::new(Vec::new()) };
+
+        let err = embed_with_fallback(&api, &settings(None), None, "text").await.unwrap_err();
+        assert!(matches!(err, BedrockError::RateLimitError(_)));
+        assert_eq!(api.calls.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_error_classification() {
+        assert!(EmbeddingErrorKind::Throttled.is_retryable());
+        assert!(EmbeddingErrorKind::Unavailable.is_retryable());
+        assert!(!EmbeddingErrorKind::InvalidInput.is_retryable());
+        assert!(!EmbeddingErrorKind::AccessDenied.is_retryable());
+    }
+}
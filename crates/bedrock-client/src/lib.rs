@@ -1,27 +1,55 @@
+use aws_config::sts::AssumeRoleProvider;
 use aws_config::Region;
 use aws_sdk_bedrockruntime as bedrock;
+use aws_sdk_bedrockruntime::config::http::HttpResponse;
+use aws_sdk_bedrockruntime::error::{ProvideErrorMetadata, SdkError};
 use aws_sdk_bedrockruntime::types::{
-    Message, StopReason, SystemContentBlock,
+    CachePointBlock, CachePointType, ContentBlock, ConversationRole, GuardrailAction, GuardrailConfiguration,
+    GuardrailStreamConfiguration, GuardrailTrace, Message, StopReason, SystemContentBlock,
     Tool, ToolConfiguration, ToolResultBlock, ToolSpecification, ToolUseBlock,
     ToolInputSchema, ToolResultContentBlock,
 };
 use aws_smithy_types::Document;
-use bedrock_config::{AgentConfig, AwsSettings};
-use bedrock_core::{BedrockError, Result};
+use bedrock_config::{AgentConfig, AgentSettings, AwsSettings, GuardrailSettings};
+use bedrock_core::{BedrockError, Result, TokenStatistics, ToolPolicy};
+use bedrock_metrics::MetricsCollector;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, field, info, info_span, warn, Instrument};
 
 pub mod ui;
+pub mod connectivity;
+pub mod content;
+pub mod embeddings;
+pub mod circuit;
+pub mod limiter;
+pub mod profile;
+pub mod rate_limit;
+pub mod strict;
+pub mod text_tools;
 mod streaming;
-pub use ui::{display_tool_execution, display_tool_result, get_tool_display_name, get_tool_emoji};
-use streaming::process_stream_with_response;
+pub use circuit::{CircuitBreaker, CircuitPermit, CircuitState};
+pub use connectivity::{probe_connectivity, ConnectivityReport};
+pub use limiter::RequestLimiter;
+pub use rate_limit::RateLimiter;
+pub use embeddings::{Embedding, EmbeddingApi, EmbeddingError, EmbeddingErrorKind};
+pub use content::{document_block_from_path, image_block_from_path, media_placeholder};
+pub use streaming::{print_text, TextCallback};
+pub use ui::{confirm_tool_execution, display_tool_execution, display_tool_result, get_tool_display_name, get_tool_emoji};
+use streaming::stream_with_fallback;
 
 pub struct BedrockClient {
     client: bedrock::Client,
     region: Region,
     config: Arc<AgentConfig>,
+    request_limiter: RequestLimiter,
+    rate_limiter: RateLimiter,
+    circuit: CircuitBreaker,
+    /// Model requests, timed from the call to the reply with rate-limit waits
+    /// included, and executions of every tool run through `execute_tools`
+    tool_metrics: Arc<std::sync::Mutex<MetricsCollector>>,
 }
 
 // For non-streaming responses
@@ -30,6 +58,10 @@ pub struct ConverseResponse {
     pub message: Message,
     pub stop_reason: StopReason,
     pub usage: Option<bedrock::types::TokenUsage>,
+    /// The stop sequence that ended the response, when `agent.include_stop_sequence` is set
+    pub stop_sequence: Option<String>,
+    /// What the configured guardrail did; `None` when no guardrail is configured
+    pub guardrail_action: Option<GuardrailAction>,
 }
 
 impl ConverseResponse {
@@ -37,6 +69,23 @@ impl ConverseResponse {
         matches!(self.stop_reason, StopReason::ToolUse)
     }
 
+    /// The response's token counts, prompt cache reads and writes included
+    pub fn token_statistics(&self) -> Option<TokenStatistics> {
+        let usage = self.usage.as_ref()?;
+        Some(TokenStatistics {
+            input_tokens: usage.input_tokens() as usize,
+            output_tokens: usage.output_tokens() as usize,
+            total_tokens: usage.total_tokens() as usize,
+            cache_hits: usage.cache_read_input_tokens().unwrap_or(0) as usize,
+            cache_write_tokens: usage.cache_write_input_tokens().unwrap_or(0) as usize,
+        })
+    }
+
+    /// The guardrail blocked the request or the response; the message holds its canned reply
+    pub fn guardrail_intervened(&self) -> bool {
+        matches!(self.guardrail_action, Some(GuardrailAction::GuardrailIntervened))
+    }
+
     pub fn get_tool_uses(&self) -> Vec<&ToolUseBlock> {
         self.message
             .content()
@@ -59,6 +108,92 @@ impl ConverseResponse {
             .collect::<Vec<_>>()
             .join("")
     }
+
+    /// This response as a plain answer: its tool calls are dropped and the turn ends
+    pub fn into_answer(mut self) -> Result<Self> {
+        self.message = Message::builder()
+            .role(self.message.role().clone())
+            .set_content(Some(
+                self.message
+                    .content()
+                    .iter()
+                    .filter(|block| !block.is_tool_use())
+                    .cloned()
+                    .collect(),
+            ))
+            .build()
+            .map_err(|e| BedrockError::Unknown(format!("Failed to build answer message: {e}")))?;
+        self.stop_reason = StopReason::EndTurn;
+        Ok(self)
+    }
+}
+
+/// Model response field holding the matched stop sequence
+const STOP_SEQUENCE_FIELD_PATH: &str = "/stop_sequence";
+
+/// Read the raw `stop_sequence` value from a model's additional response fields
+pub(crate) fn stop_sequence_field(fields: Option<&Document>) -> Option<String> {
+    match fields? {
+        Document::Object(map) => match map.get("stop_sequence")? {
+            Document::String(sequence) => Some(sequence.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Work out which stop sequence ended a response.
+///
+/// Prefers the value reported by the model; models that don't report it can
+/// still be attributed when only one stop sequence is configured.
+fn matched_stop_sequence(
+    settings: &AgentSettings,
+    stop_reason: &StopReason,
+    reported: Option<String>,
+) -> Option<String> {
+    if !settings.include_stop_sequence || !matches!(stop_reason, StopReason::StopSequence) {
+        return None;
+    }
+
+    reported.or_else(|| match settings.stop_sequences.as_slice() {
+        [only] => Some(only.clone()),
+        _ => None,
+    })
+}
+
+/// Marks the end of a cacheable prefix (tool definitions, system prompt) of a request
+fn cache_point() -> Result<CachePointBlock> {
+    CachePointBlock::builder()
+        .r#type(CachePointType::Default)
+        .build()
+        .map_err(|e| BedrockError::Unknown(format!("Failed to build cache point: {e}")))
+}
+
+/// Whether a failed request points at Bedrock being unavailable: it timed out, got
+/// no response, or got a 5xx
+fn is_service_failure<E>(error: &SdkError<E, HttpResponse>) -> bool {
+    match error {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) => true,
+        _ => error.raw_response().is_some_and(|response| response.status().is_server_error()),
+    }
+}
+
+/// `GuardrailIntervened` when the configured guardrail stopped the response
+fn guardrail_action(guardrail: Option<&GuardrailSettings>, stop_reason: &StopReason) -> Option<GuardrailAction> {
+    guardrail?;
+    Some(if matches!(stop_reason, StopReason::GuardrailIntervened) {
+        GuardrailAction::GuardrailIntervened
+    } else {
+        GuardrailAction::None
+    })
+}
+
+fn guardrail_trace(guardrail: &GuardrailSettings) -> GuardrailTrace {
+    if guardrail.trace {
+        GuardrailTrace::Enabled
+    } else {
+        GuardrailTrace::Disabled
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,41 +203,155 @@ pub struct ToolDefinition {
     pub input_schema: Value,
 }
 
+/// The model call used by the agent loops.
+///
+/// `BedrockClient` is the real implementation; tests substitute a scripted model
+/// to drive the tool loop deterministically.
+#[async_trait::async_trait]
+pub trait ConverseApi: Send + Sync {
+    async fn converse(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ConverseResponse>;
+
+    /// `converse` with `requestMetadata` entries that override the configured ones.
+    /// Models that don't record metadata ignore them.
+    async fn converse_with_metadata(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+        _request_metadata: &HashMap<String, String>,
+    ) -> Result<ConverseResponse> {
+        self.converse(model_id, messages, system_prompt, tools).await
+    }
+
+    /// `converse_with_metadata`, passing the reply's text to `on_text` as it streams in.
+    /// Models that can't stream pass it on in one piece.
+    async fn converse_stream_with_metadata(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+        request_metadata: &HashMap<String, String>,
+        on_text: &mut TextCallback<'_>,
+    ) -> Result<ConverseResponse> {
+        let response = self.converse_with_metadata(model_id, messages, system_prompt, tools, request_metadata).await?;
+        on_text(&response.get_text_content());
+        Ok(response)
+    }
+}
 
 impl BedrockClient {
     pub async fn new(config: AgentConfig) -> Result<Self> {
+        // Bedrock rejects a profile from another geography with an unhelpful validation error
+        if let Some(problem) = profile::profile_mismatch(&config.agent.model, &config.aws.region) {
+            return Err(BedrockError::ConfigError(problem));
+        }
+        if let Some(warning) = profile::profile_warning(&config.agent.model, &config.aws.region) {
+            warn!("{warning}");
+        }
         let aws_config = Self::build_aws_config(&config.aws).await?;
-        let client = bedrock::Client::new(&aws_config);
+        Ok(Self::with_aws_config(config, &aws_config))
+    }
+
+    /// Client for `config` that talks to Bedrock with an already loaded SDK config
+    fn with_aws_config(config: AgentConfig, aws_config: &aws_config::SdkConfig) -> Self {
+        let client = bedrock::Client::new(aws_config);
         let region = aws_config.region().cloned()
             .unwrap_or_else(|| Region::new(config.aws.region.clone()));
 
-        Ok(Self {
+        let request_limiter = RequestLimiter::new(config.limits.max_concurrent_requests);
+        let rate_limiter = RateLimiter::new(config.limits.max_rpm, config.limits.max_tpm);
+        let circuit = CircuitBreaker::new(&config.circuit_breaker);
+
+        Self {
             client,
             region,
             config: Arc::new(config),
-        })
+            request_limiter,
+            rate_limiter,
+            circuit,
+            tool_metrics: Arc::new(std::sync::Mutex::new(MetricsCollector::new())),
+        }
+    }
+
+    /// State of the circuit breaker guarding model calls
+    pub fn circuit_state(&self) -> CircuitState {
+        self.circuit.state()
+    }
+
+    /// Counts, failures and durations of the model requests this client has made
+    /// and the tools it has run
+    pub fn tool_metrics(&self) -> MetricsCollector {
+        self.tool_metrics.lock().unwrap().clone()
+    }
+
+    fn record_request<T>(&self, started: std::time::Instant, result: &Result<T>) {
+        let duration_ms = started.elapsed().as_millis() as u64;
+        self.tool_metrics.lock().unwrap().record_request(duration_ms, result.is_ok());
+    }
+
+    /// `model` with its cross-region inference profile prefix corrected for `region`
+    pub fn resolve_inference_profile(model: &str, region: &str) -> String {
+        profile::resolve_inference_profile(model, region)
     }
 
     pub async fn from_config_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
-        let config = AgentConfig::from_yaml(path)?;
+        let config = AgentConfig::from_file(path)?;
         Self::new(config).await
     }
 
     async fn build_aws_config(settings: &AwsSettings) -> Result<aws_config::SdkConfig> {
-        let mut config_loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
-            .region(Region::new(settings.region.clone()));
+        let loader = || {
+            let mut config_loader = aws_config::defaults(aws_config::BehaviorVersion::latest())
+                .region(Region::new(settings.region.clone()));
+            if let Some(profile) = &settings.profile {
+                config_loader = config_loader.profile_name(profile);
+            }
+            config_loader
+        };
 
         if let Some(profile) = &settings.profile {
             info!("Using AWS profile: {}", profile);
-            config_loader = config_loader.profile_name(profile);
+        }
+        let mut aws_config = loader().load().await;
+
+        // The base credentials only authorize the AssumeRole call; the SDK's
+        // identity cache refreshes the role's credentials before they expire
+        if let Some(role_arn) = &settings.role_arn {
+            info!("Assuming AWS role: {}", role_arn);
+            let provider = Self::assume_role_provider(settings, role_arn, &aws_config).await;
+            aws_config = loader().credentials_provider(provider).load().await;
         }
 
-        let aws_config = config_loader.load().await;
-        
         debug!("AWS config loaded for region: {}", settings.region);
         Ok(aws_config)
     }
 
+    /// STS provider for `role_arn`, authorized by the credentials in `base`
+    async fn assume_role_provider(
+        settings: &AwsSettings,
+        role_arn: &str,
+        base: &aws_config::SdkConfig,
+    ) -> AssumeRoleProvider {
+        let mut builder = AssumeRoleProvider::builder(role_arn)
+            .region(Region::new(settings.region.clone()))
+            .configure(base);
+        if let Some(session_name) = &settings.role_session_name {
+            builder = builder.session_name(session_name);
+        }
+        if let Some(external_id) = &settings.external_id {
+            builder = builder.external_id(external_id);
+        }
+        builder.build().await
+    }
+
     pub async fn converse(
         &self,
         model_id: &str,
@@ -110,38 +359,66 @@ impl BedrockClient {
         system_prompt: Option<String>,
         tools: Option<Vec<ToolDefinition>>,
     ) -> Result<ConverseResponse> {
-        let mut converse_request = self.client
-            .converse()
-            .model_id(model_id)
-            .set_messages(Some(messages));
-
-        if let Some(system_prompt) = system_prompt {
-            let system_content = SystemContentBlock::Text(system_prompt);
-            converse_request = converse_request.system(system_content);
-        }
-
-        let inference_config = bedrock::types::InferenceConfiguration::builder()
-            .max_tokens(self.config.agent.max_tokens as i32)
-            .temperature(self.config.agent.temperature)
-            .build();
+        self.converse_with_metadata(model_id, messages, system_prompt, tools, &HashMap::new()).await
+    }
 
-        converse_request = converse_request.inference_config(inference_config);
+    /// `converse` with `requestMetadata` entries that override `agent.request_metadata`
+    pub async fn converse_with_metadata(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+        request_metadata: &HashMap<String, String>,
+    ) -> Result<ConverseResponse> {
+        let started = std::time::Instant::now();
+        let circuit = self.circuit.check()?;
+        let result = self.send_converse(model_id, messages, system_prompt, tools, request_metadata).await;
+        circuit.record(&result);
+        self.record_request(started, &result);
+        result
+    }
 
-        if let Some(tools) = tools {
-            let tool_config = self.build_tool_config(tools)?;
-            converse_request = converse_request.tool_config(tool_config);
-        }
+    /// The converse call itself, without the circuit breaker
+    async fn send_converse(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+        request_metadata: &HashMap<String, String>,
+    ) -> Result<ConverseResponse> {
+        let estimated_tokens =
+            rate_limit::estimate_request_tokens(model_id, &messages, system_prompt.as_deref());
+        let converse_request =
+            self.converse_request(model_id, messages, system_prompt, tools, request_metadata)?;
 
+        // Stay within the configured per-minute limits, then bound in-flight calls
+        self.rate_limiter.acquire(estimated_tokens).await;
+        let _permit = self.request_limiter.acquire().await?;
         let response = converse_request.send().await
             .map_err(|e| {
                 // Extract more detailed error information
-                let error_msg = if let Some(service_error) = e.as_service_error() {
-                    format!("Bedrock API error: {:?} - {}", service_error, e)
-                } else {
-                    format!("Bedrock API error: {}", e)
+                let Some(service_error) = e.as_service_error() else {
+                    let error_msg = format!("Bedrock API error: {}", e);
+                    error!("Bedrock converse failed: {}", error_msg);
+                    return if is_service_failure(&e) {
+                        BedrockError::ServiceUnavailable(error_msg)
+                    } else {
+                        BedrockError::Unknown(error_msg)
+                    };
                 };
+                let error_msg = format!("Bedrock API error: {:?} - {}", service_error, e);
                 error!("Bedrock converse failed: {}", error_msg);
-                BedrockError::Unknown(error_msg)
+                match service_error.code() {
+                    Some("AccessDeniedException" | "UnrecognizedClientException"
+                        | "ExpiredTokenException" | "InvalidSignatureException") => BedrockError::AuthError(error_msg),
+                    _ if service_error.is_throttling_exception() => BedrockError::RateLimitError(error_msg),
+                    _ if service_error.is_resource_not_found_exception() => BedrockError::NotFound(error_msg),
+                    _ if service_error.is_validation_exception() => BedrockError::ConfigError(error_msg),
+                    _ if is_service_failure(&e) => BedrockError::ServiceUnavailable(error_msg),
+                    _ => BedrockError::Unknown(error_msg),
+                }
             })?;
 
         let message = response.output()
@@ -151,77 +428,254 @@ impl BedrockClient {
 
         let stop_reason = response.stop_reason().clone();
         let usage = response.usage().cloned();
+        let stop_sequence = matched_stop_sequence(
+            &self.config.agent,
+            &stop_reason,
+            stop_sequence_field(response.additional_model_response_fields()),
+        );
+        if let Some(guardrail) = response.trace().and_then(|trace| trace.guardrail()) {
+            debug!("Guardrail trace: {:?}", guardrail);
+        }
+        let guardrail_action = guardrail_action(self.config.guardrail.as_ref(), &stop_reason);
 
         Ok(ConverseResponse {
             message,
             stop_reason,
             usage,
+            stop_sequence,
+            guardrail_action,
         })
     }
 
-    pub async fn converse_stream(
+    /// The converse request, with the configured and given `requestMetadata` merged
+    fn converse_request(
         &self,
         model_id: &str,
         messages: Vec<Message>,
         system_prompt: Option<String>,
         tools: Option<Vec<ToolDefinition>>,
-    ) -> Result<ConverseResponse> {
+        request_metadata: &HashMap<String, String>,
+    ) -> Result<bedrock::operation::converse::builders::ConverseFluentBuilder> {
         let mut converse_request = self.client
-            .converse_stream()
+            .converse()
             .model_id(model_id)
-            .set_messages(Some(messages));
+            .set_messages(Some(messages))
+            .set_request_metadata(self.request_metadata(request_metadata)?);
 
         if let Some(system_prompt) = system_prompt {
             let system_content = SystemContentBlock::Text(system_prompt);
             converse_request = converse_request.system(system_content);
+            if self.config.agent.enable_prompt_cache {
+                converse_request = converse_request.system(SystemContentBlock::CachePoint(cache_point()?));
+            }
+        }
+
+        converse_request = converse_request.inference_config(self.inference_config());
+
+        if self.config.agent.include_stop_sequence {
+            converse_request = converse_request.additional_model_response_field_paths(STOP_SEQUENCE_FIELD_PATH);
+        }
+
+        if let Some(tools) = tools {
+            let tool_config = self.build_tool_config(model_id, tools)?;
+            converse_request = converse_request.tool_config(tool_config);
+        }
+
+        if let Some(guardrail) = &self.config.guardrail {
+            converse_request = converse_request.guardrail_config(
+                GuardrailConfiguration::builder()
+                    .guardrail_identifier(&guardrail.id)
+                    .guardrail_version(&guardrail.version)
+                    .trace(guardrail_trace(guardrail))
+                    .build(),
+            );
         }
 
-        let inference_config = bedrock::types::InferenceConfiguration::builder()
+        Ok(converse_request)
+    }
+
+    /// `agent.request_metadata` with `overrides` applied, checked against Bedrock's limits
+    fn request_metadata(&self, overrides: &HashMap<String, String>) -> Result<Option<HashMap<String, String>>> {
+        let mut metadata = self.config.agent.request_metadata.clone();
+        metadata.extend(overrides.iter().map(|(key, value)| (key.clone(), value.clone())));
+        bedrock_config::validate_request_metadata(&metadata)?;
+        Ok((!metadata.is_empty()).then_some(metadata))
+    }
+
+    fn inference_config(&self) -> bedrock::types::InferenceConfiguration {
+        let stop_sequences = &self.config.agent.stop_sequences;
+        bedrock::types::InferenceConfiguration::builder()
             .max_tokens(self.config.agent.max_tokens as i32)
             .temperature(self.config.agent.temperature)
-            .build();
+            .set_stop_sequences((!stop_sequences.is_empty()).then(|| stop_sequences.clone()))
+            .build()
+    }
 
-        converse_request = converse_request.inference_config(inference_config);
+    /// Send a single user turn built from pre-built content blocks, e.g. text plus
+    /// an image from [`image_block_from_path`] for vision-capable models.
+    pub async fn converse_with_content(
+        &self,
+        model_id: &str,
+        content: Vec<ContentBlock>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ConverseResponse> {
+        let message = Message::builder()
+            .role(ConversationRole::User)
+            .set_content(Some(content))
+            .build()
+            .map_err(|e| BedrockError::Unknown(format!("Failed to build message: {e}")))?;
 
-        if let Some(tools) = tools {
-            let tool_config = self.build_tool_config(tools)?;
+        self.converse(model_id, vec![message], system_prompt, tools).await
+    }
+
+    /// Streaming `converse` that prints the reply as it arrives
+    pub async fn converse_stream(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ConverseResponse> {
+        self.converse_stream_with_metadata(model_id, messages, system_prompt, tools, &HashMap::new(), &mut print_text)
+            .await
+    }
+
+    /// Streaming `converse_with_metadata` that passes the reply's text to `on_text`
+    pub async fn converse_stream_with_metadata(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+        request_metadata: &HashMap<String, String>,
+        on_text: &mut TextCallback<'_>,
+    ) -> Result<ConverseResponse> {
+        let started = std::time::Instant::now();
+        let estimated_tokens =
+            rate_limit::estimate_request_tokens(model_id, &messages, system_prompt.as_deref());
+
+        let mut converse_request = self.client
+            .converse_stream()
+            .model_id(model_id)
+            .set_request_metadata(self.request_metadata(request_metadata)?);
+
+        if let Some(system_prompt) = system_prompt.clone() {
+            let system_content = SystemContentBlock::Text(system_prompt);
+            converse_request = converse_request.system(system_content);
+            if self.config.agent.enable_prompt_cache {
+                converse_request = converse_request.system(SystemContentBlock::CachePoint(cache_point()?));
+            }
+        }
+
+        converse_request = converse_request.inference_config(self.inference_config());
+
+        if self.config.agent.include_stop_sequence {
+            converse_request = converse_request.additional_model_response_field_paths(STOP_SEQUENCE_FIELD_PATH);
+        }
+
+        if let Some(tools) = tools.clone() {
+            let tool_config = self.build_tool_config(model_id, tools)?;
             converse_request = converse_request.tool_config(tool_config);
         }
 
-        let stream_output = converse_request.send().await
-            .map_err(|e| BedrockError::Unknown(format!("Bedrock streaming error: {e}")))?;
+        if let Some(guardrail) = &self.config.guardrail {
+            converse_request = converse_request.guardrail_config(
+                GuardrailStreamConfiguration::builder()
+                    .guardrail_identifier(&guardrail.id)
+                    .guardrail_version(&guardrail.version)
+                    .trace(guardrail_trace(guardrail))
+                    .build(),
+            );
+        }
 
-        // Create a stream that yields ConverseStreamOutput
-        let stream = async_stream::stream! {
-            let mut event_stream = stream_output.stream;
-            loop {
-                match event_stream.recv().await {
-                    Ok(Some(output)) => {
-                        yield Ok(output);
+        // Hold the slot until the whole stream, continuations included, has been consumed
+        self.rate_limiter.acquire(estimated_tokens).await;
+        let permit = self.request_limiter.acquire().await?;
+        let circuit = self.circuit.check()?;
+        // The fallback gives the slot back first, since `converse` takes its own; it
+        // skips the circuit breaker, which this call already went through
+        let fallback = |messages| async move {
+            drop(permit);
+            self.send_converse(model_id, messages, system_prompt, tools, request_metadata).await
+        };
+
+        let max_continuations = self.config.limits.max_stream_continuations;
+        let fallback_enabled = self.config.agent.stream_fallback;
+        let result = stream_with_fallback(messages, max_continuations, fallback_enabled, |messages| {
+            let request = converse_request.clone().set_messages(Some(messages));
+            async move {
+                let stream_output = request.send().await.map_err(|e| {
+                    let error_msg = format!("Bedrock streaming error: {e}");
+                    if is_service_failure(&e) {
+                        BedrockError::ServiceUnavailable(error_msg)
+                    } else {
+                        BedrockError::Unknown(error_msg)
                     }
-                    Ok(None) => break,
-                    Err(e) => {
-                        yield Err(e);
-                        break;
+                })?;
+
+                // Create a stream that yields ConverseStreamOutput
+                Ok(async_stream::stream! {
+                    let mut event_stream = stream_output.stream;
+                    loop {
+                        match event_stream.recv().await {
+                            Ok(Some(output)) => {
+                                yield Ok(output);
+                            }
+                            Ok(None) => break,
+                            Err(e) => {
+                                yield Err(e);
+                                break;
+                            }
+                        }
                     }
-                }
+                })
             }
-        };
-
-        // Process the stream and reconstruct the full response
-        process_stream_with_response(stream).await
+        }, fallback, on_text).await;
+        circuit.record(&result);
+        self.record_request(started, &result);
+        let mut response = result?;
+        response.stop_sequence = matched_stop_sequence(
+            &self.config.agent,
+            &response.stop_reason,
+            response.stop_sequence.take(),
+        );
+        response.guardrail_action = guardrail_action(self.config.guardrail.as_ref(), &response.stop_reason);
+        Ok(response)
     }
 
-    fn build_tool_config(&self, tools: Vec<ToolDefinition>) -> Result<ToolConfiguration> {
+    fn build_tool_config(&self, model_id: &str, tools: Vec<ToolDefinition>) -> Result<ToolConfiguration> {
         let mut tool_specs = Vec::new();
         
         info!("🔧 Building tool config for {} tools", tools.len());
         
         for tool in tools {
             debug!("Adding tool to Bedrock: {}", tool.name);
+
+            let mut schema = tool.input_schema;
+            let mut is_strict = false;
+            if self.config.tools.is_strict(&tool.name) {
+                if !strict::supports_strict_tools(model_id) {
+                    debug!("Model {} doesn't support strict tools; sending '{}' as is", model_id, tool.name);
+                } else {
+                    match strict::strict_schema(&schema) {
+                        Ok(strict_schema) => {
+                            schema = strict_schema;
+                            is_strict = true;
+                        }
+                        Err(problems) => {
+                            warn!(
+                                "Schema for tool '{}' is not strict-compatible, sending it without strict: {}",
+                                tool.name,
+                                problems.join("; ")
+                            );
+                        }
+                    }
+                }
+            }
             
             // Following reference project pattern: fallback to empty schema on conversion failure
-            let doc = match Self::json_to_document(&tool.input_schema) {
+            let doc = match Self::json_to_document(&schema) {
                 Ok(d) => {
                     debug!("Schema converted successfully for tool: {}", tool.name);
                     d
@@ -237,6 +691,7 @@ impl BedrockClient {
                 .name(tool.name.clone())
                 .description(tool.description)
                 .input_schema(ToolInputSchema::Json(doc))
+                .set_strict(is_strict.then_some(true))
                 .build()
                 .map_err(|e| BedrockError::Unknown(format!("Failed to build tool spec for '{}': {}", tool.name, e)))?;
             
@@ -244,6 +699,9 @@ impl BedrockClient {
         }
         
         info!("✅ Successfully built {} tool specifications", tool_specs.len());
+        if self.config.agent.enable_prompt_cache {
+            tool_specs.push(Tool::CachePoint(cache_point()?));
+        }
         
         ToolConfiguration::builder()
             .set_tools(Some(tool_specs))
@@ -295,7 +753,7 @@ impl BedrockClient {
         }
     }
 
-    fn document_to_json(doc: &Document) -> Result<Value> {
+    pub fn document_to_json(doc: &Document) -> Result<Value> {
         match doc {
             Document::Null => Ok(Value::Null),
             Document::Bool(b) => Ok(Value::Bool(*b)),
@@ -303,11 +761,13 @@ impl BedrockClient {
                 match n {
                     aws_smithy_types::Number::PosInt(u) => Ok(Value::Number((*u).into())),
                     aws_smithy_types::Number::NegInt(i) => Ok(Value::Number((*i).into())),
-                    aws_smithy_types::Number::Float(f) => {
-                        serde_json::Number::from_f64(*f)
-                            .map(Value::Number)
-                            .ok_or_else(|| BedrockError::Unknown("Invalid float value".into()))
-                    }
+                    // JSON has no NaN or infinity; one such value mustn't fail the whole document
+                    aws_smithy_types::Number::Float(f) => Ok(serde_json::Number::from_f64(*f)
+                        .map(Value::Number)
+                        .unwrap_or_else(|| {
+                            warn!("Replacing non-finite number {} with null", f);
+                            Value::Null
+                        })),
                 }
             }
             Document::String(s) => Ok(Value::String(s.clone())),
@@ -332,55 +792,132 @@ impl BedrockClient {
         &self,
         tool_uses: &[&ToolUseBlock],
         tool_registry: &bedrock_tools::ToolRegistry,
+        permissions: &bedrock_tools::PermissionManager,
     ) -> Result<Vec<ToolResultBlock>> {
+        let (results, _) = self
+            .execute_tools_with_cost(tool_uses, tool_registry, permissions, &ToolPolicy::All)
+            .await?;
+        Ok(results)
+    }
+
+    /// Run the tool calls like `execute_tools`, also returning what the successful
+    /// calls cost under the per-tool `cost_per_call`/`cost_per_unit`. Units are read
+    /// from the full output, before it is truncated for the model. Tools outside
+    /// `policy` are refused, and tools that call other tools run under it too.
+    pub async fn execute_tools_with_cost(
+        &self,
+        tool_uses: &[&ToolUseBlock],
+        tool_registry: &bedrock_tools::ToolRegistry,
+        permissions: &bedrock_tools::PermissionManager,
+        policy: &ToolPolicy,
+    ) -> Result<(Vec<ToolResultBlock>, f64)> {
         let mut results = Vec::new();
+        let mut cost = 0.0;
 
         for tool_use in tool_uses {
             debug!("Executing tool: {}", tool_use.name());
             
-            let result = if let Some(tool) = tool_registry.get(tool_use.name()) {
+            let result = if !self.config.tools.is_allowed(tool_use.name()) || !policy.allows(tool_use.name()) {
+                // Hidden from the model, but it may still guess the name
+                warn!("Tool '{}' is not in tools.allowed", tool_use.name());
+                Self::error_tool_result(tool_use, format!("Tool '{}' not found", tool_use.name()))?
+            } else if let Some(tool) = tool_registry.get(tool_use.name()) {
                 let input_json = Self::document_to_json(tool_use.input())?;
-                match tool.execute(input_json).await {
-                    Ok(output) => {
-                        let result_doc = Self::json_to_document(&output)?;
+                if !permissions.authorize(tool_use.name(), &input_json) {
+                    warn!("Tool '{}' was not permitted to run", tool_use.name());
+                    results.push(Self::error_tool_result(
+                        tool_use,
+                        format!("Permission denied for tool '{}'", tool_use.name()),
+                    )?);
+                    continue;
+                }
+
+                let timeout_secs = self.config.tools.timeout_secs_for(tool_use.name());
+                let span = info_span!(
+                    "tool",
+                    tool.name = tool_use.name(),
+                    tool.duration_ms = field::Empty,
+                    tool.ok = field::Empty,
+                );
+                let started = std::time::Instant::now();
+                let execution = tokio::time::timeout(
+                    std::time::Duration::from_secs(timeout_secs),
+                    bedrock_tools::with_tool_policy(policy.clone(), tool.execute(input_json)),
+                ).instrument(span.clone()).await;
+                let duration_ms = started.elapsed().as_millis() as u64;
+                let ok = matches!(execution, Ok(Ok(_)));
+                span.record("tool.duration_ms", duration_ms);
+                span.record("tool.ok", ok);
+                self.tool_metrics.lock().unwrap().record_tool_execution(tool_use.name(), duration_ms, ok);
+                match execution {
+                    Ok(Ok(output)) => {
+                        cost += self.config.tools.call_cost(tool_use.name(), &output);
+                        let content = Self::limit_tool_output(
+                            tool_use.name(),
+                            &output,
+                            self.config.tools.max_tool_output_bytes,
+                        )?;
                         ToolResultBlock::builder()
                             .tool_use_id(tool_use.tool_use_id())
-                            .content(ToolResultContentBlock::Json(result_doc))
+                            .content(content)
                             .build()
                             .map_err(|e| BedrockError::Unknown(format!("Failed to build tool result: {e}")))?
                     }
-                    Err(e) => {
-                        let error_result = json!({
-                            "error": e.to_string(),
-                            "tool": tool_use.name()
-                        });
-                        let error_doc = Self::json_to_document(&error_result)?;
-                        ToolResultBlock::builder()
-                            .tool_use_id(tool_use.tool_use_id())
-                            .content(ToolResultContentBlock::Json(error_doc))
-                            .status(bedrock::types::ToolResultStatus::Error)
-                            .build()
-                            .map_err(|e| BedrockError::Unknown(format!("Failed to build error tool result: {e}")))?
+                    Ok(Err(e)) => Self::error_tool_result(tool_use, e.to_string())?,
+                    Err(_) => {
+                        warn!("Tool '{}' timed out after {}s", tool_use.name(), timeout_secs);
+                        Self::error_tool_result(
+                            tool_use,
+                            format!("Tool '{}' timed out after {}s", tool_use.name(), timeout_secs),
+                        )?
                     }
                 }
             } else {
-                let error_result = json!({
-                    "error": format!("Tool '{}' not found", tool_use.name()),
-                    "tool": tool_use.name()
-                });
-                let error_doc = Self::json_to_document(&error_result)?;
-                ToolResultBlock::builder()
-                    .tool_use_id(tool_use.tool_use_id())
-                    .content(ToolResultContentBlock::Json(error_doc))
-                    .status(bedrock::types::ToolResultStatus::Error)
-                    .build()
-                    .map_err(|e| BedrockError::Unknown(format!("Failed to build error tool result: {e}")))?
+                Self::error_tool_result(tool_use, format!("Tool '{}' not found", tool_use.name()))?
             };
             
             results.push(result);
         }
 
-        Ok(results)
+        Ok((results, cost))
+    }
+
+    /// Tool output as result content, cut to `limit` bytes of serialized JSON when larger.
+    ///
+    /// A truncated result is sent as text ending in a marker with the full size, so the
+    /// model knows to narrow its request rather than trust a partial answer.
+    fn limit_tool_output(tool: &str, output: &Value, limit: usize) -> Result<ToolResultContentBlock> {
+        let serialized = output.to_string();
+        if serialized.len() <= limit {
+            return Ok(ToolResultContentBlock::Json(Self::json_to_document(output)?));
+        }
+
+        let mut cut = limit;
+        while !serialized.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        warn!("Output of tool '{}' truncated from {} to {} bytes", tool, serialized.len(), cut);
+        Ok(ToolResultContentBlock::Text(format!(
+            "{}...[truncated {} bytes] (full output was {} bytes; narrow the request to see the rest)",
+            &serialized[..cut],
+            serialized.len() - cut,
+            serialized.len()
+        )))
+    }
+
+    /// Build an error-status tool result so the model can see what went wrong
+    fn error_tool_result(tool_use: &ToolUseBlock, message: String) -> Result<ToolResultBlock> {
+        let error_result = json!({
+            "error": message,
+            "tool": tool_use.name()
+        });
+        let error_doc = Self::json_to_document(&error_result)?;
+        ToolResultBlock::builder()
+            .tool_use_id(tool_use.tool_use_id())
+            .content(ToolResultContentBlock::Json(error_doc))
+            .status(bedrock::types::ToolResultStatus::Error)
+            .build()
+            .map_err(|e| BedrockError::Unknown(format!("Failed to build error tool result: {e}")))
     }
 
     pub fn get_region(&self) -> &str {
@@ -392,9 +929,496 @@ impl BedrockClient {
     }
 }
 
+#[async_trait::async_trait]
+impl ConverseApi for BedrockClient {
+    async fn converse(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+    ) -> Result<ConverseResponse> {
+        BedrockClient::converse(self, model_id, messages, system_prompt, tools).await
+    }
+
+    async fn converse_with_metadata(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+        request_metadata: &HashMap<String, String>,
+    ) -> Result<ConverseResponse> {
+        BedrockClient::converse_with_metadata(self, model_id, messages, system_prompt, tools, request_metadata).await
+    }
+
+    async fn converse_stream_with_metadata(
+        &self,
+        model_id: &str,
+        messages: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+        request_metadata: &HashMap<String, String>,
+        on_text: &mut TextCallback<'_>,
+    ) -> Result<ConverseResponse> {
+        BedrockClient::converse_stream_with_metadata(self, model_id, messages, system_prompt, tools, request_metadata, on_text)
+            .await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use async_trait::async_trait;
+    use bedrock_tools::{Permission, PermissionManager, PermissionPolicy, ToolRegistry};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingTool {
+        runs: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl bedrock_tools::Tool for CountingTool {
+        fn name(&self) -> &str {
+            "execute_bash"
+        }
+
+        fn description(&self) -> &str {
+            "counts executions"
+        }
+
+        fn schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, _args: Value) -> Result<Value> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({"ok": true}))
+        }
+    }
+
+    fn tool_use() -> ToolUseBlock {
+        ToolUseBlock::builder()
+            .tool_use_id("tool_1")
+            .name("execute_bash")
+            .input(Document::Object(std::collections::HashMap::new()))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn execute_tools_respects_deny_permission() {
+        let client = BedrockClient::new(AgentConfig::default()).await.unwrap();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let registry = ToolRegistry::new();
+        registry.register(CountingTool { runs: Arc::clone(&runs) }).unwrap();
+
+        let mut permissions = PermissionManager::new().with_default(Permission::Allow);
+        permissions.add_policy(PermissionPolicy {
+            tool_name: "execute_bash".to_string(),
+            permission: Permission::Deny,
+        });
+
+        let tool_use = tool_use();
+        let results = client.execute_tools(&[&tool_use], &registry, &permissions).await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        assert_eq!(results[0].status(), Some(&bedrock::types::ToolResultStatus::Error));
+
+        // Allowed by default once the deny policy is gone
+        let permissions = PermissionManager::new().with_default(Permission::Allow);
+        let results = client.execute_tools(&[&tool_use], &registry, &permissions).await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(results[0].status(), None);
+
+        // Only the call that ran is recorded
+        let metrics = client.tool_metrics();
+        assert_eq!(metrics.tool_executions["execute_bash"].executions, 1);
+        assert_eq!(metrics.tool_executions["execute_bash"].failures, 0);
+    }
+
+    /// Fields of every span, as `(name, debug value)` in the order they were recorded
+    #[derive(Clone, Default)]
+    struct SpanFields(Arc<std::sync::Mutex<Vec<(String, String)>>>);
+
+    impl tracing::field::Visit for SpanFields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.lock().unwrap().push((field.name().to_string(), format!("{value:?}")));
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanFields {
+        fn on_new_span(&self, attrs: &tracing::span::Attributes<'_>, _: &tracing::span::Id, _: tracing_subscriber::layer::Context<'_, S>) {
+            attrs.record(&mut self.clone());
+        }
+
+        fn on_record(&self, _: &tracing::span::Id, values: &tracing::span::Record<'_>, _: tracing_subscriber::layer::Context<'_, S>) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_tools_records_a_span_per_tool() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let fields = SpanFields::default();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(fields.clone()));
+        let client = BedrockClient::new(AgentConfig::default()).await.unwrap();
+        let registry = ToolRegistry::new();
+        registry.register(CountingTool { runs: Arc::new(AtomicUsize::new(0)) }).unwrap();
+        let permissions = PermissionManager::new().with_default(Permission::Allow);
+
+        client.execute_tools(&[&tool_use()], &registry, &permissions).await.unwrap();
+
+        // The SDK's own spans come first, from loading the AWS config
+        let fields = fields.0.lock().unwrap();
+        let tool_fields: Vec<_> = fields.iter().filter(|(name, _)| name.starts_with("tool.")).collect();
+        let names: Vec<&str> = tool_fields.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["tool.name", "tool.duration_ms", "tool.ok"]);
+        assert_eq!(tool_fields[0].1, "\"execute_bash\"");
+        assert_eq!(tool_fields[2].1, "true");
+    }
+
+    #[tokio::test]
+    async fn execute_tools_refuses_tools_outside_allowlist() {
+        let mut config = AgentConfig::default();
+        config.tools.allowed = vec!["fs_read".to_string()];
+        let client = BedrockClient::new(config).await.unwrap();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let registry = ToolRegistry::new();
+        registry.register(CountingTool { runs: Arc::clone(&runs) }).unwrap();
+        let permissions = PermissionManager::new().with_default(Permission::Allow);
+
+        let tool_use = tool_use();
+        let results = client.execute_tools(&[&tool_use], &registry, &permissions).await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        assert_eq!(results[0].status(), Some(&bedrock::types::ToolResultStatus::Error));
+    }
+
+    struct HangingTool;
+
+    #[async_trait]
+    impl bedrock_tools::Tool for HangingTool {
+        fn name(&self) -> &str {
+            "execute_bash"
+        }
+
+        fn description(&self) -> &str {
+            "never finishes"
+        }
+
+        fn schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, _args: Value) -> Result<Value> {
+            tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+            Ok(json!({"ok": true}))
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_tools_times_out_hanging_tool() {
+        let mut config = AgentConfig::default();
+        config.tools.permissions.insert(
+            "execute_bash".to_string(),
+            bedrock_config::ToolPermission {
+                permission: bedrock_config::Permission::Allow,
+                constraint: None,
+                timeout_secs: Some(1),
+                strict: false,
+                cost_per_call: None,
+                cost_per_unit: None,
+                cost_unit_path: None,
+            },
+        );
+        let client = BedrockClient::new(config).await.unwrap();
+        let registry = ToolRegistry::new();
+        registry.register(HangingTool).unwrap();
+        let permissions = PermissionManager::new().with_default(Permission::Allow);
+
+        let tool_use = tool_use();
+        let results = client.execute_tools(&[&tool_use], &registry, &permissions).await.unwrap();
+
+        assert_eq!(results[0].status(), Some(&bedrock::types::ToolResultStatus::Error));
+        match &results[0].content()[0] {
+            ToolResultContentBlock::Json(doc) => {
+                let value = BedrockClient::document_to_json(doc).unwrap();
+                assert_eq!(value["error"], "Tool 'execute_bash' timed out after 1s");
+            }
+            other => panic!("unexpected tool result content: {other:?}"),
+        }
+        let metrics = &client.tool_metrics().tool_executions["execute_bash"];
+        assert_eq!(metrics.failures, 1);
+        assert!(metrics.total_duration_ms >= 1000);
+    }
+
+    struct VerboseTool;
+
+    #[async_trait]
+    impl bedrock_tools::Tool for VerboseTool {
+        fn name(&self) -> &str {
+            "fs_read"
+        }
+
+        fn description(&self) -> &str {
+            "returns a large file"
+        }
+
+        fn schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, _args: Value) -> Result<Value> {
+            Ok(json!({"content": "é".repeat(100)}))
+        }
+    }
+
+    #[tokio::test]
+    async fn execute_tools_truncates_large_output() {
+        let mut config = AgentConfig::default();
+        config.tools.max_tool_output_bytes = 51;
+        let client = BedrockClient::new(config).await.unwrap();
+        let registry = ToolRegistry::new();
+        registry.register(VerboseTool).unwrap();
+        let permissions = PermissionManager::new().with_default(Permission::Allow);
+
+        let tool_use = ToolUseBlock::builder()
+            .tool_use_id("tool_1")
+            .name("fs_read")
+            .input(Document::Object(std::collections::HashMap::new()))
+            .build()
+            .unwrap();
+        let results = client.execute_tools(&[&tool_use], &registry, &permissions).await.unwrap();
+
+        // 214 bytes serialized; byte 51 falls inside an "é", so the cut backs up to 50
+        assert_eq!(results[0].status(), None);
+        match &results[0].content()[0] {
+            ToolResultContentBlock::Text(text) => {
+                assert!(text.starts_with(r#"{"content":"éé"#));
+                assert!(text.contains("...[truncated 164 bytes] (full output was 214 bytes"), "{text}");
+            }
+            other => panic!("unexpected tool result content: {other:?}"),
+        }
+
+        // Output within the limit is passed through as JSON
+        let client = BedrockClient::new(AgentConfig::default()).await.unwrap();
+        let results = client.execute_tools(&[&tool_use], &registry, &permissions).await.unwrap();
+        assert!(matches!(results[0].content()[0], ToolResultContentBlock::Json(_)));
+    }
+
+    /// Reports a count of billable units next to a long list
+    struct BilledSearchTool;
+
+    #[async_trait]
+    impl bedrock_tools::Tool for BilledSearchTool {
+        fn name(&self) -> &str {
+            "web_search"
+        }
+
+        fn description(&self) -> &str {
+            "counts"
+        }
+
+        fn schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, _args: Value) -> Result<Value> {
+            Ok(json!({"items": "x".repeat(200), "results_count": 5}))
+        }
+    }
+
+    #[tokio::test]
+    async fn tool_cost_is_counted_on_the_untruncated_output() {
+        let mut config = AgentConfig::default();
+        config.tools.max_tool_output_bytes = 50;
+        config.tools.permissions.insert(
+            "web_search".to_string(),
+            bedrock_config::ToolPermission {
+                permission: bedrock_config::Permission::Allow,
+                constraint: None,
+                timeout_secs: None,
+                strict: false,
+                cost_per_call: Some(0.01),
+                cost_per_unit: Some(0.002),
+                cost_unit_path: Some("/results_count".to_string()),
+            },
+        );
+        let client = BedrockClient::new(config).await.unwrap();
+        let registry = ToolRegistry::new();
+        registry.register(BilledSearchTool).unwrap();
+        let permissions = PermissionManager::new().with_default(Permission::Allow);
+
+        let tool_use = ToolUseBlock::builder()
+            .tool_use_id("tool_1")
+            .name("web_search")
+            .input(Document::Object(std::collections::HashMap::new()))
+            .build()
+            .unwrap();
+        let (results, cost) = client
+            .execute_tools_with_cost(&[&tool_use], &registry, &permissions, &ToolPolicy::All)
+            .await.unwrap();
+
+        // The model only sees the start of the list, without the count
+        assert!(matches!(&results[0].content()[0], ToolResultContentBlock::Text(text) if !text.contains("results_count")));
+        assert!((cost - 0.02).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn request_metadata_is_merged_and_validated() {
+        let mut config = AgentConfig::default();
+        config.agent.request_metadata =
+            HashMap::from([("project".to_string(), "atlas".to_string()), ("team".to_string(), "infra".to_string())]);
+        let client = BedrockClient::new(config).await.unwrap();
+        let overrides = HashMap::from([("team".to_string(), "search".to_string())]);
+
+        let request = client
+            .converse_request("anthropic.claude-3-haiku-20240307-v1:0", Vec::new(), None, None, &overrides)
+            .unwrap();
+        let metadata = request.get_request_metadata().as_ref().unwrap();
+        assert_eq!(metadata["project"], "atlas");
+        assert_eq!(metadata["team"], "search");
+
+        // Rejected before anything is sent
+        let invalid = HashMap::from([("cost center!".to_string(), "research".to_string())]);
+        let err = client
+            .converse_with_metadata("anthropic.claude-3-haiku-20240307-v1:0", Vec::new(), None, None, &invalid)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BedrockError::ConfigError(message) if message.contains("unsupported character '!'")));
+    }
+
+    #[tokio::test]
+    async fn profile_from_another_region_is_rejected() {
+        let mut config = AgentConfig::default();
+        config.agent.model = "us.anthropic.claude-3-5-sonnet-20241022-v2:0".to_string();
+        config.aws.region = "eu-west-1".to_string();
+
+        let error = BedrockClient::new(config).await.err().unwrap();
+        assert!(error.to_string().contains("use eu.anthropic.claude-3-5-sonnet-20241022-v2:0 instead"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn prompt_cache_marks_system_prompt_and_tools() {
+        let mut config = AgentConfig::default();
+        config.agent.enable_prompt_cache = true;
+        let client = BedrockClient::new(config).await.unwrap();
+        let tools = vec![ToolDefinition {
+            name: "fs_read".to_string(),
+            description: "Read a file".to_string(),
+            input_schema: json!({"type": "object"}),
+        }];
+
+        let request = client
+            .converse_request(
+                "anthropic.claude-3-haiku-20240307-v1:0",
+                Vec::new(),
+                Some("You are terse".to_string()),
+                Some(tools),
+                &HashMap::new(),
+            )
+            .unwrap();
+        let system = request.get_system().as_ref().unwrap();
+        assert_eq!(system.len(), 2);
+        assert!(system[1].is_cache_point());
+        let tools = request.get_tool_config().as_ref().unwrap().tools();
+        assert!(tools[0].is_tool_spec());
+        assert!(tools.last().unwrap().is_cache_point());
+
+        let response = ConverseResponse {
+            message: Message::builder().role(ConversationRole::Assistant).content(ContentBlock::Text("ok".into())).build().unwrap(),
+            stop_reason: StopReason::EndTurn,
+            usage: Some(
+                bedrock::types::TokenUsage::builder()
+                    .input_tokens(20)
+                    .output_tokens(5)
+                    .total_tokens(1225)
+                    .cache_read_input_tokens(1200)
+                    .build()
+                    .unwrap(),
+            ),
+            stop_sequence: None,
+            guardrail_action: None,
+        };
+        let stats = response.token_statistics().unwrap();
+        assert_eq!((stats.input_tokens, stats.cache_hits, stats.cache_write_tokens), (20, 1200, 0));
+    }
+
+    #[tokio::test]
+    async fn guardrail_is_attached_and_reported() {
+        let config = AgentConfig {
+            guardrail: Some(GuardrailSettings {
+                id: "gr-123".to_string(),
+                version: "2".to_string(),
+                trace: true,
+            }),
+            ..Default::default()
+        };
+        let client = BedrockClient::new(config).await.unwrap();
+
+        let request = client
+            .converse_request("anthropic.claude-3-haiku-20240307-v1:0", Vec::new(), None, None, &HashMap::new())
+            .unwrap();
+        let guardrail = request.get_guardrail_config().as_ref().unwrap();
+        assert_eq!(guardrail.guardrail_identifier(), "gr-123");
+        assert_eq!(guardrail.guardrail_version(), "2");
+        assert_eq!(guardrail.trace(), &GuardrailTrace::Enabled);
+
+        let settings = client.config.guardrail.as_ref();
+        assert_eq!(
+            guardrail_action(settings, &StopReason::GuardrailIntervened),
+            Some(GuardrailAction::GuardrailIntervened)
+        );
+        assert_eq!(guardrail_action(settings, &StopReason::EndTurn), Some(GuardrailAction::None));
+        assert_eq!(guardrail_action(None, &StopReason::GuardrailIntervened), None);
+    }
+
+    #[tokio::test]
+    async fn build_tool_config_marks_strict_tools() {
+        let mut config = AgentConfig::default();
+        for (tool, strict) in [("fs_read", true), ("fs_write", false)] {
+            config.tools.permissions.insert(
+                tool.to_string(),
+                bedrock_config::ToolPermission {
+                    permission: bedrock_config::Permission::Allow,
+                    constraint: None,
+                    timeout_secs: None,
+                    strict,
+                    cost_per_call: None,
+                    cost_per_unit: None,
+                    cost_unit_path: None,
+                },
+            );
+        }
+        let client = BedrockClient::new(config).await.unwrap();
+        let schema = json!({"type": "object", "properties": {"path": {"type": "string"}}});
+        let tools = || {
+            ["fs_read", "fs_write"]
+                .map(|name| ToolDefinition {
+                    name: name.to_string(),
+                    description: String::new(),
+                    input_schema: schema.clone(),
+                })
+                .to_vec()
+        };
+
+        let specs = |config: ToolConfiguration| -> Vec<ToolSpecification> {
+            config.tools().iter().map(|tool| tool.as_tool_spec().unwrap().clone()).collect()
+        };
+        let built = specs(client.build_tool_config("us.anthropic.claude-sonnet-4-20250514-v1:0", tools()).unwrap());
+        assert_eq!(built[0].strict(), Some(true));
+        let Some(ToolInputSchema::Json(doc)) = built[0].input_schema() else { panic!("expected a JSON schema") };
+        let sent = BedrockClient::document_to_json(doc).unwrap();
+        assert_eq!(sent["additionalProperties"], json!(false));
+        assert_eq!(sent["required"], json!(["path"]));
+        assert_eq!(built[1].strict(), None);
+
+        // Models without strict support get the schema unchanged
+        let built = specs(client.build_tool_config("amazon.nova-pro-v1:0", tools()).unwrap());
+        assert_eq!(built[0].strict(), None);
+    }
 
     #[test]
     fn json_to_document_handles_positive_integers() {
@@ -415,4 +1439,188 @@ mod tests {
             other => panic!("expected negative integer, got {:?}", other),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn document_to_json_maps_non_finite_floats_to_null() {
+        let doc = Document::Object(HashMap::from([
+            ("nan".to_string(), Document::Number(aws_smithy_types::Number::Float(f64::NAN))),
+            ("inf".to_string(), Document::Number(aws_smithy_types::Number::Float(f64::INFINITY))),
+            ("ok".to_string(), Document::Number(aws_smithy_types::Number::Float(1.5))),
+        ]));
+
+        let value = BedrockClient::document_to_json(&doc).expect("conversion should succeed");
+        assert_eq!(value, json!({"nan": null, "inf": null, "ok": 1.5}));
+    }
+
+    #[test]
+    fn stop_sequence_is_read_from_response_fields() {
+        let mut settings = AgentConfig::default().agent;
+        settings.stop_sequences = vec!["###".into(), "END".into()];
+        settings.include_stop_sequence = true;
+
+        let fields = Document::Object(std::collections::HashMap::from([(
+            "stop_sequence".to_string(),
+            Document::String("END".into()),
+        )]));
+        let reported = stop_sequence_field(Some(&fields));
+        assert_eq!(reported.as_deref(), Some("END"));
+        assert_eq!(
+            matched_stop_sequence(&settings, &StopReason::StopSequence, reported.clone()).as_deref(),
+            Some("END")
+        );
+
+        // Only reported for stop-sequence stops, and only when enabled
+        assert_eq!(matched_stop_sequence(&settings, &StopReason::EndTurn, reported.clone()), None);
+        settings.include_stop_sequence = false;
+        assert_eq!(matched_stop_sequence(&settings, &StopReason::StopSequence, reported), None);
+    }
+
+    #[test]
+    fn single_stop_sequence_is_inferred_when_not_reported() {
+        let mut settings = AgentConfig::default().agent;
+        settings.stop_sequences = vec!["###".into()];
+        settings.include_stop_sequence = true;
+
+        assert_eq!(
+            matched_stop_sequence(&settings, &StopReason::StopSequence, None).as_deref(),
+            Some("###")
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrent_converse_calls_wait_for_max_rpm() {
+        use aws_credential_types::Credentials;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Stand-in Bedrock endpoint that records when each request arrives
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let start = tokio::time::Instant::now();
+        let arrivals = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = arrivals.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    while !String::from_utf8_lossy(&request).contains("\"messages\"") {
+                        let n = socket.read(&mut buf).await.unwrap();
+                        assert!(n > 0, "connection closed before the request body");
+                        request.extend_from_slice(&buf[..n]);
+                    }
+                    recorded.lock().unwrap().push(start.elapsed().as_secs_f64().round() as u64);
+                    let body = r#"{"output":{"message":{"role":"assistant","content":[{"text":"ok"}]}},"stopReason":"end_turn","usage":{"inputTokens":1,"outputTokens":1,"totalTokens":2},"metrics":{"latencyMs":1}}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+
+        let mut config = AgentConfig::default();
+        config.limits.max_rpm = 2;
+        config.limits.max_tpm = 0;
+        let aws_config = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .endpoint_url(&endpoint)
+            .credentials_provider(Credentials::new("AKIDTEST", "secret", None, None, "test"))
+            .load()
+            .await;
+        let client = Arc::new(BedrockClient::with_aws_config(config, &aws_config));
+
+        // Four calls at once against a limit of two per minute
+        let calls: Vec<_> = (0..4)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    let message = Message::builder()
+                        .role(ConversationRole::User)
+                        .content(ContentBlock::Text("hi".into()))
+                        .build()
+                        .unwrap();
+                    client.converse("anthropic.claude-3-haiku-20240307-v1:0", vec![message], None, None).await
+                })
+            })
+            .collect();
+        for call in calls {
+            assert_eq!(call.await.unwrap().unwrap().get_text_content(), "ok");
+        }
+
+        // A burst of two goes straight out; the other two wait for the bucket to refill
+        let mut arrivals = arrivals.lock().unwrap().clone();
+        arrivals.sort();
+        assert_eq!(arrivals, vec![0, 0, 30, 60]);
+    }
+
+    #[tokio::test]
+    async fn test_role_arn_assumes_role_through_sts() {
+        use aws_credential_types::provider::ProvideCredentials;
+        use aws_credential_types::Credentials;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Stand-in STS endpoint that hands back the role's credentials
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read the headers, then the form body they announce
+            let complete = |request: &[u8]| {
+                let text = String::from_utf8_lossy(request).to_lowercase();
+                let Some(end) = text.find("\r\n\r\n") else { return false };
+                let length = text[..end]
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .and_then(|v| v.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                request.len() >= end + 4 + length
+            };
+            while !complete(&request) {
+                let n = socket.read(&mut buf).await.unwrap();
+                assert!(n > 0, "connection closed before the request body");
+                request.extend_from_slice(&buf[..n]);
+            }
+            let body = "<AssumeRoleResponse xmlns=\"https://sts.amazonaws.com/doc/2011-06-15/\"><AssumeRoleResult>\
+                <Credentials><AccessKeyId>ASIAROLE</AccessKeyId><SecretAccessKey>role-secret</SecretAccessKey>\
+                <SessionToken>role-token</SessionToken><Expiration>2099-01-01T00:00:00Z</Expiration></Credentials>\
+                <AssumedRoleUser><Arn>arn:aws:sts::123456789012:assumed-role/bedrock/agent</Arn>\
+                <AssumedRoleId>AROAEXAMPLE:agent</AssumedRoleId></AssumedRoleUser></AssumeRoleResult></AssumeRoleResponse>";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+
+        let settings = AwsSettings {
+            region: "us-east-1".to_string(),
+            profile: None,
+            role_arn: Some("arn:aws:iam::123456789012:role/bedrock".to_string()),
+            role_session_name: Some("agent".to_string()),
+            external_id: Some("ext-42".to_string()),
+        };
+        let base = aws_config::defaults(aws_config::BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .endpoint_url(&endpoint)
+            .credentials_provider(Credentials::new("AKIDBASE", "base-secret", None, None, "test"))
+            .load()
+            .await;
+
+        let provider = BedrockClient::assume_role_provider(&settings, settings.role_arn.as_deref().unwrap(), &base).await;
+        let credentials = provider.provide_credentials().await.unwrap();
+        assert_eq!(credentials.access_key_id(), "ASIAROLE");
+        assert_eq!(credentials.session_token(), Some("role-token"));
+
+        let request = server.await.unwrap();
+        assert!(request.contains("Action=AssumeRole"));
+        assert!(request.contains("RoleArn=arn%3Aaws%3Aiam%3A%3A123456789012%3Arole%2Fbedrock"));
+        assert!(request.contains("ExternalId=ext-42"));
+        assert!(request.contains("RoleSessionName=agent"));
+    }
+}
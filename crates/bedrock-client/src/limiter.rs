@@ -0,0 +1,83 @@
+//! Global bound on in-flight model requests
+//!
+//! Every `BedrockClient` clone shares the same limiter, so the number of concurrent
+//! `converse`/`converse_stream` calls stays bounded regardless of how many tasks run.
+
+use bedrock_core::{BedrockError, Result};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Debug, Clone)]
+pub struct RequestLimiter {
+    semaphore: Arc<Semaphore>,
+    max_concurrent: usize,
+}
+
+impl RequestLimiter {
+    pub fn new(max_concurrent: usize) -> Self {
+        // A limit of zero would block every request forever
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+        }
+    }
+
+    /// Wait for a request slot. The slot is released when the permit is dropped.
+    pub async fn acquire(&self) -> Result<OwnedSemaphorePermit> {
+        Arc::clone(&self.semaphore)
+            .acquire_owned()
+            .await
+            .map_err(|e| BedrockError::Unknown(format!("Request limiter closed: {e}")))
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_requests_never_exceed_limit() {
+        let limiter = RequestLimiter::new(3);
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let limiter = limiter.clone();
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak);
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire().await.unwrap();
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        assert_eq!(limiter.available(), 3);
+    }
+
+    #[test]
+    fn test_zero_limit_is_clamped() {
+        let limiter = RequestLimiter::new(0);
+        assert_eq!(limiter.max_concurrent(), 1);
+    }
+}
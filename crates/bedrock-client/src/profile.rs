@@ -0,0 +1,142 @@
+//! Cross-region inference profiles: model IDs such as `us.anthropic.claude-...`.
+//!
+//! A profile can only be called from a region of its own geography, so a `us.`
+//! model used with `eu-west-1` fails with an opaque validation error from
+//! Bedrock. [`profile_mismatch`] catches that up front and
+//! [`resolve_inference_profile`] rewrites the ID for the configured region.
+
+/// Profile prefixes and the geography of the regions that may call them
+const PROFILE_GEOGRAPHIES: &[(&str, &str)] = &[
+    ("us-gov", "us-gov"),
+    ("us", "us"),
+    ("eu", "eu"),
+    ("apac", "apac"),
+    ("jp", "apac"),
+    ("au", "apac"),
+];
+
+/// The prefix of a regional profile `model` and the geography it belongs to.
+/// `global.` profiles and bare model IDs have none.
+fn profile_geography(model: &str) -> Option<(&str, &'static str)> {
+    let (prefix, _) = model.split_once('.')?;
+    PROFILE_GEOGRAPHIES
+        .iter()
+        .find(|(p, _)| *p == prefix)
+        .map(|(_, geography)| (prefix, *geography))
+}
+
+/// Geography of `region` for inference profiles, e.g. `eu` for `eu-west-1`
+fn region_geography(region: &str) -> Option<&'static str> {
+    if region.starts_with("us-gov-") {
+        Some("us-gov")
+    } else if region.starts_with("us-") {
+        Some("us")
+    } else if region.starts_with("eu-") {
+        Some("eu")
+    } else if region.starts_with("ap-") {
+        Some("apac")
+    } else {
+        None
+    }
+}
+
+/// Region pattern shown in errors, e.g. `ap-*` for `apac`
+fn region_pattern(geography: &str) -> &str {
+    match geography {
+        "apac" => "ap-*",
+        "us-gov" => "us-gov-*",
+        "eu" => "eu-*",
+        _ => "us-*",
+    }
+}
+
+/// `model` with its inference profile prefix corrected for `region`.
+///
+/// A bare model ID or a `global.` profile is returned unchanged. A profile for
+/// another geography gets the region's prefix, or loses its prefix when the
+/// region has no profiles of its own.
+pub fn resolve_inference_profile(model: &str, region: &str) -> String {
+    match profile_geography(model) {
+        Some((prefix, geography)) if region_geography(region) != Some(geography) => {
+            let base = &model[prefix.len() + 1..];
+            match region_geography(region) {
+                Some(region_geography) => format!("{region_geography}.{base}"),
+                None => base.to_string(),
+            }
+        }
+        _ => model.to_string(),
+    }
+}
+
+/// Why `model` can't be called from `region`, with the ID to use instead.
+/// Only a region of a known, different geography is a mismatch; see [`profile_warning`].
+pub fn profile_mismatch(model: &str, region: &str) -> Option<String> {
+    let (_, geography) = profile_geography(model)?;
+    if region_geography(region)? == geography {
+        return None;
+    }
+    Some(format!(
+        "Model {model} requires a {} region, but aws.region is {region}; use {} instead",
+        region_pattern(geography),
+        resolve_inference_profile(model, region)
+    ))
+}
+
+/// A warning for a profile `model` used from a region whose geography isn't known
+/// here, e.g. `ca-central-1`, where the call may or may not be allowed
+pub fn profile_warning(model: &str, region: &str) -> Option<String> {
+    let (_, geography) = profile_geography(model)?;
+    if region_geography(region).is_some() {
+        return None;
+    }
+    Some(format!(
+        "Model {model} is a {} inference profile and aws.region {region} is not a known source region for it; \
+         if Bedrock rejects the model ID, use a profile for {region}'s geography or the bare model ID",
+        region_pattern(geography)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SONNET: &str = "anthropic.claude-3-5-sonnet-20241022-v2:0";
+
+    #[test]
+    fn matching_and_unprefixed_models_pass() {
+        assert_eq!(profile_mismatch(&format!("us.{SONNET}"), "us-west-2"), None);
+        assert_eq!(profile_mismatch(&format!("apac.{SONNET}"), "ap-southeast-1"), None);
+        assert_eq!(profile_mismatch(&format!("global.{SONNET}"), "eu-west-1"), None);
+        assert_eq!(profile_mismatch(SONNET, "eu-west-1"), None);
+        assert_eq!(profile_mismatch("amazon.titan-embed-text-v2:0", "us-east-1"), None);
+    }
+
+    #[test]
+    fn mismatched_profile_names_the_fix() {
+        let message = profile_mismatch(&format!("us.{SONNET}"), "eu-west-1").unwrap();
+        assert_eq!(
+            message,
+            format!("Model us.{SONNET} requires a us-* region, but aws.region is eu-west-1; use eu.{SONNET} instead")
+        );
+        assert!(profile_mismatch(&format!("us.{SONNET}"), "us-gov-west-1").is_some());
+    }
+
+    #[test]
+    fn unknown_region_geography_only_warns() {
+        for region in ["ca-central-1", "sa-east-1", "me-central-1", "il-central-1"] {
+            assert_eq!(profile_mismatch(&format!("us.{SONNET}"), region), None);
+            assert!(profile_warning(&format!("us.{SONNET}"), region).unwrap().contains(region));
+        }
+        assert_eq!(profile_warning(&format!("us.{SONNET}"), "eu-west-1"), None);
+        assert_eq!(profile_warning(SONNET, "ca-central-1"), None);
+    }
+
+    #[test]
+    fn resolves_profile_for_region() {
+        assert_eq!(resolve_inference_profile(&format!("us.{SONNET}"), "eu-central-1"), format!("eu.{SONNET}"));
+        assert_eq!(resolve_inference_profile(&format!("eu.{SONNET}"), "ap-northeast-1"), format!("apac.{SONNET}"));
+        assert_eq!(resolve_inference_profile(&format!("eu.{SONNET}"), "sa-east-1"), SONNET);
+        assert_eq!(resolve_inference_profile(&format!("us.{SONNET}"), "us-east-1"), format!("us.{SONNET}"));
+        assert_eq!(resolve_inference_profile(SONNET, "eu-west-1"), SONNET);
+    }
+}
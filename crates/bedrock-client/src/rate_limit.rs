@@ -0,0 +1,194 @@
+//! Requests-per-minute and tokens-per-minute limits
+//!
+//! Both limits are token buckets that refill continuously over a minute, so a full
+//! bucket allows a burst up to the limit and sustained traffic is spaced out evenly.
+//! Token usage is estimated from the request text before it is sent.
+
+use aws_sdk_bedrockruntime::types::{ContentBlock, Message, ToolResultContentBlock};
+use bedrock_metrics::estimate_tokens;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::BedrockClient;
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn per_minute(limit: usize, now: Instant) -> Option<Self> {
+        // A limit of zero means unlimited
+        (limit > 0).then(|| Self {
+            capacity: limit as f64,
+            available: limit as f64,
+            refill_per_sec: limit as f64 / 60.0,
+            last_refill: now,
+        })
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.available = (self.available + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Time until `amount` is available; zero when it already is
+    fn wait_for(&self, amount: f64) -> Duration {
+        let missing = amount - self.available;
+        if missing <= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(missing / self.refill_per_sec)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    requests: Option<TokenBucket>,
+    tokens: Option<TokenBucket>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_rpm: usize, max_tpm: usize) -> Self {
+        let now = Instant::now();
+        Self {
+            buckets: Arc::new(Mutex::new(Buckets {
+                requests: TokenBucket::per_minute(max_rpm, now),
+                tokens: TokenBucket::per_minute(max_tpm, now),
+            })),
+        }
+    }
+
+    /// Wait until there is room for one more request of roughly `estimated_tokens`
+    pub async fn acquire(&self, estimated_tokens: usize) {
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().await;
+                let now = Instant::now();
+
+                // A single request larger than the whole budget would never fit
+                let tokens_needed = buckets
+                    .tokens
+                    .as_ref()
+                    .map(|b| (estimated_tokens as f64).min(b.capacity))
+                    .unwrap_or(0.0);
+
+                let mut wait = Duration::ZERO;
+                if let Some(bucket) = buckets.requests.as_mut() {
+                    bucket.refill(now);
+                    wait = wait.max(bucket.wait_for(1.0));
+                }
+                if let Some(bucket) = buckets.tokens.as_mut() {
+                    bucket.refill(now);
+                    wait = wait.max(bucket.wait_for(tokens_needed));
+                }
+
+                if wait.is_zero() {
+                    if let Some(bucket) = buckets.requests.as_mut() {
+                        bucket.available -= 1.0;
+                    }
+                    if let Some(bucket) = buckets.tokens.as_mut() {
+                        bucket.available -= tokens_needed;
+                    }
+                    return;
+                }
+                wait
+            };
+
+            debug!("Rate limit reached, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Rough input token count of a request, used to charge the tokens-per-minute bucket
+pub fn estimate_request_tokens(model_id: &str, messages: &[Message], system_prompt: Option<&str>) -> usize {
+    let mut text = system_prompt.unwrap_or_default().to_string();
+    for message in messages {
+        for block in message.content() {
+            match block {
+                ContentBlock::Text(t) => text.push_str(t),
+                ContentBlock::ToolUse(tool_use) => {
+                    if let Ok(input) = BedrockClient::document_to_json(tool_use.input()) {
+                        text.push_str(&input.to_string());
+                    }
+                }
+                ContentBlock::ToolResult(result) => {
+                    for content in result.content() {
+                        match content {
+                            ToolResultContentBlock::Text(t) => text.push_str(t),
+                            ToolResultContentBlock::Json(doc) => {
+                                if let Ok(json) = BedrockClient::document_to_json(doc) {
+                                    text.push_str(&json.to_string());
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    estimate_tokens(&text, model_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_bedrockruntime::types::ConversationRole;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_spaced_by_max_rpm() {
+        // 2 requests per minute: a burst of two, then one every 30 seconds
+        let limiter = RateLimiter::new(2, 0);
+        let start = Instant::now();
+
+        let mut elapsed = Vec::new();
+        for _ in 0..5 {
+            limiter.acquire(0).await;
+            elapsed.push(start.elapsed().as_secs_f64().round() as u64);
+        }
+
+        assert_eq!(elapsed, vec![0, 0, 30, 60, 90]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tokens_per_minute_limit() {
+        let limiter = RateLimiter::new(0, 1200);
+        let start = Instant::now();
+
+        limiter.acquire(1000).await;
+        // Only 200 tokens left; 600 more refill in 30 seconds
+        limiter.acquire(800).await;
+        assert_eq!(start.elapsed().as_secs_f64().round(), 30.0);
+
+        // Oversized requests are capped at the bucket size instead of waiting forever
+        limiter.acquire(10_000).await;
+        assert_eq!(start.elapsed().as_secs_f64().round(), 90.0);
+    }
+
+    #[test]
+    fn test_estimate_request_tokens() {
+        let message = Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::Text("a".repeat(40)))
+            .build()
+            .unwrap();
+
+        assert_eq!(estimate_request_tokens("titan", &[message], Some("b".repeat(40).as_str())), 20);
+    }
+}
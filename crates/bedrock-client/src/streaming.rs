@@ -6,14 +6,46 @@ use aws_smithy_types::Document;
 use bedrock_core::{BedrockError, Result};
 use std::collections::HashMap;
 use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::{debug, warn};
 
-use crate::{ConverseResponse, BedrockClient};
+use crate::{stop_sequence_field, ConverseResponse, BedrockClient};
 
-/// Process a streaming response and reconstruct the full message
+/// How a response stream ended
+#[derive(Debug)]
+pub enum StreamOutcome {
+    /// The model finished its message
+    Complete(ConverseResponse),
+    /// The stream was cut off before the message finished
+    Interrupted(PartialResponse),
+}
+
+/// What an interrupted stream had produced, enough to ask the model to continue it
+#[derive(Debug, Clone, Default)]
+pub struct PartialResponse {
+    /// Text streamed before the cut
+    pub text: String,
+    /// A tool use had started; such a response can't be continued from text
+    pub has_tool_use: bool,
+    /// Why the stream ended early
+    pub error: String,
+}
+
+/// Receives a streamed reply's text as it arrives
+pub type TextCallback<'a> = dyn FnMut(&str) + Send + 'a;
+
+/// Text callback that prints to stdout as the text arrives
+pub fn print_text(text: &str) {
+    print!("{text}");
+    io::stdout().flush().ok();
+}
+
+/// Process a streaming response and reconstruct the full message, passing its
+/// text to `on_text` as it arrives
 pub async fn process_stream_with_response<E>(
     stream: impl tokio_stream::Stream<Item = std::result::Result<ConverseStreamOutput, E>>,
-) -> Result<ConverseResponse> 
+    on_text: &mut TextCallback<'_>,
+) -> Result<StreamOutcome> 
 where
     E: std::fmt::Display,
 {
@@ -23,6 +55,8 @@ where
     let mut accumulated_text = String::new();
     let mut stop_reason = StopReason::EndTurn;
     let mut token_usage: Option<TokenUsage> = None;
+    let mut stop_sequence: Option<String> = None;
+    let mut message_stopped = false;
     
     // For tool use accumulation
     let mut current_tool_name: Option<String> = None;
@@ -73,8 +107,7 @@ where
                                     }
                                 }
                                 
-                                print!("{filtered_text}");
-                                io::stdout().flush().ok();
+                                on_text(&filtered_text);
                                 accumulated_text.push_str(text); // Keep original for response
                             } else if let Ok(tool_use) = delta.as_tool_use() {
                                 // Accumulate tool input JSON as it streams
@@ -141,6 +174,8 @@ where
                         debug!("Streaming completed with stop reason: {:?}", stop.stop_reason());
                         
                         stop_reason = stop.stop_reason().clone();
+                        message_stopped = true;
+                        stop_sequence = stop_sequence_field(stop.additional_model_response_fields());
                         
                         // Add any remaining text content
                         if !accumulated_text.is_empty() {
@@ -177,13 +212,33 @@ where
                     }
                 }
             }
+            Err(e) if message_stopped => {
+                // Only the trailing metadata was lost; the message itself is whole
+                warn!("Stream error after the message finished: {}", e);
+                break;
+            }
             Err(e) => {
                 warn!("Stream error: {}", e);
-                return Err(BedrockError::Unknown(format!("Stream error: {e}")));
+                return Ok(StreamOutcome::Interrupted(partial_response(
+                    &collected_content,
+                    &accumulated_text,
+                    current_tool_name.is_some(),
+                    format!("Stream error: {e}"),
+                )));
             }
         }
     }
 
+    if !message_stopped {
+        warn!("Stream ended before the message finished");
+        return Ok(StreamOutcome::Interrupted(partial_response(
+            &collected_content,
+            &accumulated_text,
+            current_tool_name.is_some(),
+            "Stream ended before the message finished".to_string(),
+        )));
+    }
+
     // Build the message from collected content
     let message = Message::builder()
         .role(ConversationRole::Assistant)
@@ -195,10 +250,384 @@ where
         .build()
         .map_err(|e| BedrockError::Unknown(format!("Failed to build message: {e}")))?;
 
-    Ok(ConverseResponse {
+    Ok(StreamOutcome::Complete(ConverseResponse {
         message,
         stop_reason,
         usage: token_usage,
-    })
+        stop_sequence,
+        guardrail_action: None,
+    }))
+}
+
+fn partial_response(
+    collected_content: &[ContentBlock],
+    pending_text: &str,
+    tool_in_progress: bool,
+    error: String,
+) -> PartialResponse {
+    let mut text: String = collected_content
+        .iter()
+        .filter_map(|block| block.as_text().ok())
+        .map(String::as_str)
+        .collect();
+    text.push_str(pending_text);
+    PartialResponse {
+        text,
+        has_tool_use: tool_in_progress
+            || collected_content.iter().any(|block| matches!(block, ContentBlock::ToolUse(_))),
+        error,
+    }
 }
 
+/// Run a streaming request, resuming it when the stream is cut off.
+///
+/// `open` sends the request for the given messages and returns its event stream.
+/// After an interruption the partial text is sent back as the start of the
+/// assistant's turn and the model continues from there; the continuations are
+/// stitched onto the partial text. At most `max_continuations` are attempted, and
+/// responses cut off during a tool use aren't continued.
+pub async fn stream_with_continuation<F, Fut, S, E>(
+    messages: Vec<Message>,
+    max_continuations: usize,
+    mut open: F,
+    on_text: &mut TextCallback<'_>,
+) -> Result<ConverseResponse>
+where
+    F: FnMut(Vec<Message>) -> Fut,
+    Fut: std::future::Future<Output = Result<S>>,
+    S: tokio_stream::Stream<Item = std::result::Result<ConverseStreamOutput, E>>,
+    E: std::fmt::Display,
+{
+    let mut partial_text = String::new();
+    let mut usage: Option<TokenUsage> = None;
+    let mut continuations = 0;
+
+    loop {
+        let mut request = messages.clone();
+        if !partial_text.is_empty() {
+            request.push(
+                Message::builder()
+                    .role(ConversationRole::Assistant)
+                    .content(ContentBlock::Text(partial_text.clone()))
+                    .build()
+                    .map_err(|e| BedrockError::Unknown(format!("Failed to build message: {e}")))?,
+            );
+        }
+
+        match process_stream_with_response(open(request).await?, on_text).await? {
+            StreamOutcome::Complete(mut response) => {
+                if let Some(last) = response.usage.take() {
+                    usage = Some(add_usage(usage.as_ref(), &last)?);
+                }
+                response.usage = usage;
+                if !partial_text.is_empty() {
+                    response.message = prepend_text(&response.message, &partial_text)?;
+                }
+                return Ok(response);
+            }
+            StreamOutcome::Interrupted(partial) => {
+                if partial.has_tool_use {
+                    return Err(BedrockError::Unknown(format!(
+                        "{} during a tool use; not continuing",
+                        partial.error
+                    )));
+                }
+                if continuations >= max_continuations {
+                    return Err(BedrockError::Unknown(format!(
+                        "{} after {} continuation(s)",
+                        partial.error, continuations
+                    )));
+                }
+                continuations += 1;
+                partial_text.push_str(&partial.text);
+                // Models reject an assistant turn that ends in whitespace; the
+                // continuation brings it back
+                partial_text.truncate(partial_text.trim_end().len());
+                warn!(
+                    "{}; continuing from {} characters of partial output ({}/{})",
+                    partial.error,
+                    partial_text.len(),
+                    continuations,
+                    max_continuations
+                );
+            }
+        }
+    }
+}
+
+/// Run a streaming request, answering it without streaming when the stream can't be opened.
+///
+/// Only a failure to open the first stream falls back to `fallback`, and only when
+/// `fallback_enabled`; errors after events have arrived are returned as they are.
+/// The fallback's answer goes to `on_text` in one piece, where the stream would have
+/// passed it on as it arrived.
+pub async fn stream_with_fallback<F, Fut, S, E, B, BFut>(
+    messages: Vec<Message>,
+    max_continuations: usize,
+    fallback_enabled: bool,
+    mut open: F,
+    fallback: B,
+    on_text: &mut TextCallback<'_>,
+) -> Result<ConverseResponse>
+where
+    F: FnMut(Vec<Message>) -> Fut,
+    Fut: std::future::Future<Output = Result<S>>,
+    S: tokio_stream::Stream<Item = std::result::Result<ConverseStreamOutput, E>>,
+    E: std::fmt::Display,
+    B: FnOnce(Vec<Message>) -> BFut,
+    BFut: std::future::Future<Output = Result<ConverseResponse>>,
+{
+    let opened = AtomicBool::new(false);
+    let streamed = stream_with_continuation(messages.clone(), max_continuations, |request| {
+        let opening = open(request);
+        let opened = &opened;
+        async move {
+            let stream = opening.await?;
+            opened.store(true, Ordering::Relaxed);
+            Ok(stream)
+        }
+    }, on_text)
+    .await;
+
+    match streamed {
+        Err(e) if fallback_enabled && !opened.load(Ordering::Relaxed) => {
+            warn!("Could not open the response stream ({}); retrying without streaming", e);
+            let response = fallback(messages).await?;
+            on_text(&response.get_text_content());
+            Ok(response)
+        }
+        streamed => streamed,
+    }
+}
+
+/// The message with `text` joined onto the front of its first text block
+fn prepend_text(message: &Message, text: &str) -> Result<Message> {
+    let mut content = message.content().to_vec();
+    match content.first_mut() {
+        Some(ContentBlock::Text(first)) => first.insert_str(0, text),
+        _ => content.insert(0, ContentBlock::Text(text.to_string())),
+    }
+    Message::builder()
+        .role(message.role().clone())
+        .set_content(Some(content))
+        .build()
+        .map_err(|e| BedrockError::Unknown(format!("Failed to build message: {e}")))
+}
+
+fn add_usage(total: Option<&TokenUsage>, usage: &TokenUsage) -> Result<TokenUsage> {
+    let Some(total) = total else {
+        return Ok(usage.clone());
+    };
+    TokenUsage::builder()
+        .input_tokens(total.input_tokens() + usage.input_tokens())
+        .output_tokens(total.output_tokens() + usage.output_tokens())
+        .total_tokens(total.total_tokens() + usage.total_tokens())
+        .build()
+        .map_err(|e| BedrockError::Unknown(format!("Failed to build token usage: {e}")))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_sdk_bedrockruntime::types::{
+        ContentBlockDelta, ContentBlockDeltaEvent, ContentBlockStart, ContentBlockStartEvent,
+        ContentBlockStopEvent, MessageStopEvent, ToolUseBlockStart,
+    };
+    use std::sync::Mutex;
+
+    type Event = std::result::Result<ConverseStreamOutput, String>;
+
+    fn text(chunk: &str) -> Event {
+        Ok(ConverseStreamOutput::ContentBlockDelta(
+            ContentBlockDeltaEvent::builder()
+                .content_block_index(0)
+                .delta(ContentBlockDelta::Text(chunk.to_string()))
+                .build()
+                .unwrap(),
+        ))
+    }
+
+    fn block_stop() -> Event {
+        Ok(ConverseStreamOutput::ContentBlockStop(
+            ContentBlockStopEvent::builder().content_block_index(0).build().unwrap(),
+        ))
+    }
+
+    fn message_stop() -> Event {
+        Ok(ConverseStreamOutput::MessageStop(
+            MessageStopEvent::builder().stop_reason(StopReason::EndTurn).build().unwrap(),
+        ))
+    }
+
+    fn tool_start() -> Event {
+        Ok(ConverseStreamOutput::ContentBlockStart(
+            ContentBlockStartEvent::builder()
+                .content_block_index(1)
+                .start(ContentBlockStart::ToolUse(
+                    ToolUseBlockStart::builder().tool_use_id("call-1").name("grep").build().unwrap(),
+                ))
+                .build()
+                .unwrap(),
+        ))
+    }
+
+    fn cut() -> Event {
+        Err("connection reset".to_string())
+    }
+
+    fn user_message(text: &str) -> Message {
+        Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::Text(text.to_string()))
+            .build()
+            .unwrap()
+    }
+
+    /// Plays one scripted stream per request and records the requests
+    struct ScriptedStreams {
+        streams: Mutex<Vec<Vec<Event>>>,
+        requests: Mutex<Vec<Vec<Message>>>,
+    }
+
+    impl ScriptedStreams {
+        fn new(mut streams: Vec<Vec<Event>>) -> Self {
+            streams.reverse();
+            Self { streams: Mutex::new(streams), requests: Mutex::new(Vec::new()) }
+        }
+
+        async fn open(&self, messages: Vec<Message>) -> Result<impl tokio_stream::Stream<Item = Event>> {
+            self.requests.lock().unwrap().push(messages);
+            let events = self.streams.lock().unwrap().pop().expect("no stream left");
+            Ok(tokio_stream::iter(events))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_stream_is_continued() {
+        let streams = ScriptedStreams::new(vec![
+            vec![text("The quick brown "), text("fox"), cut()],
+            vec![text(" jumps over "), cut()],
+            vec![text(" the lazy dog."), block_stop(), message_stop()],
+        ]);
+
+        let response = stream_with_continuation(vec![user_message("Finish the pangram")], 2, |messages| {
+            streams.open(messages)
+        }, &mut |_| {})
+        .await
+        .unwrap();
+
+        assert_eq!(response.get_text_content(), "The quick brown fox jumps over the lazy dog.");
+        assert_eq!(response.message.content().len(), 1);
+
+        // Each continuation resends the prompt with the output so far as the assistant's turn
+        let requests = streams.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].len(), 1);
+        let prefill = requests[2].last().unwrap();
+        assert_eq!(prefill.role(), &ConversationRole::Assistant);
+        assert_eq!(prefill.content()[0].as_text().unwrap(), "The quick brown fox jumps over");
+    }
+
+    #[tokio::test]
+    async fn test_continuations_are_bounded() {
+        let streams = ScriptedStreams::new(vec![
+            vec![text("one"), cut()],
+            vec![text(" two"), cut()],
+        ]);
+
+        let err = stream_with_continuation(vec![user_message("Count")], 1, |messages| streams.open(messages), &mut |_| {})
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("after 1 continuation(s)"));
+        assert_eq!(streams.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_cut_during_tool_use_is_not_continued() {
+        let streams = ScriptedStreams::new(vec![vec![text("Let me search"), block_stop(), tool_start(), cut()]]);
+
+        let err = stream_with_continuation(vec![user_message("Find it")], 3, |messages| streams.open(messages), &mut |_| {})
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("during a tool use"));
+        assert_eq!(streams.requests.lock().unwrap().len(), 1);
+    }
+
+    fn answer(text: &str) -> ConverseResponse {
+        ConverseResponse {
+            message: Message::builder()
+                .role(ConversationRole::Assistant)
+                .content(ContentBlock::Text(text.to_string()))
+                .build()
+                .unwrap(),
+            stop_reason: StopReason::EndTurn,
+            usage: None,
+            stop_sequence: None,
+            guardrail_action: None,
+        }
+    }
+
+    async fn refuse_stream(_messages: Vec<Message>) -> Result<tokio_stream::Iter<std::vec::IntoIter<Event>>> {
+        Err(BedrockError::Unknown("Bedrock streaming error: connection refused".into()))
+    }
+
+    #[tokio::test]
+    async fn test_stream_init_failure_falls_back_to_converse() {
+        let fallback_requests = Mutex::new(Vec::new());
+        let mut shown = String::new();
+
+        let response = stream_with_fallback(vec![user_message("Say hi")], 2, true, refuse_stream, |messages| {
+            fallback_requests.lock().unwrap().push(messages);
+            async { Ok(answer("Hi there")) }
+        }, &mut |text| shown.push_str(text))
+        .await
+        .unwrap();
+
+        assert_eq!(response.get_text_content(), "Hi there");
+        assert_eq!(shown, "Hi there");
+        assert_eq!(response.stop_reason, StopReason::EndTurn);
+        let fallback_requests = fallback_requests.lock().unwrap();
+        assert_eq!(fallback_requests.len(), 1);
+        assert_eq!(fallback_requests[0][0].content()[0].as_text().unwrap(), "Say hi");
+    }
+
+    #[tokio::test]
+    async fn test_stream_failures_without_fallback_are_returned() {
+        let unused = |_| async { panic!("fallback should not run") };
+        let err = stream_with_fallback(vec![user_message("Say hi")], 2, false, refuse_stream, unused, &mut |_| {})
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("connection refused"));
+
+        // A stream that opened and then failed already showed output, so it isn't retried
+        let streams = ScriptedStreams::new(vec![vec![text("Let me search"), block_stop(), tool_start(), cut()]]);
+        let unused = |_| async { panic!("fallback should not run") };
+        let err = stream_with_fallback(vec![user_message("Find it")], 3, true, |messages| streams.open(messages), unused, &mut |_| {})
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("during a tool use"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_message_stop_is_interrupted() {
+        let mut shown = String::new();
+        let outcome = process_stream_with_response(
+            tokio_stream::iter(vec![text("par"), text("tial"), block_stop()]),
+            &mut |text| shown.push_str(text),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(shown, "partial");
+        match outcome {
+            StreamOutcome::Interrupted(partial) => {
+                assert_eq!(partial.text, "partial");
+                assert!(!partial.has_tool_use);
+            }
+            StreamOutcome::Complete(_) => panic!("stream without MessageStop should be interrupted"),
+        }
+    }
+}
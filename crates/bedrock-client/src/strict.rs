@@ -0,0 +1,151 @@
+//! Strict tool schemas: the model's tool input must match the schema exactly.
+//!
+//! Strict mode needs every object to list all of its properties as required and
+//! to forbid additional properties. [`strict_schema`] rewrites a schema that way,
+//! turning optional properties into nullable required ones, and reports anything
+//! it can't express.
+
+use serde_json::{json, Map, Value};
+
+/// Keywords strict mode doesn't accept
+const UNSUPPORTED_KEYWORDS: &[&str] = &["patternProperties", "dependencies", "dependentSchemas", "if", "not"];
+
+/// Whether the model accepts `strict` on tool specifications
+pub fn supports_strict_tools(model_id: &str) -> bool {
+    ["claude-sonnet-4", "claude-opus-4", "claude-haiku-4"]
+        .iter()
+        .any(|family| model_id.contains(&format!("anthropic.{family}")))
+}
+
+/// `schema` rewritten for strict mode, or the reasons it can't be
+pub fn strict_schema(schema: &Value) -> Result<Value, Vec<String>> {
+    if schema.get("type").and_then(Value::as_str) != Some("object") {
+        return Err(vec!["the top-level schema must be an object".to_string()]);
+    }
+    let mut strict = schema.clone();
+    let mut problems = Vec::new();
+    make_strict(&mut strict, "$", &mut problems);
+    if problems.is_empty() {
+        Ok(strict)
+    } else {
+        Err(problems)
+    }
+}
+
+fn make_strict(schema: &mut Value, path: &str, problems: &mut Vec<String>) {
+    let Some(object) = schema.as_object_mut() else { return };
+
+    for keyword in UNSUPPORTED_KEYWORDS {
+        if object.contains_key(*keyword) {
+            problems.push(format!("{path}: '{keyword}' is not supported in strict mode"));
+        }
+    }
+
+    if object.get("type").and_then(Value::as_str) == Some("object") {
+        match object.get("additionalProperties") {
+            None | Some(Value::Bool(false)) => {}
+            Some(_) => problems.push(format!("{path}: objects can't allow additional properties")),
+        }
+        object.insert("additionalProperties".to_string(), Value::Bool(false));
+
+        let required: Vec<String> = object
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).map(str::to_string).collect())
+            .unwrap_or_default();
+        let properties = object
+            .entry("properties")
+            .or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut();
+        if let Some(properties) = properties {
+            for (name, property) in properties.iter_mut() {
+                make_strict(property, &format!("{path}.{name}"), problems);
+                if !required.contains(name) {
+                    make_nullable(property);
+                }
+            }
+            let all: Vec<Value> = properties.keys().map(|name| json!(name)).collect();
+            object.insert("required".to_string(), Value::Array(all));
+        }
+    }
+
+    if let Some(items) = object.get_mut("items") {
+        make_strict(items, &format!("{path}[]"), problems);
+    }
+    for keyword in ["anyOf", "oneOf", "allOf"] {
+        if let Some(Value::Array(variants)) = object.get_mut(keyword) {
+            for (i, variant) in variants.iter_mut().enumerate() {
+                make_strict(variant, &format!("{path}.{keyword}[{i}]"), problems);
+            }
+        }
+    }
+}
+
+/// Let a formerly optional property be `null`, since strict mode requires it to be present
+fn make_nullable(property: &mut Value) {
+    let Some(object) = property.as_object_mut() else { return };
+    match object.get_mut("type") {
+        Some(Value::String(ty)) if ty != "null" => {
+            let ty = std::mem::take(ty);
+            object.insert("type".to_string(), json!([ty, "null"]));
+        }
+        Some(Value::Array(types)) if !types.contains(&json!("null")) => types.push(json!("null")),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_made_strict() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "path": {"type": "string"},
+                "limit": {"type": "integer"},
+                "filter": {
+                    "type": "object",
+                    "properties": {"glob": {"type": "string"}},
+                },
+            },
+            "required": ["path"],
+        });
+
+        let strict = strict_schema(&schema).unwrap();
+
+        assert_eq!(strict["additionalProperties"], json!(false));
+        assert_eq!(strict["required"], json!(["filter", "limit", "path"]));
+        assert_eq!(strict["properties"]["path"]["type"], json!("string"));
+        assert_eq!(strict["properties"]["limit"]["type"], json!(["integer", "null"]));
+        assert_eq!(strict["properties"]["filter"]["type"], json!(["object", "null"]));
+        assert_eq!(strict["properties"]["filter"]["additionalProperties"], json!(false));
+        assert_eq!(strict["properties"]["filter"]["required"], json!(["glob"]));
+    }
+
+    #[test]
+    fn test_incompatible_schema_is_reported() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "env": {"type": "object", "additionalProperties": {"type": "string"}},
+                "name": {"type": "string", "not": {"const": "root"}},
+            },
+        });
+
+        let problems = strict_schema(&schema).unwrap_err();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("$.env:"));
+        assert!(problems[1].contains("'not'"));
+
+        assert!(strict_schema(&json!({"type": "string"})).is_err());
+    }
+
+    #[test]
+    fn test_strict_support_by_model() {
+        assert!(supports_strict_tools("us.anthropic.claude-sonnet-4-20250514-v1:0"));
+        assert!(!supports_strict_tools("us.anthropic.claude-3-5-sonnet-20241022-v2:0"));
+        assert!(!supports_strict_tools("amazon.nova-pro-v1:0"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Text protocol for tool calling with models that have no native tool support
+//!
+//! Tool descriptions go into the system prompt and the model is asked to emit
+//! `<tool_call>{"name": ..., "input": {...}}</tool_call>` directives in its reply.
+//! Calls are turned into synthetic `ToolUseBlock`s so they run through the normal
+//! `execute_tools` path, and the results are sent back as plain text.
+
+use aws_sdk_bedrockruntime::types::{
+    ToolResultBlock, ToolResultContentBlock, ToolResultStatus, ToolUseBlock,
+};
+use bedrock_core::{BedrockError, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{BedrockClient, ToolDefinition};
+
+const CALL_OPEN: &str = "<tool_call>";
+const CALL_CLOSE: &str = "</tool_call>";
+
+/// A tool call parsed from model text
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TextToolCall {
+    pub name: String,
+    #[serde(default = "empty_input")]
+    pub input: Value,
+}
+
+fn empty_input() -> Value {
+    Value::Object(Default::default())
+}
+
+/// Describe the available tools and the call convention for the system prompt
+pub fn tool_protocol_prompt(tools: &[ToolDefinition]) -> String {
+    let mut prompt = String::from(
+        "You can use the following tools. To call a tool, reply with one or more blocks of the form\n\
+         <tool_call>{\"name\": \"tool_name\", \"input\": {...}}</tool_call>\n\
+         and nothing else. The results will be sent back in <tool_result> blocks. \
+         When you have enough information, answer without any <tool_call> blocks.\n\nTools:\n",
+    );
+    for tool in tools {
+        prompt.push_str(&format!(
+            "- {}: {}\n  input schema: {}\n",
+            tool.name, tool.description, tool.input_schema
+        ));
+    }
+    prompt
+}
+
+/// Extract every well-formed tool call directive from the model's text.
+/// Directives whose body isn't valid JSON are skipped.
+pub fn parse_tool_calls(text: &str) -> Vec<TextToolCall> {
+    let mut calls = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(CALL_OPEN) {
+        let after_open = &rest[start + CALL_OPEN.len()..];
+        let Some(end) = after_open.find(CALL_CLOSE) else { break };
+
+        if let Ok(call) = serde_json::from_str::<TextToolCall>(after_open[..end].trim()) {
+            calls.push(call);
+        }
+        rest = &after_open[end + CALL_CLOSE.len()..];
+    }
+
+    calls
+}
+
+/// Convert parsed calls into tool use blocks so they can go through `execute_tools`
+pub fn tool_use_blocks(calls: &[TextToolCall]) -> Result<Vec<ToolUseBlock>> {
+    calls
+        .iter()
+        .enumerate()
+        .map(|(i, call)| {
+            ToolUseBlock::builder()
+                .tool_use_id(format!("text_call_{i}"))
+                .name(&call.name)
+                .input(BedrockClient::json_to_document(&call.input)?)
+                .build()
+                .map_err(|e| BedrockError::Unknown(format!("Failed to build tool use: {e}")))
+        })
+        .collect()
+}
+
+/// Render tool results as text for the next user turn
+pub fn format_tool_results(calls: &[TextToolCall], results: &[ToolResultBlock]) -> Result<String> {
+    let mut text = String::new();
+    for (call, result) in calls.iter().zip(results) {
+        let mut body = Vec::new();
+        for content in result.content() {
+            match content {
+                ToolResultContentBlock::Json(doc) => {
+                    body.push(BedrockClient::document_to_json(doc)?.to_string())
+                }
+                ToolResultContentBlock::Text(t) => body.push(t.clone()),
+                _ => {}
+            }
+        }
+        let status = if result.status() == Some(&ToolResultStatus::Error) {
+            " status=\"error\""
+        } else {
+            ""
+        };
+        text.push_str(&format!(
+            "<tool_result name=\"{}\"{status}>{}</tool_result>\n",
+            call.name,
+            body.join("\n")
+        ));
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_tool_calls() {
+        let text = "Let me look.\n<tool_call>{\"name\": \"grep\", \"input\": {\"pattern\": \"fn main\"}}</tool_call>\n\
+                    <tool_call> {\"name\": \"fs_list\"} </tool_call><tool_call>not json</tool_call>";
+
+        let calls = parse_tool_calls(text);
+        assert_eq!(
+            calls,
+            vec![
+                TextToolCall { name: "grep".into(), input: json!({"pattern": "fn main"}) },
+                TextToolCall { name: "fs_list".into(), input: json!({}) },
+            ]
+        );
+        assert!(parse_tool_calls("The answer is 42").is_empty());
+    }
+
+    #[test]
+    fn test_format_tool_results() {
+        let calls = vec![TextToolCall { name: "grep".into(), input: json!({}) }];
+        let result = ToolResultBlock::builder()
+            .tool_use_id("text_call_0")
+            .content(ToolResultContentBlock::Json(BedrockClient::json_to_document(&json!({"matches": 2})).unwrap()))
+            .build()
+            .unwrap();
+
+        let text = format_tool_results(&calls, &[result]).unwrap();
+        assert_eq!(text, "<tool_result name=\"grep\">{\"matches\":2}</tool_result>\n");
+    }
+}
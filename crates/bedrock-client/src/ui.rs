@@ -8,6 +8,7 @@ pub fn get_tool_display_name(tool_name: &str) -> String {
         "fs_read" => "Read File".to_string(),
         "fs_write" => "Write File".to_string(),
         "fs_list" => "List Files".to_string(),
+        "apply_patch" => "Edit File".to_string(),
         
         // Search tools
         "grep" => "Grep Search".to_string(),
@@ -40,7 +41,7 @@ pub fn get_tool_display_name(tool_name: &str) -> String {
 pub fn get_tool_emoji(tool_name: &str) -> &'static str {
     match tool_name {
         // File operations
-        "fs_read" | "fs_write" | "fs_list" => "📄",
+        "fs_read" | "fs_write" | "fs_list" | "apply_patch" => "📄",
         
         // Search operations
         "grep" | "find" | "rg" => "🔍",
@@ -57,7 +58,18 @@ pub fn get_tool_emoji(tool_name: &str) -> &'static str {
 pub fn format_tool_execution(tool_name: &str, args: &Value) -> String {
     match tool_name {
         "execute_bash" | "execute_cmd" => {
-            if let Some(cmd) = args.get("command").and_then(|v| v.as_str()) {
+            if let Some(command) = args.get("command").and_then(|v| v.as_str()) {
+                // A shell command line is shown as written; an argv call as the words it runs, quoted
+                let cmd = if args.get("shell").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    format!("shell: {command}")
+                } else {
+                    let argv = args
+                        .get("args")
+                        .and_then(|v| v.as_array())
+                        .map(|items| items.iter().filter_map(|item| item.as_str()).collect::<Vec<_>>())
+                        .unwrap_or_default();
+                    command.split_whitespace().chain(argv).map(shell_quote).collect::<Vec<_>>().join(" ")
+                };
                 // Truncate long commands for display
                 let display_cmd = if cmd.len() > 50 {
                     format!("{cmd} | tail -20")
@@ -83,10 +95,27 @@ pub fn format_tool_execution(tool_name: &str, args: &Value) -> String {
                 "Write()".to_string()
             }
         }
+        "apply_patch" => {
+            if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
+                format!("Edit({path})")
+            } else {
+                "Edit()".to_string()
+            }
+        }
         _ => format!("{}()", get_tool_display_name(tool_name))
     }
 }
 
+/// Quote `arg` for a POSIX shell, leaving plain words as they are
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-./=:,@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
 /// Format tool result for display
 pub fn format_tool_result(tool_name: &str, result: &Value) -> String {
     match tool_name {
@@ -130,6 +159,12 @@ pub fn format_tool_result(tool_name: &str, result: &Value) -> String {
             }
             "⏺ Write complete".to_string()
         }
+        "apply_patch" => {
+            match result.get("hunks_applied").and_then(|v| v.as_array()) {
+                Some(hunks) => format!("⏺ Applied {} hunk(s)", hunks.len()),
+                None => "⏺ Edit complete".to_string(),
+            }
+        }
         _ => "⏺ Completed".to_string()
     }
 }
@@ -146,4 +181,33 @@ pub fn display_tool_execution(tool_name: &str, args: &Value) {
 pub fn display_tool_result(tool_name: &str, result: &Value) {
     let formatted = format_tool_result(tool_name, result);
     println!("    ⎿  {formatted}");
-}
\ No newline at end of file
+}
+
+/// Ask the user on stdin whether a tool marked `ask` may run. Defaults to no.
+pub fn confirm_tool_execution(tool_name: &str, args: &Value) -> bool {
+    let emoji = get_tool_emoji(tool_name);
+    println!("\n{emoji} {} wants to run: {}", get_tool_display_name(tool_name), format_tool_execution(tool_name, args));
+    print!("Allow? [y/N] ");
+    io::stdout().flush().ok();
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bash_display_quotes_argv_and_marks_shell_mode() {
+        let argv = json!({"command": "grep  -n", "args": ["two words", "it's", ""]});
+        assert_eq!(format_tool_execution("execute_bash", &argv), r"Bash(grep -n 'two words' 'it'\''s' '')");
+
+        let shell = json!({"command": "echo hi | wc -c", "shell": true});
+        assert_eq!(format_tool_execution("execute_bash", &shell), "Bash(shell: echo hi | wc -c)");
+    }
+}
@@ -0,0 +1,88 @@
+use bedrock_core::{BedrockError, Result};
+use serde_json::Value;
+use std::path::Path;
+
+/// Read a config file as JSON without substituting environment variables.
+///
+/// `.toml` files are parsed as TOML; everything else as YAML, which covers JSON too.
+pub(crate) fn parse_config_value(path: &Path) -> Result<Value> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        BedrockError::ConfigError(format!("Failed to read config file {}: {e}", path.display()))
+    })?;
+
+    let value: Value = if is_toml(path) {
+        toml::from_str(&content).map_err(|e| {
+            BedrockError::ConfigError(format!("Failed to parse TOML in {}: {e}", path.display()))
+        })?
+    } else {
+        let yaml: serde_yaml::Value = serde_yaml::from_str(&content).map_err(|e| {
+            BedrockError::ConfigError(format!("Failed to parse YAML in {}: {e}", path.display()))
+        })?;
+        serde_json::to_value(yaml)
+            .map_err(|e| BedrockError::ConfigError(format!("Failed to convert YAML to JSON: {e}")))?
+    };
+    Ok(value)
+}
+
+/// Whether the file extension says TOML
+pub(crate) fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+}
+
+/// Make a relative `agent.system_prompt_file` relative to the config file's directory.
+///
+/// Values still holding a `${VAR}` reference are left for after substitution,
+/// where they resolve against the working directory.
+pub(crate) fn resolve_config_paths(value: &mut Value, config_path: &Path) {
+    let Some(Value::String(file)) = value.pointer_mut("/agent/system_prompt_file") else { return };
+    if file.contains("${") || Path::new(file.as_str()).is_absolute() {
+        return;
+    }
+    let dir = config_path.parent().unwrap_or(Path::new(""));
+    *file = dir.join(file.as_str()).to_string_lossy().into_owned();
+}
+
+/// Merge `overlay` into `base`: objects are merged key by key, any other value
+/// in the overlay (arrays included) replaces the one in the base.
+pub(crate) fn deep_merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(base_value) => deep_merge(base_value, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deep_merge_replaces_leaves_and_arrays() {
+        let mut base = json!({
+            "agent": {"name": "base", "model": "model-a"},
+            "tools": {"allowed": ["fs_read", "grep"]}
+        });
+        deep_merge(&mut base, json!({
+            "agent": {"model": "model-b"},
+            "tools": {"allowed": ["fs_read"]},
+            "extra": true
+        }));
+
+        assert_eq!(base, json!({
+            "agent": {"name": "base", "model": "model-b"},
+            "tools": {"allowed": ["fs_read"]},
+            "extra": true
+        }));
+    }
+}
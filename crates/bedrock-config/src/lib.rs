@@ -1,10 +1,21 @@
 mod env_substitution;
+mod layering;
+mod starter;
 
-use bedrock_core::{BedrockError, Result};
+use bedrock_core::{BedrockError, Result, Task, TokenStatistics};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use env_substitution::substitute_env_vars;
+pub use env_substitution::substitute_env_vars;
+use tracing::warn;
+
+/// Model providers on Bedrock, the part of a model ID before the first `.`
+const KNOWN_MODEL_PROVIDERS: &[&str] = &[
+    "anthropic", "amazon", "meta", "mistral", "cohere", "ai21", "deepseek", "writer", "stability", "qwen", "openai",
+];
+
+/// Prefixes of cross-region inference profile IDs, e.g. `us.anthropic.claude-...`
+const INFERENCE_PROFILE_PREFIXES: &[&str] = &["us.", "eu.", "apac.", "us-gov.", "global."];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
@@ -18,6 +29,19 @@ pub struct AgentConfig {
     pub paths: PathSettings,
     #[serde(default)]
     pub mcp: McpSettings,
+    #[serde(default)]
+    pub conversation: ConversationSettings,
+    #[serde(default)]
+    pub tasks: TaskSettings,
+    #[serde(default)]
+    pub embeddings: EmbeddingSettings,
+    #[serde(default)]
+    pub audit: AuditSettings,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerSettings,
+    /// Bedrock guardrail applied to every model call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guardrail: Option<GuardrailSettings>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,16 +52,83 @@ pub struct AgentSettings {
     pub temperature: f32,
     #[serde(default = "default_max_tokens")]
     pub max_tokens: usize,
+    /// Sequences that make the model stop generating
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    /// Report which stop sequence ended a response in the task result
+    #[serde(default)]
+    pub include_stop_sequence: bool,
+    /// Whether task answers are streamed; `--stream` always streams
+    #[serde(default)]
+    pub stream_mode: StreamMode,
+    /// Answer without streaming when a response stream can't be opened
+    #[serde(default)]
+    pub stream_fallback: bool,
+    /// Sent as Bedrock `requestMetadata` to attribute and filter invocations; tasks can override keys
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub request_metadata: HashMap<String, String>,
+    /// Instructions appended to the base system prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// File holding the instructions instead, relative to the config file's directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt_file: Option<PathBuf>,
+    /// Mark the system prompt and tool definitions as cacheable, so later requests
+    /// of a task read them from Bedrock's prompt cache (on models that support it)
+    #[serde(default)]
+    pub enable_prompt_cache: bool,
 }
 
 impl AgentSettings {
     pub fn get_system_prompt(&self) -> String {
-        format!(
+        let base = format!(
             "You are {}, an AI assistant with access to various tools. \
             You can execute commands, read and write files, and search through codebases. \
             Always be helpful and provide clear explanations for your actions.",
             self.name
-        )
+        );
+
+        let extra = match &self.system_prompt_file {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(content) => Some(content),
+                Err(e) => {
+                    warn!("Failed to read system prompt file {}: {e}", path.display());
+                    None
+                }
+            },
+            None => self.system_prompt.clone(),
+        };
+        match extra {
+            Some(extra) if !extra.trim().is_empty() => format!("{base}\n\n{}", extra.trim_end()),
+            _ => base,
+        }
+    }
+}
+
+/// Prompts at least this long are expected to get long answers under [`StreamMode::Auto`]
+pub const AUTO_STREAM_MIN_PROMPT_CHARS: usize = 2000;
+
+/// When a task's answer is streamed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamMode {
+    Always,
+    #[default]
+    Never,
+    /// Stream when the task is marked `expected_long` or its prompt and context are long
+    Auto,
+}
+
+impl StreamMode {
+    pub fn streams(self, task: &Task) -> bool {
+        match self {
+            StreamMode::Always => true,
+            StreamMode::Never => false,
+            StreamMode::Auto => {
+                task.expected_long
+                    || task.prompt.chars().count() + task.context.chars().count() >= AUTO_STREAM_MIN_PROMPT_CHARS
+            }
+        }
     }
 }
 
@@ -46,15 +137,121 @@ pub struct AwsSettings {
     pub region: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub profile: Option<String>,
+    /// Role assumed through STS for Bedrock calls, using the profile or default
+    /// credentials to authorize it
     #[serde(skip_serializing_if = "Option::is_none")]
     pub role_arn: Option<String>,
+    /// Session name for the assumed role; the SDK generates one when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_session_name: Option<String>,
+    /// External ID required by the role's trust policy, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolSettings {
+    /// Tools offered to the model; empty offers every registered tool
     pub allowed: Vec<String>,
     #[serde(default)]
     pub permissions: HashMap<String, ToolPermission>,
+    /// Set to false to never offer tools to the model
+    #[serde(default = "default_tools_enabled")]
+    pub enabled: bool,
+    /// Maximum time a single tool call may run before it is abandoned
+    #[serde(default = "default_tool_timeout_secs")]
+    pub tool_timeout_secs: u64,
+    /// Serialized tool output beyond this many bytes is cut before it goes back to the model
+    #[serde(default = "default_max_tool_output_bytes")]
+    pub max_tool_output_bytes: usize,
+    /// Describe tools in the system prompt and parse calls from the reply text,
+    /// for models without native tool support
+    #[serde(default)]
+    pub text_protocol: bool,
+    /// What to do when the model returns an answer and tool calls in the same turn
+    #[serde(default)]
+    pub mixed_response: MixedResponsePolicy,
+    /// Limits of the `poll` tool for waiting on asynchronous jobs
+    #[serde(default)]
+    pub poll: PollSettings,
+}
+
+/// Extra seconds the poll tool gets beyond `poll.timeout_secs`
+pub const POLL_TIMEOUT_GRACE_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollSettings {
+    /// Seconds between checks unless the call asks for another interval
+    #[serde(default = "default_poll_interval_secs")]
+    pub interval_secs: u64,
+    /// Most checks a single poll may make
+    #[serde(default = "default_poll_max_attempts")]
+    pub max_attempts: u32,
+    /// Total seconds a single poll may take
+    #[serde(default = "default_poll_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for PollSettings {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_poll_interval_secs(),
+            max_attempts: default_poll_max_attempts(),
+            timeout_secs: default_poll_timeout_secs(),
+        }
+    }
+}
+
+impl ToolSettings {
+    /// Timeout for a tool, honouring a per-tool override in `permissions`.
+    ///
+    /// Poll always gets a little longer than `poll.timeout_secs`, so it can stop
+    /// waiting and report `timed_out` itself instead of being abandoned first.
+    pub fn timeout_secs_for(&self, tool: &str) -> u64 {
+        let timeout = self
+            .permissions
+            .get(tool)
+            .and_then(|p| p.timeout_secs)
+            .unwrap_or(self.tool_timeout_secs);
+        if tool == "poll" {
+            timeout.max(self.poll.timeout_secs + POLL_TIMEOUT_GRACE_SECS)
+        } else {
+            timeout
+        }
+    }
+
+    /// Whether the tool's schema should be sent in strict mode
+    pub fn is_strict(&self, tool: &str) -> bool {
+        self.permissions.get(tool).is_some_and(|p| p.strict)
+    }
+
+    /// What a successful call costs given its result, from `cost_per_call` and `cost_per_unit`
+    pub fn call_cost(&self, tool: &str, result: &serde_json::Value) -> f64 {
+        let Some(permission) = self.permissions.get(tool) else { return 0.0 };
+        let units = permission
+            .cost_unit_path
+            .as_deref()
+            .and_then(|path| result.pointer(path))
+            .and_then(serde_json::Value::as_f64)
+            .unwrap_or(0.0);
+        permission.cost_per_call.unwrap_or(0.0) + permission.cost_per_unit.unwrap_or(0.0) * units
+    }
+
+    /// Whether `allowed` lets the model see and call a tool
+    pub fn is_allowed(&self, tool: &str) -> bool {
+        self.allowed.is_empty() || self.allowed.iter().any(|name| name == tool)
+    }
+}
+
+/// Handling of a model turn that contains both text and tool calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MixedResponsePolicy {
+    /// Run the tools and continue the conversation
+    #[default]
+    PreferTools,
+    /// Return the text as the final answer and skip the tool calls
+    PreferAnswer,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +259,20 @@ pub struct ToolPermission {
     pub permission: Permission,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub constraint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Ask models that support it to follow the tool's input schema exactly
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub strict: bool,
+    /// Charged for every successful call, for tools that cost money to use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_per_call: Option<f64>,
+    /// Charged per unit the call reports, read from `cost_unit_path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_per_unit: Option<f64>,
+    /// JSON pointer to the number of billed units in the tool's result, e.g. `/results_count`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_unit_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,6 +289,22 @@ pub struct ModelPricing {
     pub output_per_1k: f64,
     #[serde(default = "default_currency")]
     pub currency: String,
+    /// Input tokens read from the prompt cache; a tenth of `input_per_1k` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_per_1k: Option<f64>,
+    /// Input tokens written to the prompt cache; 1.25x `input_per_1k` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write_per_1k: Option<f64>,
+}
+
+impl ModelPricing {
+    /// Cost of the input side of `tokens`, with cache reads and writes at their own rates
+    pub fn input_cost(&self, tokens: &TokenStatistics) -> f64 {
+        let per_1k = |count: usize, rate: f64| count as f64 / 1000.0 * rate;
+        per_1k(tokens.input_tokens, self.input_per_1k)
+            + per_1k(tokens.cache_hits, self.cache_read_per_1k.unwrap_or(self.input_per_1k * 0.1))
+            + per_1k(tokens.cache_write_tokens, self.cache_write_per_1k.unwrap_or(self.input_per_1k * 1.25))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +317,101 @@ pub struct LimitSettings {
     pub budget_limit: Option<f64>,
     #[serde(default = "default_alert_threshold")]
     pub alert_threshold: f64,
+    #[serde(default = "default_task_timeout_secs")]
+    pub task_timeout_secs: u64,
+    /// Maximum number of model requests in flight at once across all tasks
+    #[serde(default = "default_max_concurrent_requests")]
+    pub max_concurrent_requests: usize,
+    /// Maximum number of model/tool rounds before a task or chat gives up
+    #[serde(default = "default_max_tool_iterations")]
+    pub max_tool_iterations: usize,
+    /// Maximum number of tool calls a single task may execute across all rounds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_executions_per_task: Option<usize>,
+    /// Failed tool calls in a row before the model is told to stop using tools; 0 disables the cap
+    #[serde(default = "default_max_consecutive_tool_errors")]
+    pub max_consecutive_tool_errors: usize,
+    /// Ceiling on the estimated input tokens (messages, system prompt and tools) of a single request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_tokens: Option<usize>,
+    /// How the context is shrunk when a request exceeds `max_request_tokens`
+    #[serde(default)]
+    pub request_token_reduction: ContextReduction,
+    /// Continuation requests sent when a response stream is cut off before it finishes
+    #[serde(default)]
+    pub max_stream_continuations: usize,
+    /// Independent tasks of a task graph run at once
+    #[serde(default = "default_max_concurrent_dag_nodes")]
+    pub max_concurrent_dag_nodes: usize,
+    /// Queued tasks run at once
+    #[serde(default = "default_max_concurrent_tasks")]
+    pub max_concurrent_tasks: usize,
+    /// Slots of `max_concurrent_tasks` only High-priority queued tasks may use
+    #[serde(default)]
+    pub reserved_high_priority_slots: usize,
+    /// How long shutdown waits for running queued tasks to finish
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    /// Times a queued task is re-queued after a transient failure (throttling, timeout, IO)
+    #[serde(default = "default_max_task_retries")]
+    pub max_task_retries: u32,
+    /// Delay before a failed queued task is re-queued, doubled on each further attempt
+    #[serde(default = "default_task_retry_backoff_ms")]
+    pub task_retry_backoff_ms: u64,
+    /// Longest delay before a re-queue, however many attempts came before
+    #[serde(default = "default_max_backoff_ms")]
+    pub task_retry_max_backoff_ms: u64,
+}
+
+/// Reduction applied to a request's context to fit `max_request_tokens`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextReduction {
+    /// Drop the oldest exchanges after the first message
+    #[default]
+    TruncateOldest,
+    /// Replace the oldest exchanges with a short summary of what they contained
+    Summarize,
+}
+
+/// Persistent log file of the agent's activity, rotated by size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditSettings {
+    /// Directory for `agent.log`; unset logs to the terminal only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_dir: Option<PathBuf>,
+    /// Size at which the log is rotated
+    #[serde(default = "default_audit_max_bytes")]
+    pub max_bytes: u64,
+    /// Rotated files kept; the oldest beyond this are deleted
+    #[serde(default = "default_audit_max_files")]
+    pub max_files: usize,
+    /// Rotated files older than this are deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_days: Option<u64>,
+}
+
+/// A Bedrock guardrail, as created in the Bedrock console or API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuardrailSettings {
+    /// Guardrail ID or ARN
+    pub id: String,
+    /// Published version number, or `DRAFT`
+    pub version: String,
+    /// Include the guardrail's assessment in responses, for debugging
+    #[serde(default)]
+    pub trace: bool,
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        Self {
+            log_dir: None,
+            max_bytes: default_audit_max_bytes(),
+            max_files: default_audit_max_files(),
+            max_age_days: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,6 +434,22 @@ pub struct McpSettings {
     pub inline_servers: HashMap<String, serde_json::Value>,
     #[serde(default = "default_max_tools")]
     pub max_tools: usize,
+    /// What to do when more tools are selected than `max_tools`
+    #[serde(default)]
+    pub on_tool_overflow: ToolOverflow,
+}
+
+/// Handling of a tool selection larger than `mcp.max_tools`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolOverflow {
+    /// Keep the first `max_tools` tools in registry order and warn about the rest
+    #[default]
+    Truncate,
+    /// Fail the request with a `ConfigError`
+    Error,
+    /// Keep tools listed in `tools.allowed` first, in that order, then MCP tools
+    Prioritize,
 }
 
 impl Default for McpSettings {
@@ -122,48 +460,190 @@ impl Default for McpSettings {
             servers: vec![],
             inline_servers: HashMap::new(),
             max_tools: default_max_tools(),
+            on_tool_overflow: ToolOverflow::default(),
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationSettings {
+    /// Maximum number of tool results sent to the model in full. Older results
+    /// are replaced with a short placeholder in the context but stay in storage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_results_in_context: Option<usize>,
+    /// Send only the latest result of repeated identical tool calls in full;
+    /// earlier ones point to it
+    #[serde(default)]
+    pub dedupe_tool_results: bool,
+    /// Compact a conversation's message file once it grows past this many bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compact_at_bytes: Option<u64>,
+    /// Number of most recent messages kept verbatim when compacting
+    #[serde(default = "default_compact_keep_messages")]
+    pub compact_keep_messages: usize,
+    /// Summarize older chat messages with the model once the estimated request
+    /// size passes this many tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compact_at_tokens: Option<usize>,
+    /// Tag conversations with the names of the tools they used
+    #[serde(default = "default_tag_tools")]
+    pub tag_tools: bool,
+    /// Messages kept in memory between chat turns; older turns are forgotten first
+    #[serde(default = "default_max_chat_messages")]
+    pub max_chat_messages: usize,
+    /// Print the tokens and cost recorded so far when resuming a conversation
+    #[serde(default = "default_show_usage_on_resume")]
+    pub show_usage_on_resume: bool,
+}
+
+impl Default for ConversationSettings {
+    fn default() -> Self {
+        Self {
+            max_tool_results_in_context: None,
+            dedupe_tool_results: false,
+            compact_at_bytes: None,
+            compact_keep_messages: default_compact_keep_messages(),
+            compact_at_tokens: None,
+            tag_tools: default_tag_tools(),
+            max_chat_messages: default_max_chat_messages(),
+            show_usage_on_resume: default_show_usage_on_resume(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingSettings {
+    #[serde(default = "default_embedding_model")]
+    pub model: String,
+    /// Used when the primary model is still throttled or unavailable after retries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_model: Option<String>,
+    #[serde(default = "default_embedding_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on each further attempt
+    #[serde(default = "default_embedding_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// Longest delay between retries
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+impl Default for EmbeddingSettings {
+    fn default() -> Self {
+        Self {
+            model: default_embedding_model(),
+            fallback_model: None,
+            max_retries: default_embedding_max_retries(),
+            initial_backoff_ms: default_embedding_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+        }
+    }
+}
+
+/// Stops model calls for a while after repeated failures, so queued work fails
+/// fast while Bedrock is unavailable instead of each request timing out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerSettings {
+    #[serde(default = "default_circuit_breaker_enabled")]
+    pub enabled: bool,
+    /// Consecutive failed calls that open the circuit
+    #[serde(default = "default_circuit_failure_threshold")]
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before one probe call is let through
+    #[serde(default = "default_circuit_reset_timeout_secs")]
+    pub reset_timeout_secs: u64,
+}
+
+impl Default for CircuitBreakerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_circuit_breaker_enabled(),
+            failure_threshold: default_circuit_failure_threshold(),
+            reset_timeout_secs: default_circuit_reset_timeout_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskSettings {
+    /// Automatically include the most recent task result in the context of new tasks
+    #[serde(default)]
+    pub auto_chain: bool,
+    /// How the summary of a completed task is produced
+    #[serde(default)]
+    pub summary: SummaryMode,
+}
+
+/// Source of a completed task's summary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryMode {
+    /// The first 100 characters of the final answer
+    #[default]
+    Truncate,
+    /// A one-sentence summary written by an extra model call, truncating if that call fails
+    Model,
+    /// The task's prompt and final status, without looking at the answer
+    Template,
+}
+
 impl AgentConfig {
+    /// Load a config file; same as [`AgentConfig::from_file`]
     pub fn from_yaml(path: impl AsRef<Path>) -> Result<Self> {
-        let content = std::fs::read_to_string(path.as_ref())
-            .map_err(|e| BedrockError::ConfigError(format!("Failed to read config file: {e}")))?;
-        
-        // Parse YAML to serde_json::Value for env var substitution
-        let yaml_value: serde_yaml::Value = serde_yaml::from_str(&content)
-            .map_err(|e| BedrockError::ConfigError(format!("Failed to parse YAML: {e}")))?;
-        
-        // Convert to JSON value for processing
-        let mut json_value = serde_json::to_value(yaml_value)
-            .map_err(|e| BedrockError::ConfigError(format!("Failed to convert YAML to JSON: {e}")))?;
-        
-        // Apply environment variable substitution
-        substitute_env_vars(&mut json_value)?;
-        
-        // Convert back to config struct
-        let config: Self = serde_json::from_value(json_value)
-            .map_err(|e| BedrockError::ConfigError(format!("Failed to deserialize config: {e}")))?;
-        
-        config.validate()?;
-        
-        Ok(config)
+        Self::from_file(path)
     }
 
     pub fn from_yaml_str(yaml: &str) -> Result<Self> {
-        // Parse YAML to serde_json::Value for env var substitution
         let yaml_value: serde_yaml::Value = serde_yaml::from_str(yaml)
             .map_err(|e| BedrockError::ConfigError(format!("Failed to parse YAML: {e}")))?;
-        
-        // Convert to JSON value for processing
-        let mut json_value = serde_json::to_value(yaml_value)
+        let json_value = serde_json::to_value(yaml_value)
             .map_err(|e| BedrockError::ConfigError(format!("Failed to convert YAML to JSON: {e}")))?;
-        
-        // Apply environment variable substitution
+        Self::from_value(json_value)
+    }
+
+    /// Load a TOML config file; same as [`AgentConfig::from_file`]
+    pub fn from_toml(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_file(path)
+    }
+
+    pub fn from_toml_str(toml: &str) -> Result<Self> {
+        Self::from_value(parse_toml(toml)?)
+    }
+
+    /// Load a config file, choosing TOML for `.toml` and YAML otherwise
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_layered(&[path])
+    }
+
+    /// Load `paths` in order, later files overriding earlier ones.
+    ///
+    /// Each file is read as TOML when it ends in `.toml` and as YAML, which covers
+    /// JSON, otherwise. Objects merge key by key and any other value, arrays included,
+    /// replaces the earlier one. Environment variables are substituted once in the
+    /// merged result, so a later file may override a value whose variable isn't set.
+    pub fn from_layered(paths: &[impl AsRef<Path>]) -> Result<Self> {
+        let Some((first, rest)) = paths.split_first() else {
+            return Err(BedrockError::ConfigError("No config files given".into()));
+        };
+        let mut json_value = layering::parse_config_value(first.as_ref())?;
+        layering::resolve_config_paths(&mut json_value, first.as_ref());
+        for path in rest {
+            let mut layer = layering::parse_config_value(path.as_ref())?;
+            layering::resolve_config_paths(&mut layer, path.as_ref());
+            layering::deep_merge(&mut json_value, layer);
+        }
+        Self::from_value(json_value)
+    }
+
+    /// Load YAML `paths` in order; same as [`AgentConfig::from_layered`]
+    pub fn from_yaml_layered(paths: &[PathBuf]) -> Result<Self> {
+        Self::from_layered(paths)
+    }
+
+    /// Substitute environment variables in a parsed config, then deserialize and validate it
+    fn from_value(mut json_value: serde_json::Value) -> Result<Self> {
         substitute_env_vars(&mut json_value)?;
         
-        // Convert back to config struct
         let config: Self = serde_json::from_value(json_value)
             .map_err(|e| BedrockError::ConfigError(format!("Failed to deserialize config: {e}")))?;
         
@@ -172,7 +652,6 @@ impl AgentConfig {
         Ok(config)
     }
 
-
     fn validate(&self) -> Result<()> {
         if self.agent.name.is_empty() {
             return Err(BedrockError::ConfigError("Agent name cannot be empty".into()));
@@ -180,15 +659,71 @@ impl AgentConfig {
         if self.agent.model.is_empty() {
             return Err(BedrockError::ConfigError("Model cannot be empty".into()));
         }
+        if let Some(c) = self.agent.model.chars().find(|c| !is_model_id_char(*c)) {
+            return Err(BedrockError::ConfigError(format!(
+                "Model ID '{}' is malformed: unexpected character {c:?}",
+                self.agent.model
+            )));
+        }
+        if let Some(path) = &self.agent.system_prompt_file {
+            if self.agent.system_prompt.is_some() {
+                return Err(BedrockError::ConfigError(
+                    "Set either agent.system_prompt or agent.system_prompt_file, not both".into(),
+                ));
+            }
+            if !path.is_file() {
+                return Err(BedrockError::ConfigError(format!(
+                    "System prompt file {} does not exist",
+                    path.display()
+                )));
+            }
+        }
+        validate_request_metadata(&self.agent.request_metadata)?;
         if self.aws.region.is_empty() {
             return Err(BedrockError::ConfigError("AWS region cannot be empty".into()));
         }
         if self.agent.temperature < 0.0 || self.agent.temperature > 1.0 {
             return Err(BedrockError::ConfigError("Temperature must be between 0.0 and 1.0".into()));
         }
+        if self.limits.reserved_high_priority_slots >= self.limits.max_concurrent_tasks {
+            return Err(BedrockError::ConfigError(format!(
+                "limits.reserved_high_priority_slots ({}) must be less than limits.max_concurrent_tasks ({})",
+                self.limits.reserved_high_priority_slots, self.limits.max_concurrent_tasks
+            )));
+        }
+        if let Some(guardrail) = &self.guardrail {
+            if guardrail.id.is_empty() || guardrail.version.is_empty() {
+                return Err(BedrockError::ConfigError("guardrail.id and guardrail.version are both required".into()));
+            }
+        }
         Ok(())
     }
 
+    /// Problems with the configured model that don't stop the agent from running,
+    /// for the caller to report once logging is set up
+    pub fn model_warnings(&self) -> Vec<String> {
+        let model = &self.agent.model;
+        let mut warnings = Vec::new();
+        if !self.pricing.contains_key(model) {
+            warnings.push(format!(
+                "No pricing entry for model '{model}'; costs are estimated at $0.003/$0.015 per 1k input/output tokens"
+            ));
+        }
+        if !model.starts_with("arn:") {
+            let unprefixed = INFERENCE_PROFILE_PREFIXES
+                .iter()
+                .find_map(|prefix| model.strip_prefix(prefix))
+                .unwrap_or(model);
+            let provider = unprefixed.split('.').next().unwrap_or_default();
+            if !unprefixed.contains('.') || !KNOWN_MODEL_PROVIDERS.contains(&provider) {
+                warnings.push(format!(
+                    "Model '{model}' doesn't look like a Bedrock model ID (e.g. anthropic.claude-... or us.anthropic.claude-...)"
+                ));
+            }
+        }
+        warnings
+    }
+
     pub fn default_config_path() -> PathBuf {
         let home_dir = std::env::var("HOME_DIR")
             .unwrap_or_else(|_| {
@@ -207,6 +742,21 @@ impl Default for LimitSettings {
             max_rpm: default_max_rpm(),
             budget_limit: None,
             alert_threshold: default_alert_threshold(),
+            task_timeout_secs: default_task_timeout_secs(),
+            max_concurrent_requests: default_max_concurrent_requests(),
+            max_tool_iterations: default_max_tool_iterations(),
+            max_tool_executions_per_task: None,
+            max_consecutive_tool_errors: default_max_consecutive_tool_errors(),
+            max_request_tokens: None,
+            request_token_reduction: ContextReduction::default(),
+            max_stream_continuations: 0,
+            max_concurrent_dag_nodes: default_max_concurrent_dag_nodes(),
+            max_concurrent_tasks: default_max_concurrent_tasks(),
+            reserved_high_priority_slots: 0,
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            max_task_retries: default_max_task_retries(),
+            task_retry_backoff_ms: default_task_retry_backoff_ms(),
+            task_retry_max_backoff_ms: default_max_backoff_ms(),
         }
     }
 }
@@ -228,21 +778,31 @@ impl Default for AgentConfig {
                 model: "us.anthropic.claude-3-5-sonnet-20241022-v2:0".to_string(),
                 temperature: default_temperature(),
                 max_tokens: default_max_tokens(),
+                stop_sequences: Vec::new(),
+                include_stop_sequence: false,
+                stream_mode: StreamMode::default(),
+                stream_fallback: false,
+                request_metadata: HashMap::new(),
+                system_prompt: None,
+                system_prompt_file: None,
+                enable_prompt_cache: false,
             },
             aws: AwsSettings {
                 region: "us-east-1".to_string(),
                 profile: None,
                 role_arn: None,
+                role_session_name: None,
+                external_id: None,
             },
             tools: ToolSettings {
-                allowed: vec![
-                    "fs_read".to_string(),
-                    "fs_write".to_string(),
-                    "fs_list".to_string(),
-                    "grep".to_string(),
-                    "find".to_string(),
-                ],
+                allowed: Vec::new(),
                 permissions: HashMap::new(),
+                enabled: default_tools_enabled(),
+                tool_timeout_secs: default_tool_timeout_secs(),
+                max_tool_output_bytes: default_max_tool_output_bytes(),
+                text_protocol: false,
+                mixed_response: MixedResponsePolicy::default(),
+                poll: PollSettings::default(),
             },
             pricing: {
                 let mut pricing = HashMap::new();
@@ -252,6 +812,8 @@ impl Default for AgentConfig {
                         input_per_1k: 0.003,
                         output_per_1k: 0.015,
                         currency: default_currency(),
+                        cache_read_per_1k: None,
+                        cache_write_per_1k: None,
                     },
                 );
                 pricing
@@ -259,6 +821,12 @@ impl Default for AgentConfig {
             limits: LimitSettings::default(),
             paths: PathSettings::default(),
             mcp: McpSettings::default(),
+            conversation: ConversationSettings::default(),
+            tasks: TaskSettings::default(),
+            embeddings: EmbeddingSettings::default(),
+            audit: AuditSettings::default(),
+            circuit_breaker: CircuitBreakerSettings::default(),
+            guardrail: None,
         }
     }
 }
@@ -269,6 +837,83 @@ fn default_currency() -> String { "USD".to_string() }
 fn default_max_tpm() -> usize { 100_000 }
 fn default_max_rpm() -> usize { 100 }
 fn default_alert_threshold() -> f64 { 0.8 }
+fn default_task_timeout_secs() -> u64 { 300 }
+/// Parse TOML to serde_json::Value for env var substitution
+fn parse_toml(toml: &str) -> Result<serde_json::Value> {
+    toml::from_str(toml).map_err(|e| BedrockError::ConfigError(format!("Failed to parse TOML: {e}")))
+}
+
+/// Most entries Bedrock accepts in `requestMetadata`
+const MAX_REQUEST_METADATA_ENTRIES: usize = 16;
+
+/// Longest key or value Bedrock accepts in `requestMetadata`
+const MAX_REQUEST_METADATA_LEN: usize = 256;
+
+/// Check `requestMetadata` against Bedrock's limits, so bad entries fail before a request is sent
+pub fn validate_request_metadata(metadata: &HashMap<String, String>) -> Result<()> {
+    if metadata.len() > MAX_REQUEST_METADATA_ENTRIES {
+        return Err(BedrockError::ConfigError(format!(
+            "Request metadata has {} entries; at most {MAX_REQUEST_METADATA_ENTRIES} are allowed",
+            metadata.len()
+        )));
+    }
+    for (key, value) in metadata {
+        if key.is_empty() || key.chars().count() > MAX_REQUEST_METADATA_LEN {
+            return Err(BedrockError::ConfigError(format!(
+                "Request metadata key '{key}' must be 1-{MAX_REQUEST_METADATA_LEN} characters"
+            )));
+        }
+        if value.chars().count() > MAX_REQUEST_METADATA_LEN {
+            return Err(BedrockError::ConfigError(format!(
+                "Request metadata value for '{key}' is longer than {MAX_REQUEST_METADATA_LEN} characters"
+            )));
+        }
+        if let Some(c) = key.chars().chain(value.chars()).find(|c| !is_request_metadata_char(*c)) {
+            return Err(BedrockError::ConfigError(format!(
+                "Request metadata entry '{key}' has unsupported character {c:?}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Characters Bedrock allows in `requestMetadata` keys and values
+fn is_request_metadata_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c.is_whitespace() || ":_@$#=/+,-.".contains(c)
+}
+
+/// Characters that can appear in a model ID, inference profile ID or ARN
+fn is_model_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':' | '/')
+}
+
+fn default_max_concurrent_requests() -> usize { 10 }
+fn default_max_tool_iterations() -> usize { 10 }
+fn default_max_concurrent_dag_nodes() -> usize { 4 }
+fn default_max_consecutive_tool_errors() -> usize { 5 }
+fn default_max_concurrent_tasks() -> usize { 3 }
+fn default_max_task_retries() -> u32 { 2 }
+fn default_shutdown_timeout_secs() -> u64 { 30 }
+fn default_task_retry_backoff_ms() -> u64 { 1000 }
+fn default_max_backoff_ms() -> u64 { 30_000 }
+fn default_audit_max_bytes() -> u64 { 10 * 1024 * 1024 }
+fn default_audit_max_files() -> usize { 5 }
+fn default_tools_enabled() -> bool { true }
+fn default_tool_timeout_secs() -> u64 { 120 }
+fn default_max_tool_output_bytes() -> usize { 100_000 }
+fn default_poll_interval_secs() -> u64 { 5 }
+fn default_poll_max_attempts() -> u32 { 60 }
+fn default_poll_timeout_secs() -> u64 { 900 }
+fn default_compact_keep_messages() -> usize { 20 }
+fn default_tag_tools() -> bool { true }
+fn default_max_chat_messages() -> usize { 100 }
+fn default_show_usage_on_resume() -> bool { true }
+fn default_embedding_model() -> String { "amazon.titan-embed-text-v2:0".to_string() }
+fn default_embedding_max_retries() -> u32 { 3 }
+fn default_embedding_initial_backoff_ms() -> u64 { 500 }
+fn default_circuit_breaker_enabled() -> bool { true }
+fn default_circuit_failure_threshold() -> u32 { 5 }
+fn default_circuit_reset_timeout_secs() -> u64 { 30 }
 fn default_max_tools() -> usize { 64 }  // AWS Bedrock limit for most models
 
 fn default_home_dir() -> PathBuf {
@@ -312,6 +957,9 @@ tools:
     fs_write:
       permission: allow
       constraint: workspace_only
+    execute_bash:
+      permission: ask
+      timeout_secs: 30
 
 pricing:
   claude-3-sonnet:
@@ -324,6 +972,269 @@ pricing:
         assert_eq!(config.agent.model, "claude-3-sonnet");
         assert_eq!(config.agent.temperature, 0.5);
         assert_eq!(config.tools.allowed.len(), 2);
+        assert_eq!(config.conversation.max_tool_results_in_context, None);
+        assert_eq!(config.limits.task_timeout_secs, 300);
+        assert_eq!(config.limits.max_tool_iterations, 10);
+        assert_eq!(config.tools.timeout_secs_for("fs_write"), 120);
+        assert_eq!(config.tools.timeout_secs_for("execute_bash"), 30);
+    }
+
+    #[test]
+    fn test_poll_timeout_outlasts_its_own_limit() {
+        let mut config = AgentConfig::default();
+        config.tools.tool_timeout_secs = 120;
+        config.tools.poll.timeout_secs = 900;
+        assert_eq!(config.tools.timeout_secs_for("poll"), 900 + POLL_TIMEOUT_GRACE_SECS);
+
+        // A longer override still wins
+        config.tools.permissions.insert(
+            "poll".to_string(),
+            ToolPermission {
+                permission: Permission::Allow,
+                constraint: None,
+                timeout_secs: Some(3600),
+                strict: false,
+                cost_per_call: None,
+                cost_per_unit: None,
+                cost_unit_path: None,
+            },
+        );
+        assert_eq!(config.tools.timeout_secs_for("poll"), 3600);
+    }
+
+    #[test]
+    fn test_parse_conversation_settings() {
+        let yaml = r#"
+agent:
+  name: test-agent
+  model: claude-3-sonnet
+
+aws:
+  region: us-east-1
+
+tools:
+  allowed: []
+
+pricing: {}
+
+conversation:
+  max_tool_results_in_context: 2
+"#;
+
+        let config = AgentConfig::from_yaml_str(yaml).unwrap();
+        assert_eq!(config.conversation.max_tool_results_in_context, Some(2));
+        assert_eq!(config.conversation.compact_at_bytes, None);
+        assert_eq!(config.conversation.compact_keep_messages, 20);
+    }
+
+    #[test]
+    fn test_request_metadata_is_validated() {
+        let mut config = AgentConfig::default();
+        config.agent.request_metadata.insert("cost_center".into(), "research/ml-1".into());
+        assert!(config.validate().is_ok());
+
+        config.agent.request_metadata.insert("owner".into(), "ops;team".into());
+        assert!(matches!(config.validate(), Err(BedrockError::ConfigError(m)) if m.contains("unsupported character ';'")));
+
+        let too_many: HashMap<_, _> = (0..17).map(|i| (format!("key{i}"), String::new())).collect();
+        assert!(validate_request_metadata(&too_many).is_err());
+        assert!(validate_request_metadata(&HashMap::from([(String::new(), "x".to_string())])).is_err());
+    }
+
+    #[test]
+    fn test_tool_call_cost() {
+        let yaml = r#"
+agent:
+  name: test-agent
+  model: claude-3-sonnet
+
+aws:
+  region: us-east-1
+
+tools:
+  allowed: [web_search]
+  permissions:
+    web_search:
+      permission: allow
+      cost_per_call: 0.01
+      cost_per_unit: 0.002
+      cost_unit_path: /results_count
+
+pricing: {}
+"#;
+
+        let config = AgentConfig::from_yaml_str(yaml).unwrap();
+        let cost = config.tools.call_cost("web_search", &serde_json::json!({"results_count": 5}));
+        assert!((cost - 0.02).abs() < 1e-9);
+        assert_eq!(config.tools.call_cost("web_search", &serde_json::Value::Null), 0.01);
+        assert_eq!(config.tools.call_cost("fs_read", &serde_json::Value::Null), 0.0);
+    }
+
+    #[test]
+    fn test_overlay_overrides_base_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("base.yaml");
+        std::fs::write(&base, r#"
+agent:
+  name: test-agent
+  model: claude-3-sonnet
+  temperature: 0.5
+aws:
+  region: us-east-1
+  profile: default
+tools:
+  allowed: [fs_read, grep]
+pricing: {}
+"#).unwrap();
+        let staging = dir.path().join("staging.json");
+        std::fs::write(&staging, r#"{"agent": {"temperature": 0.2}}"#).unwrap();
+        let prod = dir.path().join("prod.toml");
+        std::fs::write(&prod, r#"
+[agent]
+model = "claude-3-opus"
+
+[aws]
+region = "eu-west-1"
+"#).unwrap();
+        
+        let config = AgentConfig::from_layered(&[&base, &staging, &prod]).unwrap();
+        
+        assert_eq!(config.agent.model, "claude-3-opus");
+        assert_eq!(config.aws.region, "eu-west-1");
+        assert_eq!(config.agent.temperature, 0.2);
+        // Everything else comes from the base
+        assert_eq!(config.agent.name, "test-agent");
+        assert_eq!(config.aws.profile, Some("default".to_string()));
+        assert_eq!(config.tools.allowed, vec!["fs_read", "grep"]);
+    }
+    
+    #[test]
+    fn test_layered_config_is_validated() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("base.yaml");
+        std::fs::write(&base, "agent:\n  name: test-agent\n  model: claude-3-sonnet\naws:\n  region: us-east-1\ntools:\n  allowed: []\npricing: {}\n").unwrap();
+        let overlay = dir.path().join("bad.yaml");
+        std::fs::write(&overlay, "agent:\n  temperature: 3.0\n").unwrap();
+        
+        assert!(AgentConfig::from_layered(&[&base, &overlay]).is_err());
+    }
+
+    #[test]
+    fn test_parse_toml_config() {
+        std::env::set_var("TOML_TEST_REGION", "eu-central-1");
+        let toml = r#"
+[agent]
+name = "test-agent"
+model = "claude-3-sonnet"
+temperature = 0.3
+
+[aws]
+region = "${TOML_TEST_REGION}"
+
+[tools]
+allowed = ["fs_read"]
+
+[pricing.claude-3-sonnet]
+input_per_1k = 0.003
+output_per_1k = 0.015
+"#;
+
+        let config = AgentConfig::from_toml_str(toml).unwrap();
+        assert_eq!(config.agent.name, "test-agent");
+        assert_eq!(config.agent.temperature, 0.3);
+        assert_eq!(config.aws.region, "eu-central-1");
+        assert_eq!(config.tools.allowed, vec!["fs_read"]);
+        assert_eq!(config.pricing["claude-3-sonnet"].currency, "USD");
+
+        assert!(AgentConfig::from_toml_str(&toml.replace("temperature = 0.3", "temperature = 3.0")).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, toml).unwrap();
+        assert_eq!(AgentConfig::from_file(&path).unwrap().aws.region, "eu-central-1");
+        assert_eq!(AgentConfig::from_toml(&path).unwrap().aws.region, "eu-central-1");
+    }
+
+    #[test]
+    fn test_yaml_layered_merges_then_substitutes() {
+        let dir = tempfile::tempdir().unwrap();
+        let shared = dir.path().join("shared.yaml");
+        std::fs::write(&shared, r#"
+agent:
+  name: test-agent
+  model: ${LAYERED_TEST_UNSET_MODEL}
+aws:
+  region: us-east-1
+tools:
+  allowed: [fs_read, grep]
+pricing: {}
+"#).unwrap();
+        let dev = dir.path().join("dev.yaml");
+        std::fs::write(&dev, "agent:\n  model: claude-3-haiku\ntools:\n  allowed: [fs_read]\n").unwrap();
+
+        // The shared file's unset variable is overridden before substitution
+        let config = AgentConfig::from_yaml_layered(&[shared.clone(), dev]).unwrap();
+
+        assert_eq!(config.agent.model, "claude-3-haiku");
+        assert_eq!(config.agent.name, "test-agent");
+        assert_eq!(config.tools.allowed, vec!["fs_read"]);
+        assert!(AgentConfig::from_yaml_layered(&[shared]).is_err());
+        assert!(AgentConfig::from_yaml_layered(&[]).is_err());
+    }
+
+    #[test]
+    fn test_system_prompt_file_is_relative_to_config() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("prompts")).unwrap();
+        std::fs::write(dir.path().join("prompts/review.md"), "Review code for:\n- correctness\n").unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, r#"
+agent:
+  name: test-agent
+  model: claude-3-sonnet
+  system_prompt_file: prompts/review.md
+aws:
+  region: us-east-1
+tools:
+  allowed: []
+pricing: {}
+"#).unwrap();
+
+        let config = AgentConfig::from_yaml(&config_path).unwrap();
+
+        assert_eq!(config.agent.system_prompt_file, Some(dir.path().join("prompts/review.md")));
+        let prompt = config.agent.get_system_prompt();
+        assert!(prompt.starts_with("You are test-agent"));
+        assert!(prompt.ends_with("\n\nReview code for:\n- correctness"));
+    }
+
+    #[test]
+    fn test_system_prompt_and_file_are_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("prompt.md"), "Be brief").unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "agent:\n  name: test-agent\n  model: claude-3-sonnet\n  system_prompt: Be verbose\n  system_prompt_file: prompt.md\naws:\n  region: us-east-1\ntools:\n  allowed: []\npricing: {}\n").unwrap();
+
+        let err = AgentConfig::from_yaml(&config_path).unwrap_err();
+        assert!(err.to_string().contains("not both"));
+
+        let mut config = AgentConfig::default();
+        config.agent.system_prompt = Some("Be verbose".to_string());
+        assert!(config.agent.get_system_prompt().ends_with("\n\nBe verbose"));
+    }
+
+    #[test]
+    fn test_auto_stream_mode_follows_task_length() {
+        let short = Task::new("What time zone is UTC+1?");
+        let long = Task::new("Review this diff:\n".to_string() + &"+ let x = 1;\n".repeat(200));
+
+        assert!(!StreamMode::Auto.streams(&short));
+        assert!(StreamMode::Auto.streams(&long));
+        assert!(StreamMode::Auto.streams(&short.clone().expecting_long_answer()));
+
+        assert!(StreamMode::Always.streams(&short));
+        assert!(!StreamMode::Never.streams(&long));
+        assert_eq!(AgentConfig::default().agent.stream_mode, StreamMode::Never);
     }
 
     #[test]
@@ -345,4 +1256,33 @@ pricing: {}
         let result = AgentConfig::from_yaml_str(yaml);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_model_warnings() {
+        let mut config = AgentConfig::default();
+        assert!(config.model_warnings().is_empty());
+
+        // Known provider behind an inference profile prefix, but not priced
+        config.agent.model = "eu.amazon.nova-pro-v1:0".to_string();
+        let warnings = config.model_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("No pricing entry"));
+
+        config.agent.model = "claude-3-sonet".to_string();
+        assert!(config.model_warnings().iter().any(|w| w.contains("doesn't look like a Bedrock model ID")));
+
+        config.agent.model = "arn:aws:bedrock:us-east-1:123456789012:inference-profile/my-profile".to_string();
+        assert_eq!(config.model_warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_malformed_model_id_is_rejected() {
+        let mut config = AgentConfig::default();
+        config.agent.model = "anthropic.claude 3".to_string();
+        assert!(matches!(config.validate(), Err(BedrockError::ConfigError(_))));
+
+        // Unknown but well-formed IDs only warn
+        config.agent.model = "claude-3-sonnet".to_string();
+        assert!(config.validate().is_ok());
+    }
 }
\ No newline at end of file
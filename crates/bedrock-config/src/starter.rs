@@ -0,0 +1,76 @@
+use bedrock_core::{BedrockError, Result};
+
+use crate::AgentConfig;
+
+/// Comment written above each top-level section of a starter config
+const SECTION_COMMENTS: &[(&str, &str)] = &[
+    ("agent", "Agent name and the Bedrock model it calls"),
+    ("aws", "AWS region; add `profile` or `role_arn` to pick credentials"),
+    (
+        "tools",
+        "Built-in tools. `allowed` lists the tools offered to the model (empty offers all,\n\
+         including MCP tools); `permissions` sets allow, ask or deny per tool",
+    ),
+    ("pricing", "USD per 1,000 tokens per model, used for cost reporting"),
+    ("limits", "Request, token and tool-loop limits"),
+    ("paths", "Where conversations, results and the tools' workspace live"),
+    ("mcp", "MCP servers: set enabled to true and list server config files or inline servers"),
+    ("conversation", "Conversation history kept between turns"),
+    ("tasks", "Task queue settings"),
+    ("embeddings", "Embedding model used for semantic search"),
+    ("audit", "Set log_dir to also write the log to rotated files there"),
+];
+
+impl AgentConfig {
+    /// This config as a commented YAML file for `bedrock-agent init`.
+    ///
+    /// The YAML is produced by serde, so it loads back with [`AgentConfig::from_yaml`].
+    pub fn to_starter_yaml(&self) -> Result<String> {
+        let yaml = serde_yaml::to_string(self)
+            .map_err(|e| BedrockError::ConfigError(format!("Failed to serialize config: {e}")))?;
+
+        let mut out = String::from(
+            "# Bedrock agent configuration\n\
+             # Values may reference environment variables as ${VAR} or ${VAR:-default}\n",
+        );
+        for line in yaml.lines() {
+            let section = line.strip_suffix(':').or_else(|| line.split_once(": ").map(|(key, _)| key));
+            if let Some((_, comment)) = section
+                .filter(|_| !line.starts_with([' ', '-']))
+                .and_then(|key| SECTION_COMMENTS.iter().find(|(name, _)| *name == key))
+            {
+                out.push('\n');
+                for comment_line in comment.lines() {
+                    out.push_str("# ");
+                    out.push_str(comment_line.trim_start());
+                    out.push('\n');
+                }
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starter_yaml_is_commented_and_loads_back() {
+        let mut config = AgentConfig::default();
+        config.tools.allowed = vec!["fs_read".to_string(), "grep".to_string()];
+
+        let yaml = config.to_starter_yaml().unwrap();
+        assert!(yaml.contains("\n# USD per 1,000 tokens per model, used for cost reporting\npricing:\n"));
+        assert!(yaml.contains("# MCP servers"));
+
+        let loaded = AgentConfig::from_yaml_str(&yaml).unwrap();
+        assert_eq!(loaded.agent.model, config.agent.model);
+        assert_eq!(loaded.aws.region, config.aws.region);
+        assert_eq!(loaded.tools.allowed, config.tools.allowed);
+        assert_eq!(loaded.pricing.len(), config.pricing.len());
+        assert!(!loaded.mcp.enabled);
+    }
+}
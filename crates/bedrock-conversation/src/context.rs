@@ -0,0 +1,535 @@
+//! Assembly of the message list sent to the model.
+//!
+//! Stored conversations keep every message verbatim (unless `compact_at_bytes` is set);
+//! the helpers here only shape the copy handed to Bedrock for a single request.
+
+use aws_sdk_bedrockruntime::types::{
+    ContentBlock, ConversationRole, Message, ToolResultBlock, ToolResultContentBlock,
+};
+use bedrock_config::{ContextReduction, ConversationSettings};
+use bedrock_core::{BedrockError, Result};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use tracing::info;
+
+use crate::manager::ConversationManager;
+
+/// Text used in place of tool results that were dropped from the context.
+pub const OMITTED_TOOL_RESULT: &str = "[Earlier tool result omitted from context]";
+
+/// Text used in place of a tool result that a later identical call superseded.
+pub const DUPLICATE_TOOL_RESULT: &str = "[Same tool call repeated later; see the latest result]";
+
+/// Characters of each dropped message kept in a reduction summary
+const SUMMARY_PREVIEW_CHARS: usize = 120;
+
+/// Build the context for the next model call according to the conversation settings.
+pub fn assemble_context(messages: &[Message], settings: &ConversationSettings) -> Result<Vec<Message>> {
+    let deduped;
+    let messages = if settings.dedupe_tool_results {
+        deduped = dedupe_tool_results(messages)?;
+        &deduped[..]
+    } else {
+        messages
+    };
+    match settings.max_tool_results_in_context {
+        Some(max) => limit_tool_results(messages, max),
+        None => Ok(messages.to_vec()),
+    }
+}
+
+/// Collapse results of repeated tool calls, keeping only the latest in full.
+///
+/// Calls are the same when they use the same tool with the same input. Earlier
+/// results are replaced with a reference to the latest one; the tool use ids are
+/// preserved so the conversation stays valid.
+pub fn dedupe_tool_results(messages: &[Message]) -> Result<Vec<Message>> {
+    // Key every tool use by its tool name and a hash of its input
+    let mut call_keys: HashMap<&str, String> = HashMap::new();
+    for block in messages.iter().flat_map(|m| m.content()) {
+        if let ContentBlock::ToolUse(tool_use) = block {
+            let input = ConversationManager::document_to_json(tool_use.input())?;
+            let hash = Sha256::digest(input.to_string().as_bytes());
+            call_keys.insert(tool_use.tool_use_id(), format!("{}:{:x}", tool_use.name(), hash));
+        }
+    }
+
+    // Walking backwards, the first result seen for a key is the latest one
+    let mut seen = HashSet::new();
+    let mut superseded = HashSet::new();
+    for block in messages.iter().rev().flat_map(|m| m.content().iter().rev()) {
+        if let ContentBlock::ToolResult(result) = block {
+            if let Some(key) = call_keys.get(result.tool_use_id()) {
+                if !seen.insert(key) {
+                    superseded.insert(result.tool_use_id());
+                }
+            }
+        }
+    }
+    if superseded.is_empty() {
+        return Ok(messages.to_vec());
+    }
+    info!("Collapsed {} repeated tool results in the context", superseded.len());
+
+    messages
+        .iter()
+        .map(|message| {
+            let replaces = |block: &ContentBlock| {
+                matches!(block, ContentBlock::ToolResult(r) if superseded.contains(r.tool_use_id()))
+            };
+            if !message.content().iter().any(replaces) {
+                return Ok(message.clone());
+            }
+            let content = message
+                .content()
+                .iter()
+                .map(|block| match block {
+                    ContentBlock::ToolResult(result) if replaces(block) => {
+                        placeholder_result(result, DUPLICATE_TOOL_RESULT).map(ContentBlock::ToolResult)
+                    }
+                    other => Ok(other.clone()),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Message::builder()
+                .role(message.role().clone())
+                .set_content(Some(content))
+                .build()
+                .map_err(|e| BedrockError::Unknown(format!("Failed to build context message: {e}")))
+        })
+        .collect()
+}
+
+/// Drop the oldest messages until at most `max_messages` remain.
+///
+/// The history is cut at a user turn that isn't a tool result, so it never starts
+/// with an assistant message or a result whose tool use was dropped.
+pub fn trim_history(messages: &mut Vec<Message>, max_messages: usize) {
+    if messages.len() <= max_messages {
+        return;
+    }
+
+    let mut start = messages.len() - max_messages;
+    while start < messages.len() && !starts_turn(&messages[start]) {
+        start += 1;
+    }
+    messages.drain(..start);
+}
+
+/// Whether a message opens a new user turn
+pub(crate) fn starts_turn(message: &Message) -> bool {
+    message.role() == &ConversationRole::User
+        && !message.content().iter().any(|block| matches!(block, ContentBlock::ToolResult(_)))
+}
+
+/// Keep only the most recent `max_full` tool results intact, replacing older ones
+/// with a placeholder. The tool use ids are preserved so the conversation stays valid.
+pub fn limit_tool_results(messages: &[Message], max_full: usize) -> Result<Vec<Message>> {
+    let total = messages
+        .iter()
+        .flat_map(|m| m.content())
+        .filter(|block| matches!(block, ContentBlock::ToolResult(_)))
+        .count();
+
+    if total <= max_full {
+        return Ok(messages.to_vec());
+    }
+
+    let mut to_omit = total - max_full;
+    let mut context = Vec::with_capacity(messages.len());
+
+    for message in messages {
+        if to_omit == 0 || !message.content().iter().any(|b| matches!(b, ContentBlock::ToolResult(_))) {
+            context.push(message.clone());
+            continue;
+        }
+
+        let mut content = Vec::with_capacity(message.content().len());
+        for block in message.content() {
+            match block {
+                ContentBlock::ToolResult(result) if to_omit > 0 => {
+                    to_omit -= 1;
+                    content.push(ContentBlock::ToolResult(placeholder_result(result, OMITTED_TOOL_RESULT)?));
+                }
+                other => content.push(other.clone()),
+            }
+        }
+
+        let message = Message::builder()
+            .role(message.role().clone())
+            .set_content(Some(content))
+            .build()
+            .map_err(|e| BedrockError::Unknown(format!("Failed to build context message: {e}")))?;
+        context.push(message);
+    }
+
+    Ok(context)
+}
+
+/// Shrink the context until `measure` puts the request at or under `max_tokens`.
+///
+/// The first message (the task or opening prompt) and the latest turn are always
+/// kept. Older exchanges after the first message are removed an assistant/user pair
+/// at a time, so tool uses leave together with their results and roles keep
+/// alternating. With [`ContextReduction::Summarize`] the removed messages are
+/// described in a note appended to the first message.
+///
+/// Errors when even the smallest context is over the limit.
+pub fn fit_to_token_limit(
+    messages: &[Message],
+    max_tokens: usize,
+    reduction: ContextReduction,
+    measure: impl Fn(&[Message]) -> usize,
+) -> Result<Vec<Message>> {
+    let needed = measure(messages);
+    if needed <= max_tokens {
+        return Ok(messages.to_vec());
+    }
+
+    let mut dropped = 0;
+    while dropped + 3 < messages.len()
+        && messages[dropped + 1].role() == &ConversationRole::Assistant
+        && messages[dropped + 2].role() == &ConversationRole::User
+    {
+        dropped += 2;
+        let context = reduce_context(messages, dropped, reduction)?;
+        let tokens = measure(&context);
+        if tokens <= max_tokens {
+            info!(
+                "Request of ~{} tokens exceeds max_request_tokens ({}): {} {} oldest messages, now ~{} tokens",
+                needed,
+                max_tokens,
+                match reduction {
+                    ContextReduction::TruncateOldest => "dropped",
+                    ContextReduction::Summarize => "summarized",
+                },
+                dropped,
+                tokens
+            );
+            return Ok(context);
+        }
+    }
+
+    // The summary note itself may be what doesn't fit
+    if reduction == ContextReduction::Summarize && dropped > 0 {
+        let context = reduce_context(messages, dropped, ContextReduction::TruncateOldest)?;
+        let tokens = measure(&context);
+        if tokens <= max_tokens {
+            info!(
+                "Request of ~{} tokens exceeds max_request_tokens ({}): dropped {} oldest messages without a summary, now ~{} tokens",
+                needed, max_tokens, dropped, tokens
+            );
+            return Ok(context);
+        }
+    }
+
+    Err(BedrockError::TaskError(format!(
+        "Request needs ~{needed} tokens but limits.max_request_tokens is {max_tokens}, \
+         and its context can't be reduced further"
+    )))
+}
+
+/// The context without the `dropped` messages that follow the first one
+fn reduce_context(messages: &[Message], dropped: usize, reduction: ContextReduction) -> Result<Vec<Message>> {
+    let mut context = Vec::with_capacity(messages.len() - dropped);
+    let first = &messages[0];
+
+    match reduction {
+        ContextReduction::TruncateOldest => context.push(first.clone()),
+        ContextReduction::Summarize => {
+            let mut summary = format!("[Summary of {dropped} earlier messages removed to fit the request size limit]");
+            for message in &messages[1..=dropped] {
+                summary.push_str(&format!("\n- {}: {}", message.role().as_str(), message_preview(message)));
+            }
+
+            let content = first.content().iter().cloned()
+                .chain(std::iter::once(ContentBlock::Text(summary)))
+                .collect();
+            let first = Message::builder()
+                .role(first.role().clone())
+                .set_content(Some(content))
+                .build()
+                .map_err(|e| BedrockError::Unknown(format!("Failed to build context message: {e}")))?;
+            context.push(first);
+        }
+    }
+
+    context.extend_from_slice(&messages[dropped + 1..]);
+    Ok(context)
+}
+
+/// A one-line description of a message for reduction summaries
+fn message_preview(message: &Message) -> String {
+    let text = message
+        .content()
+        .iter()
+        .map(|block| match block {
+            ContentBlock::Text(text) => text.clone(),
+            ContentBlock::ToolUse(tool_use) => format!("[called {}]", tool_use.name()),
+            ContentBlock::ToolResult(_) => "[tool result]".to_string(),
+            _ => "[attachment]".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('\n', " ");
+
+    let preview: String = text.chars().take(SUMMARY_PREVIEW_CHARS).collect();
+    if text.chars().count() > SUMMARY_PREVIEW_CHARS {
+        format!("{preview}...")
+    } else {
+        preview
+    }
+}
+
+/// The messages as a plain-text transcript, e.g. for asking a model to summarize them
+pub fn transcript(messages: &[Message]) -> String {
+    let to_json = |doc| {
+        ConversationManager::document_to_json(doc)
+            .map(|json| json.to_string())
+            .unwrap_or_default()
+    };
+
+    messages
+        .iter()
+        .map(|message| {
+            let text = message
+                .content()
+                .iter()
+                .map(|block| match block {
+                    ContentBlock::Text(text) => text.clone(),
+                    ContentBlock::ToolUse(tool_use) => {
+                        format!("[called {} with {}]", tool_use.name(), to_json(tool_use.input()))
+                    }
+                    ContentBlock::ToolResult(result) => {
+                        let content = result
+                            .content()
+                            .iter()
+                            .map(|content| match content {
+                                ToolResultContentBlock::Text(text) => text.clone(),
+                                ToolResultContentBlock::Json(doc) => to_json(doc),
+                                _ => "[attachment]".to_string(),
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        format!("[tool result]\n{content}")
+                    }
+                    _ => "[attachment]".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("{}: {}", message.role().as_str(), text)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn placeholder_result(result: &ToolResultBlock, text: &str) -> Result<ToolResultBlock> {
+    ToolResultBlock::builder()
+        .tool_use_id(result.tool_use_id())
+        .content(ToolResultContentBlock::Text(text.to_string()))
+        .set_status(result.status().cloned())
+        .build()
+        .map_err(|e| BedrockError::Unknown(format!("Failed to build placeholder tool result: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_result_message(index: usize) -> Message {
+        let result = ToolResultBlock::builder()
+            .tool_use_id(format!("tool_{index}"))
+            .content(ToolResultContentBlock::Text(format!("result {index}")))
+            .build()
+            .unwrap();
+        Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::ToolResult(result))
+            .build()
+            .unwrap()
+    }
+
+    fn result_text(message: &Message) -> &str {
+        match &message.content()[0] {
+            ContentBlock::ToolResult(result) => match &result.content()[0] {
+                ToolResultContentBlock::Text(text) => text,
+                _ => panic!("expected text tool result"),
+            },
+            _ => panic!("expected tool result"),
+        }
+    }
+
+    #[test]
+    fn test_cap_keeps_most_recent_tool_results() {
+        let messages: Vec<Message> = (0..5).map(tool_result_message).collect();
+        let settings = ConversationSettings {
+            max_tool_results_in_context: Some(2),
+            ..Default::default()
+        };
+
+        let context = assemble_context(&messages, &settings).unwrap();
+
+        assert_eq!(context.len(), 5);
+        for message in &context[..3] {
+            assert_eq!(result_text(message), OMITTED_TOOL_RESULT);
+        }
+        assert_eq!(result_text(&context[3]), "result 3");
+        assert_eq!(result_text(&context[4]), "result 4");
+
+        // The stored conversation is left untouched
+        assert_eq!(result_text(&messages[0]), "result 0");
+    }
+
+    /// An assistant turn calling `tool` with `path`, and the user turn carrying its result
+    fn tool_exchange(id: &str, tool: &str, path: &str, result: &str) -> [Message; 2] {
+        let input = aws_smithy_types::Document::Object(
+            [("path".to_string(), aws_smithy_types::Document::String(path.to_string()))].into(),
+        );
+        let tool_use = aws_sdk_bedrockruntime::types::ToolUseBlock::builder()
+            .tool_use_id(id)
+            .name(tool)
+            .input(input)
+            .build()
+            .unwrap();
+        let tool_result = ToolResultBlock::builder()
+            .tool_use_id(id)
+            .content(ToolResultContentBlock::Text(result.to_string()))
+            .build()
+            .unwrap();
+        [
+            Message::builder().role(ConversationRole::Assistant).content(ContentBlock::ToolUse(tool_use)).build().unwrap(),
+            Message::builder().role(ConversationRole::User).content(ContentBlock::ToolResult(tool_result)).build().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_repeated_file_reads_are_collapsed() {
+        let mut messages = vec![text_message(ConversationRole::User, "Fix the bug in main.rs")];
+        messages.extend(tool_exchange("t1", "fs_read", "src/main.rs", "fn main() {}"));
+        messages.extend(tool_exchange("t2", "fs_read", "src/lib.rs", "pub mod a;"));
+        messages.extend(tool_exchange("t3", "fs_read", "src/main.rs", "fn main() {}"));
+        messages.extend(tool_exchange("t4", "fs_read", "src/main.rs", "fn main() { run() }"));
+        let settings = ConversationSettings { dedupe_tool_results: true, ..Default::default() };
+
+        let context = assemble_context(&messages, &settings).unwrap();
+
+        assert_eq!(context.len(), messages.len());
+        assert_eq!(result_text(&context[2]), DUPLICATE_TOOL_RESULT);
+        assert_eq!(result_text(&context[4]), "pub mod a;");
+        assert_eq!(result_text(&context[6]), DUPLICATE_TOOL_RESULT);
+        assert_eq!(result_text(&context[8]), "fn main() { run() }");
+
+        // Without the setting every result is sent
+        let context = assemble_context(&messages, &ConversationSettings::default()).unwrap();
+        assert_eq!(result_text(&context[2]), "fn main() {}");
+    }
+
+    #[test]
+    fn test_no_cap_keeps_everything() {
+        let messages: Vec<Message> = (0..5).map(tool_result_message).collect();
+        let context = assemble_context(&messages, &ConversationSettings::default()).unwrap();
+
+        for (i, message) in context.iter().enumerate() {
+            assert_eq!(result_text(message), format!("result {i}"));
+        }
+    }
+
+    fn text_message(role: ConversationRole, text: &str) -> Message {
+        Message::builder()
+            .role(role)
+            .content(ContentBlock::Text(text.to_string()))
+            .build()
+            .unwrap()
+    }
+
+    /// A task prompt followed by `exchanges` assistant/user pairs of 100 characters each
+    fn long_conversation(exchanges: usize) -> Vec<Message> {
+        conversation_of(exchanges, 100)
+    }
+
+    /// A task prompt followed by `exchanges` assistant/user pairs of `chars` characters each
+    fn conversation_of(exchanges: usize, chars: usize) -> Vec<Message> {
+        let mut messages = vec![text_message(ConversationRole::User, "task")];
+        for i in 0..exchanges {
+            messages.push(text_message(ConversationRole::Assistant, &format!("{i}").repeat(chars)));
+            messages.push(text_message(ConversationRole::User, &"x".repeat(chars)));
+        }
+        messages
+    }
+
+    /// One token per character of text
+    fn count_chars(messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .flat_map(|m| m.content())
+            .filter_map(|block| block.as_text().ok())
+            .map(|text| text.chars().count())
+            .sum()
+    }
+
+    #[test]
+    fn test_truncation_brings_request_under_limit() {
+        let messages = long_conversation(4);
+        assert_eq!(count_chars(&messages), 804);
+
+        let context = fit_to_token_limit(&messages, 500, ContextReduction::TruncateOldest, count_chars).unwrap();
+
+        assert!(count_chars(&context) <= 500);
+        // Two exchanges had to go; the task prompt and the latest ones stay
+        assert_eq!(context.len(), 5);
+        assert_eq!(context[0].content()[0].as_text().unwrap(), "task");
+        assert_eq!(context[1].content()[0].as_text().unwrap(), &"2".repeat(100));
+        assert_eq!(context[1].role(), &ConversationRole::Assistant);
+    }
+
+    #[test]
+    fn test_summarize_notes_dropped_messages() {
+        // Messages longer than the summary preview, so summarizing them saves space
+        let messages = conversation_of(4, 400);
+        assert_eq!(count_chars(&messages), 3204);
+
+        let context = fit_to_token_limit(&messages, 2800, ContextReduction::Summarize, count_chars).unwrap();
+
+        assert!(count_chars(&context) <= 2800);
+        let summary = context[0].content()[1].as_text().unwrap();
+        assert!(summary.starts_with("[Summary of 2 earlier messages"));
+        assert!(summary.contains("assistant: 000"));
+    }
+
+    #[test]
+    fn test_irreducible_request_errors() {
+        let messages = vec![text_message(ConversationRole::User, &"x".repeat(1_000))];
+
+        let err = fit_to_token_limit(&messages, 100, ContextReduction::TruncateOldest, count_chars).unwrap_err();
+
+        assert!(err.to_string().contains("max_request_tokens"));
+    }
+
+    #[test]
+    fn test_request_under_limit_is_unchanged() {
+        let messages = long_conversation(2);
+        let context = fit_to_token_limit(&messages, 1_000, ContextReduction::Summarize, count_chars).unwrap();
+        assert_eq!(context.len(), messages.len());
+    }
+
+    #[test]
+    fn test_trim_history_starts_at_user_turn() {
+        // prompt, tool use, tool result, answer - twice
+        let turn = |i: usize| {
+            vec![
+                text_message(ConversationRole::User, &format!("question {i}")),
+                text_message(ConversationRole::Assistant, "calling a tool"),
+                tool_result_message(i),
+                text_message(ConversationRole::Assistant, &format!("answer {i}")),
+            ]
+        };
+        let mut history: Vec<Message> = turn(0).into_iter().chain(turn(1)).collect();
+
+        // Cutting at 6 would start on a tool result, so the whole first turn goes
+        trim_history(&mut history, 6);
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[0].content()[0].as_text().unwrap(), "question 1");
+
+        trim_history(&mut history, 10);
+        assert_eq!(history.len(), 4);
+    }
+}
@@ -1,7 +1,13 @@
 pub mod storage;
 pub mod metadata;
 pub mod manager;
+pub mod context;
+pub mod markdown;
+pub mod search;
 
 pub use storage::ConversationStorage;
-pub use metadata::{ConversationMetadata, MessageEntry, TokenUsageStats};
-pub use manager::ConversationManager;
\ No newline at end of file
+pub use metadata::{ConversationMetadata, ConversationSnapshot, ConversationSummary, MessageEntry, TokenUsageStats};
+pub use manager::ConversationManager;
+pub use markdown::{conversation_to_markdown, message_to_markdown};
+pub use search::{SearchHit, SearchQuery};
+pub use context::{assemble_context, dedupe_tool_results, fit_to_token_limit, transcript, trim_history};
\ No newline at end of file
@@ -1,17 +1,22 @@
-use aws_sdk_bedrockruntime::types::{ContentBlock, ConversationRole, Message};
+use aws_sdk_bedrockruntime::types::{ContentBlock, ConversationRole, Message, ToolResultContentBlock};
 use aws_smithy_types::Document;
+use bedrock_config::ConversationSettings;
 use bedrock_core::{BedrockError, Result};
 use serde_json::Value;
-use tracing::{debug, info};
+use std::future::Future;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-use crate::metadata::{MessageEntry, TokenUsageStats};
+use crate::context::starts_turn;
+use crate::metadata::{ConversationMetadata, ConversationSnapshot, MessageEntry, TokenUsageStats};
 use crate::storage::ConversationStorage;
 
 /// Manages conversation state and persistence
 pub struct ConversationManager {
     storage: ConversationStorage,
     conversation_id: Option<Uuid>,
+    tag_tools: bool,
+    compact_keep_messages: usize,
 }
 
 impl ConversationManager {
@@ -21,11 +26,31 @@ impl ConversationManager {
         Ok(Self {
             storage,
             conversation_id: None,
+            tag_tools: true,
+            compact_keep_messages: ConversationSettings::default().compact_keep_messages,
         })
     }
     
+    /// Apply conversation settings such as automatic compaction
+    pub fn with_settings(mut self, settings: &ConversationSettings) -> Self {
+        self.storage = self.storage.with_compaction(settings.compact_at_bytes, settings.compact_keep_messages);
+        self.tag_tools = settings.tag_tools;
+        self.compact_keep_messages = settings.compact_keep_messages;
+        self
+    }
+    
+    /// Create a conversation manager on top of an existing storage
+    pub fn with_storage(storage: ConversationStorage) -> Self {
+        Self {
+            storage,
+            conversation_id: None,
+            tag_tools: true,
+            compact_keep_messages: ConversationSettings::default().compact_keep_messages,
+        }
+    }
+    
     /// Convert AWS Document to JSON Value for serialization
-    fn document_to_json(doc: &Document) -> Result<Value> {
+    pub(crate) fn document_to_json(doc: &Document) -> Result<Value> {
         match doc {
             Document::Null => Ok(Value::Null),
             Document::Bool(b) => Ok(Value::Bool(*b)),
@@ -33,11 +58,13 @@ impl ConversationManager {
                 match n {
                     aws_smithy_types::Number::PosInt(u) => Ok(Value::Number((*u).into())),
                     aws_smithy_types::Number::NegInt(i) => Ok(Value::Number((*i).into())),
-                    aws_smithy_types::Number::Float(f) => {
-                        serde_json::Number::from_f64(*f)
-                            .map(Value::Number)
-                            .ok_or_else(|| BedrockError::Unknown("Invalid float value".into()))
-                    }
+                    // JSON has no NaN or infinity; one such value mustn't fail the whole document
+                    aws_smithy_types::Number::Float(f) => Ok(serde_json::Number::from_f64(*f)
+                        .map(Value::Number)
+                        .unwrap_or_else(|| {
+                            warn!("Replacing non-finite number {} with null", f);
+                            Value::Null
+                        })),
                 }
             }
             Document::String(s) => Ok(Value::String(s.clone())),
@@ -153,6 +180,7 @@ impl ConversationManager {
         let mut metadata = self.storage.load_metadata(&conversation_id)?;
         metadata.message_count += 1;
         metadata.updated_at = chrono::Utc::now();
+        self.tag_with_tools(&mut metadata, entry.tool_name.as_deref())?;
         self.storage.save_metadata(&metadata)?;
         
         Ok(())
@@ -192,15 +220,15 @@ impl ConversationManager {
                         })
                     },
                     ContentBlock::ToolResult(tool_result) => {
-                        // Get the actual content from tool result
+                        // Get the actual content from tool result; JSON output is kept as compact JSON text
                         let result_content = tool_result.content()
                             .iter()
-                            .filter_map(|c| {
-                                if let Ok(text) = c.as_text() {
-                                    Some(text.to_string())
-                                } else {
-                                    None
+                            .filter_map(|c| match c {
+                                ToolResultContentBlock::Text(text) => Some(text.clone()),
+                                ToolResultContentBlock::Json(doc) => {
+                                    Self::document_to_json(doc).ok().map(|json| json.to_string())
                                 }
+                                _ => None,
                             })
                             .collect::<Vec<_>>()
                             .join("\n");
@@ -263,11 +291,28 @@ impl ConversationManager {
             }
         }
         
+        let tools_used = message.content().iter()
+            .filter_map(|block| block.as_tool_use().ok())
+            .map(|tool_use| tool_use.name());
+        self.tag_with_tools(&mut metadata, tools_used)?;
+        
         self.storage.save_metadata(&metadata)?;
         
         Ok(())
     }
     
+    /// Record tool names as derived tags, refreshing the index when the tags change
+    fn tag_with_tools<'a>(
+        &self,
+        metadata: &mut ConversationMetadata,
+        tools: impl IntoIterator<Item = &'a str>,
+    ) -> Result<()> {
+        if self.tag_tools && metadata.add_derived_tags(tools) {
+            self.storage.update_index(metadata)?;
+        }
+        Ok(())
+    }
+    
     /// Save task results associated with the conversation
     pub fn save_task_results(&self, tasks: Value) -> Result<()> {
         let conversation_id = self.conversation_id
@@ -295,6 +340,101 @@ impl ConversationManager {
         Ok(())
     }
     
+    /// Record the current end of the conversation under a label
+    pub fn snapshot(&self, label: impl Into<String>) -> Result<ConversationSnapshot> {
+        let conversation_id = self.conversation_id
+            .ok_or_else(|| BedrockError::TaskError("No active conversation".to_string()))?;
+        
+        let message_count = self.storage.read_messages(&conversation_id)?.len();
+        let snapshot = ConversationSnapshot::new(label.into(), message_count);
+        
+        let mut snapshots = self.storage.load_snapshots(&conversation_id)?;
+        snapshots.push(snapshot.clone());
+        self.storage.save_snapshots(&conversation_id, &snapshots)?;
+        
+        info!("Created snapshot '{}' ({}) at message {} in conversation {}",
+              snapshot.label, snapshot.id, message_count, conversation_id);
+        Ok(snapshot)
+    }
+    
+    /// List the snapshots of the current conversation, oldest first
+    pub fn list_snapshots(&self) -> Result<Vec<ConversationSnapshot>> {
+        let conversation_id = self.conversation_id
+            .ok_or_else(|| BedrockError::TaskError("No active conversation".to_string()))?;
+        
+        self.storage.load_snapshots(&conversation_id)
+    }
+    
+    /// Discard every message added after a snapshot and return the remaining history.
+    /// Snapshots taken after the rollback point no longer exist in the history and are dropped.
+    pub fn rollback(&self, snapshot_id: Uuid) -> Result<Vec<MessageEntry>> {
+        let conversation_id = self.conversation_id
+            .ok_or_else(|| BedrockError::TaskError("No active conversation".to_string()))?;
+        
+        let mut snapshots = self.storage.load_snapshots(&conversation_id)?;
+        let snapshot = snapshots.iter()
+            .find(|s| s.id == snapshot_id)
+            .cloned()
+            .ok_or_else(|| BedrockError::NotFound(format!("Snapshot not found: {snapshot_id}")))?;
+        
+        let mut messages = self.storage.read_messages(&conversation_id)?;
+        messages.truncate(snapshot.message_count);
+        self.storage.rewrite_messages(&conversation_id, &messages)?;
+        
+        snapshots.retain(|s| s.message_count <= snapshot.message_count);
+        self.storage.save_snapshots(&conversation_id, &snapshots)?;
+        
+        let mut metadata = self.storage.load_metadata(&conversation_id)?;
+        metadata.message_count = messages.len();
+        metadata.updated_at = chrono::Utc::now();
+        self.storage.save_metadata(&metadata)?;
+        
+        info!("Rolled back conversation {} to snapshot '{}' ({} messages)",
+              conversation_id, snapshot.label, messages.len());
+        Ok(messages)
+    }
+    
+    /// Tag the current conversation. Returns whether the tag was new.
+    pub fn add_tag(&self, tag: &str) -> Result<bool> {
+        let tag = tag.trim();
+        if tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || c == ',') {
+            return Err(BedrockError::ConfigError(format!(
+                "Invalid tag '{tag}': tags must be non-empty and contain no spaces or commas"
+            )));
+        }
+        self.update_tags(|tags| match tags.binary_search_by(|t| t.as_str().cmp(tag)) {
+            Ok(_) => false,
+            Err(pos) => {
+                tags.insert(pos, tag.to_string());
+                true
+            }
+        })
+    }
+    
+    /// Remove a tag from the current conversation. Returns whether it had the tag.
+    pub fn remove_tag(&self, tag: &str) -> Result<bool> {
+        let tag = tag.trim();
+        self.update_tags(|tags| {
+            let before = tags.len();
+            tags.retain(|t| t != tag);
+            tags.len() != before
+        })
+    }
+    
+    /// Apply `change` to the current conversation's tags, saving them and the index if it reports a change
+    fn update_tags(&self, change: impl FnOnce(&mut Vec<String>) -> bool) -> Result<bool> {
+        let conversation_id = self.conversation_id
+            .ok_or_else(|| BedrockError::TaskError("No active conversation".to_string()))?;
+        
+        let mut metadata = self.storage.load_metadata(&conversation_id)?;
+        if !change(&mut metadata.tags) {
+            return Ok(false);
+        }
+        self.storage.save_metadata(&metadata)?;
+        self.storage.update_index(&metadata)?;
+        Ok(true)
+    }
+    
     /// List all conversations for the current workspace
     pub fn list_conversations(&self) -> Result<Vec<crate::metadata::ConversationSummary>> {
         self.storage.list_conversations()
@@ -308,6 +448,83 @@ impl ConversationManager {
         self.storage.export_conversation(&conversation_id, output_path)
     }
     
+    /// Summarize the oldest messages of an in-memory conversation once `measure`
+    /// puts it over `threshold` tokens.
+    ///
+    /// The first message and at least `compact_keep_messages` recent messages stay
+    /// verbatim. The exchanges between them are replaced by a single assistant
+    /// message holding the text returned by `summarize`, so roles keep alternating
+    /// and tool uses leave together with their results. The compaction is recorded
+    /// in the current conversation's metadata.
+    ///
+    /// Returns the estimated tokens saved, or 0 when nothing was compacted.
+    pub async fn compact<S, F>(
+        &self,
+        messages: &mut Vec<Message>,
+        threshold: usize,
+        measure: impl Fn(&[Message]) -> usize,
+        summarize: S,
+    ) -> Result<usize>
+    where
+        S: FnOnce(Vec<Message>) -> F,
+        F: Future<Output = Result<String>>,
+    {
+        let before = measure(messages.as_slice());
+        if before <= threshold {
+            return Ok(0);
+        }
+        
+        // The latest message is always kept, even with `compact_keep_messages: 0`
+        let mut keep_from = messages.len().saturating_sub(self.compact_keep_messages.max(1));
+        while keep_from > 0 && !starts_turn(&messages[keep_from]) {
+            keep_from -= 1;
+        }
+        // Summarizing a single message doesn't save anything
+        let summarized = keep_from.saturating_sub(1);
+        if summarized < 2 {
+            debug!("Conversation of ~{} tokens has no older messages to compact", before);
+            return Ok(0);
+        }
+        
+        let summary = summarize(messages[1..keep_from].to_vec()).await?;
+        if summary.trim().is_empty() {
+            return Err(BedrockError::TaskError("Compaction produced an empty summary".to_string()));
+        }
+        let summary_message = Message::builder()
+            .role(ConversationRole::Assistant)
+            .content(ContentBlock::Text(format!(
+                "[Summary of {} earlier messages]\n{}",
+                summarized,
+                summary.trim()
+            )))
+            .build()
+            .map_err(|e| BedrockError::Unknown(format!("Failed to build summary message: {e}")))?;
+        
+        let mut compacted = Vec::with_capacity(messages.len() - summarized + 1);
+        compacted.push(messages[0].clone());
+        compacted.push(summary_message);
+        compacted.extend_from_slice(&messages[keep_from..]);
+        
+        let after = measure(&compacted);
+        if after >= before {
+            warn!("Summary of {} messages is no smaller than the messages, keeping them", summarized);
+            return Ok(0);
+        }
+        *messages = compacted;
+        let saved = before - after;
+        info!("Compacted {} messages: ~{} -> ~{} tokens", summarized, before, after);
+        
+        if let Some(conversation_id) = self.conversation_id {
+            let mut metadata = self.storage.load_metadata(&conversation_id)?;
+            metadata.compactions += 1;
+            metadata.compaction_tokens_saved += saved;
+            metadata.updated_at = chrono::Utc::now();
+            self.storage.save_metadata(&metadata)?;
+        }
+        
+        Ok(saved)
+    }
+    
     /// Get the current conversation ID
     pub fn current_conversation_id(&self) -> Option<Uuid> {
         self.conversation_id
@@ -322,4 +539,211 @@ impl ConversationManager {
         }
         0
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    
+    fn manager(temp_dir: &TempDir) -> ConversationManager {
+        let storage = ConversationStorage::with_home_dir(temp_dir.path()).unwrap();
+        let mut manager = ConversationManager::with_storage(storage);
+        manager.start_conversation("test-model".to_string(), None).unwrap();
+        manager
+    }
+    
+    fn contents(messages: &[MessageEntry]) -> Vec<String> {
+        messages.iter().map(|m| m.content.as_str().unwrap_or_default().to_string()).collect()
+    }
+    
+    #[test]
+    fn test_rollback_restores_snapshot_state() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = manager(&temp_dir);
+        let conversation_id = manager.current_conversation_id().unwrap();
+        
+        manager.add_user_message("first question".to_string()).unwrap();
+        manager.add_assistant_message("first answer".to_string(), None).unwrap();
+        let snapshot = manager.snapshot("before detour").unwrap();
+        assert_eq!(snapshot.message_count, 2);
+        
+        manager.add_user_message("go off the rails".to_string()).unwrap();
+        manager.add_assistant_message("off the rails".to_string(), None).unwrap();
+        let later = manager.snapshot("detour").unwrap();
+        
+        let remaining = manager.rollback(snapshot.id).unwrap();
+        assert_eq!(contents(&remaining), vec!["first question", "first answer"]);
+        
+        // Storage and metadata reflect the rollback after a fresh resume
+        let history = manager.resume_conversation(conversation_id).unwrap();
+        assert_eq!(contents(&history), contents(&remaining));
+        assert_eq!(manager.get_message_count(), 2);
+        
+        // The later snapshot pointed past the rollback point and is gone
+        let snapshots = manager.list_snapshots().unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert!(matches!(manager.rollback(later.id), Err(BedrockError::NotFound(_))));
+        
+        // The conversation can carry on from the snapshot
+        manager.add_user_message("second question".to_string()).unwrap();
+        assert_eq!(manager.resume_conversation(conversation_id).unwrap().len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_compact_summarizes_older_turns() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = manager(&temp_dir);
+        manager.compact_keep_messages = 2;
+        let conversation_id = manager.current_conversation_id().unwrap();
+        
+        let text = |role: ConversationRole, text: &str| {
+            Message::builder().role(role).content(ContentBlock::Text(text.to_string())).build().unwrap()
+        };
+        let mut messages = Vec::new();
+        for turn in 0..4 {
+            messages.push(text(ConversationRole::User, &format!("question {turn} {}", "x".repeat(100))));
+            messages.push(text(ConversationRole::Assistant, &format!("answer {turn} {}", "y".repeat(100))));
+        }
+        let measure = |messages: &[Message]| crate::context::transcript(messages).len();
+        
+        // Under the threshold nothing changes
+        let saved = manager.compact(&mut messages, 10_000, measure, |_| async { Ok("unused".to_string()) }).await.unwrap();
+        assert_eq!(saved, 0);
+        assert_eq!(messages.len(), 8);
+        
+        let saved = manager
+            .compact(&mut messages, 100, measure, |older| async move {
+                assert_eq!(older.len(), 5);
+                Ok("earlier questions were answered".to_string())
+            })
+            .await
+            .unwrap();
+        
+        assert!(saved > 0);
+        let texts: Vec<_> = messages.iter().map(|m| m.content()[0].as_text().unwrap().clone()).collect();
+        assert_eq!(texts.len(), 4);
+        assert!(texts[0].starts_with("question 0"));
+        assert_eq!(texts[1], "[Summary of 5 earlier messages]\nearlier questions were answered");
+        assert!(texts[2].starts_with("question 3"));
+        assert!(texts[3].starts_with("answer 3"));
+        assert_eq!(messages[1].role(), &ConversationRole::Assistant);
+        
+        let metadata = manager.storage.load_metadata(&conversation_id).unwrap();
+        assert_eq!(metadata.compactions, 1);
+        assert_eq!(metadata.compaction_tokens_saved, saved);
+    }
+    
+    #[tokio::test]
+    async fn test_compact_with_no_kept_messages_keeps_the_last_turn() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = manager(&temp_dir);
+        manager.compact_keep_messages = 0;
+        
+        let text = |role: ConversationRole, text: &str| {
+            Message::builder().role(role).content(ContentBlock::Text(text.to_string())).build().unwrap()
+        };
+        let mut messages = Vec::new();
+        for turn in 0..3 {
+            messages.push(text(ConversationRole::User, &format!("question {turn} {}", "x".repeat(100))));
+            messages.push(text(ConversationRole::Assistant, &format!("answer {turn} {}", "y".repeat(100))));
+        }
+        let measure = |messages: &[Message]| crate::context::transcript(messages).len();
+        
+        let saved = manager
+            .compact(&mut messages, 100, measure, |_| async { Ok("earlier turns".to_string()) })
+            .await
+            .unwrap();
+        
+        assert!(saved > 0);
+        let texts: Vec<_> = messages.iter().map(|m| m.content()[0].as_text().unwrap().clone()).collect();
+        assert_eq!(texts.len(), 4);
+        assert!(texts[2].starts_with("question 2"));
+        assert!(texts[3].starts_with("answer 2"));
+    }
+    
+    #[test]
+    fn test_tools_used_become_derived_tags() {
+        use aws_sdk_bedrockruntime::types::ToolUseBlock;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let conversation_id = manager.current_conversation_id().unwrap();
+        
+        let tool_use = |name: &str| {
+            ContentBlock::ToolUse(
+                ToolUseBlock::builder()
+                    .tool_use_id(format!("{name}-1"))
+                    .name(name)
+                    .input(Document::Object(Default::default()))
+                    .build()
+                    .unwrap(),
+            )
+        };
+        let message = Message::builder()
+            .role(ConversationRole::Assistant)
+            .content(tool_use("git"))
+            .content(tool_use("execute_bash"))
+            .build()
+            .unwrap();
+        manager.save_bedrock_message(&message, None).unwrap();
+        manager.add_tool_message("git".to_string(), "git-1".to_string(), Value::Null).unwrap();
+        
+        let metadata = manager.storage.load_metadata(&conversation_id).unwrap();
+        assert_eq!(metadata.derived_tags, vec!["execute_bash", "git"]);
+        
+        let listed = manager.list_conversations().unwrap();
+        let summary = listed.iter().find(|c| c.id == conversation_id).unwrap();
+        assert_eq!(summary.derived_tags, vec!["execute_bash", "git"]);
+    }
+    
+    #[test]
+    fn test_json_tool_results_are_stored() {
+        use aws_sdk_bedrockruntime::types::ToolResultBlock;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let conversation_id = manager.current_conversation_id().unwrap();
+        
+        let output = Document::Object([("exit_code".to_string(), Document::from(0u64))].into());
+        let message = Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::ToolResult(
+                ToolResultBlock::builder()
+                    .tool_use_id("bash-1")
+                    .content(ToolResultContentBlock::Json(output))
+                    .build()
+                    .unwrap(),
+            ))
+            .build()
+            .unwrap();
+        manager.save_bedrock_message(&message, None).unwrap();
+        
+        let messages = manager.storage.read_messages(&conversation_id).unwrap();
+        let stored = &messages.last().unwrap().content[0];
+        assert_eq!(stored["tool_use_id"], "bash-1");
+        assert_eq!(stored["content"], r#"{"exit_code":0}"#);
+    }
+    
+    #[test]
+    fn test_tags_are_saved_and_listed() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager = manager(&temp_dir);
+        let conversation_id = manager.current_conversation_id().unwrap();
+        
+        assert!(manager.add_tag("release").unwrap());
+        assert!(manager.add_tag(" bugfix ").unwrap());
+        assert!(!manager.add_tag("release").unwrap());
+        assert!(manager.add_tag("two words").is_err());
+        assert_eq!(manager.storage.load_metadata(&conversation_id).unwrap().tags, vec!["bugfix", "release"]);
+        
+        assert!(manager.remove_tag("release").unwrap());
+        assert!(!manager.remove_tag("release").unwrap());
+        
+        let listed = manager.list_conversations().unwrap();
+        let summary = listed.iter().find(|c| c.id == conversation_id).unwrap();
+        assert_eq!(summary.tags, vec!["bugfix"]);
+        assert!(summary.has_tag("bugfix"));
+        assert!(!summary.has_tag("release"));
+    }
+}
@@ -0,0 +1,231 @@
+//! Markdown rendering of stored conversations for sharing and documentation
+
+use serde_json::Value;
+
+use crate::metadata::{ConversationMetadata, MessageEntry};
+
+/// Render a whole conversation: a metadata header followed by every message
+pub fn conversation_to_markdown(metadata: &ConversationMetadata, messages: &[MessageEntry]) -> String {
+    let usage = &metadata.token_usage;
+    let mut out = format!("# Conversation {}\n\n", metadata.id);
+    out.push_str("| | |\n|---|---|\n");
+    out.push_str(&format!("| Model | `{}` |\n", metadata.model_id));
+    out.push_str(&format!("| Created | {} |\n", metadata.created_at.format("%Y-%m-%d %H:%M:%S UTC")));
+    out.push_str(&format!("| Updated | {} |\n", metadata.updated_at.format("%Y-%m-%d %H:%M:%S UTC")));
+    out.push_str(&format!("| Messages | {} |\n", metadata.message_count));
+    out.push_str(&format!(
+        "| Tokens | {} input / {} output / {} total |\n",
+        usage.input_tokens, usage.output_tokens, usage.total_tokens
+    ));
+    if let Some(cost) = usage.total_cost {
+        out.push_str(&format!("| Cost | ${cost:.4} |\n"));
+    }
+    if let Some(system_prompt) = &metadata.system_prompt {
+        out.push_str(&format!("\n**System prompt**\n\n{}\n", render_text(system_prompt)));
+    }
+
+    for message in messages {
+        out.push_str("\n---\n\n");
+        out.push_str(&message_to_markdown(message));
+    }
+    out
+}
+
+/// Render one message under a heading for its role.
+///
+/// Tool calls and results become collapsible `<details>` sections.
+pub fn message_to_markdown(entry: &MessageEntry) -> String {
+    let mut out = format!(
+        "## {} · {}\n\n",
+        role_heading(&entry.role),
+        entry.timestamp.format("%Y-%m-%d %H:%M:%S")
+    );
+
+    match &entry.content {
+        Value::String(text) if entry.role == "tool" => {
+            out.push_str(&tool_result_section(entry.tool_name.as_deref(), None, text));
+        }
+        Value::String(text) => {
+            out.push_str(&render_text(text));
+            out.push('\n');
+        }
+        Value::Array(blocks) => {
+            for block in blocks {
+                out.push_str(&render_block(block));
+            }
+        }
+        Value::Null => {}
+        other if entry.role == "tool" => {
+            out.push_str(&tool_result_section(entry.tool_name.as_deref(), None, &pretty_json(other)));
+        }
+        other => out.push_str(&fence("json", &pretty_json(other))),
+    }
+    out
+}
+
+fn role_heading(role: &str) -> String {
+    match role {
+        "user" => "User".to_string(),
+        "assistant" => "Assistant".to_string(),
+        "tool" => "Tool".to_string(),
+        "summary" => "Summary".to_string(),
+        "system" => "System".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// One content block as stored by `ConversationManager::save_bedrock_message`
+fn render_block(block: &Value) -> String {
+    match block.get("type").and_then(Value::as_str) {
+        Some("text") => {
+            let text = block.get("text").and_then(Value::as_str).unwrap_or_default();
+            format!("{}\n\n", render_text(text))
+        }
+        Some("tool_use") => {
+            let name = block.get("name").and_then(Value::as_str).unwrap_or("tool");
+            let input = block.get("input").map(pretty_json).unwrap_or_default();
+            format!(
+                "<details>\n<summary>Tool call: <code>{name}</code></summary>\n\n{}\n</details>\n\n",
+                fence("json", &input)
+            )
+        }
+        Some("tool_result") => {
+            let status = block.get("status").and_then(Value::as_str);
+            let content = match block.get("content") {
+                Some(Value::String(text)) => text.clone(),
+                Some(other) => pretty_json(other),
+                None => String::new(),
+            };
+            tool_result_section(None, status, &content)
+        }
+        _ => fence("json", &pretty_json(block)),
+    }
+}
+
+fn tool_result_section(tool_name: Option<&str>, status: Option<&str>, content: &str) -> String {
+    let mut summary = "Tool result".to_string();
+    if let Some(name) = tool_name {
+        summary.push_str(&format!(": <code>{name}</code>"));
+    }
+    if let Some(status) = status {
+        summary.push_str(&format!(" ({})", status.trim_start_matches("Some(").trim_end_matches(')')));
+    }
+    let body = if serde_json::from_str::<Value>(content).is_ok_and(|v| v.is_object() || v.is_array()) {
+        fence("json", content)
+    } else {
+        fence("", content)
+    };
+    format!("<details>\n<summary>{summary}</summary>\n\n{body}\n</details>\n\n")
+}
+
+/// Text as Markdown, fencing it when it looks like code and isn't fenced already
+fn render_text(text: &str) -> String {
+    if text.contains("```") {
+        return text.to_string();
+    }
+    if serde_json::from_str::<Value>(text).is_ok_and(|v| v.is_object() || v.is_array()) {
+        return fence("json", text);
+    }
+    if looks_like_code(text) {
+        return fence("", text);
+    }
+    text.to_string()
+}
+
+/// Most non-blank lines of a multi-line text read like source code
+fn looks_like_code(text: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "fn ", "pub ", "let ", "use ", "impl ", "struct ", "def ", "class ", "import ",
+        "from ", "const ", "function ", "return ", "#include", "#!",
+    ];
+
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.len() < 2 {
+        return false;
+    }
+    let code_lines = lines
+        .iter()
+        .filter(|line| {
+            let trimmed = line.trim();
+            trimmed.ends_with([';', '{', '}', ')'])
+                || trimmed.starts_with('}')
+                || KEYWORDS.iter().any(|k| trimmed.starts_with(k))
+        })
+        .count();
+    code_lines * 2 >= lines.len()
+}
+
+/// A fenced block long enough not to be closed by backticks in the body
+fn fence(lang: &str, body: &str) -> String {
+    let mut ticks = "```".to_string();
+    while body.contains(&ticks) {
+        ticks.push('`');
+    }
+    format!("{ticks}{lang}\n{}\n{ticks}\n", body.trim_end())
+}
+
+fn pretty_json(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_header_and_turns() {
+        let mut metadata = ConversationMetadata::new("test-model".to_string(), None);
+        metadata.message_count = 2;
+        metadata.token_usage.total_tokens = 150;
+        metadata.token_usage.total_cost = Some(0.0123);
+        let messages = vec![
+            MessageEntry::user("What does this do?".to_string()),
+            MessageEntry::assistant("fn main() {\n    println!(\"hi\");\n}".to_string()),
+        ];
+
+        let markdown = conversation_to_markdown(&metadata, &messages);
+
+        assert!(markdown.starts_with(&format!("# Conversation {}", metadata.id)));
+        assert!(markdown.contains("| Model | `test-model` |"));
+        assert!(markdown.contains("/ 150 total |"));
+        assert!(markdown.contains("| Cost | $0.0123 |"));
+        assert!(markdown.contains("## User · "));
+        assert!(markdown.contains("What does this do?\n"));
+        assert!(markdown.contains("## Assistant · "));
+        assert!(markdown.contains("```\nfn main() {\n    println!(\"hi\");\n}\n```"));
+    }
+
+    #[test]
+    fn test_prose_is_not_fenced() {
+        assert_eq!(render_text("Sure.\nHere is why it works."), "Sure.\nHere is why it works.");
+        assert_eq!(render_text("Already ```fenced```"), "Already ```fenced```");
+    }
+
+    #[test]
+    fn test_tool_blocks_are_collapsible() {
+        let mut call = MessageEntry::assistant(String::new());
+        call.content = json!([
+            {"type": "text", "text": "Searching"},
+            {"type": "tool_use", "tool_use_id": "t1", "name": "grep", "input": {"pattern": "TODO"}},
+        ]);
+        let mut result = MessageEntry::user(String::new());
+        result.content = json!([
+            {"type": "tool_result", "tool_use_id": "t1", "status": "Some(Success)", "content": "src/main.rs:3: TODO"},
+        ]);
+
+        let call_md = message_to_markdown(&call);
+        assert!(call_md.contains("Searching\n"));
+        assert!(call_md.contains("<summary>Tool call: <code>grep</code></summary>"));
+        assert!(call_md.contains("\"pattern\": \"TODO\""));
+
+        let result_md = message_to_markdown(&result);
+        assert!(result_md.contains("<summary>Tool result (Success)</summary>"));
+        assert!(result_md.contains("```\nsrc/main.rs:3: TODO\n```"));
+    }
+
+    #[test]
+    fn test_fence_grows_past_backticks_in_body() {
+        assert_eq!(fence("", "a ``` b"), "````\na ``` b\n````\n");
+    }
+}
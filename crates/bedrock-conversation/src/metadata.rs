@@ -27,6 +27,18 @@ pub struct ConversationMetadata {
     pub failed_tasks: usize,
     #[serde(default)]
     pub token_usage: TokenUsageStats,
+    /// Tags derived from the tools used in the conversation, sorted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived_tags: Vec<String>,
+    /// Tags applied by the user, sorted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Times older messages were summarized to keep the context small
+    #[serde(default)]
+    pub compactions: usize,
+    /// Estimated tokens removed from the context by those summaries
+    #[serde(default)]
+    pub compaction_tokens_saved: usize,
 }
 
 impl ConversationMetadata {
@@ -49,7 +61,43 @@ impl ConversationMetadata {
             completed_tasks: 0,
             failed_tasks: 0,
             token_usage: TokenUsageStats::default(),
+            derived_tags: Vec::new(),
+            tags: Vec::new(),
+            compactions: 0,
+            compaction_tokens_saved: 0,
+        }
+    }
+    
+    /// Add derived tags, keeping them sorted and unique. Returns whether any were new.
+    pub fn add_derived_tags<'a>(&mut self, tags: impl IntoIterator<Item = &'a str>) -> bool {
+        let mut changed = false;
+        for tag in tags {
+            if let Err(pos) = self.derived_tags.binary_search_by(|t| t.as_str().cmp(tag)) {
+                self.derived_tags.insert(pos, tag.to_string());
+                changed = true;
+            }
         }
+        changed
+    }
+
+    /// Lines printed when the conversation is resumed; `show_usage` adds the
+    /// tokens and cost recorded so far
+    pub fn resume_header(&self, message_count: usize, show_usage: bool) -> String {
+        let mut out = format!("\n📖 Resumed conversation: {}\n", self.id);
+        out.push_str(&format!("Found {} previous messages\n", message_count));
+        if show_usage {
+            let usage = &self.token_usage;
+            out.push_str(&format!("Model: {}\n", self.model_id));
+            out.push_str(&format!(
+                "Tokens so far: {} ({} input, {} output)\n",
+                usage.total_tokens, usage.input_tokens, usage.output_tokens
+            ));
+            match usage.total_cost {
+                Some(cost) => out.push_str(&format!("Cost so far: ${cost:.4}\n")),
+                None => out.push_str("Cost so far: not recorded\n"),
+            }
+        }
+        out
     }
 }
 
@@ -90,6 +138,18 @@ impl MessageEntry {
         }
     }
     
+    /// A digest standing in for messages collapsed by compaction
+    pub fn summary(content: String) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            role: "summary".to_string(),
+            content: serde_json::Value::String(content),
+            tool_name: None,
+            tool_use_id: None,
+            tokens: None,
+        }
+    }
+    
     pub fn tool(tool_name: String, tool_use_id: String, result: serde_json::Value) -> Self {
         Self {
             timestamp: Utc::now(),
@@ -102,6 +162,27 @@ impl MessageEntry {
     }
 }
 
+/// A labelled point in a conversation that it can be rolled back to.
+/// The message log is append-only, so a snapshot only needs the log length.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationSnapshot {
+    pub id: Uuid,
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+    pub message_count: usize,
+}
+
+impl ConversationSnapshot {
+    pub fn new(label: String, message_count: usize) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            label,
+            created_at: Utc::now(),
+            message_count,
+        }
+    }
+}
+
 /// Summary of a conversation for listing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversationSummary {
@@ -114,6 +195,17 @@ pub struct ConversationSummary {
     pub task_count: usize,
     pub completed_tasks: usize,
     pub failed_tasks: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived_tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl ConversationSummary {
+    /// Whether the conversation carries `tag`, applied or derived
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().chain(&self.derived_tags).any(|t| t == tag)
+    }
 }
 
 impl From<&ConversationMetadata> for ConversationSummary {
@@ -128,6 +220,8 @@ impl From<&ConversationMetadata> for ConversationSummary {
             task_count: meta.task_count,
             completed_tasks: meta.completed_tasks,
             failed_tasks: meta.failed_tasks,
+            derived_tags: meta.derived_tags.clone(),
+            tags: meta.tags.clone(),
         }
     }
 }
@@ -166,4 +260,33 @@ impl ConversationIndex {
         }
         self.last_updated = Utc::now();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_header_shows_stored_usage() {
+        let mut metadata = ConversationMetadata::new("test-model".to_string(), None);
+        metadata.token_usage = TokenUsageStats {
+            input_tokens: 1200,
+            output_tokens: 300,
+            total_tokens: 1500,
+            total_cost: Some(0.0123),
+        };
+
+        let header = metadata.resume_header(4, true);
+        assert!(header.contains(&format!("Resumed conversation: {}", metadata.id)));
+        assert!(header.contains("Found 4 previous messages"));
+        assert!(header.contains("Model: test-model"));
+        assert!(header.contains("Tokens so far: 1500 (1200 input, 300 output)"));
+        assert!(header.contains("Cost so far: $0.0123"));
+
+        let quiet = metadata.resume_header(4, false);
+        assert!(!quiet.contains("Tokens so far"));
+        assert!(!quiet.contains("Cost so far"));
+
+        metadata.token_usage.total_cost = None;
+        assert!(metadata.resume_header(4, true).contains("Cost so far: not recorded"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Full-text search over stored conversations.
+//!
+//! Queries are case-insensitive substring matches. Whitespace-separated terms must
+//! all appear in a conversation (`AND` may be written explicitly), `OR` separates
+//! alternatives, and double quotes keep a phrase together: `"rate limiter" OR backoff`.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::metadata::MessageEntry;
+
+/// Characters of context kept on each side of a match in a snippet
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+/// Snippets kept per conversation
+const MAX_SNIPPETS: usize = 3;
+
+/// A conversation that matched a search
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub conversation_id: Uuid,
+    /// Occurrences of all query terms across the conversation's messages
+    pub match_count: usize,
+    /// Excerpts around matches, with the matched text wrapped in `**`
+    pub snippets: Vec<String>,
+}
+
+/// A parsed query: any one group matches when all of its terms are present
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    groups: Vec<Vec<String>>,
+}
+
+impl SearchQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut groups = vec![Vec::new()];
+        for (token, quoted) in tokenize(query) {
+            match token.as_str() {
+                "OR" if !quoted => groups.push(Vec::new()),
+                "AND" if !quoted => {}
+                _ => groups.last_mut().unwrap().push(token.to_lowercase()),
+            }
+        }
+        groups.retain(|group| !group.is_empty());
+        Self { groups }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Every distinct term in the query
+    fn terms(&self) -> Vec<&str> {
+        let mut terms: Vec<&str> = self.groups.iter().flatten().map(String::as_str).collect();
+        terms.sort_unstable();
+        terms.dedup();
+        terms
+    }
+
+    /// Search one conversation's messages, returning a hit if the query matches
+    pub fn match_messages(&self, conversation_id: Uuid, messages: &[MessageEntry]) -> Option<SearchHit> {
+        let texts: Vec<String> = messages.iter().map(|m| searchable_text(&m.content)).collect();
+        let lowered: Vec<String> = texts.iter().map(|t| t.to_lowercase()).collect();
+
+        let matches = self.groups.iter().any(|group| {
+            group.iter().all(|term| lowered.iter().any(|text| text.contains(term.as_str())))
+        });
+        if !matches {
+            return None;
+        }
+
+        let terms = self.terms();
+        let mut match_count = 0;
+        let mut snippets = Vec::new();
+        for (text, lower) in texts.iter().zip(&lowered) {
+            let count: usize = terms.iter().map(|term| lower.matches(term).count()).sum();
+            if count == 0 {
+                continue;
+            }
+            match_count += count;
+            if snippets.len() < MAX_SNIPPETS {
+                // Offsets into the lowercased text only line up when lowercasing kept the length
+                let source = if lower.len() == text.len() { text } else { lower };
+                snippets.push(snippet(source, lower, &terms));
+            }
+        }
+
+        Some(SearchHit { conversation_id, match_count, snippets })
+    }
+}
+
+/// Split a query into terms, keeping quoted phrases whole
+fn tokenize(query: &str) -> Vec<(String, bool)> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    for c in query.chars() {
+        match c {
+            '"' => {
+                if !current.is_empty() {
+                    tokens.push((std::mem::take(&mut current), in_quotes));
+                }
+                in_quotes = !in_quotes;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push((std::mem::take(&mut current), false));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push((current, in_quotes));
+    }
+    tokens
+}
+
+/// The text of a message: the string itself, or every string inside structured content
+fn searchable_text(content: &serde_json::Value) -> String {
+    fn collect(value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::String(s) => out.push(s.clone()),
+            serde_json::Value::Array(items) => items.iter().for_each(|item| collect(item, out)),
+            serde_json::Value::Object(map) => map.values().for_each(|item| collect(item, out)),
+            _ => {}
+        }
+    }
+    let mut parts = Vec::new();
+    collect(content, &mut parts);
+    parts.join("\n")
+}
+
+/// An excerpt around the first match with every match inside it highlighted
+fn snippet(text: &str, lower: &str, terms: &[&str]) -> String {
+    let Some(first) = terms.iter().filter_map(|term| lower.find(term)).min() else {
+        return String::new();
+    };
+
+    let start = floor_char_boundary(text, first.saturating_sub(SNIPPET_CONTEXT_CHARS));
+    let end = floor_char_boundary(text, (first + SNIPPET_CONTEXT_CHARS * 2).min(text.len()));
+
+    // Collect non-overlapping match ranges inside the window
+    let mut ranges: Vec<(usize, usize)> = terms
+        .iter()
+        .flat_map(|term| lower[start..end].match_indices(term).map(|(i, m)| (start + i, start + i + m.len())))
+        .collect();
+    ranges.sort_unstable();
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push_str("...");
+    }
+    let mut pos = start;
+    for (from, to) in ranges {
+        if from < pos {
+            continue;
+        }
+        out.push_str(&text[pos..from]);
+        out.push_str("**");
+        out.push_str(&text[from..to]);
+        out.push_str("**");
+        pos = to;
+    }
+    out.push_str(&text[pos..end]);
+    if end < text.len() {
+        out.push_str("...");
+    }
+    out.replace('\n', " ")
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_or_phrases() {
+        let query = SearchQuery::parse(r#""Rate Limiter" AND retry OR backoff"#);
+        assert_eq!(
+            query.groups,
+            vec![vec!["rate limiter".to_string(), "retry".to_string()], vec!["backoff".to_string()]]
+        );
+        assert!(SearchQuery::parse("  ").is_empty());
+    }
+
+    #[test]
+    fn test_match_requires_all_terms_of_a_group() {
+        let messages = vec![
+            MessageEntry::user("How does the rate limiter work?".to_string()),
+            MessageEntry::assistant("It uses a token bucket.".to_string()),
+        ];
+        let id = Uuid::new_v4();
+
+        assert!(SearchQuery::parse("limiter bucket").match_messages(id, &messages).is_some());
+        assert!(SearchQuery::parse("limiter backoff").match_messages(id, &messages).is_none());
+        assert!(SearchQuery::parse("limiter backoff OR bucket").match_messages(id, &messages).is_some());
+    }
+
+    #[test]
+    fn test_structured_content_is_searched() {
+        let messages = vec![MessageEntry::tool(
+            "grep".to_string(),
+            "call-1".to_string(),
+            serde_json::json!({"matches": ["src/limiter.rs: struct RateLimiter"]}),
+        )];
+
+        let hit = SearchQuery::parse("ratelimiter").match_messages(Uuid::new_v4(), &messages).unwrap();
+        assert_eq!(hit.match_count, 1);
+        // Keys aren't content
+        assert!(SearchQuery::parse("matches").match_messages(Uuid::new_v4(), &messages).is_none());
+    }
+
+    #[test]
+    fn test_snippet_highlights_matches() {
+        let text = format!("{} the Rate limiter caps requests {}", "a".repeat(60), "b".repeat(100));
+        let snippet = snippet(&text, &text.to_lowercase(), &["rate limiter"]);
+
+        assert!(snippet.starts_with("..."));
+        assert!(snippet.ends_with("..."));
+        assert!(snippet.contains("the **Rate limiter** caps"));
+    }
+}
@@ -2,19 +2,26 @@ use bedrock_core::{BedrockError, Result};
 use std::fs;
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 use sha2::{Sha256, Digest};
 use chrono::Utc;
 
 use crate::metadata::{
-    ConversationIndex, ConversationMetadata, ConversationSummary, MessageEntry,
+    ConversationIndex, ConversationMetadata, ConversationSnapshot, ConversationSummary,
+    MessageEntry,
 };
+use crate::search::{SearchHit, SearchQuery};
+
+/// Characters of each collapsed message kept in a compaction summary
+const SUMMARY_PREVIEW_CHARS: usize = 120;
 
 /// File-based conversation storage with proper HOME_DIR handling
 pub struct ConversationStorage {
     base_dir: PathBuf,
     workspace_key: String,
+    compact_at_bytes: Option<u64>,
+    compact_keep_messages: usize,
 }
 
 impl ConversationStorage {
@@ -27,7 +34,12 @@ impl ConversationStorage {
                     .unwrap_or_else(|| "./.bedrock-agent".to_string())
             });
         
-        let base_dir = PathBuf::from(home_dir).join("conversations");
+        Self::with_home_dir(home_dir)
+    }
+    
+    /// Create a storage instance rooted at an explicit home directory
+    pub fn with_home_dir(home_dir: impl Into<PathBuf>) -> Result<Self> {
+        let base_dir = home_dir.into().join("conversations");
         let workspace_key = Self::generate_workspace_key()?;
         
         debug!("ConversationStorage initialized: base_dir={:?}, workspace_key={}", 
@@ -36,13 +48,22 @@ impl ConversationStorage {
         Ok(Self {
             base_dir,
             workspace_key,
+            compact_at_bytes: None,
+            compact_keep_messages: 0,
         })
     }
     
+    /// Compact a conversation automatically once its message file exceeds `compact_at_bytes`
+    pub fn with_compaction(mut self, compact_at_bytes: Option<u64>, keep_messages: usize) -> Self {
+        self.compact_at_bytes = compact_at_bytes;
+        self.compact_keep_messages = keep_messages;
+        self
+    }
+    
     /// Generate a normalized workspace key using hash + directory name
     fn generate_workspace_key() -> Result<String> {
         let cwd = std::env::current_dir()
-            .map_err(|e| BedrockError::IoError(e))?;
+            .map_err(BedrockError::IoError)?;
         
         // Create hash of full path
         let mut hasher = Sha256::new();
@@ -76,7 +97,7 @@ impl ConversationStorage {
     fn ensure_workspace_dir(&self) -> Result<PathBuf> {
         let dir = self.get_workspace_dir();
         fs::create_dir_all(&dir)
-            .map_err(|e| BedrockError::IoError(e))?;
+            .map_err(BedrockError::IoError)?;
         Ok(dir)
     }
     
@@ -101,7 +122,7 @@ impl ConversationStorage {
         
         let json = serde_json::to_string_pretty(metadata)?;
         fs::write(&meta_path, json)
-            .map_err(|e| BedrockError::IoError(e))?;
+            .map_err(BedrockError::IoError)?;
         
         debug!("Saved metadata for conversation {}", metadata.id);
         Ok(())
@@ -113,22 +134,31 @@ impl ConversationStorage {
         let meta_path = dir.join(format!("{}.meta.json", conversation_id));
         
         let json = fs::read_to_string(&meta_path)
-            .map_err(|e| BedrockError::IoError(e))?;
+            .map_err(BedrockError::IoError)?;
         
         let metadata: ConversationMetadata = serde_json::from_str(&json)?;
         Ok(metadata)
     }
     
-    /// Append a message to the conversation JSONL file
+    /// Append a message to the conversation JSONL file.
+    /// If the file has already grown past the compaction threshold it is compacted first.
     pub fn append_message(&self, conversation_id: &Uuid, entry: &MessageEntry) -> Result<()> {
         let dir = self.ensure_workspace_dir()?;
         let jsonl_path = dir.join(format!("{}.jsonl", conversation_id));
         
+        if let Some(limit) = self.compact_at_bytes {
+            let size = fs::metadata(&jsonl_path).map(|m| m.len()).unwrap_or(0);
+            if size > limit {
+                info!("Conversation {} is {} bytes (limit {}), compacting", conversation_id, size, limit);
+                self.compact_messages(conversation_id, self.compact_keep_messages)?;
+            }
+        }
+        
         let mut file = fs::OpenOptions::new()
             .create(true)
             .append(true)
             .open(&jsonl_path)
-            .map_err(|e| BedrockError::IoError(e))?;
+            .map_err(BedrockError::IoError)?;
         
         let json = serde_json::to_string(entry)?;
         writeln!(file, "{}", json)?;
@@ -137,6 +167,50 @@ impl ConversationStorage {
         Ok(())
     }
     
+    /// Collapse all but the most recent `keep_recent` messages into a single summary entry.
+    /// Returns the number of messages collapsed.
+    ///
+    /// Snapshots refer to positions in the log, so they are discarded once it is compacted.
+    pub fn compact_messages(&self, conversation_id: &Uuid, keep_recent: usize) -> Result<usize> {
+        let mut messages = self.read_messages(conversation_id)?;
+        // Collapsing a single message into a summary doesn't save anything
+        if messages.len() <= keep_recent + 1 {
+            return Ok(0);
+        }
+        
+        let recent = messages.split_off(messages.len() - keep_recent);
+        let collapsed = messages.len();
+        
+        let mut summary = format!("[Compacted {} earlier messages]", collapsed);
+        for entry in &messages {
+            let text = match &entry.content {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            let preview: String = text.chars().take(SUMMARY_PREVIEW_CHARS).collect();
+            let ellipsis = if text.chars().count() > SUMMARY_PREVIEW_CHARS { "..." } else { "" };
+            summary.push_str(&format!("\n- {}: {}{}", entry.role, preview.replace('\n', " "), ellipsis));
+        }
+        
+        let mut compacted = Vec::with_capacity(recent.len() + 1);
+        compacted.push(MessageEntry::summary(summary));
+        compacted.extend(recent);
+        self.rewrite_messages(conversation_id, &compacted)?;
+        
+        if !self.load_snapshots(conversation_id)?.is_empty() {
+            info!("Discarding snapshots of conversation {} after compaction", conversation_id);
+            self.save_snapshots(conversation_id, &[])?;
+        }
+        
+        let mut metadata = self.load_metadata(conversation_id)?;
+        metadata.message_count = compacted.len();
+        metadata.updated_at = Utc::now();
+        self.save_metadata(&metadata)?;
+        
+        info!("Compacted {} messages in conversation {}", collapsed, conversation_id);
+        Ok(collapsed)
+    }
+    
     /// Read all messages from a conversation
     pub fn read_messages(&self, conversation_id: &Uuid) -> Result<Vec<MessageEntry>> {
         let dir = self.get_workspace_dir();
@@ -147,7 +221,7 @@ impl ConversationStorage {
         }
         
         let file = fs::File::open(&jsonl_path)
-            .map_err(|e| BedrockError::IoError(e))?;
+            .map_err(BedrockError::IoError)?;
         
         let reader = BufReader::new(file);
         let mut messages = Vec::new();
@@ -156,7 +230,7 @@ impl ConversationStorage {
             let line = line?;
             if !line.trim().is_empty() {
                 let entry: MessageEntry = serde_json::from_str(&line)
-                    .map_err(|e| BedrockError::SerializationError(e))?;
+                    .map_err(BedrockError::SerializationError)?;
                 messages.push(entry);
             }
         }
@@ -164,6 +238,54 @@ impl ConversationStorage {
         Ok(messages)
     }
     
+    /// Replace the whole message log, e.g. after a rollback.
+    /// Written to a temporary file first and renamed so a crash never leaves a partial log.
+    pub fn rewrite_messages(&self, conversation_id: &Uuid, entries: &[MessageEntry]) -> Result<()> {
+        let dir = self.ensure_workspace_dir()?;
+        let jsonl_path = dir.join(format!("{}.jsonl", conversation_id));
+        let tmp_path = dir.join(format!("{}.jsonl.tmp", conversation_id));
+        
+        let mut file = fs::File::create(&tmp_path)
+            .map_err(BedrockError::IoError)?;
+        for entry in entries {
+            let json = serde_json::to_string(entry)?;
+            writeln!(file, "{}", json)?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp_path, &jsonl_path)
+            .map_err(BedrockError::IoError)?;
+        
+        debug!("Rewrote conversation {} with {} messages", conversation_id, entries.len());
+        Ok(())
+    }
+    
+    /// Load the snapshots recorded for a conversation
+    pub fn load_snapshots(&self, conversation_id: &Uuid) -> Result<Vec<ConversationSnapshot>> {
+        let dir = self.get_workspace_dir();
+        let snapshots_path = dir.join(format!("{}.snapshots.json", conversation_id));
+        
+        if !snapshots_path.exists() {
+            return Ok(Vec::new());
+        }
+        
+        let json = fs::read_to_string(&snapshots_path)
+            .map_err(BedrockError::IoError)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+    
+    /// Save the snapshots recorded for a conversation
+    pub fn save_snapshots(&self, conversation_id: &Uuid, snapshots: &[ConversationSnapshot]) -> Result<()> {
+        let dir = self.ensure_workspace_dir()?;
+        let snapshots_path = dir.join(format!("{}.snapshots.json", conversation_id));
+        
+        let json = serde_json::to_string_pretty(snapshots)?;
+        fs::write(&snapshots_path, json)
+            .map_err(BedrockError::IoError)?;
+        
+        debug!("Saved {} snapshots for conversation {}", snapshots.len(), conversation_id);
+        Ok(())
+    }
+    
     /// Save task results associated with a conversation
     pub fn save_task_results(
         &self,
@@ -175,14 +297,14 @@ impl ConversationStorage {
         
         let json = serde_json::to_string_pretty(tasks)?;
         fs::write(&tasks_path, json)
-            .map_err(|e| BedrockError::IoError(e))?;
+            .map_err(BedrockError::IoError)?;
         
         debug!("Saved tasks for conversation {}", conversation_id);
         Ok(())
     }
     
     /// Update the workspace conversation index
-    fn update_index(&self, metadata: &ConversationMetadata) -> Result<()> {
+    pub(crate) fn update_index(&self, metadata: &ConversationMetadata) -> Result<()> {
         let dir = self.ensure_workspace_dir()?;
         let index_path = dir.join("index.json");
         
@@ -197,7 +319,7 @@ impl ConversationStorage {
         
         let json = serde_json::to_string_pretty(&index)?;
         fs::write(&index_path, json)
-            .map_err(|e| BedrockError::IoError(e))?;
+            .map_err(BedrockError::IoError)?;
         
         Ok(())
     }
@@ -226,13 +348,14 @@ impl ConversationStorage {
             format!("{}.jsonl", conversation_id),
             format!("{}.meta.json", conversation_id),
             format!("{}.tasks.json", conversation_id),
+            format!("{}.snapshots.json", conversation_id),
         ];
         
         for pattern in patterns {
             let path = dir.join(pattern);
             if path.exists() {
                 fs::remove_file(&path)
-                    .map_err(|e| BedrockError::IoError(e))?;
+                    .map_err(BedrockError::IoError)?;
             }
         }
         
@@ -253,6 +376,36 @@ impl ConversationStorage {
         Ok(())
     }
     
+    /// Find conversations whose messages match `query` (see [`SearchQuery`]),
+    /// ranked by match count and then by most recent activity
+    pub fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
+        let query = SearchQuery::parse(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let mut conversations = self.list_conversations()?;
+        conversations.sort_by_key(|c| std::cmp::Reverse(c.updated_at));
+        
+        let mut hits = Vec::new();
+        for conversation in conversations {
+            let messages = match self.read_messages(&conversation.id) {
+                Ok(messages) => messages,
+                Err(e) => {
+                    warn!("Skipping conversation {} in search: {}", conversation.id, e);
+                    continue;
+                }
+            };
+            if let Some(hit) = query.match_messages(conversation.id, &messages) {
+                hits.push(hit);
+            }
+        }
+        
+        // Stable sort keeps the most recent first among equal counts
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.match_count));
+        Ok(hits)
+    }
+    
     /// Export a conversation to a standalone file
     pub fn export_conversation(&self, conversation_id: &Uuid, output_path: &Path) -> Result<()> {
         let metadata = self.load_metadata(conversation_id)?;
@@ -266,7 +419,7 @@ impl ConversationStorage {
         
         let json = serde_json::to_string_pretty(&export)?;
         fs::write(output_path, json)
-            .map_err(|e| BedrockError::IoError(e))?;
+            .map_err(BedrockError::IoError)?;
         
         info!("Exported conversation {} to {:?}", conversation_id, output_path);
         Ok(())
@@ -304,4 +457,76 @@ mod tests {
         assert!(workspace_dir.join(format!("{}.meta.json", meta.id)).exists());
         assert!(workspace_dir.join("index.json").exists());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_compaction_triggered_past_byte_threshold() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = ConversationStorage::with_home_dir(temp_dir.path())
+            .unwrap()
+            .with_compaction(Some(1_500), 3);
+        let meta = storage.create_conversation("test-model".to_string(), None).unwrap();
+        let jsonl_path = storage.get_workspace_dir().join(format!("{}.jsonl", meta.id));
+        
+        // Each message is ~330 bytes on disk, so the file passes 1500 bytes after the 5th
+        let mut uncompacted = 0;
+        for i in 0..7 {
+            let entry = MessageEntry::user(format!("message {i} {}", "x".repeat(250)));
+            uncompacted += serde_json::to_string(&entry).unwrap().len() as u64 + 1;
+            storage.append_message(&meta.id, &entry).unwrap();
+        }
+        
+        let messages = storage.read_messages(&meta.id).unwrap();
+        assert!(messages.len() < 7);
+        assert_eq!(messages[0].role, "summary");
+        assert!(messages[0].content.as_str().unwrap().starts_with("[Compacted "));
+        
+        // The most recent messages survive verbatim
+        let recent: Vec<_> = messages[messages.len() - 3..]
+            .iter()
+            .map(|m| m.content.as_str().unwrap()[..9].to_string())
+            .collect();
+        assert_eq!(recent, vec!["message 4", "message 5", "message 6"]);
+        
+        assert!(fs::metadata(&jsonl_path).unwrap().len() < uncompacted);
+    }
+    
+    #[test]
+    fn test_search_ranks_by_match_count() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = ConversationStorage::with_home_dir(temp_dir.path()).unwrap();
+        
+        let once = storage.create_conversation("test-model".to_string(), None).unwrap();
+        storage.append_message(&once.id, &MessageEntry::user("Add a rate limiter".to_string())).unwrap();
+        
+        let twice = storage.create_conversation("test-model".to_string(), None).unwrap();
+        storage.append_message(&twice.id, &MessageEntry::user("Why is the Rate Limiter slow?".to_string())).unwrap();
+        storage.append_message(&twice.id, &MessageEntry::assistant("The rate limiter sleeps too long.".to_string())).unwrap();
+        
+        let unrelated = storage.create_conversation("test-model".to_string(), None).unwrap();
+        storage.append_message(&unrelated.id, &MessageEntry::user("Fix the parser".to_string())).unwrap();
+        
+        let hits = storage.search("rate limiter").unwrap();
+        let ids: Vec<Uuid> = hits.iter().map(|h| h.conversation_id).collect();
+        assert_eq!(ids, vec![twice.id, once.id]);
+        // Each word counts on its own when the query isn't quoted
+        assert_eq!(hits[0].match_count, 4);
+        assert_eq!(hits[0].snippets[0], "Why is the **Rate** **Limiter** slow?");
+        
+        assert_eq!(storage.search("\"rate limiter\"").unwrap()[0].match_count, 2);
+        assert!(storage.search("backoff").unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_no_compaction_below_threshold() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = ConversationStorage::with_home_dir(temp_dir.path())
+            .unwrap()
+            .with_compaction(Some(1_000_000), 3);
+        let meta = storage.create_conversation("test-model".to_string(), None).unwrap();
+        
+        for i in 0..10 {
+            storage.append_message(&meta.id, &MessageEntry::user(format!("message {i}"))).unwrap();
+        }
+        assert_eq!(storage.read_messages(&meta.id).unwrap().len(), 10);
+    }
+}
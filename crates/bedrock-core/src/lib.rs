@@ -1,14 +1,38 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use thiserror::Error;
 
+pub mod log_rotation;
+pub mod retry;
+pub use log_rotation::RotatingFileWriter;
+pub use retry::{Backoff, RetryPolicy};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
     pub task_id: Uuid,
     pub context: String,
     pub prompt: String,
     pub created_at: DateTime<Utc>,
+    /// Overrides the configured task timeout when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Overrides which tools the task may use; `None` defers to the configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_policy: Option<ToolPolicy>,
+    /// Nudge the model to call a tool if its first reply answers without one
+    #[serde(default)]
+    pub require_tool_use_before_answer: bool,
+    /// Free-form labels (e.g. cost center, project) carried into the task result
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tags: HashMap<String, String>,
+    /// Hint that the answer will be long, so `stream_mode: auto` streams it
+    #[serde(default)]
+    pub expected_long: bool,
+    /// Bedrock `requestMetadata` for this task's requests, overriding `agent.request_metadata` keys
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub request_metadata: HashMap<String, String>,
 }
 
 impl Task {
@@ -18,6 +42,12 @@ impl Task {
             context: String::new(),
             prompt: prompt.into(),
             created_at: Utc::now(),
+            timeout_secs: None,
+            tool_policy: None,
+            require_tool_use_before_answer: false,
+            tags: HashMap::new(),
+            expected_long: false,
+            request_metadata: HashMap::new(),
         }
     }
 
@@ -25,6 +55,54 @@ impl Task {
         self.context = context.into();
         self
     }
+
+    pub fn with_timeout(mut self, timeout_secs: u64) -> Self {
+        self.timeout_secs = Some(timeout_secs);
+        self
+    }
+
+    pub fn with_tool_policy(mut self, policy: ToolPolicy) -> Self {
+        self.tool_policy = Some(policy);
+        self
+    }
+
+    pub fn expecting_long_answer(mut self) -> Self {
+        self.expected_long = true;
+        self
+    }
+
+    pub fn require_tool_use(mut self) -> Self {
+        self.require_tool_use_before_answer = true;
+        self
+    }
+
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Which tools are offered to the model for a task
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPolicy {
+    /// Every registered tool
+    #[default]
+    All,
+    /// Only the named tools
+    Subset(Vec<String>),
+    /// Tools deliberately disabled
+    None,
+}
+
+impl ToolPolicy {
+    pub fn allows(&self, tool_name: &str) -> bool {
+        match self {
+            ToolPolicy::All => true,
+            ToolPolicy::Subset(names) => names.iter().any(|n| n == tool_name),
+            ToolPolicy::None => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +115,15 @@ pub struct TaskResult {
     pub result: Option<serde_json::Value>,
     pub token_stats: TokenStatistics,
     pub cost: CostDetails,
+    /// Number of tool calls executed while running the task
+    #[serde(default)]
+    pub tool_executions: usize,
+    /// Stop sequence that ended the final response, if one did and reporting is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequence: Option<String>,
+    /// Tags copied from the task, used to attribute cost
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tags: HashMap<String, String>,
     pub started_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
     pub duration_ms: Option<u64>,
@@ -57,6 +144,8 @@ pub enum TaskStatus {
     Completed,
     Failed,
     Cancelled,
+    /// Stopped by the configured Bedrock guardrail
+    Blocked,
 }
 
 // Message types are now handled by aws_sdk_bedrockruntime::types::Message
@@ -68,13 +157,32 @@ pub struct TokenStatistics {
     pub input_tokens: usize,
     pub output_tokens: usize,
     pub total_tokens: usize,
+    /// Input tokens read from the prompt cache, not counted in `input_tokens`
     pub cache_hits: usize,
+    /// Input tokens written to the prompt cache, not counted in `input_tokens`
+    #[serde(default)]
+    pub cache_write_tokens: usize,
+}
+
+impl TokenStatistics {
+    /// Add another response's counts to these
+    pub fn accumulate(&mut self, other: &TokenStatistics) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.total_tokens += other.total_tokens;
+        self.cache_hits += other.cache_hits;
+        self.cache_write_tokens += other.cache_write_tokens;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CostDetails {
     pub input_cost: f64,
     pub output_cost: f64,
+    /// Spent on paid tools, separate from model usage
+    #[serde(default)]
+    pub tool_cost: f64,
+    /// Model and tool costs together
     pub total_cost: f64,
     pub currency: String,
     pub model: String,
@@ -85,6 +193,7 @@ impl Default for CostDetails {
         Self {
             input_cost: 0.0,
             output_cost: 0.0,
+            tool_cost: 0.0,
             total_cost: 0.0,
             currency: "USD".to_string(),
             model: String::new(),
@@ -115,6 +224,17 @@ pub enum BedrockError {
     #[error("Resource not found: {0}")]
     NotFound(String),
     
+    #[error("Timed out: {0}")]
+    Timeout(String),
+    
+    #[error("Circuit open: {0}")]
+    CircuitOpen(String),
+    
+    /// The service failed on its side or couldn't be reached: a 5xx answer, or a
+    /// request that never got one
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+    
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
     
@@ -125,6 +245,13 @@ pub enum BedrockError {
     Unknown(String),
 }
 
+impl BedrockError {
+    /// Whether the same operation may succeed if tried again later
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, BedrockError::RateLimitError(_) | BedrockError::Timeout(_) | BedrockError::CircuitOpen(_))
+    }
+}
+
 pub type Result<T> = std::result::Result<T, BedrockError>;
 
 #[async_trait::async_trait]
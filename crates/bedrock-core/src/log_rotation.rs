@@ -0,0 +1,175 @@
+//! A log file writer that rotates by size and prunes old files.
+//!
+//! The active file is `<dir>/<name>`; rotated files are `<name>.1` (newest) up to
+//! `<name>.<max_files>` (oldest). Anything past `max_files`, or older than
+//! `max_age` when set, is deleted.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub struct RotatingFileWriter {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    max_files: usize,
+    max_age: Option<Duration>,
+}
+
+impl RotatingFileWriter {
+    /// Open (or create) `dir/name` for appending, pruning expired rotated files
+    pub fn open(dir: impl AsRef<Path>, name: &str, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        let path = dir.as_ref().join(name);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size, max_bytes, max_files, max_age: None })
+    }
+
+    /// Also delete rotated files last written more than `max_age` ago
+    pub fn with_max_age(mut self, max_age: Duration) -> io::Result<Self> {
+        self.max_age = Some(max_age);
+        self.prune()?;
+        Ok(self)
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{index}"));
+        self.path.with_file_name(name)
+    }
+
+    /// Shift every rotated file up one place and start a new active file
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            // Nothing is kept; start the active file over
+            self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+            self.size = 0;
+            return Ok(());
+        }
+
+        let oldest = self.rotated_path(self.max_files);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.prune()
+    }
+
+    /// Delete rotated files beyond `max_files` or older than `max_age`
+    fn prune(&self) -> io::Result<()> {
+        let Some(dir) = self.path.parent() else { return Ok(()) };
+        let prefix = format!("{}.", self.path.file_name().unwrap_or_default().to_string_lossy());
+        let now = SystemTime::now();
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(index) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|suffix| suffix.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let expired = self.max_age.is_some_and(|max_age| {
+                entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| now.duration_since(modified).ok())
+                    .is_some_and(|age| age > max_age)
+            });
+            if index > self.max_files || expired {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A single record larger than the limit still goes into a file of its own
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("log-rotation-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_rotates_past_max_bytes() {
+        let dir = temp_dir();
+        let mut writer = RotatingFileWriter::open(&dir, "agent.log", 10, 3).unwrap();
+
+        writer.write_all(b"first-").unwrap();
+        writer.write_all(b"second").unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("agent.log")).unwrap(), "second");
+        assert_eq!(fs::read_to_string(dir.join("agent.log.1")).unwrap(), "first-");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_retention_deletes_oldest_beyond_max_files() {
+        let dir = temp_dir();
+        let mut writer = RotatingFileWriter::open(&dir, "agent.log", 4, 2).unwrap();
+
+        for record in ["aaaa", "bbbb", "cccc", "dddd", "eeee"] {
+            writer.write_all(record.as_bytes()).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(dir.join("agent.log")).unwrap(), "eeee");
+        assert_eq!(fs::read_to_string(dir.join("agent.log.1")).unwrap(), "dddd");
+        assert_eq!(fs::read_to_string(dir.join("agent.log.2")).unwrap(), "cccc");
+        assert!(!dir.join("agent.log.3").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_expired_files_are_deleted() {
+        let dir = temp_dir();
+        fs::write(dir.join("agent.log.1"), "recent").unwrap();
+        fs::write(dir.join("agent.log.2"), "stale").unwrap();
+        let two_days_ago = SystemTime::now() - Duration::from_secs(2 * 24 * 3600);
+        File::options().write(true).open(dir.join("agent.log.2")).unwrap().set_modified(two_days_ago).unwrap();
+
+        RotatingFileWriter::open(&dir, "agent.log", 1024, 5)
+            .unwrap()
+            .with_max_age(Duration::from_secs(24 * 3600))
+            .unwrap();
+
+        assert!(dir.join("agent.log.1").exists());
+        assert!(!dir.join("agent.log.2").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
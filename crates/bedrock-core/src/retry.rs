@@ -0,0 +1,143 @@
+//! Backoff shared by the retry paths: embedding requests, re-queued tasks, MCP
+//! server restarts and anything else that retries a transient failure.
+
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How the delay grows from one retry to the next
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backoff {
+    /// Every retry waits `initial_delay`
+    Fixed,
+    /// Retry `n` (0-based) waits `initial_delay * (n + 1)`
+    Linear,
+    /// Retry `n` (0-based) waits `initial_delay * multiplier^n`
+    #[default]
+    Exponential,
+}
+
+/// Backoff with jitter.
+///
+/// The delay before each retry grows as `backoff` says, is capped at `max_delay`,
+/// then moved up or down by a random share of at most `jitter` of itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub backoff: Backoff,
+    pub multiplier: f64,
+    /// Share of each delay to randomize, from 0.0 (none) to 1.0
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            backoff: Backoff::Exponential,
+            multiplier: 2.0,
+            jitter: 0.1,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Doubling backoff from `initial_delay`, giving up after `max_attempts` attempts
+    pub fn new(max_attempts: u32, initial_delay: Duration) -> Self {
+        Self { max_attempts, initial_delay, ..Self::default() }
+    }
+
+    /// Cap each delay at `max_delay`, or at `initial_delay` if that's longer
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay.max(self.initial_delay);
+        self
+    }
+
+    /// Whether another attempt may follow `attempts` failed ones
+    pub fn allows_retry(&self, attempts: u32) -> bool {
+        attempts < self.max_attempts
+    }
+
+    /// Delay before retry `attempt`, counting from 0
+    pub fn next_delay(&self, attempt: u32) -> Duration {
+        if self.initial_delay.is_zero() {
+            return Duration::ZERO;
+        }
+        let initial = self.initial_delay.as_secs_f64();
+        let grown = match self.backoff {
+            Backoff::Fixed => initial,
+            Backoff::Linear => initial * (f64::from(attempt) + 1.0),
+            // Past 2^63 the delay is long since capped
+            Backoff::Exponential => initial * self.multiplier.max(1.0).powi(attempt.min(63) as i32),
+        };
+        let delay = grown.min(self.max_delay.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0) * (2.0 * random_unit() - 1.0);
+        Duration::from_secs_f64(delay * (1.0 + jitter)).min(self.max_delay)
+    }
+}
+
+/// A random number in `[0, 1)`, from the 48 leading random bits of a v4 UUID
+fn random_unit() -> f64 {
+    (Uuid::new_v4().as_u128() >> 80) as f64 / (1u64 << 48) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn without_jitter() -> RetryPolicy {
+        RetryPolicy { jitter: 0.0, ..RetryPolicy::new(4, Duration::from_millis(100)) }
+    }
+
+    #[test]
+    fn test_delay_grows_until_capped() {
+        let policy = RetryPolicy { max_delay: Duration::from_millis(500), ..without_jitter() };
+
+        let delays: Vec<u128> = (0..5).map(|attempt| policy.next_delay(attempt).as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+        assert_eq!(policy.next_delay(u32::MAX), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_fixed_and_linear_backoff() {
+        let fixed = RetryPolicy { backoff: Backoff::Fixed, ..without_jitter() };
+        let linear = RetryPolicy { backoff: Backoff::Linear, max_delay: Duration::from_millis(350), ..without_jitter() };
+
+        let delays = |policy: &RetryPolicy| (0..4).map(|attempt| policy.next_delay(attempt).as_millis()).collect::<Vec<_>>();
+        assert_eq!(delays(&fixed), vec![100, 100, 100, 100]);
+        assert_eq!(delays(&linear), vec![100, 200, 300, 350]);
+    }
+
+    #[test]
+    fn test_max_delay_is_configurable_but_not_below_initial_delay() {
+        let long = RetryPolicy::new(8, Duration::from_secs(20)).with_max_delay(Duration::from_secs(300));
+        assert_eq!(RetryPolicy { jitter: 0.0, ..long }.next_delay(2), Duration::from_secs(80));
+
+        let short = RetryPolicy::new(8, Duration::from_secs(20)).with_max_delay(Duration::from_secs(5));
+        assert_eq!(short.max_delay, Duration::from_secs(20));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let policy = RetryPolicy { jitter: 0.5, ..without_jitter() };
+
+        for _ in 0..100 {
+            let delay = policy.next_delay(1);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(300), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn test_zero_delay_and_attempt_limit() {
+        let policy = RetryPolicy::new(3, Duration::ZERO);
+        assert_eq!(policy.next_delay(10), Duration::ZERO);
+
+        assert!(policy.allows_retry(1));
+        assert!(policy.allows_retry(2));
+        assert!(!policy.allows_retry(3));
+    }
+}
@@ -12,10 +12,20 @@ use crate::config::McpServerConfig;
 use crate::transport::Transport;
 use crate::types::{
     ClientCapabilities, ClientInfo, ContentItem, InitializeParams, InitializeResult,
-    JsonRpcRequest, JsonRpcResponse, JsonRpcNotification, ListToolsResult, McpTool,
-    ToolCallParams, ToolCallResult,
+    JsonRpcRequest, JsonRpcResponse, JsonRpcNotification, ListResourcesResult, ListToolsResult,
+    McpResource, McpTool, ReadResourceParams, ReadResourceResult, ServerCapabilities, ToolCallParams,
+    ToolCallResult,
 };
 
+/// JSON-RPC error code for an unknown method
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// Protocol version requested in `initialize`
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Protocol versions a server may answer `initialize` with
+const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2024-11-05", "2025-03-26", "2025-06-18"];
+
 /// MCP client for communicating with an MCP server
 pub struct McpClient {
     /// Server name for identification
@@ -33,8 +43,8 @@ pub struct McpClient {
     /// Cached tools from the server
     tools_cache: Vec<McpTool>,
     
-    /// Timeout duration for requests (in milliseconds)
-    timeout_ms: u64,
+    /// Maximum wait for the reply to a single request (in milliseconds)
+    request_timeout_ms: u64,
 }
 
 impl McpClient {
@@ -42,22 +52,49 @@ impl McpClient {
     pub async fn new(name: String, config: McpServerConfig) -> Result<Self> {
         let transport_config = config.to_transport_config();
         let transport = transport_config.create_transport().await?;
-        let transport = Arc::new(RwLock::new(transport));
-        
-        // Extract timeout from config
-        let timeout_ms = match &config {
-            McpServerConfig::Stdio { timeout, .. } => *timeout,
-            McpServerConfig::Sse { timeout, .. } => *timeout,
-        };
         
-        Ok(Self {
+        Ok(Self::with_transport(name, transport, config.request_timeout()))
+    }
+    
+    /// Create a client and complete the initialize handshake within the server's
+    /// `handshake_timeout`, so a server that hangs can't stall startup
+    pub async fn connect(name: String, config: McpServerConfig) -> Result<Self> {
+        let handshake_timeout = Duration::from_millis(config.handshake_timeout());
+        let mut client = Self::new(name, config).await?;
+        client.initialize_within(handshake_timeout).await?;
+        Ok(client)
+    }
+    
+    /// Initialize, giving up after `limit`. A server that doesn't finish the handshake
+    /// in time is disconnected and a retryable `Timeout` error returned.
+    pub async fn initialize_within(&mut self, limit: Duration) -> Result<InitializeResult> {
+        match timeout(limit, self.initialize()).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!("MCP server '{}' did not finish initializing within {:?}", self.name, limit);
+                if let Err(e) = self.close().await {
+                    debug!("Error closing MCP client '{}': {}", self.name, e);
+                }
+                Err(BedrockError::Timeout(format!(
+                    "MCP server '{}' did not complete the initialize handshake within {}ms",
+                    self.name,
+                    limit.as_millis()
+                )))
+            }
+        }
+    }
+    
+    /// Create a client on top of an already connected transport, waiting at most
+    /// `request_timeout_ms` for each reply
+    pub fn with_transport(name: String, transport: Box<dyn Transport>, request_timeout_ms: u64) -> Self {
+        Self {
             name,
-            transport,
+            transport: Arc::new(RwLock::new(transport)),
             request_id: Arc::new(AtomicU64::new(1)),
             capabilities: None,
             tools_cache: Vec::new(),
-            timeout_ms,
-        })
+            request_timeout_ms,
+        }
     }
     
     /// Get the next request ID
@@ -66,12 +103,13 @@ impl McpClient {
         format!("{}", id)
     }
     
-    /// Initialize the MCP connection
+    /// Initialize the MCP connection. A server answering with a protocol version we
+    /// don't speak is disconnected and a `ConfigError` returned.
     pub async fn initialize(&mut self) -> Result<InitializeResult> {
         info!("Initializing MCP client: {}", self.name);
         
         let params = InitializeParams {
-            protocol_version: "2024-11-05".to_string(),
+            protocol_version: PROTOCOL_VERSION.to_string(),
             capabilities: ClientCapabilities::default(),
             client_info: ClientInfo {
                 name: "bedrock-cli-agent".to_string(),
@@ -100,6 +138,18 @@ impl McpClient {
             })?
         )?;
         
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&result.protocol_version.as_str()) {
+            if let Err(e) = self.close().await {
+                debug!("Error closing MCP client '{}': {}", self.name, e);
+            }
+            return Err(BedrockError::ConfigError(format!(
+                "MCP server '{}' uses protocol version {}; supported versions are {}",
+                self.name,
+                result.protocol_version,
+                SUPPORTED_PROTOCOL_VERSIONS.join(", ")
+            )));
+        }
+        
         info!(
             "MCP client '{}' initialized with protocol version: {}",
             self.name, result.protocol_version
@@ -135,13 +185,27 @@ impl McpClient {
         Ok(result)
     }
     
+    /// Capabilities the server advertised in `initialize`; `None` before it completes
+    pub fn server_capabilities(&self) -> Option<&ServerCapabilities> {
+        self.capabilities.as_ref().map(|init| &init.capabilities)
+    }
+    
     /// Get cached tools (populated during initialization)
     pub async fn get_tools(&self) -> Vec<McpTool> {
         self.tools_cache.clone()
     }
     
-    /// List available tools from the MCP server
+    /// List available tools from the MCP server. Empty without a request when the
+    /// server didn't advertise the `tools` capability.
     pub async fn list_tools(&mut self) -> Result<Vec<McpTool>> {
+        if let Some(capabilities) = self.server_capabilities() {
+            if capabilities.tools.is_none() {
+                debug!("MCP server '{}' does not advertise tools", self.name);
+                self.tools_cache.clear();
+                return Ok(Vec::new());
+            }
+        }
+        
         debug!("Listing tools from MCP server: {}", self.name);
         
         let request = JsonRpcRequest::new(
@@ -217,6 +281,116 @@ impl McpClient {
         Ok(result.content)
     }
     
+    /// List the resources exposed by the MCP server.
+    /// Servers without the `resources` capability have none, which is not an error.
+    pub async fn list_resources(&mut self) -> Result<Vec<McpResource>> {
+        if let Some(ref init) = self.capabilities {
+            if init.capabilities.resources.is_none() {
+                debug!("MCP server '{}' does not advertise resources", self.name);
+                return Ok(Vec::new());
+            }
+        }
+        
+        debug!("Listing resources from MCP server: {}", self.name);
+        
+        let mut resources = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = cursor.as_ref().map(|c| serde_json::json!({ "cursor": c }));
+            let request = JsonRpcRequest::new(
+                self.next_request_id(),
+                "resources/list".to_string(),
+                params,
+            );
+            
+            let response = self.send_request(request).await?;
+            
+            if let Some(error) = response.error {
+                if error.code == METHOD_NOT_FOUND {
+                    debug!("MCP server '{}' does not support resources/list", self.name);
+                    return Ok(Vec::new());
+                }
+                return Err(BedrockError::McpError(format!(
+                    "Failed to list resources: {}",
+                    error.message
+                )));
+            }
+            
+            let result: ListResourcesResult = serde_json::from_value(
+                response.result.ok_or_else(|| {
+                    BedrockError::McpError("List resources response missing result".into())
+                })?
+            )?;
+            
+            resources.extend(result.resources);
+            match result.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        
+        info!(
+            "Discovered {} resources from MCP server '{}'",
+            resources.len(),
+            self.name
+        );
+        
+        Ok(resources)
+    }
+    
+    /// Read a resource from the MCP server
+    pub async fn read_resource(&mut self, uri: &str) -> Result<Vec<ContentItem>> {
+        debug!("Reading MCP resource '{}' from server '{}'", uri, self.name);
+        
+        let params = ReadResourceParams {
+            uri: uri.to_string(),
+        };
+        
+        let request = JsonRpcRequest::new(
+            self.next_request_id(),
+            "resources/read".to_string(),
+            Some(serde_json::to_value(params)?),
+        );
+        
+        let response = self.send_request(request).await?;
+        
+        if let Some(error) = response.error {
+            return Err(BedrockError::McpError(format!(
+                "Failed to read resource '{}': {}",
+                uri, error.message
+            )));
+        }
+        
+        let result: ReadResourceResult = serde_json::from_value(
+            response.result.ok_or_else(|| {
+                BedrockError::McpError(format!("Resource '{}' response missing result", uri))
+            })?
+        )?;
+        
+        Ok(result.contents
+            .into_iter()
+            .map(|resource| ContentItem::Resource { resource })
+            .collect())
+    }
+    
+    /// Check that the server responds. Servers that don't implement `ping`
+    /// are checked with a `tools/list` instead.
+    pub async fn ping(&mut self) -> Result<()> {
+        let request = JsonRpcRequest::new(
+            self.next_request_id(),
+            "ping".to_string(),
+            None,
+        );
+        
+        let response = self.send_request(request).await?;
+        
+        match response.error {
+            None => Ok(()),
+            Some(error) if error.code == METHOD_NOT_FOUND => self.list_tools().await.map(|_| ()),
+            Some(error) => Err(BedrockError::McpError(format!("Ping failed: {}", error.message))),
+        }
+    }
+    
     /// Send a request and wait for response with direct correlation
     async fn send_request(&mut self, request: JsonRpcRequest) -> Result<JsonRpcResponse> {
         let request_id = request.id.clone();
@@ -228,27 +402,17 @@ impl McpClient {
         }
         
         // Wait for response with timeout and correlation
-        let timeout_duration = Duration::from_millis(self.timeout_ms);
+        let timeout_duration = Duration::from_millis(self.request_timeout_ms);
         
         timeout(timeout_duration, self.wait_for_response(request_id.clone())).await
             .map_err(|_| BedrockError::McpError(
-                format!("Request {} timed out after {}ms", request_id, self.timeout_ms)
+                format!("request {} timed out after {}ms", request_id, self.request_timeout_ms)
             ))?
     }
     
-    /// Wait for a specific response by ID
+    /// Wait for a specific response by ID; `send_request` bounds the wait
     async fn wait_for_response(&mut self, request_id: String) -> Result<JsonRpcResponse> {
-        let start = std::time::Instant::now();
-        let max_wait = Duration::from_millis(self.timeout_ms);
-        
         loop {
-            // Check for timeout
-            if start.elapsed() > max_wait {
-                return Err(BedrockError::McpError(
-                    format!("Timeout waiting for response to request {}", request_id)
-                ));
-            }
-            
             // Try to receive response
             let mut transport = self.transport.write().await;
             if let Some(response) = transport.receive_response().await? {
@@ -290,4 +454,231 @@ impl Drop for McpClient {
         // Nothing to clean up with simplified design
         debug!("Dropping MCP client: {}", self.name);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{JsonRpcError, ResourcesCapability, ServerCapabilities};
+    use serde_json::json;
+    use std::collections::VecDeque;
+    
+    /// Answers each request with the next scripted result, echoing the request id
+    #[derive(Debug, Default)]
+    struct ScriptedTransport {
+        replies: VecDeque<std::result::Result<Value, JsonRpcError>>,
+        requests: Arc<std::sync::Mutex<Vec<JsonRpcRequest>>>,
+        pending: VecDeque<JsonRpcResponse>,
+    }
+    
+    #[async_trait::async_trait]
+    impl Transport for ScriptedTransport {
+        async fn send_request(&mut self, request: JsonRpcRequest) -> Result<()> {
+            let reply = self.replies.pop_front().expect("unexpected request");
+            let (result, error) = match reply {
+                Ok(result) => (Some(result), None),
+                Err(error) => (None, Some(error)),
+            };
+            self.pending.push_back(JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id.clone(),
+                result,
+                error,
+            });
+            self.requests.lock().unwrap().push(request);
+            Ok(())
+        }
+        
+        async fn send_notification(&mut self, _notification: JsonRpcNotification) -> Result<()> {
+            Ok(())
+        }
+        
+        async fn receive_response(&mut self) -> Result<Option<JsonRpcResponse>> {
+            Ok(self.pending.pop_front())
+        }
+        
+        async fn is_connected(&self) -> bool {
+            true
+        }
+        
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+    
+    fn scripted_client(replies: Vec<std::result::Result<Value, JsonRpcError>>) -> (McpClient, Arc<std::sync::Mutex<Vec<JsonRpcRequest>>>) {
+        let transport = ScriptedTransport { replies: replies.into(), ..Default::default() };
+        let requests = transport.requests.clone();
+        (McpClient::with_transport("test".to_string(), Box::new(transport), 1000), requests)
+    }
+    
+    fn initialized(resources: bool) -> InitializeResult {
+        InitializeResult {
+            protocol_version: "2024-11-05".to_string(),
+            capabilities: ServerCapabilities {
+                resources: resources.then_some(ResourcesCapability { list_changed: None }),
+                ..Default::default()
+            },
+            server_info: None,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_initialize_rejects_unsupported_protocol_version() {
+        let (mut client, requests) = scripted_client(vec![
+            Ok(json!({"protocolVersion": "2099-01-01", "capabilities": {"tools": {}}})),
+        ]);
+        
+        let err = client.initialize().await.unwrap_err();
+        
+        assert!(matches!(&err, BedrockError::ConfigError(message) if message.contains("protocol version 2099-01-01")), "{err}");
+        assert!(client.server_capabilities().is_none());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_list_tools_skipped_without_tools_capability() {
+        let (mut client, requests) = scripted_client(vec![
+            Ok(json!({"protocolVersion": "2025-03-26", "capabilities": {"resources": {}}})),
+        ]);
+        
+        client.initialize().await.unwrap();
+        let capabilities = client.server_capabilities().unwrap();
+        assert!(capabilities.tools.is_none());
+        assert!(capabilities.resources.is_some());
+        
+        assert!(client.list_tools().await.unwrap().is_empty());
+        let methods: Vec<String> = requests.lock().unwrap().iter().map(|r| r.method.clone()).collect();
+        assert_eq!(methods, vec!["initialize"]);
+    }
+    
+    #[tokio::test]
+    async fn test_list_resources_follows_cursor() {
+        let (mut client, requests) = scripted_client(vec![
+            Ok(json!({"resources": [{"uri": "doc://a", "name": "a"}], "nextCursor": "page-2"})),
+            Ok(json!({"resources": [{"uri": "doc://b", "name": "b"}]})),
+        ]);
+        client.capabilities = Some(initialized(true));
+        
+        let resources = client.list_resources().await.unwrap();
+        let uris: Vec<&str> = resources.iter().map(|r| r.uri.as_str()).collect();
+        assert_eq!(uris, vec!["doc://a", "doc://b"]);
+        assert_eq!(requests.lock().unwrap()[1].params, Some(json!({"cursor": "page-2"})));
+    }
+    
+    #[tokio::test]
+    async fn test_list_resources_without_capability_is_empty() {
+        // Capability not advertised: no request is sent at all
+        let (mut client, requests) = scripted_client(vec![]);
+        client.capabilities = Some(initialized(false));
+        assert!(client.list_resources().await.unwrap().is_empty());
+        assert!(requests.lock().unwrap().is_empty());
+        
+        // Capabilities unknown and the server rejects the method
+        let (mut client, _) = client_with_error(METHOD_NOT_FOUND);
+        assert!(client.list_resources().await.unwrap().is_empty());
+        
+        // Other errors are still reported
+        let (mut client, _) = client_with_error(-32603);
+        assert!(client.list_resources().await.is_err());
+    }
+    
+    fn client_with_error(code: i32) -> (McpClient, Arc<std::sync::Mutex<Vec<JsonRpcRequest>>>) {
+        scripted_client(vec![Err(JsonRpcError { code, message: "nope".to_string(), data: None })])
+    }
+    
+    #[tokio::test]
+    async fn test_read_resource() {
+        let (mut client, requests) = scripted_client(vec![Ok(json!({
+            "contents": [{"uri": "doc://a", "mimeType": "text/markdown", "text": "# A"}]
+        }))]);
+        
+        let items = client.read_resource("doc://a").await.unwrap();
+        
+        assert_eq!(requests.lock().unwrap()[0].params, Some(json!({"uri": "doc://a"})));
+        assert!(matches!(
+            &items[..],
+            [ContentItem::Resource { resource }] if resource.text.as_deref() == Some("# A")
+        ));
+    }
+    
+    /// Accepts requests but never answers them
+    #[derive(Debug, Default)]
+    struct SilentTransport {
+        closed: Arc<std::sync::atomic::AtomicBool>,
+    }
+    
+    #[async_trait::async_trait]
+    impl Transport for SilentTransport {
+        async fn send_request(&mut self, _request: JsonRpcRequest) -> Result<()> {
+            Ok(())
+        }
+        
+        async fn send_notification(&mut self, _notification: JsonRpcNotification) -> Result<()> {
+            Ok(())
+        }
+        
+        async fn receive_response(&mut self) -> Result<Option<JsonRpcResponse>> {
+            Ok(None)
+        }
+        
+        async fn is_connected(&self) -> bool {
+            !self.closed.load(Ordering::SeqCst)
+        }
+        
+        async fn close(&mut self) -> Result<()> {
+            self.closed.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_handshake_timeout_fails_fast() {
+        let transport = SilentTransport::default();
+        let closed = transport.closed.clone();
+        // The request timeout alone would wait a minute
+        let mut client = McpClient::with_transport("slow".to_string(), Box::new(transport), 60_000);
+        
+        let started = std::time::Instant::now();
+        let err = client.initialize_within(Duration::from_millis(100)).await.unwrap_err();
+        
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.is_retryable(), "{err}");
+        assert!(err.to_string().contains("'slow' did not complete the initialize handshake within 100ms"));
+        assert!(closed.load(Ordering::SeqCst));
+        assert!(!client.is_connected().await);
+    }
+    
+    #[tokio::test]
+    async fn test_unanswered_tool_call_times_out_as_tool_error() {
+        use crate::tool_wrapper::McpToolWrapper;
+        use bedrock_tools::Tool;
+        
+        let mut client = McpClient::with_transport("hung".to_string(), Box::new(SilentTransport::default()), 100);
+        let err = client.call_tool("search", json!({})).await.unwrap_err();
+        assert!(matches!(&err, BedrockError::McpError(message) if message == "request 1 timed out after 100ms"), "{err}");
+        
+        // The wrapper reports the timeout to the model instead of failing the task
+        let tool = McpTool { name: "search".to_string(), description: "Search".to_string(), input_schema: json!({}) };
+        let wrapper = McpToolWrapper::new(tool, Arc::new(RwLock::new(client)), "hung".to_string());
+        let output = wrapper.execute(json!({})).await.unwrap();
+        assert_eq!(output["success"], json!(false));
+        assert_eq!(output["error"], json!("MCP communication error: request 2 timed out after 100ms"));
+    }
+    
+    #[tokio::test]
+    async fn test_ping_falls_back_to_tools_list() {
+        let (mut client, requests) = scripted_client(vec![Ok(json!({}))]);
+        client.ping().await.unwrap();
+        
+        let (mut client, _) = scripted_client(vec![
+            Err(JsonRpcError { code: METHOD_NOT_FOUND, message: "unknown method".to_string(), data: None }),
+            Ok(json!({"tools": []})),
+        ]);
+        client.ping().await.unwrap();
+        assert_eq!(requests.lock().unwrap()[0].method, "ping");
+        
+        let (mut client, _) = client_with_error(-32603);
+        assert!(client.ping().await.is_err());
+    }
+}
@@ -3,10 +3,12 @@
 //! Supports the standard Amazon Q / Claude Code configuration format
 //! with environment variable substitution and multi-level loading.
 
-use bedrock_core::Result;
+use bedrock_config::substitute_env_vars;
+use bedrock_core::{Backoff, BedrockError, Result, RetryPolicy};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
+use std::time::Duration;
 use tracing::{info, warn};
 
 use crate::transport::TransportConfig;
@@ -20,7 +22,7 @@ pub struct McpConfig {
 }
 
 /// Individual MCP server configuration
-/// Supports both stdio and SSE transport types
+/// Supports stdio, SSE, streamable HTTP and WebSocket transport types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum McpServerConfig {
@@ -41,6 +43,15 @@ pub enum McpServerConfig {
         #[serde(default = "default_timeout")]
         timeout: u64,
         
+        /// Maximum time in milliseconds for the initialize handshake (default: 10000)
+        #[serde(default = "default_handshake_timeout")]
+        handshake_timeout: u64,
+        
+        /// Maximum time in milliseconds to wait for the reply to a single request,
+        /// such as a `tools/call` (default: `timeout`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_timeout: Option<u64>,
+        
         /// Whether this server is disabled
         #[serde(default)]
         disabled: bool,
@@ -54,13 +65,13 @@ pub enum McpServerConfig {
         restart_policy: Option<RestartPolicy>,
     },
     
-    /// SSE-based server (HTTP Server-Sent Events)
+    /// URL-based server: SSE (HTTP Server-Sent Events), streamable HTTP or WebSocket
     Sse {
-        /// Transport type (can be "sse" or omitted)
+        /// Transport type ("sse", "http", "websocket" or omitted to infer from the URL scheme)
         #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
         transport_type: Option<String>,
         
-        /// URL of the SSE endpoint
+        /// URL of the SSE or streamable HTTP endpoint, or a ws:// / wss:// WebSocket URL
         url: String,
         
         /// Headers to include in requests
@@ -71,6 +82,15 @@ pub enum McpServerConfig {
         #[serde(default = "default_timeout")]
         timeout: u64,
         
+        /// Maximum time in milliseconds for the initialize handshake (default: 10000)
+        #[serde(default = "default_handshake_timeout")]
+        handshake_timeout: u64,
+        
+        /// Maximum time in milliseconds to wait for the reply to a single request,
+        /// such as a `tools/call` (default: `timeout`)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_timeout: Option<u64>,
+        
         /// Whether this server is disabled
         #[serde(default)]
         disabled: bool,
@@ -86,6 +106,17 @@ pub enum McpServerConfig {
 }
 
 impl McpServerConfig {
+    /// Parse the definition of server `name`, resolving `${VAR}` and `${VAR:-default}`
+    /// placeholders in its strings (command, args, env, url, headers) first
+    pub fn from_value(name: &str, mut value: serde_json::Value) -> Result<Self> {
+        substitute_env_vars(&mut value).map_err(|e| match e {
+            BedrockError::ConfigError(message) => BedrockError::ConfigError(format!("MCP server '{name}': {message}")),
+            other => other,
+        })?;
+        serde_json::from_value(value)
+            .map_err(|e| BedrockError::ConfigError(format!("Invalid MCP server '{name}': {e}")))
+    }
+    
     /// Check if server is disabled
     pub fn is_disabled(&self) -> bool {
         match self {
@@ -102,6 +133,23 @@ impl McpServerConfig {
         }
     }
     
+    /// Get the initialize handshake timeout in milliseconds
+    pub fn handshake_timeout(&self) -> u64 {
+        match self {
+            McpServerConfig::Stdio { handshake_timeout, .. } => *handshake_timeout,
+            McpServerConfig::Sse { handshake_timeout, .. } => *handshake_timeout,
+        }
+    }
+    
+    /// Get the per-request reply timeout in milliseconds, `timeout` unless set
+    pub fn request_timeout(&self) -> u64 {
+        match self {
+            McpServerConfig::Stdio { request_timeout, .. } => *request_timeout,
+            McpServerConfig::Sse { request_timeout, .. } => *request_timeout,
+        }
+        .unwrap_or_else(|| self.timeout())
+    }
+    
     /// Get health check configuration
     pub fn health_check(&self) -> Option<&HealthCheckConfig> {
         match self {
@@ -129,6 +177,24 @@ impl McpServerConfig {
                     timeout: *timeout,
                 }
             }
+            McpServerConfig::Sse { transport_type, url, headers, timeout, .. }
+                if is_websocket(transport_type.as_deref(), url) =>
+            {
+                TransportConfig::WebSocket {
+                    url: url.clone(),
+                    headers: headers.clone(),
+                    timeout: *timeout,
+                }
+            }
+            McpServerConfig::Sse { transport_type, url, headers, timeout, .. }
+                if is_streamable_http(transport_type.as_deref()) =>
+            {
+                TransportConfig::StreamableHttp {
+                    url: url.clone(),
+                    headers: headers.clone(),
+                    timeout: *timeout,
+                }
+            }
             McpServerConfig::Sse { url, headers, timeout, .. } => {
                 TransportConfig::Sse {
                     url: url.clone(),
@@ -140,6 +206,24 @@ impl McpServerConfig {
     }
 }
 
+/// Whether a URL-based server should use the WebSocket transport
+fn is_websocket(transport_type: Option<&str>, url: &str) -> bool {
+    match transport_type {
+        Some(t) => t.eq_ignore_ascii_case("websocket") || t.eq_ignore_ascii_case("ws"),
+        None => url.starts_with("ws://") || url.starts_with("wss://"),
+    }
+}
+
+/// Whether a URL-based server uses the streamable HTTP transport. Its URLs look
+/// like SSE ones, so it has to be asked for by type.
+fn is_streamable_http(transport_type: Option<&str>) -> bool {
+    transport_type.is_some_and(|t| {
+        ["http", "streamable-http", "streamable_http", "streamableHttp"]
+            .iter()
+            .any(|name| t.eq_ignore_ascii_case(name))
+    })
+}
+
 /// Health check configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthCheckConfig {
@@ -197,23 +281,37 @@ impl Default for RestartPolicy {
     }
 }
 
+impl RestartPolicy {
+    /// The backoff between connection attempts, without jitter
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_retries.saturating_add(1),
+            initial_delay: Duration::from_secs(self.initial_delay),
+            max_delay: Duration::from_secs(self.max_delay),
+            backoff: match self.backoff {
+                BackoffStrategy::Linear => Backoff::Linear,
+                BackoffStrategy::Exponential => Backoff::Exponential,
+                BackoffStrategy::Fixed => Backoff::Fixed,
+            },
+            multiplier: 2.0,
+            jitter: 0.0,
+        }
+    }
+}
+
 /// Backoff strategy for retries
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum BackoffStrategy {
     Linear,
+    #[default]
     Exponential,
     Fixed,
 }
 
-impl Default for BackoffStrategy {
-    fn default() -> Self {
-        BackoffStrategy::Exponential
-    }
-}
-
 // Default values
 fn default_timeout() -> u64 { 30000 }
+fn default_handshake_timeout() -> u64 { 10000 }
 fn default_health_interval() -> u64 { 60 }
 fn default_health_timeout() -> u64 { 5 }
 fn default_max_failures() -> u32 { 3 }
@@ -237,12 +335,31 @@ impl McpConfig {
                 format!("Failed to read MCP config file {}: {}", path.display(), e)
             ))?;
         
-        let config: McpConfig = serde_yaml::from_str(&content)
-            .map_err(|e| bedrock_core::BedrockError::ConfigError(
-                format!("Failed to parse MCP config YAML from {}: {}", path.display(), e)
-            ))?;
+        Self::from_yaml_str(&content).map_err(|e| match e {
+            BedrockError::ConfigError(message) => BedrockError::ConfigError(
+                format!("Failed to parse MCP config YAML from {}: {}", path.display(), message)
+            ),
+            other => other,
+        })
+    }
+    
+    /// Parse a configuration, substituting environment variables in each server
+    pub fn from_yaml_str(yaml: &str) -> Result<Self> {
+        /// Server definitions before substitution
+        #[derive(Deserialize)]
+        struct RawMcpConfig {
+            #[serde(rename = "mcpServers")]
+            mcp_servers: HashMap<String, serde_json::Value>,
+        }
         
-        Ok(config)
+        let raw: RawMcpConfig = serde_yaml::from_str(yaml)
+            .map_err(|e| BedrockError::ConfigError(e.to_string()))?;
+        let mcp_servers = raw
+            .mcp_servers
+            .into_iter()
+            .map(|(name, value)| McpServerConfig::from_value(&name, value).map(|config| (name, config)))
+            .collect::<Result<_>>()?;
+        Ok(Self { mcp_servers })
     }
     
     /// Load all YAML files from a directory
@@ -323,6 +440,51 @@ mcpServers:
         let server_config = &config.mcp_servers["filesystem"];
         assert!(!server_config.is_disabled());
         assert_eq!(server_config.timeout(), 30000);
+        // Falls back to timeout when unset
+        assert_eq!(server_config.request_timeout(), 30000);
+    }
+
+    #[test]
+    fn test_env_vars_substituted_in_server_config() {
+        std::env::set_var("MCP_TEST_API_TOKEN", "secret");
+        let yaml = r#"
+mcpServers:
+  github:
+    url: https://${MCP_TEST_HOST:-api.example.com}/sse
+    headers:
+      Authorization: Bearer ${MCP_TEST_API_TOKEN}
+  local:
+    command: ${MCP_TEST_BIN:-npx}
+    args: ["--token", "${MCP_TEST_API_TOKEN}"]
+    env:
+      TOKEN: ${MCP_TEST_API_TOKEN}
+"#;
+
+        let config = McpConfig::from_yaml_str(yaml).unwrap();
+        match &config.mcp_servers["github"] {
+            McpServerConfig::Sse { url, headers, .. } => {
+                assert_eq!(url, "https://api.example.com/sse");
+                assert_eq!(headers["Authorization"], "Bearer secret");
+            }
+            other => panic!("expected an SSE server, got {other:?}"),
+        }
+        match &config.mcp_servers["local"] {
+            McpServerConfig::Stdio { command, args, env, .. } => {
+                assert_eq!(command, "npx");
+                assert_eq!(args, &["--token", "secret"]);
+                assert_eq!(env["TOKEN"], "secret");
+            }
+            other => panic!("expected a stdio server, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_missing_env_var_names_variable_and_server() {
+        let value = serde_json::json!({"url": "http://localhost", "headers": {"token": "${MCP_TEST_UNSET_TOKEN}"}});
+        let err = McpServerConfig::from_value("redux-api", value).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("MCP server 'redux-api'"), "{message}");
+        assert!(message.contains("MCP_TEST_UNSET_TOKEN"), "{message}");
     }
 
     #[test]
@@ -335,6 +497,7 @@ mcpServers:
     headers:
       Authorization: Bearer token123
     timeout: 60000
+    request_timeout: 5000
 "#;
 
         let config: McpConfig = serde_yaml::from_str(yaml).unwrap();
@@ -344,6 +507,7 @@ mcpServers:
         let server_config = &config.mcp_servers["github"];
         assert!(!server_config.is_disabled());
         assert_eq!(server_config.timeout(), 60000);
+        assert_eq!(server_config.request_timeout(), 5000);
     }
 
     #[test]
@@ -367,4 +531,35 @@ mcpServers:
         assert_eq!(health_check.timeout, 10);
         assert_eq!(health_check.max_failures, 5);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_websocket_config_parsing() {
+        let yaml = r#"
+mcpServers:
+  local:
+    url: ws://localhost:9000
+  remote:
+    type: websocket
+    url: wss://mcp.example.com/rpc
+    headers:
+      Authorization: Bearer token123
+  events:
+    url: http://localhost:8080
+  streaming:
+    type: http
+    url: https://mcp.example.com/mcp
+"#;
+
+        let config: McpConfig = serde_yaml::from_str(yaml).unwrap();
+        let transport = |name: &str| config.mcp_servers[name].to_transport_config();
+
+        assert_eq!(transport("local").transport_type(), "websocket");
+        assert_eq!(transport("remote").transport_type(), "websocket");
+        assert_eq!(transport("events").transport_type(), "sse");
+        assert_eq!(transport("streaming").transport_type(), "streamable_http");
+        assert!(matches!(
+            transport("remote"),
+            TransportConfig::WebSocket { headers, .. } if headers["Authorization"] == "Bearer token123"
+        ));
+    }
+}
@@ -61,7 +61,7 @@ mod tests {
         let images = vec![];
         
         let result = process_mcp_response(text, images);
-        assert_eq!(result, Value::String("Hello, world!".to_string()));
+        assert_eq!(result, json!({"success": true, "content": "Hello, world!"}));
     }
     
     #[test]
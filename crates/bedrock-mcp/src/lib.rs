@@ -1,7 +1,7 @@
 //! Model Context Protocol (MCP) integration for external tool discovery
 //! 
-//! This module provides MCP client support with stdio and SSE transports,
-//! enabling connection to MCP servers that provide additional tools.
+//! This module provides MCP client support with stdio, SSE, streamable HTTP and WebSocket transports,
+//! enabling connection to MCP servers that provide additional tools and resources.
 
 pub mod client;
 pub mod config;
@@ -15,6 +15,6 @@ pub mod types;
 pub use client::McpClient;
 pub use config::{McpConfig, McpServerConfig, HealthCheckConfig, RestartPolicy, BackoffStrategy};
 pub use conversions::{process_mcp_response, validate_json_for_mcp};
-pub use manager::McpManager;
+pub use manager::{McpManager, ServerHealth, ServerStatus};
 pub use tool_wrapper::McpToolWrapper;
-pub use types::{McpTool, ContentItem, JsonRpcRequest, JsonRpcResponse};
\ No newline at end of file
+pub use types::{McpTool, McpResource, ResourceContents, ContentItem, JsonRpcRequest, JsonRpcResponse};
\ No newline at end of file
@@ -2,18 +2,30 @@
 //! 
 //! Manages multiple MCP servers with configuration loading,
 //! lifecycle management, health monitoring, and tool registration.
+//!
+//! Every started server is supervised: it is pinged on the `health_check` interval and,
+//! after `max_failures` consecutive failed checks, restarted according to its
+//! `restart_policy`. A restarted server's tools are registered again; a server that
+//! can't be restarted is removed and its tools unregistered.
+//!
+//! A server that doesn't finish its initialize handshake within `handshake_timeout`
+//! doesn't hold up startup: it is marked failed and connected in the background,
+//! then supervised like the others once it answers. One that answers with an
+//! unsupported protocol version is skipped without retrying.
 
 use bedrock_core::{BedrockError, Result};
-use bedrock_tools::ToolRegistry;
+use bedrock_tools::{Tool, ToolRegistry};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
 use crate::client::McpClient;
-use crate::config::{BackoffStrategy, McpConfig, McpServerConfig};
-use crate::tool_wrapper::McpToolWrapper;
+use crate::config::{McpConfig, McpServerConfig, RestartPolicy};
+use crate::tool_wrapper::{namespaced_tool_name, McpToolWrapper};
+use crate::types::{ContentItem, McpResource, McpTool};
 
 /// Handle to a running MCP server
 pub struct McpServerHandle {
@@ -43,6 +55,35 @@ pub struct McpManager {
     
     /// Configuration (merged from all sources)
     config: Arc<RwLock<McpConfig>>,
+    
+    /// Health of supervised servers, kept after a server fails permanently
+    health: Arc<RwLock<HashMap<String, ServerHealth>>>,
+    
+    /// Background connection attempts for servers whose startup handshake timed out
+    reconnecting: Arc<std::sync::Mutex<HashMap<String, JoinHandle<()>>>>,
+}
+
+/// Supervision state of an MCP server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ServerStatus {
+    /// Responding to health checks
+    #[default]
+    Healthy,
+    /// Failing health checks, not yet restarted
+    Unhealthy,
+    /// Being restarted
+    Restarting,
+    /// Could not be restarted; removed from the running servers
+    Failed,
+}
+
+/// Health of a supervised MCP server
+#[derive(Debug, Clone, Default)]
+pub struct ServerHealth {
+    pub status: ServerStatus,
+    pub consecutive_failures: u32,
+    pub restarts: u32,
+    pub last_error: Option<String>,
 }
 
 impl McpManager {
@@ -52,6 +93,8 @@ impl McpManager {
             servers: Arc::new(RwLock::new(HashMap::new())),
             tool_registry,
             config: Arc::new(RwLock::new(McpConfig::new())),
+            health: Arc::new(RwLock::new(HashMap::new())),
+            reconnecting: Arc::new(std::sync::Mutex::new(HashMap::new())),
         }
     }
     
@@ -145,12 +188,22 @@ impl McpManager {
     /// Start a specific MCP server with retry logic
     async fn start_server_with_retry(&mut self, name: String, config: McpServerConfig) -> Result<()> {
         let restart_policy = config.restart_policy().cloned().unwrap_or_default();
+        let retry = restart_policy.retry_policy();
         let mut retry_count = 0;
-        let mut delay = restart_policy.initial_delay;
         
         loop {
             match self.start_server(name.clone(), config.clone()).await {
                 Ok(()) => return Ok(()),
+                Err(e) if e.is_retryable() => {
+                    warn!("MCP server '{}' will be connected in the background: {}", name, e);
+                    self.connect_later(name, config, &e).await;
+                    return Err(e);
+                }
+                // An incompatible server won't become compatible by retrying
+                Err(e @ BedrockError::ConfigError(_)) => {
+                    warn!("Skipping MCP server '{}': {}", name, e);
+                    return Err(e);
+                }
                 Err(e) => {
                     if retry_count >= restart_policy.max_retries {
                         error!(
@@ -160,29 +213,37 @@ impl McpManager {
                         return Err(e);
                     }
                     
+                    let delay = retry.next_delay(retry_count);
                     retry_count += 1;
                     warn!(
-                        "Failed to start MCP server '{}', retrying in {} seconds (attempt {}/{}): {}",
+                        "Failed to start MCP server '{}', retrying in {:?} (attempt {}/{}): {}",
                         name, delay, retry_count, restart_policy.max_retries, e
                     );
                     
-                    tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
-                    
-                    // Calculate next delay based on backoff strategy
-                    delay = match restart_policy.backoff {
-                        BackoffStrategy::Fixed => delay,
-                        BackoffStrategy::Linear => {
-                            (delay + restart_policy.initial_delay).min(restart_policy.max_delay)
-                        }
-                        BackoffStrategy::Exponential => {
-                            (delay * 2).min(restart_policy.max_delay)
-                        }
-                    };
+                    tokio::time::sleep(delay).await;
                 }
             }
         }
     }
     
+    /// Mark a server as failed and keep trying to connect it without blocking startup
+    async fn connect_later(&self, name: String, config: McpServerConfig, error: &BedrockError) {
+        self.health.write().await.insert(name.clone(), ServerHealth {
+            status: ServerStatus::Failed,
+            last_error: Some(error.to_string()),
+            ..Default::default()
+        });
+        let task = tokio::spawn(reconnect(
+            name.clone(),
+            config,
+            self.servers.clone(),
+            self.health.clone(),
+            self.tool_registry.clone(),
+            self.reconnecting.clone(),
+        ));
+        self.reconnecting.lock().unwrap().insert(name, task);
+    }
+    
     /// Start a specific MCP server
     async fn start_server(&mut self, name: String, config: McpServerConfig) -> Result<()> {
         info!("Starting MCP server: {}", name);
@@ -193,85 +254,18 @@ impl McpManager {
             return Ok(());
         }
         
-        // Create and initialize client
-        let mut client = McpClient::new(name.clone(), config.clone()).await?;
-        client.initialize().await?;
+        let (client_arc, tool_names) = connect_server(&name, &config, &self.tool_registry, &[]).await?;
         
-        // Discover tools
-        let tools = client.list_tools().await?;
-        let tool_names: Vec<String> = tools.iter().map(|t| t.name.clone()).collect();
+        self.health.write().await.insert(name.clone(), ServerHealth::default());
         
-        info!(
-            "MCP server '{}' started with {} tools",
-            name,
-            tool_names.len()
-        );
-        
-        // Register tools with the tool registry (following reference project pattern)
-        let client_arc = Arc::new(RwLock::new(client));
-        for tool in &tools {
-            // Use simple tool name without server prefix for better compatibility
-            let wrapper = McpToolWrapper::new(
-                tool.clone(),
-                client_arc.clone(),
-                name.clone(),
-            );
-            
-            // Register with tool registry
-            self.tool_registry.register(wrapper)?;
-            debug!("Registered MCP tool: {} from server {}", tool.name, name);
-        }
-        
-        // Start health monitoring if configured
-        let health_monitor = if let Some(health_config) = config.health_check() {
-            let interval = tokio::time::Duration::from_secs(health_config.interval);
-            let max_failures = health_config.max_failures;
-            
-            let name_clone = name.clone();
-            let client_clone = client_arc.clone();
-            let servers = self.servers.clone();
-            
-            Some(tokio::spawn(async move {
-                let mut consecutive_failures = 0;
-                
-                loop {
-                    tokio::time::sleep(interval).await;
-                    
-                    // Check if server is still connected
-                    let connected = {
-                        let client = client_clone.read().await;
-                        client.is_connected().await
-                    };
-                    
-                    if connected {
-                        consecutive_failures = 0;
-                        debug!("Health check passed for MCP server '{}'", name_clone);
-                    } else {
-                        consecutive_failures += 1;
-                        warn!(
-                            "Health check failed for MCP server '{}' ({}/{})",
-                            name_clone, consecutive_failures, max_failures
-                        );
-                        
-                        if consecutive_failures >= max_failures {
-                            error!(
-                                "MCP server '{}' failed {} consecutive health checks, marking as failed",
-                                name_clone, max_failures
-                            );
-                            
-                            // Remove from active servers
-                            let mut servers_guard = servers.write().await;
-                            servers_guard.remove(&name_clone);
-                            
-                            // Note: In a production system, we might want to trigger restart here
-                            break;
-                        }
-                    }
-                }
-            }))
-        } else {
-            None
-        };
+        // Supervise the server: ping it periodically and restart it when it stops responding
+        let health_monitor = Some(tokio::spawn(supervise(
+            name.clone(),
+            config,
+            self.servers.clone(),
+            self.health.clone(),
+            self.tool_registry.clone(),
+        )));
         
         // Store server handle
         let handle = McpServerHandle {
@@ -287,10 +281,21 @@ impl McpManager {
         Ok(())
     }
     
+    /// Health of every supervised server, including ones that failed permanently
+    pub async fn server_health(&self) -> HashMap<String, ServerHealth> {
+        self.health.read().await.clone()
+    }
+    
     /// Stop a specific MCP server
     pub async fn stop_server(&mut self, name: &str) -> Result<()> {
         info!("Stopping MCP server: {}", name);
         
+        let pending = self.reconnecting.lock().unwrap().remove(name);
+        if let Some(task) = &pending {
+            task.abort();
+            self.health.write().await.remove(name);
+        }
+        
         let mut servers = self.servers.write().await;
         if let Some(mut handle) = servers.remove(name) {
             // Stop health monitor
@@ -304,11 +309,13 @@ impl McpManager {
                 warn!("Error closing MCP client '{}': {}", name, e);
             }
             
-            // TODO: Unregister tools when ToolRegistry supports it
-            // Currently ToolRegistry doesn't have unregister method
+            for tool in &handle.tools {
+                self.tool_registry.unregister(tool)?;
+            }
+            self.health.write().await.remove(name);
             
             info!("MCP server '{}' stopped", name);
-        } else {
+        } else if pending.is_none() {
             warn!("MCP server '{}' not found", name);
         }
         
@@ -319,10 +326,11 @@ impl McpManager {
     pub async fn stop_all(&mut self) -> Result<()> {
         info!("Stopping all MCP servers");
         
-        let server_names: Vec<String> = {
+        let mut server_names: Vec<String> = {
             let servers = self.servers.read().await;
             servers.keys().cloned().collect()
         };
+        server_names.extend(self.reconnecting.lock().unwrap().keys().cloned());
         
         for name in server_names {
             self.stop_server(&name).await?;
@@ -337,6 +345,55 @@ impl McpManager {
         servers.keys().cloned().collect()
     }
     
+    /// List the resources of every running server, keyed by server name
+    pub async fn list_resources(&self) -> HashMap<String, Vec<McpResource>> {
+        let servers = self.servers.read().await;
+        let mut resources = HashMap::new();
+        for (name, handle) in servers.iter() {
+            let mut client = handle.client.write().await;
+            match client.list_resources().await {
+                Ok(list) => {
+                    resources.insert(name.clone(), list);
+                }
+                Err(e) => warn!("Failed to list resources from MCP server '{}': {}", name, e),
+            }
+        }
+        resources
+    }
+    
+    /// Read a resource from a specific server
+    pub async fn read_resource(&self, server: &str, uri: &str) -> Result<Vec<ContentItem>> {
+        let client = {
+            let servers = self.servers.read().await;
+            servers.get(server)
+                .map(|handle| handle.client.clone())
+                .ok_or_else(|| BedrockError::McpError(format!("MCP server '{}' is not running", server)))?
+        };
+        let mut client = client.write().await;
+        client.read_resource(uri).await
+    }
+    
+    /// Read a resource as text suitable for a task's context.
+    /// Binary contents are noted by URI and MIME type rather than inlined.
+    pub async fn read_resource_text(&self, server: &str, uri: &str) -> Result<String> {
+        let items = self.read_resource(server, uri).await?;
+        Ok(items
+            .into_iter()
+            .filter_map(|item| match item {
+                ContentItem::Text { text } => Some(text),
+                ContentItem::Resource { resource } => Some(resource.text.unwrap_or_else(|| {
+                    format!(
+                        "[binary resource {} ({})]",
+                        resource.uri,
+                        resource.mime_type.as_deref().unwrap_or("application/octet-stream")
+                    )
+                })),
+                ContentItem::Image { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    
     /// Get information about a specific server
     pub async fn get_server_info(&self, name: &str) -> Option<(Vec<String>, bool)> {
         let servers = self.servers.read().await;
@@ -352,6 +409,258 @@ impl McpManager {
     }
 }
 
+/// Connect to a server, discover its tools and register them.
+/// Tools from `previous_tools` that the server no longer offers are unregistered.
+async fn connect_server(
+    name: &str,
+    config: &McpServerConfig,
+    tool_registry: &ToolRegistry,
+    previous_tools: &[String],
+) -> Result<(Arc<RwLock<McpClient>>, Vec<String>)> {
+    // Create and initialize client
+    let mut client = McpClient::connect(name.to_string(), config.clone()).await?;
+    
+    // Discover tools
+    let tools = client.list_tools().await?;
+    
+    info!(
+        "MCP server '{}' started with {} tools",
+        name,
+        tools.len()
+    );
+    
+    let client_arc = Arc::new(RwLock::new(client));
+    let registered = register_tools(name, &tools, &client_arc, tool_registry, previous_tools)?;
+    
+    Ok((client_arc, registered))
+}
+
+/// Register a server's tools, returning the names they were registered under.
+///
+/// A tool keeps its plain name unless another server (or a built-in tool) already
+/// registered that name, in which case it is exposed as `server__tool`.
+fn register_tools(
+    name: &str,
+    tools: &[McpTool],
+    client: &Arc<RwLock<McpClient>>,
+    tool_registry: &ToolRegistry,
+    previous_tools: &[String],
+) -> Result<Vec<String>> {
+    let mut registered = Vec::new();
+    for tool in tools {
+        let mut wrapper = McpToolWrapper::new(tool.clone(), client.clone(), name.to_string());
+        
+        // A name this server registered before is ours to reuse across restarts
+        let namespaced = namespaced_tool_name(name, &tool.name);
+        let taken = tool_registry.get(&tool.name).is_some() && !previous_tools.contains(&tool.name);
+        if previous_tools.contains(&namespaced) || taken {
+            warn!(
+                "MCP tool '{}' from server '{}' collides with an existing tool, registering it as '{}'",
+                tool.name, name, namespaced
+            );
+            wrapper = wrapper.namespaced();
+        }
+        
+        let exposed = wrapper.name().to_string();
+        tool_registry.register(wrapper)?;
+        debug!("Registered MCP tool: {} from server {}", exposed, name);
+        registered.push(exposed);
+    }
+    for stale in previous_tools.iter().filter(|t| !registered.contains(t)) {
+        tool_registry.unregister(stale)?;
+    }
+    
+    Ok(registered)
+}
+
+/// Ping a server, treating a dropped transport or a slow reply as a failure
+async fn check_server(client: &Arc<RwLock<McpClient>>, timeout: Duration) -> Result<()> {
+    // Wait for any in-flight tool call first so a long call isn't mistaken for a hang
+    let mut client = client.write().await;
+    if !client.is_connected().await {
+        return Err(BedrockError::McpError("transport disconnected".into()));
+    }
+    tokio::time::timeout(timeout, client.ping())
+        .await
+        .map_err(|_| BedrockError::McpError(format!("ping timed out after {:?}", timeout)))?
+}
+
+/// Reconnect a server, retrying with the policy's backoff
+async fn restart_server(
+    name: &str,
+    config: &McpServerConfig,
+    policy: &RestartPolicy,
+    tool_registry: &ToolRegistry,
+    previous_tools: &[String],
+) -> Result<(Arc<RwLock<McpClient>>, Vec<String>)> {
+    let retry = policy.retry_policy();
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match connect_server(name, config, tool_registry, previous_tools).await {
+            Ok(connected) => return Ok(connected),
+            Err(e) if attempt >= policy.max_retries.max(1) => return Err(e),
+            Err(e) => {
+                let delay = retry.next_delay(attempt - 1);
+                warn!(
+                    "Restart of MCP server '{}' failed, retrying in {:?} (attempt {}/{}): {}",
+                    name, delay, attempt, policy.max_retries, e
+                );
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+/// Connect a server that failed its startup handshake, retrying with its restart
+/// policy, then supervise it like a server that started normally
+async fn reconnect(
+    name: String,
+    config: McpServerConfig,
+    servers: Arc<RwLock<HashMap<String, McpServerHandle>>>,
+    health: Arc<RwLock<HashMap<String, ServerHealth>>>,
+    tool_registry: Arc<ToolRegistry>,
+    reconnecting: Arc<std::sync::Mutex<HashMap<String, JoinHandle<()>>>>,
+) {
+    let restart_policy = config.restart_policy().cloned().unwrap_or_default();
+    tokio::time::sleep(Duration::from_secs(restart_policy.initial_delay)).await;
+    
+    let connected = restart_server(&name, &config, &restart_policy, &tool_registry, &[]).await;
+    reconnecting.lock().unwrap().remove(&name);
+    match connected {
+        Ok((client, tools)) => {
+            info!("MCP server '{}' connected after failing at startup", name);
+            health.write().await.insert(name.clone(), ServerHealth::default());
+            servers.write().await.insert(name.clone(), McpServerHandle {
+                name: name.clone(),
+                client,
+                tools,
+                health_monitor: None,
+                restart_count: 0,
+            });
+            supervise(name, config, servers, health, tool_registry).await;
+        }
+        Err(e) => {
+            error!("Giving up on MCP server '{}': {}", name, e);
+            health.write().await.insert(name, ServerHealth {
+                status: ServerStatus::Failed,
+                last_error: Some(e.to_string()),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// Health check loop for one server; returns when the server is stopped or given up on
+async fn supervise(
+    name: String,
+    config: McpServerConfig,
+    servers: Arc<RwLock<HashMap<String, McpServerHandle>>>,
+    health: Arc<RwLock<HashMap<String, ServerHealth>>>,
+    tool_registry: Arc<ToolRegistry>,
+) {
+    let health_config = config.health_check().cloned().unwrap_or_default();
+    let restart_policy = config.restart_policy().cloned().unwrap_or_default();
+    let interval = Duration::from_secs(health_config.interval);
+    let check_timeout = Duration::from_secs(health_config.timeout);
+    let mut consecutive_failures = 0;
+    
+    let set_health = |update: ServerHealth| {
+        let health = health.clone();
+        let name = name.clone();
+        async move {
+            health.write().await.insert(name, update);
+        }
+    };
+    let mut current = ServerHealth::default();
+    
+    loop {
+        tokio::time::sleep(interval).await;
+        
+        let client = match servers.read().await.get(&name) {
+            Some(handle) => handle.client.clone(),
+            None => return,
+        };
+        
+        match check_server(&client, check_timeout).await {
+            Ok(()) => {
+                debug!("Health check passed for MCP server '{}'", name);
+                consecutive_failures = 0;
+                current.status = ServerStatus::Healthy;
+                current.consecutive_failures = 0;
+                current.last_error = None;
+                set_health(current.clone()).await;
+                continue;
+            }
+            Err(e) => {
+                consecutive_failures += 1;
+                warn!(
+                    "Health check failed for MCP server '{}' ({}/{}): {}",
+                    name, consecutive_failures, health_config.max_failures, e
+                );
+                current.status = ServerStatus::Unhealthy;
+                current.consecutive_failures = consecutive_failures;
+                current.last_error = Some(e.to_string());
+                set_health(current.clone()).await;
+            }
+        }
+        
+        if consecutive_failures < health_config.max_failures {
+            continue;
+        }
+        
+        warn!("Restarting MCP server '{}'", name);
+        current.status = ServerStatus::Restarting;
+        set_health(current.clone()).await;
+        
+        if let Err(e) = client.write().await.close().await {
+            debug!("Error closing unhealthy MCP client '{}': {}", name, e);
+        }
+        let previous_tools = servers.read().await
+            .get(&name)
+            .map(|handle| handle.tools.clone())
+            .unwrap_or_default();
+        
+        match restart_server(&name, &config, &restart_policy, &tool_registry, &previous_tools).await {
+            Ok((client, tools)) => {
+                let mut servers = servers.write().await;
+                let Some(handle) = servers.get_mut(&name) else {
+                    // Stopped while restarting; undo the restart
+                    for tool in &tools {
+                        let _ = tool_registry.unregister(tool);
+                    }
+                    let _ = client.write().await.close().await;
+                    return;
+                };
+                handle.client = client;
+                handle.tools = tools;
+                handle.restart_count += 1;
+                
+                info!("MCP server '{}' restarted (restart #{})", name, handle.restart_count);
+                consecutive_failures = 0;
+                current = ServerHealth {
+                    status: ServerStatus::Healthy,
+                    consecutive_failures: 0,
+                    restarts: handle.restart_count,
+                    last_error: None,
+                };
+                set_health(current.clone()).await;
+            }
+            Err(e) => {
+                error!("Giving up on MCP server '{}': {}", name, e);
+                for tool in &previous_tools {
+                    let _ = tool_registry.unregister(tool);
+                }
+                servers.write().await.remove(&name);
+                current.status = ServerStatus::Failed;
+                current.last_error = Some(e.to_string());
+                set_health(current).await;
+                return;
+            }
+        }
+    }
+}
+
 impl Drop for McpManager {
     fn drop(&mut self) {
         // Stop all servers when manager is dropped
@@ -368,6 +677,192 @@ impl Clone for McpManager {
             servers: self.servers.clone(),
             tool_registry: self.tool_registry.clone(),
             config: self.config.clone(),
+            health: self.health.clone(),
+            reconnecting: self.reconnecting.clone(),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BackoffStrategy;
+
+    fn policy(backoff: BackoffStrategy) -> RestartPolicy {
+        RestartPolicy {
+            max_retries: 5,
+            initial_delay: 2,
+            max_delay: 10,
+            backoff,
+        }
+    }
+
+    fn delays(policy: &RestartPolicy) -> Vec<u64> {
+        let retry = policy.retry_policy();
+        (0..5).map(|attempt| retry.next_delay(attempt).as_secs()).collect()
+    }
+
+    #[test]
+    fn test_restart_backoff() {
+        assert_eq!(delays(&policy(BackoffStrategy::Fixed)), vec![2, 2, 2, 2, 2]);
+        assert_eq!(delays(&policy(BackoffStrategy::Linear)), vec![2, 4, 6, 8, 10]);
+        assert_eq!(delays(&policy(BackoffStrategy::Exponential)), vec![2, 4, 8, 10, 10]);
+    }
+
+    #[tokio::test]
+    async fn test_server_health_empty_without_servers() {
+        let manager = McpManager::new(Arc::new(ToolRegistry::new()));
+        assert!(manager.server_health().await.is_empty());
+        assert!(manager.list_servers().await.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_hung_handshake_does_not_block_startup() {
+        let mut manager = McpManager::new(Arc::new(ToolRegistry::new()));
+        let config: McpServerConfig = serde_json::from_value(serde_json::json!({
+            "command": "sleep",
+            "args": ["30"],
+            "handshake_timeout": 200,
+            "restart_policy": {"max_retries": 1, "initial_delay": 60}
+        }))
+        .unwrap();
+        manager.add_servers_from_config(HashMap::from([("hung".to_string(), config)])).await.unwrap();
+        
+        let started = std::time::Instant::now();
+        let result = manager.start_servers(Vec::new()).await;
+        
+        // Failed without retrying inline, and left to the background reconnect
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(manager.list_servers().await.is_empty());
+        let health = manager.server_health().await;
+        assert_eq!(health["hung"].status, ServerStatus::Failed);
+        assert!(health["hung"].last_error.as_ref().unwrap().contains("handshake"));
+        assert!(manager.reconnecting.lock().unwrap().contains_key("hung"));
+        
+        manager.stop_all().await.unwrap();
+        assert!(manager.reconnecting.lock().unwrap().is_empty());
+        assert!(manager.server_health().await.is_empty());
+    }
+    
+    /// Minimal stdio MCP server offering one tool; writes its PID to the file in `$1`
+    const FAKE_SERVER: &str = r#"echo $$ > "$1"
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/.*"id":"\([^"]*\)".*/\1/p')
+  [ -z "$id" ] && continue
+  case "$line" in
+    *'"initialize"'*) result='{"protocolVersion":"2024-11-05","capabilities":{"tools":{}}}' ;;
+    *'"tools/list"'*) result='{"tools":[{"name":"fake_echo","description":"Echo","inputSchema":{"type":"object"}}]}' ;;
+    *) result='{}' ;;
+  esac
+  printf '{"jsonrpc":"2.0","id":"%s","result":%s}\n' "$id" "$result"
+done"#;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_killed_server_is_restarted() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let read_pid = || std::fs::read_to_string(&pid_file).ok().and_then(|pid| pid.trim().parse::<u32>().ok());
+        let registry = Arc::new(ToolRegistry::new());
+        let mut manager = McpManager::new(registry.clone());
+        let config: McpServerConfig = serde_json::from_value(serde_json::json!({
+            "command": "sh",
+            "args": ["-c", FAKE_SERVER, "sh", pid_file],
+            "health_check": {"interval": 1, "timeout": 1, "max_failures": 1},
+            "restart_policy": {"max_retries": 3, "initial_delay": 0}
+        }))
+        .unwrap();
+        manager.add_servers_from_config(HashMap::from([("fake".to_string(), config)])).await.unwrap();
+        manager.start_servers(Vec::new()).await.unwrap();
+        assert!(registry.get("fake_echo").is_some());
+        let first_pid = read_pid().unwrap();
+        
+        std::process::Command::new("kill").args(["-9", &first_pid.to_string()]).status().unwrap();
+        
+        let deadline = std::time::Instant::now() + Duration::from_secs(15);
+        let health = loop {
+            let health = manager.server_health().await["fake"].clone();
+            if health.restarts > 0 && health.status == ServerStatus::Healthy {
+                break health;
+            }
+            assert!(std::time::Instant::now() < deadline, "not restarted: {health:?}");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        };
+        
+        assert_eq!(health.restarts, 1);
+        assert_ne!(read_pid(), Some(first_pid));
+        assert_eq!(manager.list_servers().await, vec!["fake"]);
+        assert!(registry.get("fake_echo").is_some());
+        manager.stop_all().await.unwrap();
+    }
+    
+    /// Answers every request with a text result naming its server
+    #[derive(Debug)]
+    struct EchoTransport {
+        server: String,
+        pending: Option<crate::types::JsonRpcResponse>,
+    }
+    
+    #[async_trait::async_trait]
+    impl crate::transport::Transport for EchoTransport {
+        async fn send_request(&mut self, request: crate::types::JsonRpcRequest) -> Result<()> {
+            self.pending = Some(crate::types::JsonRpcResponse {
+                jsonrpc: "2.0".to_string(),
+                id: request.id,
+                result: Some(serde_json::json!({
+                    "content": [{"type": "text", "text": format!("from {}", self.server)}]
+                })),
+                error: None,
+            });
+            Ok(())
         }
+        
+        async fn send_notification(&mut self, _notification: crate::types::JsonRpcNotification) -> Result<()> {
+            Ok(())
+        }
+        
+        async fn receive_response(&mut self) -> Result<Option<crate::types::JsonRpcResponse>> {
+            Ok(self.pending.take())
+        }
+        
+        async fn is_connected(&self) -> bool {
+            true
+        }
+        
+        async fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+    
+    fn echo_client(server: &str) -> Arc<RwLock<McpClient>> {
+        let transport = EchoTransport { server: server.to_string(), pending: None };
+        Arc::new(RwLock::new(McpClient::with_transport(server.to_string(), Box::new(transport), 1000)))
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_colliding_tool_names_are_namespaced() {
+        let registry = ToolRegistry::new();
+        let search = McpTool {
+            name: "search".to_string(),
+            description: "Search things".to_string(),
+            input_schema: serde_json::json!({"type": "object"}),
+        };
+        
+        let alpha = register_tools("alpha", std::slice::from_ref(&search), &echo_client("alpha"), &registry, &[]).unwrap();
+        let beta = register_tools("beta", std::slice::from_ref(&search), &echo_client("beta"), &registry, &[]).unwrap();
+        assert_eq!(alpha, vec!["search"]);
+        assert_eq!(beta, vec!["beta__search"]);
+        
+        let namespaced = registry.get("beta__search").unwrap();
+        assert!(namespaced.description().contains("'search'"));
+        
+        for (name, server) in [("search", "alpha"), ("beta__search", "beta")] {
+            let result = registry.get(name).unwrap().execute(serde_json::json!({})).await.unwrap();
+            assert!(result.to_string().contains(&format!("from {}", server)), "{name}: {result}");
+        }
+        
+        // Re-registering after a restart keeps the names each server had
+        let beta_again = register_tools("beta", &[search], &echo_client("beta"), &registry, &beta).unwrap();
+        assert_eq!(beta_again, beta);
+    }
+}
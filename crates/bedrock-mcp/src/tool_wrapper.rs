@@ -20,20 +20,72 @@ pub struct McpToolWrapper {
     /// MCP client for executing the tool
     client: Arc<RwLock<McpClient>>,
     
-    /// Server name (for tracking, not exposed in tool name)
+    /// Server name (for tracking; only exposed in the tool name when namespaced)
     server_name: String,
+    
+    /// Name the tool is registered under; differs from the MCP name when namespaced
+    exposed_name: String,
+    
+    /// Description shown to the model
+    description: String,
+}
+
+/// Separator between server and tool name in namespaced tool names
+const NAMESPACE_SEPARATOR: &str = "__";
+
+/// Bedrock accepts tool names of at most 64 characters
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Build a `server__tool` name restricted to the characters Bedrock accepts.
+///
+/// A name too long for Bedrock is cut short and ends in a hash of the full name,
+/// so two long names sharing a prefix stay apart.
+pub fn namespaced_tool_name(server_name: &str, tool_name: &str) -> String {
+    let full = format!("{}{}{}", server_name, NAMESPACE_SEPARATOR, tool_name);
+    let name: String = full
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    if name.len() <= MAX_TOOL_NAME_LEN {
+        return name;
+    }
+    let suffix = format!("_{:08x}", fnv1a(full.as_bytes()));
+    format!("{}{}", &name[..MAX_TOOL_NAME_LEN - suffix.len()], suffix)
+}
+
+/// 32-bit FNV-1a, so a shortened name is the same on every run
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193))
 }
 
 impl McpToolWrapper {
     /// Create a new MCP tool wrapper
     pub fn new(tool_def: McpTool, client: Arc<RwLock<McpClient>>, server_name: String) -> Self {
         Self {
+            exposed_name: tool_def.name.clone(),
+            description: tool_def.description.clone(),
             tool_def,
             client,
             server_name,
         }
     }
     
+    /// Expose the tool as `server__tool`, e.g. when another server already
+    /// registered a tool with the same name. The original name stays in the description.
+    pub fn namespaced(mut self) -> Self {
+        self.exposed_name = namespaced_tool_name(&self.server_name, &self.tool_def.name);
+        self.description = format!(
+            "{} (tool '{}' from MCP server '{}')",
+            self.tool_def.description, self.tool_def.name, self.server_name
+        );
+        self
+    }
+    
+    /// Name of the tool on the MCP server
+    pub fn original_name(&self) -> &str {
+        &self.tool_def.name
+    }
+    
     /// Get the server name this tool belongs to
     pub fn server_name(&self) -> &str {
         &self.server_name
@@ -43,18 +95,25 @@ impl McpToolWrapper {
 #[async_trait]
 impl Tool for McpToolWrapper {
     fn name(&self) -> &str {
-        // Use simple tool name without server prefix for Bedrock compatibility
-        &self.tool_def.name
+        // Plain tool name unless it had to be namespaced to avoid a collision
+        &self.exposed_name
     }
 
     fn description(&self) -> &str {
-        &self.tool_def.description
+        &self.description
     }
 
     fn schema(&self) -> Value {
         // Return raw schema directly - Bedrock handles it fine
         // Following reference project pattern (no cleaning needed)
-        self.tool_def.input_schema.clone()
+        let mut schema = self.tool_def.input_schema.clone();
+        if self.exposed_name != self.tool_def.name {
+            // A title naming the server-side tool would contradict the namespaced name
+            if let Value::Object(ref mut map) = schema {
+                map.remove("title");
+            }
+        }
+        schema
     }
 
     async fn execute(&self, args: Value) -> Result<Value> {
@@ -83,6 +142,18 @@ impl Tool for McpToolWrapper {
                                 "mime_type": mime_type
                             }));
                         }
+                        ContentItem::Resource { resource } => {
+                            if let Some(text) = resource.text {
+                                text_content.push(text);
+                            } else if let Some(blob) = resource.blob {
+                                images.push(json!({
+                                    "type": "resource",
+                                    "uri": resource.uri,
+                                    "data": blob,
+                                    "mime_type": resource.mime_type
+                                }));
+                            }
+                        }
                     }
                 }
                 
@@ -139,6 +210,8 @@ mod tests {
             args: vec![],
             env: Default::default(),
             timeout: 30000,
+            handshake_timeout: 10000,
+            request_timeout: None,
             disabled: false,
             health_check: None,
             restart_policy: None,
@@ -161,4 +234,19 @@ mod tests {
         assert_eq!(wrapper.description(), "Read contents of a file");
         assert_eq!(wrapper.server_name(), "test-server");
     }
+    
+    #[test]
+    fn test_namespaced_tool_name() {
+        assert_eq!(namespaced_tool_name("github", "search"), "github__search");
+        assert_eq!(namespaced_tool_name("my.server", "search"), "my_server__search");
+        assert_eq!(namespaced_tool_name(&"s".repeat(70), "search").len(), MAX_TOOL_NAME_LEN);
+
+        // Names that only differ past the limit don't collide
+        let server = "s".repeat(60);
+        let first = namespaced_tool_name(&server, "search_issues");
+        let second = namespaced_tool_name(&server, "search_pulls");
+        assert_eq!(first.len(), MAX_TOOL_NAME_LEN);
+        assert_ne!(first, second);
+        assert_eq!(first, namespaced_tool_name(&server, "search_issues"));
+    }
 }
\ No newline at end of file
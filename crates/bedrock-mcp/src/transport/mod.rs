@@ -3,6 +3,8 @@
 //! Provides transport abstractions for MCP communication with support for:
 //! - Stdio (process-based) transport
 //! - SSE (Server-Sent Events) transport
+//! - Streamable HTTP transport
+//! - WebSocket transport
 
 use async_trait::async_trait;
 use bedrock_core::Result;
@@ -14,9 +16,13 @@ use crate::types::{JsonRpcRequest, JsonRpcResponse, JsonRpcNotification};
 
 pub mod stdio;
 pub mod sse;
+pub mod streamable_http;
+pub mod websocket;
 
 pub use stdio::StdioTransport;
 pub use sse::SseTransport;
+pub use streamable_http::StreamableHttpTransport;
+pub use websocket::WsTransport;
 
 /// Transport trait for MCP communication
 #[async_trait]
@@ -59,6 +65,30 @@ pub enum TransportConfig {
         #[serde(default = "default_timeout")]
         timeout: u64,
     },
+
+    /// Streamable HTTP transport (JSON-RPC POSTs answered with JSON or SSE)
+    ///
+    /// Shares its shape with `Sse` like `WebSocket`; server entries pick it with
+    /// `type: http` (or `streamable-http`).
+    StreamableHttp {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default = "default_timeout")]
+        timeout: u64,
+    },
+
+    /// WebSocket transport (JSON-RPC over text frames)
+    ///
+    /// Shares its shape with `Sse`, so an untagged `{url}` deserializes as `Sse`;
+    /// server entries pick this variant from a `ws://`/`wss://` URL instead.
+    WebSocket {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default = "default_timeout")]
+        timeout: u64,
+    },
 }
 
 fn default_timeout() -> u64 {
@@ -86,6 +116,22 @@ impl TransportConfig {
                 ).await?;
                 Ok(Box::new(transport))
             }
+            TransportConfig::StreamableHttp { url, headers, timeout } => {
+                let transport = StreamableHttpTransport::new(
+                    url.clone(),
+                    headers.clone(),
+                    *timeout,
+                ).await?;
+                Ok(Box::new(transport))
+            }
+            TransportConfig::WebSocket { url, headers, timeout } => {
+                let transport = WsTransport::new(
+                    url.clone(),
+                    headers.clone(),
+                    *timeout,
+                ).await?;
+                Ok(Box::new(transport))
+            }
         }
     }
     
@@ -94,6 +140,8 @@ impl TransportConfig {
         match self {
             TransportConfig::Stdio { .. } => "stdio",
             TransportConfig::Sse { .. } => "sse",
+            TransportConfig::StreamableHttp { .. } => "streamable_http",
+            TransportConfig::WebSocket { .. } => "websocket",
         }
     }
 }
\ No newline at end of file
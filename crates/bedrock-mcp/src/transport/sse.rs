@@ -226,14 +226,14 @@ impl SseTransport {
 impl Transport for SseTransport {
     async fn send_request(&mut self, request: JsonRpcRequest) -> Result<()> {
         let json = serde_json::to_string(&request)
-            .map_err(|e| BedrockError::SerializationError(e))?;
+            .map_err(BedrockError::SerializationError)?;
         
         self.send_message(json).await
     }
     
     async fn send_notification(&mut self, notification: JsonRpcNotification) -> Result<()> {
         let json = serde_json::to_string(&notification)
-            .map_err(|e| BedrockError::SerializationError(e))?;
+            .map_err(BedrockError::SerializationError)?;
         
         self.send_message(json).await
     }
@@ -255,7 +255,7 @@ impl Transport for SseTransport {
 }
 
 /// Resolve environment variable values (including secret patterns)
-fn resolve_env_value(value: &str) -> String {
+pub(super) fn resolve_env_value(value: &str) -> String {
     if value.starts_with("${") && value.ends_with("}") {
         let inner = &value[2..value.len()-1];
         
@@ -170,7 +170,7 @@ impl Transport for StdioTransport {
         let mut stdin_guard = self.stdin.lock().await;
         if let Some(stdin) = stdin_guard.as_mut() {
             let json = serde_json::to_string(&request)
-                .map_err(|e| BedrockError::SerializationError(e))?;
+                .map_err(BedrockError::SerializationError)?;
             
             debug!("Sending to MCP server: {}", json);
             stdin.write_all(json.as_bytes()).await
@@ -190,7 +190,7 @@ impl Transport for StdioTransport {
         let mut stdin_guard = self.stdin.lock().await;
         if let Some(stdin) = stdin_guard.as_mut() {
             let json = serde_json::to_string(&notification)
-                .map_err(|e| BedrockError::SerializationError(e))?;
+                .map_err(BedrockError::SerializationError)?;
             
             debug!("Sending notification to MCP server: {}", json);
             stdin.write_all(json.as_bytes()).await
@@ -0,0 +1,390 @@
+//! Streamable HTTP transport, the HTTP transport of the current MCP spec
+//!
+//! Every JSON-RPC message is POSTed to a single endpoint. The server answers a
+//! request either with a JSON body or with an SSE stream carrying the response,
+//! and answers notifications with `202 Accepted`. The session id the server
+//! returns from `initialize` in the `Mcp-Session-Id` header is sent with every
+//! later message, and the session is ended with a `DELETE` when the transport closes.
+
+use async_trait::async_trait;
+use bedrock_core::{BedrockError, Result};
+use eventsource_stream::Eventsource;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::pin::pin;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tracing::{debug, error, info, warn};
+
+use crate::types::{JsonRpcRequest, JsonRpcResponse, JsonRpcNotification};
+use super::sse::resolve_env_value;
+use super::Transport;
+
+/// Header carrying the session id assigned by the server
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// Streamable HTTP transport for MCP servers
+pub struct StreamableHttpTransport {
+    /// The MCP endpoint every message is POSTed to
+    url: String,
+
+    /// HTTP client with the configured headers
+    client: reqwest::Client,
+
+    /// Session id from the server's `Mcp-Session-Id` header, once assigned
+    session_id: Arc<RwLock<Option<String>>>,
+
+    /// Responses read from JSON bodies and SSE streams
+    response_tx: mpsc::Sender<JsonRpcResponse>,
+
+    /// Channel for receiving responses
+    response_rx: Arc<Mutex<mpsc::Receiver<JsonRpcResponse>>>,
+
+    /// Connection state; cleared when the session expires or the transport closes
+    connected: Arc<RwLock<bool>>,
+}
+
+impl std::fmt::Debug for StreamableHttpTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamableHttpTransport")
+            .field("url", &self.url)
+            .field("session_id", &self.session_id)
+            .field("connected", &self.connected)
+            .finish()
+    }
+}
+
+impl StreamableHttpTransport {
+    /// Create a transport for the MCP endpoint at `url`. Nothing is sent until the
+    /// first message, which is normally `initialize`.
+    pub async fn new(
+        url: String,
+        headers: HashMap<String, String>,
+        timeout: u64,
+    ) -> Result<Self> {
+        info!("Connecting to MCP server via streamable HTTP: {}", url);
+
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(BedrockError::McpError("Streamable HTTP URL must start with http:// or https://".into()));
+        }
+
+        let mut default_headers = reqwest::header::HeaderMap::new();
+        for (key, value) in &headers {
+            let header_name = reqwest::header::HeaderName::from_bytes(key.as_bytes())
+                .map_err(|e| BedrockError::McpError(format!("Invalid header name '{}': {}", key, e)))?;
+            let header_value = reqwest::header::HeaderValue::from_str(&resolve_env_value(value))
+                .map_err(|e| BedrockError::McpError(format!("Invalid header value for '{}': {}", key, e)))?;
+            default_headers.insert(header_name, header_value);
+        }
+
+        // The timeout bounds connecting only: an SSE response stays open while the server works
+        let client = reqwest::Client::builder()
+            .connect_timeout(std::time::Duration::from_millis(timeout))
+            .default_headers(default_headers)
+            .build()
+            .map_err(|e| BedrockError::McpError(format!("Failed to build HTTP client: {}", e)))?;
+
+        let (response_tx, response_rx) = mpsc::channel::<JsonRpcResponse>(100);
+
+        Ok(Self {
+            url,
+            client,
+            session_id: Arc::new(RwLock::new(None)),
+            response_tx,
+            response_rx: Arc::new(Mutex::new(response_rx)),
+            connected: Arc::new(RwLock::new(true)),
+        })
+    }
+
+    /// POST one message and queue whatever responses come back with it
+    async fn post(&self, json: String) -> Result<()> {
+        debug!("Sending message to {}: {}", self.url, json);
+
+        let mut request = self.client.post(&self.url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json, text/event-stream");
+        let session_id = self.session_id.read().await.clone();
+        if let Some(ref session_id) = session_id {
+            request = request.header(SESSION_HEADER, session_id);
+        }
+
+        let response = request
+            .body(json)
+            .send()
+            .await
+            .map_err(|e| BedrockError::McpError(format!("Failed to send HTTP request: {}", e)))?;
+
+        if let Some(assigned) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            if session_id.as_deref() != Some(assigned) {
+                debug!("MCP session id assigned: {}", assigned);
+                *self.session_id.write().await = Some(assigned.to_string());
+            }
+        }
+
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND && session_id.is_some() {
+            // The server dropped the session; the client has to initialize again
+            *self.session_id.write().await = None;
+            *self.connected.write().await = false;
+            return Err(BedrockError::McpError(format!("MCP session expired at {}", self.url)));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(BedrockError::McpError(format!("HTTP request failed with status {}: {}", status, body)));
+        }
+        if status == reqwest::StatusCode::ACCEPTED {
+            return Ok(());
+        }
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        if content_type.starts_with("text/event-stream") {
+            // Read the stream in the background so the client's request timeout applies
+            tokio::spawn(read_event_stream(response.bytes_stream(), self.response_tx.clone()));
+        } else {
+            let body = response.text().await
+                .map_err(|e| BedrockError::McpError(format!("Failed to read HTTP response: {}", e)))?;
+            if !body.trim().is_empty() {
+                forward_message(&body, &self.response_tx).await;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Forward the responses in an SSE stream until the server ends it. Bytes are
+/// decoded per event, so a character split across chunks arrives whole.
+async fn read_event_stream<S, B, E>(stream: S, response_tx: mpsc::Sender<JsonRpcResponse>)
+where
+    S: Stream<Item = std::result::Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut events = pin!(stream.eventsource());
+
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(event) if !event.data.is_empty() => forward_message(&event.data, &response_tx).await,
+            Ok(_) => {}
+            Err(e) => {
+                error!("Streamable HTTP stream error: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Queue the response, or batch of responses, in a JSON-RPC message body.
+/// Requests and notifications from the server are not supported and skipped.
+async fn forward_message(body: &str, response_tx: &mpsc::Sender<JsonRpcResponse>) {
+    let messages = match serde_json::from_str::<Value>(body) {
+        Ok(Value::Array(batch)) => batch,
+        Ok(message) => vec![message],
+        Err(e) => {
+            debug!("Failed to parse message as JSON-RPC: {} - {}", body, e);
+            return;
+        }
+    };
+
+    for message in messages {
+        if message.get("method").is_some() {
+            debug!("Ignoring server-initiated message: {}", message);
+            continue;
+        }
+        match serde_json::from_value::<JsonRpcResponse>(message) {
+            Ok(response) => {
+                if let Err(e) = response_tx.send(response).await {
+                    error!("Failed to send response through channel: {}", e);
+                }
+            }
+            Err(e) => debug!("Failed to parse message as JSON-RPC response: {}", e),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for StreamableHttpTransport {
+    async fn send_request(&mut self, request: JsonRpcRequest) -> Result<()> {
+        let json = serde_json::to_string(&request)
+            .map_err(BedrockError::SerializationError)?;
+
+        self.post(json).await
+    }
+
+    async fn send_notification(&mut self, notification: JsonRpcNotification) -> Result<()> {
+        let json = serde_json::to_string(&notification)
+            .map_err(BedrockError::SerializationError)?;
+
+        self.post(json).await
+    }
+
+    async fn receive_response(&mut self) -> Result<Option<JsonRpcResponse>> {
+        let mut rx_guard = self.response_rx.lock().await;
+        Ok(rx_guard.recv().await)
+    }
+
+    async fn is_connected(&self) -> bool {
+        *self.connected.read().await
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        info!("Closing streamable HTTP transport");
+        *self.connected.write().await = false;
+
+        // Tell the server the session is over; it may not support ending sessions
+        if let Some(session_id) = self.session_id.write().await.take() {
+            if let Err(e) = self.client.delete(&self.url).header(SESSION_HEADER, session_id).send().await {
+                warn!("Failed to end MCP session at {}: {}", self.url, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Read one HTTP request, returning its head and body
+    async fn read_request(socket: &mut TcpStream) -> (String, String) {
+        let mut data = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut chunk).await.unwrap();
+            data.extend_from_slice(&chunk[..n]);
+            let text = String::from_utf8_lossy(&data).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                    .unwrap_or(0);
+                if body.len() >= length || n == 0 {
+                    return (head.to_string(), body.to_string());
+                }
+            }
+        }
+    }
+
+    /// Serve `initialize` with a JSON body and a session id, notifications with 202,
+    /// other requests with an SSE stream. Returns the URL and the request heads seen.
+    async fn spawn_server() -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let heads = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = heads.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let (head, body) = read_request(&mut socket).await;
+                seen.lock().unwrap().push(head.clone());
+
+                let response = if head.starts_with("DELETE") {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                } else {
+                    let message: Value = serde_json::from_str(&body).unwrap();
+                    match message["method"].as_str().unwrap() {
+                        "initialize" => {
+                            let reply = serde_json::json!({"jsonrpc": "2.0", "id": message["id"], "result": {"protocolVersion": "2025-03-26"}}).to_string();
+                            format!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nMcp-Session-Id: session-1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{reply}",
+                                reply.len()
+                            )
+                        }
+                        method if method.starts_with("notifications/") => {
+                            "HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+                        }
+                        method => {
+                            let progress = serde_json::json!({"jsonrpc": "2.0", "method": "notifications/progress", "params": {}});
+                            let reply = serde_json::json!({"jsonrpc": "2.0", "id": message["id"], "result": {"method": method}});
+                            format!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\ndata: {progress}\r\n\r\nevent: message\r\ndata: {reply}\r\n\r\n"
+                            )
+                        }
+                    }
+                };
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+        });
+
+        (url, heads)
+    }
+
+    async fn next_response(transport: &mut StreamableHttpTransport) -> JsonRpcResponse {
+        tokio::time::timeout(Duration::from_secs(10), transport.receive_response())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_session_json_and_sse_responses() {
+        let (url, heads) = spawn_server().await;
+        let mut transport = StreamableHttpTransport::new(url, HashMap::new(), 5000).await.unwrap();
+
+        transport.send_request(JsonRpcRequest::new("1".into(), "initialize".into(), None)).await.unwrap();
+        assert_eq!(next_response(&mut transport).await.id, "1");
+
+        transport
+            .send_notification(JsonRpcNotification {
+                jsonrpc: "2.0".to_string(),
+                method: "notifications/initialized".to_string(),
+                params: None,
+            })
+            .await
+            .unwrap();
+
+        // The progress notification in the stream is skipped
+        transport.send_request(JsonRpcRequest::new("2".into(), "tools/list".into(), None)).await.unwrap();
+        let response = next_response(&mut transport).await;
+        assert_eq!(response.id, "2");
+        assert_eq!(response.result.unwrap()["method"], "tools/list");
+
+        transport.close().await.unwrap();
+        assert!(!transport.is_connected().await);
+
+        let heads = heads.lock().unwrap();
+        let has_session = |head: &String| head.to_ascii_lowercase().contains("mcp-session-id: session-1");
+        assert_eq!(heads.len(), 4);
+        assert!(!has_session(&heads[0]));
+        assert!(heads[1..].iter().all(has_session));
+        assert!(heads[3].starts_with("DELETE /mcp"));
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_keeps_characters_split_across_chunks() {
+        let event = "data: {\"jsonrpc\": \"2.0\", \"id\": \"1\", \"result\": {\"text\": \"café\"}}\r\n\r\n";
+        let bytes = event.as_bytes();
+        // Split inside the two bytes of 'é'
+        let split = event.find('é').unwrap() + 1;
+        let chunks = vec![
+            Ok::<_, std::io::Error>(bytes[..split].to_vec()),
+            Ok(bytes[split..].to_vec()),
+        ];
+        let (response_tx, mut response_rx) = mpsc::channel(1);
+
+        read_event_stream(futures::stream::iter(chunks), response_tx).await;
+
+        let response = response_rx.recv().await.unwrap();
+        assert_eq!(response.result.unwrap()["text"], "café");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_non_http_url() {
+        let err = StreamableHttpTransport::new("ws://localhost:9000".into(), HashMap::new(), 1000)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("http://"));
+    }
+}
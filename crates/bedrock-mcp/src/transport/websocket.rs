@@ -0,0 +1,349 @@
+//! WebSocket transport for MCP servers that speak JSON-RPC over WebSocket
+//!
+//! Each JSON-RPC message is sent as a single text frame. A background task owns
+//! the socket: it answers pings, forwards responses to the transport and, when the
+//! connection drops, reconnects with exponential backoff until the transport is closed.
+
+use async_trait::async_trait;
+use bedrock_core::{BedrockError, Result};
+use futures::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, error, info, warn};
+
+use crate::types::{JsonRpcRequest, JsonRpcResponse, JsonRpcNotification};
+use super::sse::resolve_env_value;
+use super::Transport;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Why the socket loop stopped
+enum Disconnect {
+    /// The transport was closed on our side; don't reconnect
+    Closed,
+    /// The server went away or the connection failed
+    Dropped,
+}
+
+/// Connection settings shared with the background task for reconnects
+#[derive(Clone)]
+struct Endpoint {
+    url: String,
+    headers: HashMap<String, String>,
+    timeout: Duration,
+}
+
+impl Endpoint {
+    async fn connect(&self) -> Result<WsStream> {
+        let mut request = self.url.as_str().into_client_request()
+            .map_err(|e| BedrockError::McpError(format!("Invalid WebSocket URL '{}': {}", self.url, e)))?;
+
+        for (key, value) in &self.headers {
+            let header_name = HeaderName::from_bytes(key.as_bytes())
+                .map_err(|e| BedrockError::McpError(format!("Invalid header name '{}': {}", key, e)))?;
+            let header_value = HeaderValue::from_str(&resolve_env_value(value))
+                .map_err(|e| BedrockError::McpError(format!("Invalid header value for '{}': {}", key, e)))?;
+            request.headers_mut().insert(header_name, header_value);
+        }
+
+        let (stream, _) = tokio::time::timeout(self.timeout, tokio_tungstenite::connect_async(request))
+            .await
+            .map_err(|_| BedrockError::McpError(format!("Timed out connecting to {}", self.url)))?
+            .map_err(|e| BedrockError::McpError(format!("WebSocket connection to {} failed: {}", self.url, e)))?;
+
+        Ok(stream)
+    }
+}
+
+/// WebSocket transport for MCP servers
+pub struct WsTransport {
+    /// WebSocket endpoint URL
+    url: String,
+
+    /// Frames waiting to be written by the socket task
+    outgoing_tx: mpsc::Sender<Message>,
+
+    /// Channel for receiving responses
+    response_rx: Arc<Mutex<mpsc::Receiver<JsonRpcResponse>>>,
+
+    /// Connection state
+    connected: Arc<RwLock<bool>>,
+}
+
+impl std::fmt::Debug for WsTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WsTransport")
+            .field("url", &self.url)
+            .field("connected", &self.connected)
+            .finish()
+    }
+}
+
+impl WsTransport {
+    /// Connect to a WebSocket MCP server
+    pub async fn new(
+        url: String,
+        headers: HashMap<String, String>,
+        timeout: u64,
+    ) -> Result<Self> {
+        info!("Connecting to MCP server via WebSocket: {}", url);
+
+        if !url.starts_with("ws://") && !url.starts_with("wss://") {
+            return Err(BedrockError::McpError("WebSocket URL must start with ws:// or wss://".into()));
+        }
+
+        let endpoint = Endpoint {
+            url: url.clone(),
+            headers,
+            timeout: Duration::from_millis(timeout),
+        };
+
+        // Connect up front so configuration errors surface immediately
+        let stream = endpoint.connect().await?;
+        info!("WebSocket connection opened");
+
+        let (outgoing_tx, outgoing_rx) = mpsc::channel::<Message>(100);
+        let (response_tx, response_rx) = mpsc::channel::<JsonRpcResponse>(100);
+        let connected = Arc::new(RwLock::new(true));
+
+        tokio::spawn(Self::run(endpoint, stream, outgoing_rx, response_tx, connected.clone()));
+
+        Ok(Self {
+            url,
+            outgoing_tx,
+            response_rx: Arc::new(Mutex::new(response_rx)),
+            connected,
+        })
+    }
+
+    /// Drive the socket, reconnecting whenever the connection drops
+    async fn run(
+        endpoint: Endpoint,
+        mut stream: WsStream,
+        mut outgoing_rx: mpsc::Receiver<Message>,
+        response_tx: mpsc::Sender<JsonRpcResponse>,
+        connected: Arc<RwLock<bool>>,
+    ) {
+        loop {
+            let disconnect = Self::pump(&mut stream, &mut outgoing_rx, &response_tx).await;
+            *connected.write().await = false;
+
+            if let Disconnect::Closed = disconnect {
+                info!("WebSocket transport for {} closed", endpoint.url);
+                return;
+            }
+
+            let mut delay = INITIAL_RECONNECT_DELAY;
+            stream = loop {
+                // Nobody is left to send requests; stop trying
+                if outgoing_rx.is_closed() {
+                    return;
+                }
+                warn!("WebSocket connection to {} lost, reconnecting in {:?}", endpoint.url, delay);
+                tokio::time::sleep(delay).await;
+
+                match endpoint.connect().await {
+                    Ok(stream) => break stream,
+                    Err(e) => {
+                        debug!("Reconnect failed: {}", e);
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                }
+            };
+
+            info!("Reconnected to {}", endpoint.url);
+            *connected.write().await = true;
+        }
+    }
+
+    /// Move frames in both directions until the connection ends
+    async fn pump(
+        stream: &mut WsStream,
+        outgoing_rx: &mut mpsc::Receiver<Message>,
+        response_tx: &mpsc::Sender<JsonRpcResponse>,
+    ) -> Disconnect {
+        loop {
+            tokio::select! {
+                outgoing = outgoing_rx.recv() => match outgoing {
+                    Some(Message::Close(frame)) => {
+                        let _ = stream.send(Message::Close(frame)).await;
+                        return Disconnect::Closed;
+                    }
+                    // The transport was dropped
+                    None => {
+                        let _ = stream.send(Message::Close(None)).await;
+                        return Disconnect::Closed;
+                    }
+                    Some(message) => {
+                        if let Err(e) = stream.send(message).await {
+                            error!("Failed to send WebSocket frame: {}", e);
+                            return Disconnect::Dropped;
+                        }
+                    }
+                },
+                incoming = stream.next() => match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        match serde_json::from_str::<JsonRpcResponse>(&text) {
+                            Ok(response) => {
+                                if let Err(e) = response_tx.send(response).await {
+                                    error!("Failed to send response through channel: {}", e);
+                                }
+                            }
+                            Err(e) => {
+                                debug!("Failed to parse message as JSON-RPC response: {} - {}", text, e);
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Ping(payload))) => {
+                        if let Err(e) = stream.send(Message::Pong(payload)).await {
+                            error!("Failed to answer WebSocket ping: {}", e);
+                            return Disconnect::Dropped;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | None => return Disconnect::Dropped,
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => {
+                        error!("WebSocket stream error: {}", e);
+                        return Disconnect::Dropped;
+                    }
+                },
+            }
+        }
+    }
+
+    async fn send_text(&self, json: String) -> Result<()> {
+        debug!("Sending WebSocket message to {}: {}", self.url, json);
+        self.outgoing_tx
+            .send(Message::Text(json))
+            .await
+            .map_err(|_| BedrockError::McpError("WebSocket transport is closed".into()))
+    }
+}
+
+#[async_trait]
+impl Transport for WsTransport {
+    async fn send_request(&mut self, request: JsonRpcRequest) -> Result<()> {
+        let json = serde_json::to_string(&request)
+            .map_err(BedrockError::SerializationError)?;
+
+        self.send_text(json).await
+    }
+
+    async fn send_notification(&mut self, notification: JsonRpcNotification) -> Result<()> {
+        let json = serde_json::to_string(&notification)
+            .map_err(BedrockError::SerializationError)?;
+
+        self.send_text(json).await
+    }
+
+    async fn receive_response(&mut self) -> Result<Option<JsonRpcResponse>> {
+        let mut rx_guard = self.response_rx.lock().await;
+        Ok(rx_guard.recv().await)
+    }
+
+    async fn is_connected(&self) -> bool {
+        *self.connected.read().await
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        info!("Closing WebSocket transport");
+        // The socket task may already be gone if the server closed first
+        let _ = self.outgoing_tx.send(Message::Close(None)).await;
+        *self.connected.write().await = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Accepts two connections: the first is pinged and then closed, the second
+    /// answers JSON-RPC requests. Reports whether the ping was answered.
+    async fn spawn_server() -> (String, tokio::sync::oneshot::Receiver<bool>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (pong_tx, pong_rx) = tokio::sync::oneshot::channel();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+            ws.send(Message::Ping(b"alive?".to_vec())).await.unwrap();
+            let answered = matches!(ws.next().await, Some(Ok(Message::Pong(p))) if p == b"alive?");
+            let _ = pong_tx.send(answered);
+            ws.close(None).await.unwrap();
+
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+            while let Some(Ok(Message::Text(text))) = ws.next().await {
+                let request: JsonRpcRequest = serde_json::from_str(&text).unwrap();
+                let response = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request.id,
+                    "result": {"method": request.method}
+                });
+                ws.send(Message::Text(response.to_string())).await.unwrap();
+            }
+        });
+
+        (url, pong_rx)
+    }
+
+    async fn wait_for_connected(transport: &WsTransport, connected: bool) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while transport.is_connected().await != connected {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ping_reconnect_and_round_trip() {
+        let (url, pong_rx) = spawn_server().await;
+        let mut transport = WsTransport::new(url, HashMap::new(), 5000).await.unwrap();
+
+        assert!(pong_rx.await.unwrap(), "ping should be answered with a matching pong");
+
+        // The server closes the first connection; the transport reconnects on its own
+        wait_for_connected(&transport, false).await;
+        wait_for_connected(&transport, true).await;
+
+        transport
+            .send_request(JsonRpcRequest::new("1".into(), "tools/list".into(), None))
+            .await
+            .unwrap();
+        let response = tokio::time::timeout(Duration::from_secs(10), transport.receive_response())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(response.id, "1");
+        assert_eq!(response.result.unwrap()["method"], "tools/list");
+        assert!(transport.is_connected().await);
+
+        transport.close().await.unwrap();
+        assert!(!transport.is_connected().await);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_non_websocket_url() {
+        let err = WsTransport::new("http://localhost:9000".into(), HashMap::new(), 1000)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("ws://"));
+    }
+}
@@ -188,6 +188,54 @@ pub struct ToolCallResult {
 pub enum ContentItem {
     Text { text: String },
     Image { data: String, mime_type: String },
+    /// Embedded resource contents (also returned by `resources/read`)
+    Resource { resource: ResourceContents },
+}
+
+/// Resource advertised by an MCP server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResource {
+    pub uri: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Contents of a resource: `text` for text resources, base64 `blob` for binary ones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+/// List resources result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListResourcesResult {
+    pub resources: Vec<McpResource>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Read resource parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadResourceParams {
+    pub uri: String,
+}
+
+/// Read resource result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadResourceResult {
+    pub contents: Vec<ResourceContents>,
 }
 
 /// List tools result
@@ -251,4 +299,23 @@ mod tests {
         let json = serde_json::to_string(&image).unwrap();
         assert!(json.contains("\"type\":\"image\""));
     }
+
+    #[test]
+    fn test_resource_deserialization() {
+        let list: ListResourcesResult = serde_json::from_value(serde_json::json!({
+            "resources": [
+                {"uri": "file:///docs/api.md", "name": "API docs", "mimeType": "text/markdown"}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(list.resources[0].mime_type.as_deref(), Some("text/markdown"));
+        assert!(list.next_cursor.is_none());
+
+        let item: ContentItem = serde_json::from_value(serde_json::json!({
+            "type": "resource",
+            "resource": {"uri": "file:///docs/api.md", "text": "# API"}
+        }))
+        .unwrap();
+        assert!(matches!(item, ContentItem::Resource { resource } if resource.text.as_deref() == Some("# API")));
+    }
 }
\ No newline at end of file
@@ -1,12 +1,18 @@
 use bedrock_config::{AgentConfig, ModelPricing};
-use bedrock_core::{CostDetails, TokenStatistics};
+use bedrock_core::{BedrockError, CostDetails, Result, TokenStatistics};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton, CoreBPE};
 use tracing::debug;
 
+pub mod prometheus;
+
+pub use prometheus::render_prometheus;
+
 pub struct TokenTracker {
     input_tokens: AtomicUsize,
     output_tokens: AtomicUsize,
@@ -86,6 +92,7 @@ impl TokenTracker {
             total_tokens: self.input_tokens.load(Ordering::Relaxed) 
                 + self.output_tokens.load(Ordering::Relaxed),
             cache_hits: self.cache_tokens.load(Ordering::Relaxed),
+            cache_write_tokens: 0,
         }
     }
 
@@ -125,7 +132,7 @@ impl CostCalculator {
         
         match pricing {
             Some(p) => {
-                let input_cost = (tokens.input_tokens as f64 / 1000.0) * p.input_per_1k;
+                let input_cost = p.input_cost(tokens);
                 let output_cost = (tokens.output_tokens as f64 / 1000.0) * p.output_per_1k;
                 let total = input_cost + output_cost;
                 
@@ -137,6 +144,7 @@ impl CostCalculator {
                 CostDetails {
                     input_cost,
                     output_cost,
+                    tool_cost: 0.0,
                     total_cost: total,
                     currency: p.currency.clone(),
                     model: model.to_string(),
@@ -153,6 +161,11 @@ impl CostCalculator {
         }
     }
 
+    /// Count a paid tool call towards the total and the budget
+    pub fn add_tool_cost(&self, amount: f64) {
+        *self.total_cost.write().unwrap() += amount;
+    }
+
     pub fn check_budget(&self) -> BudgetStatus {
         let current_cost = *self.total_cost.read().unwrap();
         
@@ -178,6 +191,14 @@ impl CostCalculator {
         *self.total_cost.read().unwrap()
     }
 
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn budget_limit(&self) -> Option<f64> {
+        self.budget_limit
+    }
+
     pub fn reset(&self) {
         *self.total_cost.write().unwrap() = 0.0;
     }
@@ -208,6 +229,25 @@ pub struct ToolMetrics {
     pub total_duration_ms: u64,
 }
 
+impl ToolMetrics {
+    pub fn average_duration_ms(&self) -> f64 {
+        if self.executions == 0 {
+            0.0
+        } else {
+            self.total_duration_ms as f64 / self.executions as f64
+        }
+    }
+
+    /// Failed executions, as a percentage
+    pub fn failure_rate(&self) -> f64 {
+        if self.executions == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.executions as f64 * 100.0
+        }
+    }
+}
+
 impl MetricsCollector {
     pub fn new() -> Self {
         Self {
@@ -272,6 +312,75 @@ impl MetricsCollector {
     pub fn reset(&mut self) {
         *self = Self::new();
     }
+
+    /// Add the counts of `other` to this collector, keeping the earlier start time
+    pub fn merge(&mut self, other: &MetricsCollector) {
+        self.requests += other.requests;
+        self.successful_requests += other.successful_requests;
+        self.failed_requests += other.failed_requests;
+        self.total_latency_ms += other.total_latency_ms;
+        for (tool, metrics) in &other.tool_executions {
+            let merged = self.tool_executions.entry(tool.clone())
+                .or_insert_with(|| ToolMetrics {
+                    name: tool.clone(),
+                    ..Default::default()
+                });
+            merged.executions += metrics.executions;
+            merged.failures += metrics.failures;
+            merged.total_duration_ms += metrics.total_duration_ms;
+        }
+        self.started_at = match (self.started_at, other.started_at) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// Metrics saved by `save`; a missing file reads as an empty collector
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(BedrockError::IoError(e)),
+        }
+    }
+
+    /// Add these metrics to the ones saved at `path`.
+    ///
+    /// Every run merges into the same file, so the load, merge and save happen under
+    /// an exclusive lock on a `.lock` file beside it; concurrent runs take turns
+    /// instead of losing each other's counts.
+    pub fn merge_into_file(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // `save` replaces the metrics file, so the lock is held on a file that stays put
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path.with_extension("json.lock"))?;
+        lock.lock()?;
+        let mut saved = Self::load(path)?;
+        saved.merge(self);
+        saved.save(path)
+    }
+
+    /// Write the metrics to `path`, assuming no other writer; see `merge_into_file`
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write to a temp file and rename so a crash never leaves a half-written file
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Where tool executions are kept between runs, next to the task queue
+pub fn tool_metrics_path(config: &AgentConfig) -> PathBuf {
+    config.paths.workspace_dir.join("tool_metrics.json")
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -282,14 +391,67 @@ pub struct MetricsSummary {
     pub uptime_seconds: u64,
 }
 
-pub fn estimate_tokens(text: &str, model: &str) -> usize {
-    let chars_per_token = if model.contains("claude") {
-        3.5
+/// Model families whose tokenizer `cl100k_base` approximates
+const CL100K_FAMILIES: &[&str] = &["anthropic.", "claude", "amazon.", "cohere.", "ai21."];
+
+/// Model families with large vocabularies that `o200k_base` approximates
+const O200K_FAMILIES: &[&str] = &["meta.", "llama", "mistral", "deepseek", "openai."];
+
+/// BPE encoding approximating `model`'s tokenizer, if its family is known
+fn encoding_for(model: &str) -> Option<&'static CoreBPE> {
+    let model = model.to_ascii_lowercase();
+    if CL100K_FAMILIES.iter().any(|family| model.contains(family)) {
+        Some(cl100k_base_singleton())
+    } else if O200K_FAMILIES.iter().any(|family| model.contains(family)) {
+        Some(o200k_base_singleton())
     } else {
-        4.0
+        None
+    }
+}
+
+/// Runs of text without whitespace longer than this are estimated from their length,
+/// since BPE merging is quadratic in the length of a run
+const MAX_ENCODED_RUN_BYTES: usize = 1024;
+
+/// Texts longer than this are counted with the runtime's blessing to block
+const BLOCKING_ESTIMATE_BYTES: usize = 256 * 1024;
+
+/// Estimated tokens of `text` for `model`.
+///
+/// Known model families are counted with a BPE encoding close to their
+/// tokenizer; anything else, and runs without whitespace longer than
+/// `MAX_ENCODED_RUN_BYTES` such as base64 blobs, falls back to a
+/// characters-per-token heuristic. Long texts are counted through
+/// `block_in_place` on a multi-threaded runtime so other tasks can move on.
+pub fn estimate_tokens(text: &str, model: &str) -> usize {
+    let Some(encoding) = encoding_for(model) else {
+        return heuristic_tokens(text.len());
     };
-    
-    (text.len() as f64 / chars_per_token).ceil() as usize
+    let on_multi_thread_runtime = tokio::runtime::Handle::try_current()
+        .is_ok_and(|handle| handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread);
+    if text.len() > BLOCKING_ESTIMATE_BYTES && on_multi_thread_runtime {
+        tokio::task::block_in_place(|| encode_tokens(encoding, text))
+    } else {
+        encode_tokens(encoding, text)
+    }
+}
+
+fn encode_tokens(encoding: &CoreBPE, text: &str) -> usize {
+    let mut tokens = 0;
+    let mut start = 0;
+    let mut offset = 0;
+    for word in text.split_inclusive(char::is_whitespace) {
+        if word.len() > MAX_ENCODED_RUN_BYTES {
+            tokens += encoding.encode_ordinary(&text[start..offset]).len() + heuristic_tokens(word.len());
+            start = offset + word.len();
+        }
+        offset += word.len();
+    }
+    tokens + encoding.encode_ordinary(&text[start..]).len()
+}
+
+fn heuristic_tokens(bytes: usize) -> usize {
+    bytes.div_ceil(4)
 }
 
 #[cfg(test)]
@@ -329,6 +491,74 @@ mod tests {
         assert!((collector.get_success_rate() - 66.67).abs() < 0.01);
     }
 
+    #[test]
+    fn test_tool_metrics_merge_and_persist() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("tool_metrics.json");
+        assert!(MetricsCollector::load(&path).unwrap().tool_executions.is_empty());
+
+        let mut earlier = MetricsCollector::new();
+        earlier.record_tool_execution("grep", 30, true);
+        earlier.record_request(100, true);
+        earlier.save(&path).unwrap();
+
+        let mut session = MetricsCollector::new();
+        session.record_tool_execution("grep", 90, false);
+        session.record_tool_execution("fs_read", 5, true);
+        session.record_request(300, false);
+        session.merge_into_file(&path).unwrap();
+
+        let loaded = MetricsCollector::load(&path).unwrap();
+        let grep = &loaded.tool_executions["grep"];
+        assert_eq!(grep.executions, 2);
+        assert_eq!(grep.average_duration_ms(), 60.0);
+        assert_eq!(grep.failure_rate(), 50.0);
+        assert_eq!(loaded.tool_executions["fs_read"].failures, 0);
+        assert_eq!((loaded.requests, loaded.failed_requests), (2, 1));
+        assert_eq!(loaded.get_average_latency(), 200.0);
+        assert_eq!(loaded.started_at, earlier.started_at);
+    }
+
+    #[test]
+    fn test_concurrent_merges_keep_every_count() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("tool_metrics.json");
+
+        let writers: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..5 {
+                        let mut session = MetricsCollector::new();
+                        session.record_request(10, true);
+                        session.record_tool_execution("grep", 1, true);
+                        session.merge_into_file(&path).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let loaded = MetricsCollector::load(&path).unwrap();
+        assert_eq!(loaded.requests, 40);
+        assert_eq!(loaded.tool_executions["grep"].executions, 40);
+    }
+
+    #[test]
+    fn test_tool_cost_counts_toward_budget() {
+        let mut config = AgentConfig::default();
+        config.limits.budget_limit = Some(1.0);
+        let costs = CostCalculator::from_config(&config);
+
+        costs.add_tool_cost(0.4);
+        assert!(matches!(costs.check_budget(), BudgetStatus::Ok));
+        costs.add_tool_cost(0.7);
+        assert!(matches!(costs.check_budget(), BudgetStatus::Exceeded { .. }));
+        assert!((costs.get_total_cost() - 1.1).abs() < 1e-9);
+    }
+
     #[test]
     fn test_token_estimation() {
         let text = "This is a test message";
@@ -336,4 +566,47 @@ mod tests {
         assert!(tokens > 0);
         assert!(tokens < text.len());
     }
+
+    fn assert_close(estimate: usize, known: usize) {
+        let tolerance = (known as f64 * 0.1).ceil() as usize;
+        assert!(estimate.abs_diff(known) <= tolerance, "estimated {estimate}, known {known}");
+    }
+
+    #[test]
+    fn test_token_estimation_matches_known_counts() {
+        let claude = "anthropic.claude-3-5-sonnet-20241022-v2:0";
+        assert_close(estimate_tokens("hello world", claude), 2);
+        assert_close(estimate_tokens("The quick brown fox jumps over the lazy dog.", claude), 10);
+        assert_close(estimate_tokens("fn main() {\n    println!(\"Hello, world!\");\n}", claude), 13);
+        assert_close(estimate_tokens("hello world", "meta.llama3-70b-instruct-v1:0"), 2);
+    }
+
+    // Multi-threaded so the long text is counted through block_in_place
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_long_runs_without_whitespace_use_the_heuristic() {
+        let claude = "anthropic.claude-3-5-sonnet-20241022-v2:0";
+        // Would take minutes to merge as a single BPE piece
+        let blob = "QUJD".repeat(512 * 1024);
+        let started = std::time::Instant::now();
+        assert_eq!(estimate_tokens(&blob, claude), blob.len() / 4);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+        // Text around the run is still encoded
+        let text = format!("hello world {blob} hello world");
+        let tokens = estimate_tokens(&text, claude);
+        assert!(tokens > blob.len() / 4 + 4 && tokens < blob.len() / 4 + 10, "{tokens}");
+    }
+
+    #[test]
+    fn test_token_estimation_handles_non_english_text() {
+        // Three bytes per character; the character heuristic would guess 6
+        let text = "日本語のテキスト";
+        let tokens = estimate_tokens(text, "anthropic.claude-3-haiku-20240307-v1:0");
+        assert!(tokens >= text.chars().count() / 2 && tokens <= text.chars().count() * 2, "{tokens}");
+    }
+
+    #[test]
+    fn test_unknown_model_uses_character_heuristic() {
+        assert_eq!(estimate_tokens("abcdefgh", "acme.unknown-model"), 2);
+    }
 }
\ No newline at end of file
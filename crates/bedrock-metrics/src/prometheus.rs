@@ -0,0 +1,198 @@
+//! Prometheus text exposition format for the in-process collectors
+
+use std::fmt::Write;
+
+use crate::{CostCalculator, MetricsCollector, TokenTracker};
+
+/// Render the current state of the collectors in Prometheus exposition format.
+///
+/// Requests, tool executions and tokens are exported as counters; cost and budget
+/// as gauges. Per-model and per-tool series are sorted for stable output.
+pub fn render_prometheus(
+    tokens: &TokenTracker,
+    collector: &MetricsCollector,
+    costs: &CostCalculator,
+) -> String {
+    let mut out = String::new();
+
+    header(&mut out, "bedrock_requests_total", "counter", "Total model requests");
+    sample(&mut out, "bedrock_requests_total", &[], collector.requests as f64);
+    header(&mut out, "bedrock_requests_successful_total", "counter", "Successful model requests");
+    sample(&mut out, "bedrock_requests_successful_total", &[], collector.successful_requests as f64);
+    header(&mut out, "bedrock_requests_failed_total", "counter", "Failed model requests");
+    sample(&mut out, "bedrock_requests_failed_total", &[], collector.failed_requests as f64);
+    header(&mut out, "bedrock_request_latency_ms_total", "counter", "Cumulative model request latency in milliseconds");
+    sample(&mut out, "bedrock_request_latency_ms_total", &[], collector.total_latency_ms as f64);
+
+    let mut tools: Vec<_> = collector.tool_executions.values().collect();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+
+    header(&mut out, "bedrock_tool_executions_total", "counter", "Tool executions by tool");
+    for tool in &tools {
+        sample(&mut out, "bedrock_tool_executions_total", &[("tool", &tool.name)], tool.executions as f64);
+    }
+    header(&mut out, "bedrock_tool_failures_total", "counter", "Failed tool executions by tool");
+    for tool in &tools {
+        sample(&mut out, "bedrock_tool_failures_total", &[("tool", &tool.name)], tool.failures as f64);
+    }
+    header(&mut out, "bedrock_tool_duration_ms_total", "counter", "Cumulative tool execution time in milliseconds");
+    for tool in &tools {
+        sample(&mut out, "bedrock_tool_duration_ms_total", &[("tool", &tool.name)], tool.total_duration_ms as f64);
+    }
+
+    let model_stats = tokens.get_model_stats();
+    let mut models: Vec<_> = model_stats.values().collect();
+    models.sort_by(|a, b| a.model_id.cmp(&b.model_id));
+
+    header(&mut out, "bedrock_tokens_total", "counter", "Tokens by model and type");
+    for model in &models {
+        for (kind, value) in [
+            ("input", model.input_tokens),
+            ("output", model.output_tokens),
+            ("cache", model.cache_tokens),
+        ] {
+            sample(&mut out, "bedrock_tokens_total", &[("model", &model.model_id), ("type", kind)], value as f64);
+        }
+    }
+    header(&mut out, "bedrock_model_requests_total", "counter", "Model requests by model");
+    for model in &models {
+        sample(&mut out, "bedrock_model_requests_total", &[("model", &model.model_id)], model.requests as f64);
+    }
+
+    header(&mut out, "bedrock_cost_total", "gauge", "Accumulated cost");
+    sample(&mut out, "bedrock_cost_total", &[("currency", costs.currency())], costs.get_total_cost());
+    if let Some(limit) = costs.budget_limit() {
+        header(&mut out, "bedrock_budget_limit", "gauge", "Configured budget limit");
+        sample(&mut out, "bedrock_budget_limit", &[("currency", costs.currency())], limit);
+    }
+
+    out
+}
+
+impl MetricsCollector {
+    /// Render this collector together with token and cost state as a Prometheus scrape body
+    pub fn to_prometheus(&self, token_tracker: &TokenTracker, cost: &CostCalculator) -> String {
+        render_prometheus(token_tracker, self, cost)
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    if labels.is_empty() {
+        let _ = writeln!(out, "{name} {value}");
+    } else {
+        let labels = labels
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{}\"", escape_label(v)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let _ = writeln!(out, "{name}{{{labels}}} {value}");
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bedrock_config::AgentConfig;
+    use bedrock_core::TokenStatistics;
+
+    #[test]
+    fn test_render_known_state() {
+        let tokens = TokenTracker::new();
+        tokens.add_input(1000, "claude-3");
+        tokens.add_output(500, "claude-3");
+
+        let mut collector = MetricsCollector::new();
+        collector.record_request(120, true);
+        collector.record_request(80, false);
+        collector.record_tool_execution("fs_read", 15, true);
+        collector.record_tool_execution("fs_read", 5, false);
+
+        let mut config = AgentConfig::default();
+        config.pricing.insert(
+            "claude-3".to_string(),
+            bedrock_config::ModelPricing {
+                input_per_1k: 0.003,
+                output_per_1k: 0.015,
+                currency: "USD".to_string(),
+                cache_read_per_1k: None,
+                cache_write_per_1k: None,
+            },
+        );
+        let costs = CostCalculator::from_config(&config);
+        costs.calculate(
+            &TokenStatistics { input_tokens: 1000, output_tokens: 1000, ..Default::default() },
+            "claude-3",
+        );
+
+        let text = render_prometheus(&tokens, &collector, &costs);
+
+        assert!(text.contains("# TYPE bedrock_requests_total counter"));
+        assert!(text.contains("bedrock_requests_total 2\n"));
+        assert!(text.contains("bedrock_requests_successful_total 1\n"));
+        assert!(text.contains("bedrock_requests_failed_total 1\n"));
+        assert!(text.contains("bedrock_request_latency_ms_total 200\n"));
+        assert!(text.contains("bedrock_tool_executions_total{tool=\"fs_read\"} 2\n"));
+        assert!(text.contains("bedrock_tool_failures_total{tool=\"fs_read\"} 1\n"));
+        assert!(text.contains("bedrock_tokens_total{model=\"claude-3\",type=\"input\"} 1000\n"));
+        assert!(text.contains("bedrock_tokens_total{model=\"claude-3\",type=\"output\"} 500\n"));
+        assert!(text.contains("bedrock_model_requests_total{model=\"claude-3\"} 1\n"));
+        assert!(text.contains("# TYPE bedrock_cost_total gauge"));
+        assert!(text.contains("bedrock_cost_total{currency=\"USD\"} 0.018\n"));
+        assert!(!text.contains("bedrock_budget_limit"));
+    }
+
+    #[test]
+    fn test_scrape_body_is_well_formed() {
+        let tokens = TokenTracker::new();
+        tokens.add_input(10, "model \"a\"");
+        let mut collector = MetricsCollector::new();
+        collector.record_request(5, true);
+        collector.record_tool_execution("grep", 1, true);
+        let costs = CostCalculator::from_config(&bedrock_config::AgentConfig::default());
+
+        let text = collector.to_prometheus(&tokens, &costs);
+        assert!(text.ends_with('\n'));
+
+        let mut typed = std::collections::HashSet::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let mut parts = rest.split(' ');
+                let name = parts.next().unwrap();
+                assert!(matches!(parts.next(), Some("counter") | Some("gauge")), "bad type line: {line}");
+                typed.insert(name.to_string());
+                continue;
+            }
+            if line.starts_with("# HELP ") {
+                continue;
+            }
+
+            // Samples are `name{labels} value` and must follow their TYPE line
+            let (series, value) = line.rsplit_once(' ').expect("sample without value");
+            assert!(value.parse::<f64>().is_ok(), "bad value: {line}");
+            let name = series.split('{').next().unwrap();
+            assert!(typed.contains(name), "sample before TYPE: {line}");
+            assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'), "bad name: {line}");
+            if let Some(labels) = series.strip_prefix(name) {
+                assert!(labels.is_empty() || (labels.starts_with('{') && labels.ends_with('}')), "bad labels: {line}");
+            }
+        }
+        assert!(text.contains("bedrock_tokens_total{model=\"model \\\"a\\\"\",type=\"input\"} 10\n"));
+    }
+
+    #[test]
+    fn test_label_escaping() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,142 @@
+//! Cost attribution: summing saved task results per value of a task tag
+
+use bedrock_core::{BedrockError, Result, TaskResult};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Group name for results that don't carry the tag
+pub const UNTAGGED: &str = "(untagged)";
+
+/// Totals for the tasks sharing one value of a tag
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TagCost {
+    pub value: String,
+    pub tasks: usize,
+    pub total_tokens: usize,
+    pub total_cost: f64,
+}
+
+/// Load every task result in the results directory, skipping files that don't parse
+pub fn load_task_results(results_dir: &Path) -> Result<Vec<TaskResult>> {
+    if !results_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut results = Vec::new();
+    for entry in std::fs::read_dir(results_dir).map_err(BedrockError::IoError)? {
+        let path = entry.map_err(BedrockError::IoError)?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Ok(json) = std::fs::read_to_string(&path) else { continue };
+        if let Ok(result) = serde_json::from_str::<TaskResult>(&json) {
+            results.push(result);
+        }
+    }
+    Ok(results)
+}
+
+/// Sum tokens and cost per value of `tag`, most expensive first.
+///
+/// Results without the tag are grouped under [`UNTAGGED`].
+pub fn cost_by_tag(results: &[TaskResult], tag: &str) -> Vec<TagCost> {
+    let mut groups: BTreeMap<&str, TagCost> = BTreeMap::new();
+    for result in results {
+        let value = result.tags.get(tag).map(String::as_str).unwrap_or(UNTAGGED);
+        let group = groups.entry(value).or_insert_with(|| TagCost {
+            value: value.to_string(),
+            ..Default::default()
+        });
+        group.tasks += 1;
+        group.total_tokens += result.token_stats.total_tokens;
+        group.total_cost += result.cost.total_cost;
+    }
+
+    let mut costs: Vec<TagCost> = groups.into_values().collect();
+    costs.sort_by(|a, b| b.total_cost.total_cmp(&a.total_cost));
+    costs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bedrock_core::{CostDetails, TaskStatus, TokenStatistics};
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+    use uuid::Uuid;
+
+    fn tagged_result(cost_center: Option<&str>, tokens: usize, cost: f64) -> TaskResult {
+        let tags = cost_center
+            .map(|value| HashMap::from([("cost_center".to_string(), value.to_string())]))
+            .unwrap_or_default();
+        TaskResult {
+            task_id: Uuid::new_v4(),
+            status: TaskStatus::Completed,
+            summary: "done".to_string(),
+            conversation: None,
+            result: None,
+            token_stats: TokenStatistics { total_tokens: tokens, ..Default::default() },
+            cost: CostDetails { total_cost: cost, ..Default::default() },
+            tool_executions: 0,
+            stop_sequence: None,
+            tags,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(0),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_cost_summed_per_tag_value() {
+        let results = vec![
+            tagged_result(Some("research"), 100, 0.25),
+            tagged_result(Some("platform"), 50, 0.10),
+            tagged_result(Some("research"), 300, 0.50),
+            tagged_result(None, 10, 0.01),
+        ];
+
+        let costs = cost_by_tag(&results, "cost_center");
+
+        let values: Vec<&str> = costs.iter().map(|c| c.value.as_str()).collect();
+        assert_eq!(values, vec!["research", "platform", UNTAGGED]);
+        assert_eq!(costs[0].tasks, 2);
+        assert_eq!(costs[0].total_tokens, 400);
+        assert!((costs[0].total_cost - 0.75).abs() < 1e-9);
+        assert_eq!(costs[1].tasks, 1);
+        assert!((costs[1].total_cost - 0.10).abs() < 1e-9);
+        assert_eq!(costs[2].total_tokens, 10);
+    }
+
+    #[test]
+    fn test_tags_persist_with_saved_results() {
+        let temp_dir = TempDir::new().unwrap();
+        for result in [
+            tagged_result(Some("research"), 100, 0.25),
+            tagged_result(Some("platform"), 50, 0.10),
+        ] {
+            std::fs::write(
+                temp_dir.path().join(format!("{}.json", result.task_id)),
+                serde_json::to_string_pretty(&result).unwrap(),
+            )
+            .unwrap();
+        }
+        std::fs::write(temp_dir.path().join("notes.json"), "not a result").unwrap();
+
+        let results = load_task_results(temp_dir.path()).unwrap();
+        assert_eq!(results.len(), 2);
+
+        let costs = cost_by_tag(&results, "cost_center");
+        assert_eq!(costs.len(), 2);
+        assert_eq!(costs[0].value, "research");
+        assert_eq!(costs[1].value, "platform");
+
+        // Grouping by a tag no task carries puts everything in one bucket
+        let by_project = cost_by_tag(&results, "project");
+        assert_eq!(by_project.len(), 1);
+        assert_eq!(by_project[0].value, UNTAGGED);
+        assert_eq!(by_project[0].tasks, 2);
+    }
+}
@@ -0,0 +1,137 @@
+//! Lightweight task chaining: feed a previous task's result into a new task's context
+
+use bedrock_core::{BedrockError, Result, Task, TaskResult};
+use std::path::Path;
+use uuid::Uuid;
+
+/// Load a saved task result from the results directory
+pub fn load_task_result(results_dir: &Path, task_id: &Uuid) -> Result<TaskResult> {
+    let file_path = results_dir.join(format!("{task_id}.json"));
+    if !file_path.exists() {
+        return Err(BedrockError::NotFound(format!("Task result not found: {task_id}")));
+    }
+
+    let json = std::fs::read_to_string(file_path).map_err(BedrockError::IoError)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+/// Find the most recently completed task result in the results directory, if any
+pub fn latest_task_result(results_dir: &Path) -> Result<Option<TaskResult>> {
+    if !results_dir.exists() {
+        return Ok(None);
+    }
+
+    let mut latest: Option<TaskResult> = None;
+    for entry in std::fs::read_dir(results_dir).map_err(BedrockError::IoError)? {
+        let path = entry.map_err(BedrockError::IoError)?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        // Skip files that aren't task results rather than failing the whole lookup
+        let Ok(json) = std::fs::read_to_string(&path) else { continue };
+        let Ok(result) = serde_json::from_str::<TaskResult>(&json) else { continue };
+
+        let finished = result.completed_at.unwrap_or(result.started_at);
+        let is_newer = latest
+            .as_ref()
+            .map(|l| finished > l.completed_at.unwrap_or(l.started_at))
+            .unwrap_or(true);
+        if is_newer {
+            latest = Some(result);
+        }
+    }
+
+    Ok(latest)
+}
+
+/// Add the previous task's summary and output to the new task's context
+pub fn chain_after(mut task: Task, previous: &TaskResult) -> Task {
+    let mut previous_context = format!(
+        "Result of previous task {} ({:?}):\nSummary: {}",
+        previous.task_id, previous.status, previous.summary
+    );
+
+    // Only include the output when it carries more than the summary itself
+    if let Some(output) = &previous.result {
+        let is_summary_only = output
+            .as_object()
+            .map(|o| o.len() == 1 && o.contains_key("summary"))
+            .unwrap_or(false);
+        if !is_summary_only {
+            previous_context.push_str(&format!("\nOutput: {output}"));
+        }
+    }
+
+    task.context = if task.context.is_empty() {
+        previous_context
+    } else {
+        format!("{previous_context}\n\n{}", task.context)
+    };
+    task
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bedrock_core::{CostDetails, TaskStatus, TokenStatistics};
+    use chrono::{Duration, Utc};
+    use tempfile::TempDir;
+
+    fn saved_result(dir: &Path, summary: &str, age_secs: i64) -> TaskResult {
+        let finished = Utc::now() - Duration::seconds(age_secs);
+        let result = TaskResult {
+            task_id: Uuid::new_v4(),
+            status: TaskStatus::Completed,
+            summary: summary.to_string(),
+            conversation: None,
+            result: Some(serde_json::json!({"summary": summary})),
+            token_stats: TokenStatistics::default(),
+            cost: CostDetails::default(),
+            tool_executions: 0,
+            stop_sequence: None,
+            tags: Default::default(),
+            started_at: finished,
+            completed_at: Some(finished),
+            duration_ms: Some(0),
+            error: None,
+        };
+        std::fs::write(
+            dir.join(format!("{}.json", result.task_id)),
+            serde_json::to_string_pretty(&result).unwrap(),
+        )
+        .unwrap();
+        result
+    }
+
+    #[test]
+    fn test_after_loads_prior_result_into_context() {
+        let temp_dir = TempDir::new().unwrap();
+        let previous = saved_result(temp_dir.path(), "Found 3 failing tests in parser", 0);
+
+        let loaded = load_task_result(temp_dir.path(), &previous.task_id).unwrap();
+        let task = chain_after(Task::new("Fix the failing tests").with_context("Use cargo"), &loaded);
+
+        assert!(task.context.contains("Found 3 failing tests in parser"));
+        assert!(task.context.contains(&previous.task_id.to_string()));
+        assert!(task.context.ends_with("Use cargo"));
+    }
+
+    #[test]
+    fn test_missing_prior_result() {
+        let temp_dir = TempDir::new().unwrap();
+        let err = load_task_result(temp_dir.path(), &Uuid::new_v4()).unwrap_err();
+        assert!(matches!(err, BedrockError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_latest_task_result() {
+        let temp_dir = TempDir::new().unwrap();
+        saved_result(temp_dir.path(), "older", 60);
+        let newest = saved_result(temp_dir.path(), "newest", 0);
+
+        let latest = latest_task_result(temp_dir.path()).unwrap().unwrap();
+        assert_eq!(latest.task_id, newest.task_id);
+        assert!(latest_task_result(&temp_dir.path().join("missing")).unwrap().is_none());
+    }
+}
@@ -0,0 +1,88 @@
+//! Run one prompt against several models at once for side-by-side comparison
+
+use bedrock_conversation::ConversationManager;
+use bedrock_core::{Result, Task, TaskResult};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{Mutex, Semaphore};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::TaskExecutor;
+
+/// Outcome of running the task against one model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelComparison {
+    pub model: String,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<TaskResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TaskExecutor {
+    /// Run `task` against each model concurrently, at most
+    /// `limits.max_concurrent_requests` at a time.
+    ///
+    /// Every run gets its own task ID and is priced with that model's pricing.
+    /// Results are returned in the order of `models`; a failed run is reported
+    /// in its entry instead of failing the comparison.
+    pub async fn compare_models(&self, task: Task, models: &[String]) -> Vec<ModelComparison> {
+        info!("Comparing {} models on task {}", models.len(), task.task_id);
+        let permits = Arc::new(Semaphore::new(self.config.limits.max_concurrent_requests.max(1)));
+
+        let handles: Vec<_> = models
+            .iter()
+            .map(|model| {
+                let executor = self.for_model(model);
+                let task = Task { task_id: Uuid::new_v4(), ..task.clone() };
+                let permits = Arc::clone(&permits);
+                let model = model.clone();
+                tokio::spawn(async move {
+                    let _permit = permits.acquire_owned().await.ok();
+                    let started = Instant::now();
+                    let outcome = match executor {
+                        Ok(executor) => executor.execute_task(task).await,
+                        Err(e) => Err(e),
+                    };
+                    let latency_ms = started.elapsed().as_millis() as u64;
+                    match outcome {
+                        Ok(result) => ModelComparison { model, latency_ms, result: Some(result), error: None },
+                        Err(e) => ModelComparison { model, latency_ms, result: None, error: Some(e.to_string()) },
+                    }
+                })
+            })
+            .collect();
+
+        let mut comparisons = Vec::with_capacity(handles.len());
+        for (handle, model) in handles.into_iter().zip(models) {
+            comparisons.push(handle.await.unwrap_or_else(|e| {
+                warn!("Comparison run for {} panicked: {}", model, e);
+                ModelComparison {
+                    model: model.clone(),
+                    latency_ms: 0,
+                    result: None,
+                    error: Some(format!("run aborted: {e}")),
+                }
+            }));
+        }
+        comparisons
+    }
+
+    /// A copy of this executor that calls and prices `model` instead of the configured one.
+    ///
+    /// The conversation manager is held for a whole task, so each copy gets its own
+    /// to let the runs proceed in parallel.
+    pub(crate) fn for_model(&self, model: &str) -> Result<TaskExecutor> {
+        let mut config = (*self.config).clone();
+        config.agent.model = model.to_string();
+        let conversation_manager = ConversationManager::new()?.with_settings(&config.conversation);
+        Ok(TaskExecutor {
+            config: Arc::new(config),
+            conversation_manager: Arc::new(Mutex::new(conversation_manager)),
+            ..self.clone()
+        })
+    }
+}
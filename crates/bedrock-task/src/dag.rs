@@ -0,0 +1,159 @@
+//! Run a graph of tasks where a task may depend on the results of others.
+//!
+//! Independent tasks run concurrently, at most `limits.max_concurrent_dag_nodes`
+//! at a time. A task starts once all of its dependencies have completed, with their
+//! results added to its context as for [`chain_after`].
+
+use bedrock_core::{BedrockError, Result, Task, TaskResult, TaskStatus};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::chain::chain_after;
+use crate::TaskExecutor;
+
+/// A task and the tasks whose results it needs
+#[derive(Debug, Clone)]
+pub struct DagNode {
+    pub task: Task,
+    pub depends_on: Vec<Uuid>,
+}
+
+impl DagNode {
+    pub fn new(task: Task) -> Self {
+        Self { task, depends_on: Vec::new() }
+    }
+
+    pub fn after(mut self, task_id: Uuid) -> Self {
+        self.depends_on.push(task_id);
+        self
+    }
+}
+
+/// Outcome of running a graph
+#[derive(Debug, Default)]
+pub struct DagRun {
+    /// Results in the order the tasks finished
+    pub results: Vec<TaskResult>,
+    /// Tasks that could not be run, with the error
+    pub errors: Vec<(Uuid, String)>,
+    /// Tasks not started because a dependency didn't complete
+    pub skipped: Vec<Uuid>,
+}
+
+pub struct DagExecutor {
+    executor: TaskExecutor,
+    max_concurrent: usize,
+}
+
+impl DagExecutor {
+    pub fn new(executor: TaskExecutor) -> Self {
+        let max_concurrent = executor.config.limits.max_concurrent_dag_nodes;
+        Self { executor, max_concurrent }
+    }
+
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent;
+        self
+    }
+
+    /// Run every node once its dependencies have completed.
+    ///
+    /// Fails up front if a node depends on a task that isn't in the graph or the
+    /// graph has a cycle. A failed node doesn't stop unrelated nodes; its
+    /// dependents are reported as skipped.
+    pub async fn run(&self, nodes: Vec<DagNode>) -> Result<DagRun> {
+        validate(&nodes)?;
+        info!("Running a graph of {} tasks, {} at a time", nodes.len(), self.max_concurrent);
+
+        let permits = Arc::new(Semaphore::new(self.max_concurrent.max(1)));
+        let mut pending: HashMap<Uuid, DagNode> = nodes.into_iter().map(|n| (n.task.task_id, n)).collect();
+        let mut completed: HashMap<Uuid, TaskResult> = HashMap::new();
+        let mut running = JoinSet::new();
+        let mut run = DagRun::default();
+
+        loop {
+            // Dispatch every node whose dependencies are all done; the semaphore bounds how many run
+            let ready: Vec<Uuid> = pending
+                .values()
+                .filter(|node| node.depends_on.iter().all(|dep| completed.contains_key(dep)))
+                .map(|node| node.task.task_id)
+                .collect();
+            for task_id in ready {
+                let node = pending.remove(&task_id).expect("ready node is pending");
+                let task = node
+                    .depends_on
+                    .iter()
+                    .fold(node.task, |task, dep| chain_after(task, &completed[dep]));
+                // Each node gets its own conversation manager so nodes don't serialize on it
+                let executor = self.executor.for_model(&self.executor.config.agent.model);
+                let permits = Arc::clone(&permits);
+                running.spawn(async move {
+                    let _permit = permits.acquire_owned().await.ok();
+                    let outcome = match executor {
+                        Ok(executor) => executor.execute_task(task).await,
+                        Err(e) => Err(e),
+                    };
+                    (task_id, outcome)
+                });
+            }
+
+            let Some(joined) = running.join_next().await else { break };
+            let (task_id, outcome) =
+                joined.map_err(|e| BedrockError::TaskError(format!("Graph task aborted: {e}")))?;
+            match outcome {
+                Ok(result) if result.status == TaskStatus::Completed => {
+                    completed.insert(task_id, result.clone());
+                    run.results.push(result);
+                }
+                Ok(result) => run.results.push(result),
+                Err(e) => {
+                    warn!("Graph task {} failed: {}", task_id, e);
+                    run.errors.push((task_id, e.to_string()));
+                }
+            }
+        }
+
+        // Whatever is left waits on a task that didn't complete
+        run.skipped = pending.into_keys().collect();
+        if !run.skipped.is_empty() {
+            warn!("Skipped {} tasks whose dependencies didn't complete", run.skipped.len());
+        }
+        Ok(run)
+    }
+}
+
+/// Reject dependencies on unknown tasks and cycles
+fn validate(nodes: &[DagNode]) -> Result<()> {
+    let ids: HashSet<Uuid> = nodes.iter().map(|n| n.task.task_id).collect();
+    for node in nodes {
+        if let Some(missing) = node.depends_on.iter().find(|dep| !ids.contains(dep)) {
+            return Err(BedrockError::TaskError(format!(
+                "Task {} depends on {missing}, which is not in the graph",
+                node.task.task_id
+            )));
+        }
+    }
+
+    // Kahn's algorithm: a cycle leaves nodes that never become ready
+    let mut resolved = HashSet::new();
+    loop {
+        let newly: Vec<Uuid> = nodes
+            .iter()
+            .filter(|n| !resolved.contains(&n.task.task_id))
+            .filter(|n| n.depends_on.iter().all(|dep| resolved.contains(dep)))
+            .map(|n| n.task.task_id)
+            .collect();
+        if newly.is_empty() {
+            break;
+        }
+        resolved.extend(newly);
+    }
+    if resolved.len() < nodes.len() {
+        return Err(BedrockError::TaskError("Task graph has a dependency cycle".into()));
+    }
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! Estimate what a task's first request will cost without sending it
+
+use bedrock_core::{CostDetails, Result, Task, TokenStatistics};
+use bedrock_metrics::estimate_tokens;
+use serde::{Deserialize, Serialize};
+
+use crate::{build_tool_definitions, resolve_tool_policy, TaskExecutor};
+
+/// Estimated input of the first request a task would send
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEstimate {
+    /// Prompt, system prompt and tool definitions together
+    pub input_tokens: usize,
+    /// Serialized size of the tool definitions offered to the model
+    pub tool_schema_bytes: usize,
+    pub tools_offered: usize,
+    /// Cost of `input_tokens`; the reply and any tool round trips come on top
+    pub cost: CostDetails,
+}
+
+impl TaskExecutor {
+    /// Estimate the input tokens and cost of the request `execute_task` would start
+    /// with: the same prompt, system prompt and tool definitions, counted with
+    /// `estimate_tokens` and priced with the configured `pricing`. Nothing is sent.
+    pub fn estimate(&self, task: &Task) -> Result<TaskEstimate> {
+        let tools = self.tool_registry.select(&resolve_tool_policy(&self.config.tools, task.tool_policy.as_ref()));
+        let tool_definitions = build_tool_definitions(&self.config, tools)?;
+
+        // Mirrors the system prompt and tool payload each execution path sends
+        let system_prompt = self.task_system_prompt(task, &tool_definitions);
+        let tool_specs = if tool_definitions.is_empty() || self.config.tools.text_protocol {
+            String::new()
+        } else {
+            serde_json::to_string(&tool_definitions).unwrap_or_default()
+        };
+
+        let model = &self.config.agent.model;
+        let input_tokens = estimate_tokens(&format!("{}{}{}", system_prompt, task.prompt, tool_specs), model);
+        let tokens = TokenStatistics {
+            input_tokens,
+            total_tokens: input_tokens,
+            ..Default::default()
+        };
+
+        Ok(TaskEstimate {
+            input_tokens,
+            tool_schema_bytes: tool_specs.len(),
+            tools_offered: tool_definitions.len(),
+            cost: self.calculate_cost(&tokens, 0.0),
+        })
+    }
+}
@@ -0,0 +1,28 @@
+//! Progress events a task reports while it runs
+
+use bedrock_core::TokenStatistics;
+use serde::Serialize;
+use tokio::sync::mpsc::Sender;
+
+/// What a running task is doing, for rendering live progress
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TaskEvent {
+    /// A model request is about to be sent; `n` counts from 1
+    IterationStarted { n: usize },
+    /// The model asked for this tool and it is about to run
+    ToolCalled { name: String },
+    ToolCompleted { name: String, ok: bool },
+    /// Token totals for the task so far, after each model response
+    TokensUpdated { stats: TokenStatistics },
+    Completed { summary: String },
+    /// The task timed out, ran out of iterations or hit an error
+    Failed { error: String },
+}
+
+/// Send an event if someone is listening; a dropped receiver doesn't stop the task
+pub(crate) async fn emit(events: Option<&Sender<TaskEvent>>, event: TaskEvent) {
+    if let Some(events) = events {
+        let _ = events.send(event).await;
+    }
+}
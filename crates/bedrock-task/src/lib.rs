@@ -1,41 +1,219 @@
 use aws_sdk_bedrockruntime::types::{
-    ContentBlock, ConversationRole, Message,
+    ContentBlock, ConversationRole, Message, ToolResultBlock, ToolResultContentBlock,
+    ToolResultStatus, ToolUseBlock,
 };
-use bedrock_client::{BedrockClient, ToolDefinition};
-use bedrock_config::AgentConfig;
-use bedrock_conversation::{ConversationManager, TokenUsageStats};
+use bedrock_client::rate_limit::estimate_request_tokens;
+use bedrock_client::{media_placeholder, text_tools, BedrockClient, ConverseApi, ConverseResponse, TextCallback, ToolDefinition};
+use bedrock_config::{AgentConfig, MixedResponsePolicy, ModelPricing, SummaryMode, ToolOverflow, ToolSettings};
+use bedrock_conversation::{assemble_context, fit_to_token_limit, ConversationManager, TokenUsageStats};
 use bedrock_core::{
-    BedrockError, CostDetails, Result, Task, TaskResult, TaskStatus,
-    TokenStatistics,
+    BedrockError, CostDetails, Result, RetryPolicy, Task, TaskResult, TaskStatus,
+    TokenStatistics, ToolPolicy,
 };
-use bedrock_tools::ToolRegistry;
-use chrono::Utc;
+use bedrock_tools::{Permission, PermissionManager, Tool, ToolRegistry};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::BinaryHeap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::Mutex;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::{watch, Mutex, MutexGuard};
 use tokio::time::timeout;
-use tracing::{debug, error, info, instrument, warn};
+use tracing::{debug, error, field, info, info_span, instrument, warn, Instrument, Span};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use events::emit;
+
+pub mod attribution;
+pub mod chain;
+pub mod compare;
+pub mod dag;
+pub mod estimate;
+pub mod events;
+pub mod replay;
+
+pub use attribution::{cost_by_tag, load_task_results, TagCost};
+pub use compare::ModelComparison;
+pub use dag::{DagExecutor, DagNode, DagRun};
+pub use estimate::TaskEstimate;
+pub use events::TaskEvent;
+pub use replay::{Expected, ReplayBundle, ReplayReport, TurnExpectation, TurnOutcome};
+
+/// Sent once when a task requires tool use and the model answers without calling a tool
+const TOOL_USE_NUDGE: &str = "You must use a tool to gather information before answering";
+
+/// Sent with the tool results once the task's tool execution budget is spent
+const TOOL_BUDGET_NOTICE: &str =
+    "The tool execution budget for this task is exhausted. Do not call any more tools; \
+     conclude with the information you already have";
+
+/// Sent with the tool results once `limits.max_consecutive_tool_errors` calls in a row have failed
+const TOOL_ERRORS_NOTICE: &str =
+    "The last several tool calls all failed. Do not call any more tools; conclude with the \
+     information you already have, or explain what is blocking the task";
+
+/// Instruction for the extra model call made under `tasks.summary: model`
+const SUMMARY_PROMPT: &str =
+    "Summarize the following answer in one sentence of at most 100 characters. Reply with the summary only.";
+
+/// Summary of a task stopped by its timeout
+const TIMED_OUT_SUMMARY: &str = "Task timed out";
+
+/// Resolve which tools a task may use.
+///
+/// A task-level policy wins over the configuration; tools disabled in the
+/// configuration, or left out of `tools.allowed`, can't be re-enabled by a task.
+pub fn resolve_tool_policy(settings: &ToolSettings, requested: Option<&ToolPolicy>) -> ToolPolicy {
+    if !settings.enabled {
+        return ToolPolicy::None;
+    }
+    let requested = requested.cloned().unwrap_or_default();
+    if settings.allowed.is_empty() {
+        return requested;
+    }
+    match requested {
+        ToolPolicy::All => ToolPolicy::Subset(settings.allowed.clone()),
+        ToolPolicy::Subset(names) => {
+            ToolPolicy::Subset(names.into_iter().filter(|name| settings.is_allowed(name)).collect())
+        }
+        ToolPolicy::None => ToolPolicy::None,
+    }
+}
+
+/// How a model turn holding both an answer and tool calls is handled under
+/// `tools.mixed_response`, or `None` for any other turn. A required tool call
+/// still has to happen before an answer is accepted.
+pub fn resolve_mixed_response(
+    settings: &ToolSettings,
+    response: &ConverseResponse,
+    tool_required: bool,
+) -> Option<MixedResponsePolicy> {
+    if !response.has_tool_use() || response.get_text_content().trim().is_empty() {
+        return None;
+    }
+    let policy = if tool_required { MixedResponsePolicy::PreferTools } else { settings.mixed_response };
+    info!(
+        "Model returned an answer together with tool calls, {}",
+        match policy {
+            MixedResponsePolicy::PreferAnswer => "returning the answer",
+            MixedResponsePolicy::PreferTools => "running the tools",
+        }
+    );
+    Some(policy)
+}
+
+/// Enforce `limits.max_request_tokens` on a request about to be sent.
+///
+/// The estimate covers the messages, the system prompt and the tool specs; only the
+/// messages are reduced (see [`fit_to_token_limit`]).
+pub fn fit_request_tokens(
+    config: &AgentConfig,
+    context: Vec<Message>,
+    system_prompt: Option<&str>,
+    tools: Option<&[ToolDefinition]>,
+) -> Result<Vec<Message>> {
+    let Some(max_tokens) = config.limits.max_request_tokens else {
+        return Ok(context);
+    };
+
+    let tool_specs = tools.map(serde_json::to_string).transpose()?.unwrap_or_default();
+    let fixed = format!("{}{}", system_prompt.unwrap_or_default(), tool_specs);
+    let model = &config.agent.model;
+    fit_to_token_limit(&context, max_tokens, config.limits.request_token_reduction, |messages| {
+        estimate_request_tokens(model, messages, Some(&fixed))
+    })
+}
+
+/// Turn the selected tools into model tool definitions, capped at `mcp.max_tools`
+/// as `mcp.on_tool_overflow` says
+pub fn build_tool_definitions(config: &AgentConfig, mut all_tools: Vec<Arc<dyn Tool>>) -> Result<Vec<ToolDefinition>> {
+    // Build tool definitions
+    debug!("Building tool definitions for {} tools", all_tools.len());
+    
+    // Limit tools to max_tools setting from config (default 64, Bedrock limit)
+    let max_tools = config.mcp.max_tools;
+    let tools_to_use = if all_tools.len() > max_tools {
+        let overflow = config.mcp.on_tool_overflow;
+        if overflow == ToolOverflow::Error {
+            return Err(BedrockError::ConfigError(format!(
+                "{} tools selected but mcp.max_tools is {}; narrow tools.allowed or raise the limit",
+                all_tools.len(),
+                max_tools
+            )));
+        }
+        if overflow == ToolOverflow::Prioritize {
+            // Stable, so MCP tools keep their registry order after the allowed ones
+            let allowed = &config.tools.allowed;
+            all_tools.sort_by_key(|tool| {
+                allowed.iter().position(|name| name == tool.name()).unwrap_or(allowed.len())
+            });
+        }
+        let dropped = all_tools.split_off(max_tools);
+        warn!(
+            "Tool count ({}) exceeds max_tools limit ({}). Dropping: {}",
+            max_tools + dropped.len(),
+            max_tools,
+            dropped.iter().map(|tool| tool.name()).collect::<Vec<_>>().join(", ")
+        );
+        all_tools
+    } else {
+        all_tools
+    };
+    
+    let tool_definitions: Vec<ToolDefinition> = tools_to_use
+        .into_iter()
+        .map(|tool| {
+            debug!("Processing tool: {}", tool.name());
+            let schema = tool.schema();
+            debug!("Got schema for tool: {}, size: {} bytes", 
+                tool.name(), 
+                serde_json::to_string(&schema).unwrap_or_default().len()
+            );
+            ToolDefinition {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                input_schema: schema,
+            }
+        })
+        .collect();
+    
+    debug!("Built {} tool definitions", tool_definitions.len());
+    Ok(tool_definitions)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Priority {
     High = 3,
     Normal = 2,
     Low = 1,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueuedTask {
     pub task: Task,
     pub priority: Priority,
     pub queued_at: chrono::DateTime<chrono::Utc>,
+    /// Times the task has been taken off the queue and run
+    #[serde(default)]
+    pub attempts: u32,
+    /// Re-queues allowed after a transient failure
+    #[serde(default)]
+    pub max_retries: u32,
+    /// Running when the queue was saved; such a task runs again from the start
+    /// when the queue is restored
+    #[serde(default)]
+    pub in_progress: bool,
+    /// A re-queued task waits out its backoff in the queue and isn't admitted before this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+// Equality follows the ordering so the two never disagree
 impl PartialEq for QueuedTask {
     fn eq(&self, other: &Self) -> bool {
-        self.task.task_id == other.task.task_id
+        self.cmp(other) == std::cmp::Ordering::Equal
     }
 }
 
@@ -47,23 +225,32 @@ impl PartialOrd for QueuedTask {
     }
 }
 
+/// Ordering for the max-heap queue, where the greatest task runs next: higher
+/// priority first, then the earliest `queued_at` (an older task compares
+/// greater), then the task ID so distinct tasks never compare equal.
 impl Ord for QueuedTask {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         (self.priority.clone() as u8)
             .cmp(&(other.priority.clone() as u8))
             .then_with(|| other.queued_at.cmp(&self.queued_at))
+            .then_with(|| self.task.task_id.cmp(&other.task.task_id))
     }
 }
 
 pub struct TaskExecutor {
     bedrock_client: Arc<BedrockClient>,
+    model: Arc<dyn ConverseApi>,
     tool_registry: Arc<ToolRegistry>,
     config: Arc<AgentConfig>,
     task_queue: Arc<Mutex<BinaryHeap<QueuedTask>>>,
-    active_tasks: Arc<Mutex<Vec<Uuid>>>,
+    /// Queued tasks currently running; kept in `queue.json` until they finish
+    active_tasks: Arc<Mutex<Vec<QueuedTask>>>,
     max_concurrent_tasks: usize,
     max_tool_iterations: usize,
     conversation_manager: Arc<Mutex<ConversationManager>>,
+    permissions: Arc<PermissionManager>,
+    /// Set once `shutdown` starts; the queue admits nothing afterwards
+    shutdown: Arc<watch::Sender<bool>>,
 }
 
 impl TaskExecutor {
@@ -72,444 +259,822 @@ impl TaskExecutor {
         tool_registry: Arc<ToolRegistry>,
         config: Arc<AgentConfig>,
     ) -> Result<Self> {
-        let conversation_manager = ConversationManager::new()?;
+        let conversation_manager = ConversationManager::new()?.with_settings(&config.conversation);
+        let max_tool_iterations = config.limits.max_tool_iterations;
+        let max_concurrent_tasks = config.limits.max_concurrent_tasks;
+        let model: Arc<dyn ConverseApi> = bedrock_client.clone();
+        // Pick up tasks that were still queued when the agent last stopped
+        let queue = read_queue_file(&queue_path(&config));
+        if !queue.is_empty() {
+            info!("Restored {} queued task(s)", queue.len());
+        }
         Ok(Self {
             bedrock_client,
+            model,
             tool_registry,
             config,
-            task_queue: Arc::new(Mutex::new(BinaryHeap::new())),
+            task_queue: Arc::new(Mutex::new(queue)),
             active_tasks: Arc::new(Mutex::new(Vec::new())),
-            max_concurrent_tasks: 3,
-            max_tool_iterations: 10,
+            max_concurrent_tasks,
+            max_tool_iterations,
             conversation_manager: Arc::new(Mutex::new(conversation_manager)),
+            // Tools run unrestricted unless a permission manager is supplied
+            permissions: Arc::new(PermissionManager::new().with_default(Permission::Allow)),
+            shutdown: Arc::new(watch::channel(false).0),
         })
     }
 
+    /// Use a different model backend for converse calls (tools still run locally)
+    pub fn with_model(mut self, model: Arc<dyn ConverseApi>) -> Self {
+        self.model = model;
+        self
+    }
+
+    pub fn with_permissions(mut self, permissions: Arc<PermissionManager>) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
     pub async fn queue_task(&self, task: Task, priority: Priority) -> Result<()> {
+        let active = self.active_tasks.lock().await;
         let mut queue = self.task_queue.lock().await;
         queue.push(QueuedTask {
             task,
             priority,
             queued_at: Utc::now(),
+            attempts: 0,
+            max_retries: self.config.limits.max_task_retries,
+            in_progress: false,
+            not_before: None,
         });
         info!("Task queued. Queue size: {}", queue.len());
-        Ok(())
+        write_queue_file(&queue_path(&self.config), &queue, &active)
     }
 
-    pub async fn process_queue(&self) {
-        loop {
-            let active_count = self.active_tasks.lock().await.len();
-            if active_count >= self.max_concurrent_tasks {
-                tokio::time::sleep(Duration::from_millis(100)).await;
-                continue;
+    /// Write the pending and running tasks to `queue.json` in the workspace directory
+    pub async fn persist_queue(&self) -> Result<()> {
+        let active = self.active_tasks.lock().await;
+        let queue = self.task_queue.lock().await;
+        write_queue_file(&queue_path(&self.config), &queue, &active)
+    }
+
+    /// Re-queue tasks saved by `persist_queue`, returning how many were loaded.
+    ///
+    /// A corrupt queue file is logged and ignored.
+    pub async fn load_queue(&self) -> Result<usize> {
+        let loaded = read_queue_file(&queue_path(&self.config));
+        let count = loaded.len();
+        let active = self.active_tasks.lock().await;
+        let mut queue = self.task_queue.lock().await;
+        for queued_task in loaded {
+            let task_id = queued_task.task.task_id;
+            if !queue.iter().chain(active.iter()).any(|q| q.task.task_id == task_id) {
+                queue.push(queued_task);
             }
+        }
+        Ok(count)
+    }
 
-            let task = {
-                let mut queue = self.task_queue.lock().await;
-                queue.pop()
-            };
+    /// Number of queued tasks currently running
+    pub async fn active_task_count(&self) -> usize {
+        self.active_tasks.lock().await.len()
+    }
+
+    /// Take the next task off the queue and mark it active, if a slot is free
+    /// for its priority. Normal and Low tasks can't use the slots reserved by
+    /// `limits.reserved_high_priority_slots`; tasks still backing off are passed over.
+    async fn admit_next(&self) -> Option<QueuedTask> {
+        if *self.shutdown.borrow() {
+            return None;
+        }
+        let mut active = self.active_tasks.lock().await;
+        let mut queue = self.task_queue.lock().await;
+        let now = Utc::now();
+        let mut backing_off = Vec::new();
+        while queue.peek().is_some_and(|next| next.not_before.is_some_and(|at| at > now)) {
+            backing_off.extend(queue.pop());
+        }
+        let limit = queue.peek().map(|next| match next.priority {
+            Priority::High => self.max_concurrent_tasks,
+            Priority::Normal | Priority::Low => self
+                .max_concurrent_tasks
+                .saturating_sub(self.config.limits.reserved_high_priority_slots),
+        });
+        let admitted = limit.filter(|&limit| active.len() < limit).and_then(|_| queue.pop());
+        queue.extend(backing_off);
+
+        let mut queued_task = admitted?;
+        queued_task.not_before = None;
+        active.push(queued_task.clone());
+        if let Err(e) = write_queue_file(&queue_path(&self.config), &queue, &active) {
+            warn!("Failed to persist task queue: {}", e);
+        }
+        Some(queued_task)
+    }
+
+    /// Run queued tasks until `shutdown` is called
+    pub async fn process_queue(&self) {
+        self.drive_queue(None).await
+    }
 
-            if let Some(queued_task) = task {
+    /// Run queued tasks until the queue is empty and none are running, saving
+    /// each final result and sending it on `results`. A task that ended in an
+    /// error is reported as a failed result.
+    pub async fn drain_queue(&self, results: Sender<TaskResult>) {
+        self.drive_queue(Some(results)).await
+    }
+
+    async fn drive_queue(&self, results: Option<Sender<TaskResult>>) {
+        let mut shutdown = self.shutdown.subscribe();
+        loop {
+            if *shutdown.borrow_and_update() {
+                info!("Task queue stopped");
+                return;
+            }
+            if let Some(queued_task) = self.admit_next().await {
                 let executor = self.clone();
+                let results = results.clone();
                 tokio::spawn(async move {
-                    let task_id = queued_task.task.task_id;
-                    {
-                        let mut active = executor.active_tasks.lock().await;
-                        active.push(task_id);
+                    let task = queued_task.task.clone();
+                    let outcome = executor.run_queued(queued_task).await;
+                    if let (Some(results), Some(outcome)) = (results, outcome) {
+                        let result = outcome.unwrap_or_else(|e| error_result(&task, &e));
+                        if let Err(e) = executor.save_result(&result).await {
+                            warn!("Failed to save result of task {}: {}", task.task_id, e);
+                        }
+                        let _ = results.send(result).await;
                     }
 
-                    let _result = executor.execute_task(queued_task.task).await;
-
-                    {
-                        let mut active = executor.active_tasks.lock().await;
-                        active.retain(|&id| id != task_id);
+                    let mut active = executor.active_tasks.lock().await;
+                    active.retain(|running| running.task.task_id != task.task_id);
+                    let queue = executor.task_queue.lock().await;
+                    if let Err(e) = write_queue_file(&queue_path(&executor.config), &queue, &active) {
+                        warn!("Failed to persist task queue: {}", e);
                     }
                 });
+            } else if results.is_some() && self.active_task_count().await == 0 && self.task_queue.lock().await.is_empty() {
+                return;
             } else {
-                tokio::time::sleep(Duration::from_millis(100)).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+                    _ = shutdown.changed() => {}
+                }
+            }
+        }
+    }
+
+    /// Stop taking tasks off the queue, wait up to `limits.shutdown_timeout_secs`
+    /// for running tasks to finish, then persist the tasks that never started.
+    /// Tasks still running are persisted too, and run again on the next start.
+    ///
+    /// Returns how many tasks were still running when the wait ended.
+    pub async fn shutdown(&self) -> Result<usize> {
+        self.shutdown.send_replace(true);
+
+        let wait = Duration::from_secs(self.config.limits.shutdown_timeout_secs);
+        let drained = timeout(wait, async {
+            while self.active_task_count().await > 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await;
+        let still_running = self.active_task_count().await;
+        if drained.is_err() {
+            warn!(
+                "{} task(s) still running after {}s; they will run again on the next start",
+                still_running,
+                wait.as_secs()
+            );
+        }
+
+        self.persist_queue().await?;
+        Ok(still_running)
+    }
+
+    /// Run a task taken off the queue, re-queueing it when it fails transiently
+    /// and has retries left. It waits out the backoff in the queue, not in a slot.
+    ///
+    /// Returns `None` when the task was re-queued. A result after more than one
+    /// attempt has the attempt number appended to its summary.
+    async fn run_queued(&self, mut queued_task: QueuedTask) -> Option<Result<TaskResult>> {
+        queued_task.attempts += 1;
+        let attempts = queued_task.attempts;
+        let result = self.execute_task(queued_task.task.clone()).await;
+
+        let retry = RetryPolicy::new(
+            queued_task.max_retries + 1,
+            Duration::from_millis(self.config.limits.task_retry_backoff_ms),
+        )
+        .with_max_delay(Duration::from_millis(self.config.limits.task_retry_max_backoff_ms));
+        if is_transient_failure(&result) && retry.allows_retry(attempts) {
+            let delay = retry.next_delay(attempts - 1);
+            warn!(
+                "Task {} failed transiently on attempt {}, re-queueing in {:?}",
+                queued_task.task.task_id, attempts, delay
+            );
+            queued_task.queued_at = Utc::now();
+            queued_task.not_before = chrono::Duration::from_std(delay).ok().map(|delay| queued_task.queued_at + delay);
+            let active = self.active_tasks.lock().await;
+            let mut queue = self.task_queue.lock().await;
+            queue.push(queued_task);
+            if let Err(e) = write_queue_file(&queue_path(&self.config), &queue, &active) {
+                warn!("Failed to persist task queue: {}", e);
             }
+            return None;
         }
+
+        Some(result.map(|mut result| {
+            if attempts > 1 {
+                result.summary = format!("{} (attempt {attempts})", result.summary);
+            }
+            result
+        }))
     }
 
     #[instrument(skip(self, task), fields(task_id = %task.task_id))]
     pub async fn execute_task(&self, task: Task) -> Result<TaskResult> {
+        self.execute_task_with_events(task, None).await
+    }
+
+    /// Run a task, reporting its progress on `events` when given.
+    ///
+    /// The last event is always `Completed` or `Failed`.
+    pub async fn execute_task_with_events(
+        &self,
+        task: Task,
+        events: Option<Sender<TaskEvent>>,
+    ) -> Result<TaskResult> {
+        let result = self.run_task(task, events.as_ref(), None).await;
+        let outcome = match &result {
+            Ok(result) if result.status == TaskStatus::Completed => {
+                TaskEvent::Completed { summary: result.summary.clone() }
+            }
+            Ok(result) => TaskEvent::Failed {
+                error: result.error.clone().unwrap_or_else(|| result.summary.clone()),
+            },
+            Err(e) => TaskEvent::Failed { error: e.to_string() },
+        };
+        emit(events.as_ref(), outcome).await;
+        result
+    }
+
+    /// Run a task like `execute_task`, streaming the model's text to `on_text` as it
+    /// arrives. Over the text tool protocol only the final answer is passed on, since
+    /// the other replies are tool-call markup.
+    #[instrument(skip(self, task, on_text), fields(task_id = %task.task_id))]
+    pub async fn execute_task_stream(&self, task: Task, on_text: &mut TextCallback<'_>) -> Result<TaskResult> {
+        self.run_task(task, None, Some(on_text)).await
+    }
+
+    async fn run_task(
+        &self,
+        task: Task,
+        events: Option<&Sender<TaskEvent>>,
+        on_text: Option<&mut TextCallback<'_>>,
+    ) -> Result<TaskResult> {
         info!("Executing task: {}", task.task_id);
 
         if task.prompt.is_empty() {
             return Err(BedrockError::TaskError("Task prompt is empty".into()));
         }
 
-        let task_timeout = Duration::from_secs(300); // 5 minute default timeout
+        // Per-task override first, then the configured default
+        let timeout_secs = task.timeout_secs.unwrap_or(self.config.limits.task_timeout_secs);
+        let task_timeout = Duration::from_secs(timeout_secs);
         
-        match timeout(task_timeout, self.execute_internal(task.clone())).await {
+        match timeout(task_timeout, self.execute_internal(task.clone(), events, on_text)).await {
             Ok(result) => result,
             Err(_) => {
-                error!("Task {} timed out after 300 seconds", task.task_id);
+                error!("Task {} timed out after {} seconds", task.task_id, timeout_secs);
                 Ok(TaskResult {
                     task_id: task.task_id,
                     status: TaskStatus::Failed,
-                    summary: "Task timed out".to_string(),
+                    summary: TIMED_OUT_SUMMARY.to_string(),
                     conversation: Some(vec![]),
                     result: None,
                     token_stats: TokenStatistics::default(),
                     cost: CostDetails::default(),
+                    tool_executions: 0,
+                    stop_sequence: None,
+                    tags: task.tags.clone(),
                     started_at: Utc::now(),
                     completed_at: Some(Utc::now()),
-                    duration_ms: Some(300_000),
-                    error: Some("Task timed out after 300 seconds".to_string()),
+                    duration_ms: Some(timeout_secs * 1000),
+                    error: Some(format!("Task timed out after {timeout_secs} seconds")),
                 })
             }
         }
     }
 
-    async fn execute_internal(&self, task: Task) -> Result<TaskResult> {
+    async fn execute_internal(
+        &self,
+        task: Task,
+        events: Option<&Sender<TaskEvent>>,
+        on_text: Option<&mut TextCallback<'_>>,
+    ) -> Result<TaskResult> {
         let started_at = Utc::now();
         
-        if !self.tool_registry.list().is_empty() {
-            self.execute_with_tools(task, started_at).await
+        let policy = resolve_tool_policy(&self.config.tools, task.tool_policy.as_ref());
+        let tools = self.tool_registry.select(&policy);
+        debug!("Resolved tool policy {:?} to {} tools", policy, tools.len());
+        
+        if tools.is_empty() {
+            self.execute_without_tools(task, started_at, events, on_text).await
+        } else if self.config.tools.text_protocol {
+            self.execute_with_text_tools(task, tools, started_at, events, on_text).await
         } else {
-            self.execute_without_tools(task, started_at).await
+            self.execute_with_tools(task, tools, started_at, events, on_text).await
         }
     }
 
-    #[instrument(skip(self, task), fields(task_id = %task.task_id))]
+    /// One model call for `task`, streamed to `on_text` when given
+    async fn call_model(
+        &self,
+        context: Vec<Message>,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+        task: &Task,
+        on_text: Option<&mut TextCallback<'_>>,
+    ) -> Result<ConverseResponse> {
+        let model_id = &self.config.agent.model;
+        match on_text {
+            Some(on_text) => {
+                self.model
+                    .converse_stream_with_metadata(model_id, context, system_prompt, tools, &task.request_metadata, on_text)
+                    .await
+            }
+            None => {
+                self.model
+                    .converse_with_metadata(model_id, context, system_prompt, tools, &task.request_metadata)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip(self, task, all_tools, events, on_text), fields(task_id = %task.task_id))]
     async fn execute_with_tools(
         &self,
         task: Task,
+        all_tools: Vec<Arc<dyn Tool>>,
         started_at: chrono::DateTime<chrono::Utc>,
+        events: Option<&Sender<TaskEvent>>,
+        mut on_text: Option<&mut TextCallback<'_>>,
     ) -> Result<TaskResult> {
-        info!("Starting task execution with {} tools", self.tool_registry.list().len());
+        info!("Starting task execution with {} tools", all_tools.len());
 
-        // Build tool definitions
-        let all_tools = self.tool_registry.get_all();
-        debug!("Building tool definitions for {} tools", all_tools.len());
-        
-        // Limit tools to max_tools setting from config (default 64, Bedrock limit)
-        let max_tools = self.config.mcp.max_tools;
-        let tools_to_use = if all_tools.len() > max_tools {
-            warn!(
-                "Tool count ({}) exceeds max_tools limit ({}). Limiting to first {} tools.",
-                all_tools.len(), max_tools, max_tools
-            );
-            all_tools.into_iter().take(max_tools).collect()
-        } else {
-            all_tools
-        };
-        
-        let tool_definitions: Vec<ToolDefinition> = tools_to_use
-            .into_iter()
-            .map(|tool| {
-                debug!("Processing tool: {}", tool.name());
-                let schema = tool.schema();
-                debug!("Got schema for tool: {}, size: {} bytes", 
-                    tool.name(), 
-                    serde_json::to_string(&schema).unwrap_or_default().len()
-                );
-                ToolDefinition {
-                    name: tool.name().to_string(),
-                    description: tool.description().to_string(),
-                    input_schema: schema,
-                }
-            })
-            .collect();
-        
-        debug!("Built {} tool definitions (limited from {} total)", 
-            tool_definitions.len(), 
-            self.tool_registry.list().len()
-        );
+        let tool_definitions = build_tool_definitions(&self.config, all_tools)?;
+        let system_prompt = Some(self.task_system_prompt(&task, &tool_definitions));
+        let mut run = self.start_run(&task, system_prompt.clone(), started_at, events).await?;
 
-        // Initialize conversation manager for this task
-        let mut conv_manager = self.conversation_manager.lock().await;
-        let conversation_id = conv_manager.start_conversation(
-            self.config.agent.model.clone(),
-            if task.context.is_empty() { None } else { Some(task.context.clone()) },
-        )?;
-        debug!("Started conversation {} for task {}", conversation_id, task.task_id);
+        let mut tool_used = false;
+        let mut tool_use_enforced = false;
+        // Which way the last turn with both an answer and tool calls went
+        let mut mixed_response: Option<&str> = None;
 
-        // Initialize conversation with user prompt
-        let user_message = Message::builder()
-            .role(ConversationRole::User)
-            .content(ContentBlock::Text(task.prompt.clone()))
-            .build()
-            .map_err(|e| BedrockError::Unknown(e.to_string()))?;
+        for iteration in 1..=self.max_tool_iterations {
+            let tools = if tool_definitions.is_empty() {
+                None
+            } else {
+                Some(tool_definitions.clone())
+            };
+            let response = self
+                .converse_turn(&mut run, iteration, system_prompt.clone(), tools, on_text.as_deref_mut())
+                .await?;
 
-        // Save user message to conversation
-        conv_manager.save_bedrock_message(&user_message, None)?;
+            if response.guardrail_intervened() {
+                return self.blocked_run(run, &response);
+            }
 
-        let mut conversation = vec![user_message];
-        let mut total_tokens = TokenStatistics::default();
+            // Check if we need to handle tool calls
+            debug!("Response stop_reason: {:?}, has_tool_use: {}",
+                response.stop_reason, response.has_tool_use());
 
-        // Execute conversation with tool support
-        let mut iterations = 0;
-        loop {
-            iterations += 1;
-            if iterations > self.max_tool_iterations {
-                warn!("Maximum tool iterations reached");
-                break;
+            let tool_required = task.require_tool_use_before_answer && !tool_used;
+            let mixed = resolve_mixed_response(&self.config.tools, &response, tool_required);
+            let prefer_answer = mixed == Some(MixedResponsePolicy::PreferAnswer);
+            if mixed.is_some() {
+                mixed_response = Some(if prefer_answer { "answer" } else { "tools" });
             }
+            let run_tools = response.has_tool_use() && !prefer_answer;
 
-            // Call the model
-            let response = self.bedrock_client
-                .converse(
-                    &self.config.agent.model,
-                    conversation.clone(),
-                    if task.context.is_empty() {
-                        None
-                    } else {
-                        Some(task.context.clone())
-                    },
-                    if tool_definitions.is_empty() {
-                        None
-                    } else {
-                        Some(tool_definitions.clone())
-                    },
-                )
-                .await?;
+            let tool_uses = if run_tools { response.get_tool_uses() } else { Vec::new() };
+            if !tool_uses.is_empty() {
+                if run.error_streak.reached() {
+                    return self.stopped_run(run);
+                }
+                debug!("Processing {} tool calls", tool_uses.len());
+                tool_used = true;
 
-            // Update token statistics
-            let mut token_usage_stats = None;
-            if let Some(usage) = &response.usage {
-                total_tokens.input_tokens += usage.input_tokens() as usize;
-                total_tokens.output_tokens += usage.output_tokens() as usize;
-                total_tokens.total_tokens += usage.total_tokens() as usize;
-                
-                // Create token usage stats for this response
-                token_usage_stats = Some(TokenUsageStats {
-                    input_tokens: usage.input_tokens() as u32,
-                    output_tokens: usage.output_tokens() as u32,
-                    total_tokens: usage.total_tokens() as u32,
-                    total_cost: None, // Will be calculated at the end
-                });
+                let round = self.run_tool_round(&mut run, &tool_uses).await?;
+                let mut content: Vec<ContentBlock> = round.results
+                    .into_iter()
+                    .map(ContentBlock::ToolResult)
+                    .collect();
+                content.extend(round.notices.into_iter().map(|notice| ContentBlock::Text(notice.to_string())));
+                run.push_user(content)?;
+                if let Some(limit) = self.budget_reached(&run) {
+                    return self.over_budget_run(run, limit);
+                }
+                continue;
+            }
+
+            // The model answered without gathering anything; nudge it once and retry
+            if task.require_tool_use_before_answer && !tool_used && !tool_use_enforced {
+                info!("Model answered without using a tool, enforcing tool use");
+                tool_use_enforced = true;
+                run.push_user(vec![ContentBlock::Text(TOOL_USE_NUDGE.to_string())])?;
+                continue;
             }
 
-            // Save assistant response to conversation
-            conv_manager.save_bedrock_message(&response.message, token_usage_stats)?;
+            let details = serde_json::json!({
+                "tool_use_enforced": tool_use_enforced,
+                "mixed_response": mixed_response,
+            });
+            return self.completed_run(run, &response, details).await;
+        }
 
-            // Add assistant response to conversation
-            conversation.push(response.message.clone());
+        self.exhausted_run(run)
+    }
 
-            // Check if we need to handle tool calls
-            debug!("Response stop_reason: {:?}, has_tool_use: {}", 
-                response.stop_reason, response.has_tool_use());
-            
-            if response.has_tool_use() {
-                // Get tool uses from the response
-                let tool_uses = response.get_tool_uses();
-                
-                if !tool_uses.is_empty() {
-                    debug!("Processing {} tool calls", tool_uses.len());
-                    
-                    // Execute tools and get results
-                    let tool_results = self.bedrock_client
-                        .execute_tools(&tool_uses, &self.tool_registry)
-                        .await?;
-                    
-                    // Create a message with tool results
-                    let tool_result_message = Message::builder()
-                        .role(ConversationRole::User)
-                        .set_content(Some(
-                            tool_results
-                                .into_iter()
-                                .map(ContentBlock::ToolResult)
-                                .collect(),
-                        ))
-                        .build()
-                        .map_err(|e| BedrockError::Unknown(e.to_string()))?;
-                    
-                    // Save tool result message to conversation
-                    conv_manager.save_bedrock_message(&tool_result_message, None)?;
-                    
-                    conversation.push(tool_result_message);
-                    
-                    // Continue conversation with tool results
-                    continue;
-                }
+    /// Tool loop for models without native tool support: tools are described in the
+    /// system prompt, calls are parsed from the reply text and results sent back as text
+    #[instrument(skip(self, task, all_tools, events, on_text), fields(task_id = %task.task_id))]
+    async fn execute_with_text_tools(
+        &self,
+        task: Task,
+        all_tools: Vec<Arc<dyn Tool>>,
+        started_at: chrono::DateTime<chrono::Utc>,
+        events: Option<&Sender<TaskEvent>>,
+        on_text: Option<&mut TextCallback<'_>>,
+    ) -> Result<TaskResult> {
+        info!("Starting task execution with {} tools over the text protocol", all_tools.len());
+
+        let tool_definitions = build_tool_definitions(&self.config, all_tools)?;
+        let system_prompt = self.task_system_prompt(&task, &tool_definitions);
+        let mut run = self.start_run(&task, Some(system_prompt.clone()), started_at, events).await?;
+
+        for iteration in 1..=self.max_tool_iterations {
+            // Replies are tool-call markup until the final answer, so nothing is streamed
+            let response = self
+                .converse_turn(&mut run, iteration, Some(system_prompt.clone()), None, None)
+                .await?;
+
+            if response.guardrail_intervened() {
+                return self.blocked_run(run, &response);
             }
 
-            // No more tool calls, task is complete
-            let cost = self.calculate_cost(&total_tokens);
             let text_content = response.get_text_content();
-            let summary = if text_content.is_empty() {
-                "Task completed".to_string()
-            } else {
-                self.generate_summary(&text_content)
-            };
+            let calls = text_tools::parse_tool_calls(&text_content);
 
-            // Convert conversation to JSON for storage
-            let conversation_json = self.messages_to_json(&conversation)?;
-
-            let duration_ms = (Utc::now() - started_at).num_milliseconds() as u64;
-            return Ok(TaskResult {
-                task_id: task.task_id,
-                status: TaskStatus::Completed,
-                summary: summary.clone(),
-                conversation: Some(conversation_json),
-                result: Some(serde_json::json!({"summary": summary})),
-                token_stats: total_tokens,
-                cost,
-                started_at,
-                completed_at: Some(Utc::now()),
-                duration_ms: Some(duration_ms),
-                error: None,
-            });
+            if calls.is_empty() {
+                if let Some(on_text) = on_text {
+                    on_text(&text_content);
+                }
+                return self.completed_run(run, &response, serde_json::json!({})).await;
+            }
+
+            if run.error_streak.reached() {
+                return self.stopped_run(run);
+            }
+
+            debug!("Processing {} text protocol tool calls", calls.len());
+            let tool_uses = text_tools::tool_use_blocks(&calls)?;
+            let tool_use_refs: Vec<_> = tool_uses.iter().collect();
+            let round = self.run_tool_round(&mut run, &tool_use_refs).await?;
+
+            let mut results_text = text_tools::format_tool_results(&calls, &round.results)?;
+            for notice in round.notices {
+                results_text.push_str(notice);
+            }
+            run.push_user(vec![ContentBlock::Text(results_text)])?;
+            if let Some(limit) = self.budget_reached(&run) {
+                return self.over_budget_run(run, limit);
+            }
         }
 
-        // Max iterations reached
-        let cost = self.calculate_cost(&total_tokens);
-        let conversation_json = self.messages_to_json(&conversation)?;
-        
-        let duration_ms = (Utc::now() - started_at).num_milliseconds() as u64;
-        Ok(TaskResult {
-            task_id: task.task_id,
-            status: TaskStatus::Failed,
-            summary: "Task failed: max tool iterations reached".to_string(),
-            conversation: Some(conversation_json),
-            result: None,
-            token_stats: total_tokens,
-            cost,
-            started_at,
-            completed_at: Some(Utc::now()),
-            duration_ms: Some(duration_ms),
-            error: Some("Max tool iterations reached".to_string()),
-        })
+        self.exhausted_run(run)
     }
 
     async fn execute_without_tools(
         &self,
         task: Task,
         started_at: chrono::DateTime<chrono::Utc>,
+        events: Option<&Sender<TaskEvent>>,
+        on_text: Option<&mut TextCallback<'_>>,
     ) -> Result<TaskResult> {
         info!("Executing task without tools");
 
-        // Initialize conversation manager for this task
+        let system_prompt = Some(self.task_system_prompt(&task, &[]));
+        let mut run = self.start_run(&task, system_prompt.clone(), started_at, events).await?;
+        let response = self.converse_turn(&mut run, 1, system_prompt, None, on_text).await?;
+
+        if response.guardrail_intervened() {
+            return self.blocked_run(run, &response);
+        }
+        self.completed_run(run, &response, serde_json::json!({})).await
+    }
+
+    /// System prompt of a task: the configured `agent.system_prompt` (or
+    /// `system_prompt_file`), the task's context, and over the text tool protocol the
+    /// description of `tool_definitions`
+    fn task_system_prompt(&self, task: &Task, tool_definitions: &[ToolDefinition]) -> String {
+        let mut parts = vec![self.config.agent.get_system_prompt()];
+        if !task.context.is_empty() {
+            parts.push(task.context.clone());
+        }
+        if self.config.tools.text_protocol && !tool_definitions.is_empty() {
+            parts.push(text_tools::tool_protocol_prompt(tool_definitions));
+        }
+        parts.join("\n\n")
+    }
+
+    /// Start the task's conversation with its prompt as the first message
+    async fn start_run<'a>(
+        &'a self,
+        task: &'a Task,
+        system_prompt: Option<String>,
+        started_at: DateTime<Utc>,
+        events: Option<&'a Sender<TaskEvent>>,
+    ) -> Result<TaskRun<'a>> {
         let mut conv_manager = self.conversation_manager.lock().await;
-        let conversation_id = conv_manager.start_conversation(
-            self.config.agent.model.clone(),
-            if task.context.is_empty() { None } else { Some(task.context.clone()) },
-        )?;
+        let conversation_id = conv_manager.start_conversation(self.config.agent.model.clone(), system_prompt)?;
         debug!("Started conversation {} for task {}", conversation_id, task.task_id);
 
-        // Initialize conversation with user prompt
-        let user_message = Message::builder()
-            .role(ConversationRole::User)
-            .content(ContentBlock::Text(task.prompt.clone()))
-            .build()
-            .map_err(|e| BedrockError::Unknown(e.to_string()))?;
+        let mut run = TaskRun {
+            task,
+            events,
+            started_at,
+            conv_manager,
+            conversation: Vec::new(),
+            total_tokens: TokenStatistics::default(),
+            tool_executions: 0,
+            tool_cost: 0.0,
+            policy: resolve_tool_policy(&self.config.tools, task.tool_policy.as_ref()),
+            tool_budget: self.config.limits.max_tool_executions_per_task,
+            error_streak: ToolErrorStreak::new(self.config.limits.max_consecutive_tool_errors),
+        };
+        run.push_user(vec![ContentBlock::Text(task.prompt.clone())])?;
+        Ok(run)
+    }
 
-        // Save user message to conversation
-        conv_manager.save_bedrock_message(&user_message, None)?;
-
-        let conversation = vec![user_message];
-
-        // Call the model
-        let response = self.bedrock_client
-            .converse(
-                &self.config.agent.model,
-                conversation.clone(),
-                if task.context.is_empty() {
-                    None
-                } else {
-                    Some(task.context.clone())
-                },
-                None,
-            )
+    /// One model call of a task: the conversation is fitted to the context limits,
+    /// the call gets its own `converse` span, and the reply is counted and saved
+    async fn converse_turn(
+        &self,
+        run: &mut TaskRun<'_>,
+        iteration: usize,
+        system_prompt: Option<String>,
+        tools: Option<Vec<ToolDefinition>>,
+        on_text: Option<&mut TextCallback<'_>>,
+    ) -> Result<ConverseResponse> {
+        emit(run.events, TaskEvent::IterationStarted { n: iteration }).await;
+        let context = fit_request_tokens(
+            &self.config,
+            assemble_context(&run.conversation, &self.config.conversation)?,
+            system_prompt.as_deref(),
+            tools.as_deref(),
+        )?;
+        let span = converse_span(iteration);
+        let response = self
+            .call_model(context, system_prompt, tools, run.task, on_text)
+            .instrument(span.clone())
             .await?;
+        record_usage(&span, &response);
 
-        // Calculate token statistics
-        let mut total_tokens = TokenStatistics::default();
         let mut token_usage_stats = None;
-        if let Some(usage) = &response.usage {
-            total_tokens.input_tokens = usage.input_tokens() as usize;
-            total_tokens.output_tokens = usage.output_tokens() as usize;
-            total_tokens.total_tokens = usage.total_tokens() as usize;
-            
-            // Create token usage stats for conversation
+        if let (Some(usage), Some(stats)) = (&response.usage, response.token_statistics()) {
+            run.total_tokens.accumulate(&stats);
             token_usage_stats = Some(TokenUsageStats {
                 input_tokens: usage.input_tokens() as u32,
                 output_tokens: usage.output_tokens() as u32,
                 total_tokens: usage.total_tokens() as u32,
-                total_cost: None, // Will be calculated below
+                total_cost: Some(self.calculate_cost(&stats, 0.0).total_cost),
             });
+            emit(run.events, TaskEvent::TokensUpdated { stats: run.total_tokens.clone() }).await;
         }
 
-        let cost = self.calculate_cost(&total_tokens);
-        
-        // Update token usage with cost if available
-        if let Some(ref mut stats) = token_usage_stats {
-            stats.total_cost = Some(cost.total_cost);
+        run.push(response.message.clone(), token_usage_stats)?;
+        Ok(response)
+    }
+
+    /// Run one round of tool calls. Calls beyond the task's tool budget are answered
+    /// with an error instead of run; the round's notices tell the model which limits it hit
+    async fn run_tool_round(&self, run: &mut TaskRun<'_>, tool_uses: &[&ToolUseBlock]) -> Result<ToolRound> {
+        let allowed = run.tool_budget
+            .map(|budget| budget.saturating_sub(run.tool_executions))
+            .unwrap_or(tool_uses.len())
+            .min(tool_uses.len());
+        let (to_run, refused) = tool_uses.split_at(allowed);
+
+        for tool_use in to_run {
+            emit(run.events, TaskEvent::ToolCalled { name: tool_use.name().to_string() }).await;
         }
-        
-        // Save assistant response to conversation
-        conv_manager.save_bedrock_message(&response.message, token_usage_stats)?;
-        
-        let text_content = response.get_text_content();
-        let summary = if text_content.is_empty() {
-            "Task completed".to_string()
+        let (mut results, cost) = if to_run.is_empty() {
+            (Vec::new(), 0.0)
         } else {
-            self.generate_summary(&text_content)
+            self.bedrock_client
+                .execute_tools_with_cost(to_run, &self.tool_registry, &self.permissions, &run.policy)
+                .await?
         };
+        emit_tool_results(run.events, to_run, &results).await;
+        run.tool_executions += to_run.len();
+        run.tool_cost += cost;
+        let errors_capped = run.error_streak.record(&results);
+        for tool_use in refused {
+            results.push(budget_exhausted_result(tool_use)?);
+        }
 
-        // Build final conversation with response
-        let mut final_conversation = conversation;
-        final_conversation.push(response.message);
-        
-        let conversation_json = self.messages_to_json(&final_conversation)?;
+        let mut notices = Vec::new();
+        if run.tool_budget.is_some_and(|budget| run.tool_executions >= budget) {
+            info!("Tool budget of {} executions reached", run.tool_executions);
+            notices.push(TOOL_BUDGET_NOTICE);
+        }
+        if errors_capped {
+            notices.push(TOOL_ERRORS_NOTICE);
+        }
+        Ok(ToolRound { results, notices })
+    }
 
-        let duration_ms = (Utc::now() - started_at).num_milliseconds() as u64;
+    /// Completed result for a run that ended with `response`; `details` are added to
+    /// the result next to the summary
+    async fn completed_run(&self, mut run: TaskRun<'_>, response: &ConverseResponse, details: Value) -> Result<TaskResult> {
+        let text_content = response.get_text_content();
+        let summary = self.summarize(run.task, &text_content, &mut run.total_tokens).await;
+        let (conversation, cost) = self.run_totals(&run)?;
+
+        let mut result = serde_json::json!({"summary": summary});
+        if let (Some(result), Value::Object(details)) = (result.as_object_mut(), details) {
+            result.extend(details);
+        }
+        let duration_ms = (Utc::now() - run.started_at).num_milliseconds() as u64;
         Ok(TaskResult {
-            task_id: task.task_id,
+            task_id: run.task.task_id,
             status: TaskStatus::Completed,
-            summary: summary.clone(),
-            conversation: Some(conversation_json),
-            result: Some(serde_json::json!({"summary": summary})),
-            token_stats: total_tokens,
+            summary,
+            conversation: Some(conversation),
+            result: Some(result),
+            token_stats: run.total_tokens,
             cost,
-            started_at,
+            tool_executions: run.tool_executions,
+            stop_sequence: response.stop_sequence.clone(),
+            tags: run.task.tags.clone(),
+            started_at: run.started_at,
             completed_at: Some(Utc::now()),
             duration_ms: Some(duration_ms),
             error: None,
         })
     }
 
-    fn calculate_cost(&self, tokens: &TokenStatistics) -> CostDetails {
+    /// Failed result for a run that used up `limits.max_tool_iterations`
+    fn exhausted_run(&self, run: TaskRun<'_>) -> Result<TaskResult> {
+        warn!("Maximum tool iterations reached ({})", self.max_tool_iterations);
+        let summary = format!("Task failed: max tool iterations reached ({} allowed)", self.max_tool_iterations);
+        let error = format!(
+            "Max tool iterations reached ({} allowed). Increase limits.max_tool_iterations to allow more.",
+            self.max_tool_iterations
+        );
+        self.failed_run(run, summary, error)
+    }
+
+    /// `limits.budget_limit` once the run's tokens and tool calls have cost that much
+    fn budget_reached(&self, run: &TaskRun<'_>) -> Option<f64> {
+        let limit = self.config.limits.budget_limit?;
+        (self.calculate_cost(&run.total_tokens, run.tool_cost).total_cost >= limit).then_some(limit)
+    }
+
+    /// Failed result for a run stopped at `limits.budget_limit`
+    fn over_budget_run(&self, run: TaskRun<'_>, limit: f64) -> Result<TaskResult> {
+        warn!("Stopping task {} at its cost budget of {}", run.task.task_id, limit);
+        let cost = self.calculate_cost(&run.total_tokens, run.tool_cost);
+        let summary = format!("Task failed: cost budget of {limit} {} reached", cost.currency);
+        let error = format!(
+            "Stopped after costing {:.4} {}, over limits.budget_limit ({limit}). Raise the limit to allow more.",
+            cost.total_cost, cost.currency
+        );
+        self.failed_run(run, summary, error)
+    }
+
+    /// Failed result for a run stopped by one of its limits
+    fn failed_run(&self, run: TaskRun<'_>, summary: String, error: String) -> Result<TaskResult> {
+        let (conversation, cost) = self.run_totals(&run)?;
+
+        let duration_ms = (Utc::now() - run.started_at).num_milliseconds() as u64;
+        Ok(TaskResult {
+            task_id: run.task.task_id,
+            status: TaskStatus::Failed,
+            summary,
+            conversation: Some(conversation),
+            result: None,
+            token_stats: run.total_tokens,
+            cost,
+            tool_executions: run.tool_executions,
+            stop_sequence: None,
+            tags: run.task.tags.clone(),
+            started_at: run.started_at,
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(duration_ms),
+            error: Some(error),
+        })
+    }
+
+    fn blocked_run(&self, run: TaskRun<'_>, response: &ConverseResponse) -> Result<TaskResult> {
+        let (conversation, cost) = self.run_totals(&run)?;
+        Ok(blocked_result(run.task, response, conversation, run.total_tokens, cost, run.tool_executions, run.started_at))
+    }
+
+    fn stopped_run(&self, run: TaskRun<'_>) -> Result<TaskResult> {
+        let (conversation, cost) = self.run_totals(&run)?;
+        Ok(run.error_streak.result(run.task, conversation, run.total_tokens, cost, run.tool_executions, run.started_at))
+    }
+
+    /// The run's conversation for storage and what it cost so far
+    fn run_totals(&self, run: &TaskRun<'_>) -> Result<(Vec<Value>, CostDetails)> {
+        Ok((self.messages_to_json(&run.conversation)?, self.calculate_cost(&run.total_tokens, run.tool_cost)))
+    }
+
+    fn calculate_cost(&self, tokens: &TokenStatistics, tool_cost: f64) -> CostDetails {
         // Get pricing for the model being used
         let pricing = self.config.pricing.get(&self.config.agent.model);
         
         let (input_cost, output_cost, currency) = if let Some(pricing) = pricing {
-            let input_cost = (tokens.input_tokens as f64 / 1000.0) * pricing.input_per_1k;
             let output_cost = (tokens.output_tokens as f64 / 1000.0) * pricing.output_per_1k;
-            (input_cost, output_cost, pricing.currency.clone())
+            (pricing.input_cost(tokens), output_cost, pricing.currency.clone())
         } else {
             // Default pricing if model not in config
-            let input_cost = (tokens.input_tokens as f64 / 1000.0) * 0.003;
-            let output_cost = (tokens.output_tokens as f64 / 1000.0) * 0.015;
-            (input_cost, output_cost, "USD".to_string())
+            let pricing = ModelPricing {
+                input_per_1k: 0.003,
+                output_per_1k: 0.015,
+                currency: "USD".to_string(),
+                cache_read_per_1k: None,
+                cache_write_per_1k: None,
+            };
+            let output_cost = (tokens.output_tokens as f64 / 1000.0) * pricing.output_per_1k;
+            (pricing.input_cost(tokens), output_cost, pricing.currency)
         };
         
         CostDetails {
             input_cost,
             output_cost,
-            total_cost: input_cost + output_cost,
+            tool_cost,
+            total_cost: input_cost + output_cost + tool_cost,
             currency,
             model: self.config.agent.model.clone(),
         }
     }
 
-    fn generate_summary(&self, content: &str) -> String {
-        if content.len() <= 100 {
-            content.to_string()
-        } else {
-            let summary = content.chars().take(97).collect::<String>();
-            format!("{summary}...")
+    /// Summary of a completed task in the configured `tasks.summary` style
+    async fn summarize(&self, task: &Task, content: &str, total_tokens: &mut TokenStatistics) -> String {
+        match self.config.tasks.summary {
+            SummaryMode::Template => format!("Completed: {}", self.generate_summary(&task.prompt)),
+            _ if content.is_empty() => "Task completed".to_string(),
+            SummaryMode::Truncate => self.generate_summary(content),
+            SummaryMode::Model => match self.model_summary(content, total_tokens).await {
+                Ok(summary) => summary,
+                Err(e) => {
+                    warn!("Summary model call failed, truncating the answer instead: {}", e);
+                    self.generate_summary(content)
+                }
+            },
         }
     }
 
-    // Convert AWS SDK Messages to JSON for storage
-    fn messages_to_json(&self, messages: &[Message]) -> Result<Vec<Value>> {
-        let mut json_messages = Vec::new();
+    async fn model_summary(&self, content: &str, total_tokens: &mut TokenStatistics) -> Result<String> {
+        let request = Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::Text(format!("{SUMMARY_PROMPT}\n\n{content}")))
+            .build()
+            .map_err(|e| BedrockError::Unknown(e.to_string()))?;
+        let response = self.model
+            .converse(&self.config.agent.model, vec![request], None, None)
+            .await?;
+        if let Some(stats) = response.token_statistics() {
+            total_tokens.accumulate(&stats);
+        }
+
+        let summary = response.get_text_content();
+        let summary = summary.trim();
+        if summary.is_empty() {
+            return Err(BedrockError::Unknown("summary model returned no text".into()));
+        }
+        Ok(self.generate_summary(summary))
+    }
+
+    fn generate_summary(&self, content: &str) -> String {
+        if content.len() <= 100 {
+            content.to_string()
+        } else {
+            let summary = content.chars().take(97).collect::<String>();
+            format!("{summary}...")
+        }
+    }
+
+    /// Messages as JSON for the task result: a readable `content`, plus the input of
+    /// every tool call under `tool_calls` and the (already truncated) output of every
+    /// tool result under `tool_results`
+    fn messages_to_json(&self, messages: &[Message]) -> Result<Vec<Value>> {
+        let mut json_messages = Vec::new();
         
         for msg in messages {
-            let role = format!("{:?}", msg.role());
             let content = msg.content()
                 .iter()
                 .filter_map(|block| {
@@ -520,17 +1085,46 @@ impl TaskExecutor {
                     } else if let Ok(_tool_result) = block.as_tool_result() {
                         Some("[Tool Result]".to_string())
                     } else {
-                        None
+                        // Images and documents are stored as a small placeholder
+                        media_placeholder(block).map(|placeholder| placeholder.to_string())
                     }
                 })
                 .collect::<Vec<_>>()
                 .join("\n");
             
-            json_messages.push(serde_json::json!({
-                "role": role,
+            let mut json_message = serde_json::json!({
+                "role": msg.role().as_str(),
                 "content": content,
                 "timestamp": Utc::now().to_rfc3339()
-            }));
+            });
+            
+            let tool_calls = msg.content()
+                .iter()
+                .filter_map(|block| block.as_tool_use().ok())
+                .map(|tool_use| Ok(serde_json::json!({
+                    "id": tool_use.tool_use_id(),
+                    "name": tool_use.name(),
+                    "input": BedrockClient::document_to_json(tool_use.input())?,
+                })))
+                .collect::<Result<Vec<_>>>()?;
+            if !tool_calls.is_empty() {
+                json_message["tool_calls"] = Value::Array(tool_calls);
+            }
+            
+            let tool_results = msg.content()
+                .iter()
+                .filter_map(|block| block.as_tool_result().ok())
+                .map(|result| Ok(serde_json::json!({
+                    "tool_use_id": result.tool_use_id(),
+                    "status": if result.status() == Some(&ToolResultStatus::Error) { "error" } else { "success" },
+                    "content": tool_result_payload(result)?,
+                })))
+                .collect::<Result<Vec<_>>>()?;
+            if !tool_results.is_empty() {
+                json_message["tool_results"] = Value::Array(tool_results);
+            }
+            
+            json_messages.push(json_message);
         }
         
         Ok(json_messages)
@@ -557,6 +1151,7 @@ impl TaskExecutor {
             "error": result.error,
             "token_stats": result.token_stats,
             "cost": result.cost,
+            "tags": result.tags,
             "duration_ms": result.duration_ms,
         });
         
@@ -585,17 +1180,29 @@ impl TaskExecutor {
     pub async fn load_result(&self, task_id: &Uuid) -> Result<TaskResult> {
         // For now, maintain backward compatibility with workspace/results
         let results_dir = self.config.paths.workspace_dir.join("results");
-        let file_path = results_dir.join(format!("{task_id}.json"));
-        
-        if file_path.exists() {
-            let json = std::fs::read_to_string(file_path)
-                .map_err(BedrockError::IoError)?;
-            
-            let result: TaskResult = serde_json::from_str(&json)?;
-            Ok(result)
-        } else {
-            Err(BedrockError::NotFound(format!("Task result not found: {}", task_id)))
-        }
+        chain::load_task_result(&results_dir, task_id)
+    }
+
+    /// Inject a previous task's result into the task context.
+    ///
+    /// Uses `after` when given, otherwise the most recent result if `tasks.auto_chain`
+    /// is enabled. Returns the task unchanged when there is nothing to chain onto.
+    pub async fn chain_task(&self, task: Task, after: Option<Uuid>) -> Result<Task> {
+        let results_dir = self.config.paths.workspace_dir.join("results");
+
+        let previous = match after {
+            Some(task_id) => Some(chain::load_task_result(&results_dir, &task_id)?),
+            None if self.config.tasks.auto_chain => chain::latest_task_result(&results_dir)?,
+            None => None,
+        };
+
+        Ok(match previous {
+            Some(previous) => {
+                info!("Chaining task {} after task {}", task.task_id, previous.task_id);
+                chain::chain_after(task, &previous)
+            }
+            None => task,
+        })
     }
     
     /// Resume a conversation by ID
@@ -615,10 +1222,283 @@ impl TaskExecutor {
     }
 }
 
+/// Output of a tool result: its JSON or text, or an array when it has several parts
+fn tool_result_payload(result: &ToolResultBlock) -> Result<Value> {
+    let mut parts = result.content()
+        .iter()
+        .filter_map(|part| match part {
+            ToolResultContentBlock::Json(doc) => Some(BedrockClient::document_to_json(doc)),
+            ToolResultContentBlock::Text(text) => Some(Ok(Value::String(text.clone()))),
+            _ => None,
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(if parts.len() == 1 { parts.remove(0) } else { Value::Array(parts) })
+}
+
+/// Span of one model call in the tool loop, so traces show each iteration
+fn converse_span(iteration: usize) -> Span {
+    info_span!("converse", iteration, input_tokens = field::Empty, output_tokens = field::Empty)
+}
+
+fn record_usage(span: &Span, response: &ConverseResponse) {
+    if let Some(usage) = &response.usage {
+        span.record("input_tokens", usage.input_tokens());
+        span.record("output_tokens", usage.output_tokens());
+    }
+}
+
+/// Report how each executed tool call went; a result with error status counts as failed
+async fn emit_tool_results(events: Option<&Sender<TaskEvent>>, tool_uses: &[&ToolUseBlock], results: &[ToolResultBlock]) {
+    for (tool_use, result) in tool_uses.iter().zip(results) {
+        let ok = result.status() != Some(&ToolResultStatus::Error);
+        emit(events, TaskEvent::ToolCompleted { name: tool_use.name().to_string(), ok }).await;
+    }
+}
+
+/// Error result for a tool call refused because the task's tool budget is spent
+fn budget_exhausted_result(tool_use: &ToolUseBlock) -> Result<ToolResultBlock> {
+    ToolResultBlock::builder()
+        .tool_use_id(tool_use.tool_use_id())
+        .content(ToolResultContentBlock::Text(format!(
+            "Tool '{}' was not run: the tool execution budget for this task is exhausted",
+            tool_use.name()
+        )))
+        .status(ToolResultStatus::Error)
+        .build()
+        .map_err(|e| BedrockError::Unknown(format!("Failed to build tool result: {e}")))
+}
+
+/// One run of a task: its conversation so far and what it used against its limits
+struct TaskRun<'a> {
+    task: &'a Task,
+    events: Option<&'a Sender<TaskEvent>>,
+    started_at: DateTime<Utc>,
+    conv_manager: MutexGuard<'a, ConversationManager>,
+    conversation: Vec<Message>,
+    total_tokens: TokenStatistics,
+    tool_executions: usize,
+    tool_cost: f64,
+    /// Tools this task may call, directly or through poll
+    policy: ToolPolicy,
+    /// `limits.max_tool_executions_per_task`
+    tool_budget: Option<usize>,
+    error_streak: ToolErrorStreak,
+}
+
+impl TaskRun<'_> {
+    /// Save `message` and add it to the conversation
+    fn push(&mut self, message: Message, tokens: Option<TokenUsageStats>) -> Result<()> {
+        self.conv_manager.save_bedrock_message(&message, tokens)?;
+        self.conversation.push(message);
+        Ok(())
+    }
+
+    fn push_user(&mut self, content: Vec<ContentBlock>) -> Result<()> {
+        let message = Message::builder()
+            .role(ConversationRole::User)
+            .set_content(Some(content))
+            .build()
+            .map_err(|e| BedrockError::Unknown(e.to_string()))?;
+        self.push(message, None)
+    }
+}
+
+/// Results of one round of tool calls, refused calls included, and the notices to
+/// send after them
+struct ToolRound {
+    results: Vec<ToolResultBlock>,
+    notices: Vec<&'static str>,
+}
+
+/// Failed tool calls in a row, against `limits.max_consecutive_tool_errors`
+struct ToolErrorStreak {
+    /// 0 means no cap
+    cap: usize,
+    count: usize,
+}
+
+impl ToolErrorStreak {
+    fn new(cap: usize) -> Self {
+        Self { cap, count: 0 }
+    }
+
+    /// Count a round of results in order; true when this round reached the cap
+    fn record(&mut self, results: &[ToolResultBlock]) -> bool {
+        let was_reached = self.reached();
+        for result in results {
+            if result.status() == Some(&ToolResultStatus::Error) {
+                self.count += 1;
+            } else {
+                self.count = 0;
+            }
+        }
+        !was_reached && self.reached()
+    }
+
+    fn reached(&self) -> bool {
+        self.cap > 0 && self.count >= self.cap
+    }
+
+    /// Result for a task whose model kept calling tools after being told to stop
+    fn result(
+        &self,
+        task: &Task,
+        conversation: Vec<serde_json::Value>,
+        token_stats: TokenStatistics,
+        cost: CostDetails,
+        tool_executions: usize,
+        started_at: DateTime<Utc>,
+    ) -> TaskResult {
+        warn!("Stopping task {} after {} consecutive tool errors", task.task_id, self.count);
+        let duration_ms = (Utc::now() - started_at).num_milliseconds() as u64;
+        TaskResult {
+            task_id: task.task_id,
+            status: TaskStatus::Failed,
+            summary: format!("Task failed: {} tool calls in a row failed", self.count),
+            conversation: Some(conversation),
+            result: Some(serde_json::json!({"consecutive_tool_errors": self.count})),
+            token_stats,
+            cost,
+            tool_executions,
+            stop_sequence: None,
+            tags: task.tags.clone(),
+            started_at,
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(duration_ms),
+            error: Some(format!(
+                "Stopped after {} consecutive tool errors; the model kept calling tools after being asked to conclude. \
+                 See the tool results in the conversation, or raise limits.max_consecutive_tool_errors.",
+                self.count
+            )),
+        }
+    }
+}
+
+/// Result for a task the guardrail stopped, keeping the guardrail's reply
+fn blocked_result(
+    task: &Task,
+    response: &ConverseResponse,
+    conversation: Vec<serde_json::Value>,
+    token_stats: TokenStatistics,
+    cost: CostDetails,
+    tool_executions: usize,
+    started_at: DateTime<Utc>,
+) -> TaskResult {
+    warn!("Guardrail blocked task {}", task.task_id);
+    let reply = response.get_text_content();
+    let duration_ms = (Utc::now() - started_at).num_milliseconds() as u64;
+    TaskResult {
+        task_id: task.task_id,
+        status: TaskStatus::Blocked,
+        summary: "Task blocked by guardrail".to_string(),
+        conversation: Some(conversation),
+        result: Some(serde_json::json!({
+            "guardrail_action": "intervened",
+            "message": reply,
+        })),
+        token_stats,
+        cost,
+        tool_executions,
+        stop_sequence: None,
+        tags: task.tags.clone(),
+        started_at,
+        completed_at: Some(Utc::now()),
+        duration_ms: Some(duration_ms),
+        error: Some(if reply.is_empty() {
+            "The configured guardrail blocked this request".to_string()
+        } else {
+            format!("The configured guardrail blocked this request: {reply}")
+        }),
+    }
+}
+
+/// A failed result for a task whose run ended in `error`
+fn error_result(task: &Task, error: &BedrockError) -> TaskResult {
+    TaskResult {
+        task_id: task.task_id,
+        status: TaskStatus::Failed,
+        summary: format!("Task failed: {error}"),
+        conversation: None,
+        result: None,
+        token_stats: TokenStatistics::default(),
+        cost: CostDetails::default(),
+        tool_executions: 0,
+        stop_sequence: None,
+        tags: task.tags.clone(),
+        started_at: Utc::now(),
+        completed_at: Some(Utc::now()),
+        duration_ms: None,
+        error: Some(error.to_string()),
+    }
+}
+
+/// Whether a failed run may succeed if the task is run again: throttling, a
+/// timeout or an IO error. Validation errors and failures the model caused are permanent.
+fn is_transient_failure(result: &Result<TaskResult>) -> bool {
+    match result {
+        Ok(result) => result.status == TaskStatus::Failed && result.summary == TIMED_OUT_SUMMARY,
+        Err(e) => e.is_retryable() || matches!(e, BedrockError::IoError(_)),
+    }
+}
+
+fn queue_path(config: &AgentConfig) -> PathBuf {
+    config.paths.workspace_dir.join("queue.json")
+}
+
+/// Save the pending tasks, then the running ones marked `in_progress`, so a
+/// task taken off the queue isn't lost if the agent stops before it finishes
+fn write_queue_file(path: &Path, queue: &BinaryHeap<QueuedTask>, active: &[QueuedTask]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(BedrockError::IoError)?;
+    }
+    // Highest priority first so the file reads in execution order
+    let mut tasks: Vec<QueuedTask> = queue.iter().cloned().collect();
+    tasks.sort_by(|a, b| b.cmp(a));
+    // A re-queued task is pending already
+    let running: Vec<QueuedTask> = active
+        .iter()
+        .filter(|running| !tasks.iter().any(|q| q.task.task_id == running.task.task_id))
+        .map(|running| QueuedTask { in_progress: true, ..running.clone() })
+        .collect();
+    tasks.extend(running);
+    let json = serde_json::to_string_pretty(&tasks)?;
+
+    // Write to a temp file and rename so a crash never leaves a half-written queue
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json).map_err(BedrockError::IoError)?;
+    std::fs::rename(&tmp_path, path).map_err(BedrockError::IoError)?;
+    Ok(())
+}
+
+fn read_queue_file(path: &Path) -> BinaryHeap<QueuedTask> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return BinaryHeap::new(),
+        Err(e) => {
+            warn!("Failed to read task queue {}: {}; starting with an empty queue", path.display(), e);
+            return BinaryHeap::new();
+        }
+    };
+    match serde_json::from_str::<Vec<QueuedTask>>(&content) {
+        Ok(tasks) => {
+            let interrupted = tasks.iter().filter(|q| q.in_progress).count();
+            if interrupted > 0 {
+                warn!("Re-queueing {} task(s) that were running when the agent stopped", interrupted);
+            }
+            tasks.into_iter().map(|q| QueuedTask { in_progress: false, ..q }).collect()
+        }
+        Err(e) => {
+            warn!("Task queue {} is corrupt: {}; starting with an empty queue", path.display(), e);
+            BinaryHeap::new()
+        }
+    }
+}
+
 impl Clone for TaskExecutor {
     fn clone(&self) -> Self {
         Self {
             bedrock_client: Arc::clone(&self.bedrock_client),
+            model: Arc::clone(&self.model),
             tool_registry: Arc::clone(&self.tool_registry),
             config: Arc::clone(&self.config),
             task_queue: Arc::clone(&self.task_queue),
@@ -626,6 +1506,1535 @@ impl Clone for TaskExecutor {
             max_concurrent_tasks: self.max_concurrent_tasks,
             max_tool_iterations: self.max_tool_iterations,
             conversation_manager: Arc::clone(&self.conversation_manager),
+            permissions: Arc::clone(&self.permissions),
+            shutdown: Arc::clone(&self.shutdown),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use aws_sdk_bedrockruntime::types::{StopReason, ToolUseBlock};
+    use bedrock_client::ConverseResponse;
+    use bedrock_conversation::MessageEntry;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    struct NamedTool(&'static str);
+
+    #[async_trait]
+    impl Tool for NamedTool {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn description(&self) -> &str {
+            "test tool"
+        }
+
+        fn schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, _args: Value) -> Result<Value> {
+            Ok(json!({}))
+        }
+    }
+
+    /// A tool that fails every call
+    struct BrokenTool;
+
+    #[async_trait]
+    impl Tool for BrokenTool {
+        fn name(&self) -> &str {
+            "deploy"
+        }
+
+        fn description(&self) -> &str {
+            "always fails"
+        }
+
+        fn schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, _args: Value) -> Result<Value> {
+            Err(BedrockError::ToolError { tool: "deploy".into(), message: "cluster unreachable".into() })
+        }
+    }
+
+    fn populated_registry() -> ToolRegistry {
+        let registry = ToolRegistry::new();
+        registry.register(NamedTool("fs_read")).unwrap();
+        registry.register(NamedTool("grep")).unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_explicit_none_policy_takes_no_tools_path() {
+        let registry = populated_registry();
+        let settings = AgentConfig::default().tools;
+        let task = Task::new("summarize").with_tool_policy(ToolPolicy::None);
+
+        let policy = resolve_tool_policy(&settings, task.tool_policy.as_ref());
+        assert_eq!(policy, ToolPolicy::None);
+        assert!(registry.select(&policy).is_empty());
+    }
+
+    #[test]
+    fn test_tool_policy_resolution() {
+        let mut settings = AgentConfig::default().tools;
+        let task = Task::new("search");
+        assert_eq!(resolve_tool_policy(&settings, task.tool_policy.as_ref()), ToolPolicy::All);
+
+        let subset = Task::new("search").with_tool_policy(ToolPolicy::Subset(vec!["grep".into()]));
+        assert_eq!(populated_registry().select(&resolve_tool_policy(&settings, subset.tool_policy.as_ref())).len(), 1);
+
+        // Disabling tools in config can't be overridden by the task
+        settings.enabled = false;
+        assert_eq!(resolve_tool_policy(&settings, subset.tool_policy.as_ref()), ToolPolicy::None);
+    }
+
+    #[test]
+    fn test_allowlist_narrows_tool_policy() {
+        let mut settings = AgentConfig::default().tools;
+        settings.allowed = vec!["fs_read".to_string()];
+        let registry = populated_registry();
+
+        let selected = registry.select(&resolve_tool_policy(&settings, None));
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].name(), "fs_read");
+
+        // A task can't ask for a tool the allowlist hides
+        let subset = Task::new("search").with_tool_policy(ToolPolicy::Subset(vec!["grep".into()]));
+        assert!(registry.select(&resolve_tool_policy(&settings, subset.tool_policy.as_ref())).is_empty());
+    }
+
+    /// Replays canned responses and records every request it receives
+    struct ScriptedModel {
+        responses: std::sync::Mutex<Vec<ConverseResponse>>,
+        requests: std::sync::Mutex<Vec<Vec<Message>>>,
+        /// Names of the tool definitions sent with each request
+        tools_offered: std::sync::Mutex<Vec<Option<Vec<String>>>>,
+        /// `requestMetadata` overrides sent with each request
+        request_metadata: std::sync::Mutex<Vec<HashMap<String, String>>>,
+        system_prompts: std::sync::Mutex<Vec<Option<String>>>,
+    }
+
+    impl ScriptedModel {
+        fn new(mut responses: Vec<ConverseResponse>) -> Self {
+            responses.reverse();
+            Self {
+                responses: std::sync::Mutex::new(responses),
+                requests: std::sync::Mutex::new(Vec::new()),
+                tools_offered: std::sync::Mutex::new(Vec::new()),
+                request_metadata: std::sync::Mutex::new(Vec::new()),
+                system_prompts: std::sync::Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ConverseApi for ScriptedModel {
+        async fn converse(
+            &self,
+            _model_id: &str,
+            messages: Vec<Message>,
+            system_prompt: Option<String>,
+            tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ConverseResponse> {
+            self.requests.lock().unwrap().push(messages);
+            self.system_prompts.lock().unwrap().push(system_prompt);
+            let names = tools.map(|tools| tools.into_iter().map(|tool| tool.name).collect());
+            self.tools_offered.lock().unwrap().push(names);
+            self.responses
+                .lock()
+                .unwrap()
+                .pop()
+                .ok_or_else(|| BedrockError::Unknown("script exhausted".into()))
+        }
+
+        async fn converse_with_metadata(
+            &self,
+            model_id: &str,
+            messages: Vec<Message>,
+            system_prompt: Option<String>,
+            tools: Option<Vec<ToolDefinition>>,
+            request_metadata: &HashMap<String, String>,
+        ) -> Result<ConverseResponse> {
+            self.request_metadata.lock().unwrap().push(request_metadata.clone());
+            self.converse(model_id, messages, system_prompt, tools).await
+        }
+    }
+
+    fn text_response(text: &str) -> ConverseResponse {
+        ConverseResponse {
+            message: Message::builder()
+                .role(ConversationRole::Assistant)
+                .content(ContentBlock::Text(text.to_string()))
+                .build()
+                .unwrap(),
+            stop_reason: StopReason::EndTurn,
+            usage: None,
+            stop_sequence: None,
+            guardrail_action: None,
+        }
+    }
+
+    fn tool_use_response(tool: &str) -> ConverseResponse {
+        let tool_use = ToolUseBlock::builder()
+            .tool_use_id("call-1")
+            .name(tool)
+            .input(aws_smithy_types::Document::Object(Default::default()))
+            .build()
+            .unwrap();
+        ConverseResponse {
+            message: Message::builder()
+                .role(ConversationRole::Assistant)
+                .content(ContentBlock::ToolUse(tool_use))
+                .build()
+                .unwrap(),
+            stop_reason: StopReason::ToolUse,
+            usage: None,
+            stop_sequence: None,
+            guardrail_action: None,
+        }
+    }
+
+    fn tool_uses_response(tools: &[&str]) -> ConverseResponse {
+        let blocks = tools
+            .iter()
+            .enumerate()
+            .map(|(i, tool)| {
+                ContentBlock::ToolUse(
+                    ToolUseBlock::builder()
+                        .tool_use_id(format!("call-{i}"))
+                        .name(*tool)
+                        .input(aws_smithy_types::Document::Object(Default::default()))
+                        .build()
+                        .unwrap(),
+                )
+            })
+            .collect();
+        ConverseResponse {
+            message: Message::builder()
+                .role(ConversationRole::Assistant)
+                .set_content(Some(blocks))
+                .build()
+                .unwrap(),
+            stop_reason: StopReason::ToolUse,
+            usage: None,
+            stop_sequence: None,
+            guardrail_action: None,
         }
     }
-}
\ No newline at end of file
+
+    fn answer_with_tool_response(text: &str, tool: &str) -> ConverseResponse {
+        let mut response = tool_use_response(tool);
+        response.message = Message::builder()
+            .role(ConversationRole::Assistant)
+            .set_content(Some(
+                std::iter::once(ContentBlock::Text(text.to_string()))
+                    .chain(response.message.content().iter().cloned())
+                    .collect(),
+            ))
+            .build()
+            .unwrap();
+        response
+    }
+
+    async fn executor_with(model: Arc<dyn ConverseApi>) -> TaskExecutor {
+        executor_with_config(model, AgentConfig::default()).await
+    }
+
+    async fn executor_with_config(model: Arc<dyn ConverseApi>, config: AgentConfig) -> TaskExecutor {
+        executor_with_registry(model, config, Arc::new(populated_registry())).await
+    }
+
+    async fn executor_with_registry(
+        model: Arc<dyn ConverseApi>,
+        config: AgentConfig,
+        registry: Arc<ToolRegistry>,
+    ) -> TaskExecutor {
+        // Conversations are written under HOME_DIR; keep them out of the real home
+        static HOME: std::sync::OnceLock<tempfile::TempDir> = std::sync::OnceLock::new();
+        let home = HOME.get_or_init(|| tempfile::TempDir::new().unwrap());
+        std::env::set_var("HOME_DIR", home.path());
+
+        let client = Arc::new(BedrockClient::new(config.clone()).await.unwrap());
+        TaskExecutor::new(client, registry, Arc::new(config))
+            .unwrap()
+            .with_model(model)
+    }
+
+    fn is_nudge(message: &Message) -> bool {
+        message
+            .content()
+            .iter()
+            .any(|block| matches!(block, ContentBlock::Text(text) if text == TOOL_USE_NUDGE))
+    }
+
+    #[tokio::test]
+    async fn test_only_allowed_tools_are_sent_to_the_model() {
+        let model = Arc::new(ScriptedModel::new(vec![text_response("done")]));
+        let mut config = AgentConfig::default();
+        config.tools.allowed = vec!["fs_read".to_string()];
+        let executor = executor_with_config(model.clone(), config).await;
+
+        executor.execute_task(Task::new("Read the README")).await.unwrap();
+
+        // grep is registered but not allowed
+        let offered = model.tools_offered.lock().unwrap();
+        assert_eq!(offered.as_slice(), &[Some(vec!["fs_read".to_string()])]);
+    }
+
+    /// Stands in for execute_bash, counting how often it runs
+    struct ShellTool {
+        runs: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for ShellTool {
+        fn name(&self) -> &str {
+            "execute_bash"
+        }
+
+        fn description(&self) -> &str {
+            "runs commands"
+        }
+
+        fn schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, _args: Value) -> Result<Value> {
+            self.runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(json!({"status": "done"}))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_poll_cannot_reach_tools_outside_the_task_policy() {
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let registry = Arc::new(ToolRegistry::new());
+        registry.register(ShellTool { runs: Arc::clone(&runs) }).unwrap();
+        registry.register(bedrock_tools::PollTool::new(Arc::downgrade(&registry))).unwrap();
+
+        let mut call = tool_use_response("poll");
+        let input = BedrockClient::json_to_document(&json!({
+            "tool": "execute_bash",
+            "input": {"command": "rm -rf /"},
+            "max_attempts": 1
+        }))
+        .unwrap();
+        call.message = Message::builder()
+            .role(ConversationRole::Assistant)
+            .content(ContentBlock::ToolUse(
+                ToolUseBlock::builder().tool_use_id("call-1").name("poll").input(input).build().unwrap(),
+            ))
+            .build()
+            .unwrap();
+        let model = Arc::new(ScriptedModel::new(vec![call, text_response("done")]));
+        let executor = executor_with_registry(model.clone(), AgentConfig::default(), registry).await;
+
+        let task = Task::new("Wait for the job").with_tool_policy(ToolPolicy::Subset(vec!["poll".into()]));
+        executor.execute_task(task).await.unwrap();
+
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 0);
+        let requests = model.requests.lock().unwrap();
+        let result = requests[1]
+            .last()
+            .unwrap()
+            .content()
+            .iter()
+            .find_map(|block| match block {
+                ContentBlock::ToolResult(result) => Some(result.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(result.status(), Some(&ToolResultStatus::Error));
+        assert!(format!("{:?}", result.content()).contains("not available to this task"));
+    }
+
+    #[tokio::test]
+    async fn test_stored_conversation_keeps_tool_inputs_and_outputs() {
+        let executor = executor_with(Arc::new(ScriptedModel::new(vec![]))).await;
+        let input = aws_smithy_types::Document::Object(
+            [("pattern".to_string(), aws_smithy_types::Document::String("TODO".into()))].into(),
+        );
+        let call = Message::builder()
+            .role(ConversationRole::Assistant)
+            .content(ContentBlock::Text("Searching".into()))
+            .content(ContentBlock::ToolUse(
+                ToolUseBlock::builder().tool_use_id("call-1").name("grep").input(input).build().unwrap(),
+            ))
+            .build()
+            .unwrap();
+        let result = Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::ToolResult(
+                ToolResultBlock::builder()
+                    .tool_use_id("call-1")
+                    .content(ToolResultContentBlock::Text("src/lib.rs:1: TODO".into()))
+                    .status(ToolResultStatus::Error)
+                    .build()
+                    .unwrap(),
+            ))
+            .build()
+            .unwrap();
+
+        let stored = executor.messages_to_json(&[call, result]).unwrap();
+
+        assert_eq!(stored[0]["role"], "assistant");
+        assert_eq!(stored[0]["content"], "Searching\n[Tool: grep]");
+        assert_eq!(stored[0]["tool_calls"], json!([{"id": "call-1", "name": "grep", "input": {"pattern": "TODO"}}]));
+        assert_eq!(stored[1]["role"], "user");
+        assert_eq!(
+            stored[1]["tool_results"],
+            json!([{"tool_use_id": "call-1", "status": "error", "content": "src/lib.rs:1: TODO"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_overflow_truncates_by_default() {
+        let model = Arc::new(ScriptedModel::new(vec![text_response("done")]));
+        let mut config = AgentConfig::default();
+        config.mcp.max_tools = 1;
+        let executor = executor_with_config(model.clone(), config).await;
+
+        executor.execute_task(Task::new("Read the README")).await.unwrap();
+
+        let offered = model.tools_offered.lock().unwrap();
+        assert_eq!(offered[0].as_ref().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_tool_overflow_error_fails_before_calling_the_model() {
+        let model = Arc::new(ScriptedModel::new(vec![text_response("unused")]));
+        let mut config = AgentConfig::default();
+        config.mcp.max_tools = 1;
+        config.mcp.on_tool_overflow = bedrock_config::ToolOverflow::Error;
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let err = executor.execute_task(Task::new("Read the README")).await.unwrap_err();
+
+        assert!(matches!(&err, BedrockError::ConfigError(message) if message.starts_with("2 tools selected but mcp.max_tools is 1")));
+        assert!(model.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tool_overflow_prioritize_keeps_allowed_order() {
+        let model = Arc::new(ScriptedModel::new(vec![text_response("done")]));
+        let mut config = AgentConfig::default();
+        config.tools.allowed = vec!["grep".to_string(), "fs_read".to_string()];
+        config.mcp.max_tools = 1;
+        config.mcp.on_tool_overflow = bedrock_config::ToolOverflow::Prioritize;
+        let executor = executor_with_config(model.clone(), config).await;
+
+        executor.execute_task(Task::new("Find the TODOs")).await.unwrap();
+
+        let offered = model.tools_offered.lock().unwrap();
+        assert_eq!(offered.as_slice(), &[Some(vec!["grep".to_string()])]);
+    }
+
+    #[tokio::test]
+    async fn test_guardrail_intervention_blocks_task() {
+        let mut blocked = text_response("Sorry, the model cannot answer this question.");
+        blocked.stop_reason = StopReason::GuardrailIntervened;
+        blocked.guardrail_action = Some(aws_sdk_bedrockruntime::types::GuardrailAction::GuardrailIntervened);
+        let model = Arc::new(ScriptedModel::new(vec![blocked, text_response("unused")]));
+        let executor = executor_with(model.clone()).await;
+
+        let result = executor.execute_task(Task::new("Something off-limits")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Blocked);
+        assert_eq!(result.summary, "Task blocked by guardrail");
+        assert!(result.error.unwrap().contains("Sorry, the model cannot answer this question."));
+        assert_eq!(model.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_tool_use_enforced_once() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            text_response("The answer is 42"),
+            tool_use_response("grep"),
+            text_response("Having searched, the answer is 42"),
+        ]));
+        let executor = executor_with(model.clone()).await;
+
+        let result = executor
+            .execute_task(Task::new("What is the answer?").require_tool_use())
+            .await
+            .unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.result.unwrap()["tool_use_enforced"], json!(true));
+
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let nudges = requests
+            .last()
+            .unwrap()
+            .iter()
+            .filter(|message| is_nudge(message))
+            .count();
+        assert_eq!(nudges, 1);
+    }
+
+    #[tokio::test]
+    async fn test_tool_use_not_enforced_by_default() {
+        let model = Arc::new(ScriptedModel::new(vec![text_response("The answer is 42")]));
+        let executor = executor_with(model.clone()).await;
+
+        let result = executor.execute_task(Task::new("What is the answer?")).await.unwrap();
+
+        assert_eq!(result.result.unwrap()["tool_use_enforced"], json!(false));
+        assert_eq!(model.requests.lock().unwrap().len(), 1);
+    }
+
+    fn message_text(message: &Message) -> String {
+        message
+            .content()
+            .iter()
+            .filter_map(|block| block.as_text().ok().cloned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_text_protocol_tool_loop() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            text_response(r#"<tool_call>{"name": "grep", "input": {"pattern": "main"}}</tool_call>"#),
+            text_response("main is defined in src/main.rs"),
+        ]));
+        let mut config = AgentConfig::default();
+        config.tools.text_protocol = true;
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let result = executor.execute_task(Task::new("Where is main?")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.summary, "main is defined in src/main.rs");
+
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let tool_results = message_text(requests[1].last().unwrap());
+        assert_eq!(tool_results, "<tool_result name=\"grep\">{}</tool_result>\n");
+        assert!(model.tools_offered.lock().unwrap().iter().all(Option::is_none));
+    }
+
+    #[tokio::test]
+    async fn test_configured_system_prompt_reaches_every_loop() {
+        for (text_protocol, policy) in [(false, ToolPolicy::All), (true, ToolPolicy::All), (false, ToolPolicy::None)] {
+            let model = Arc::new(ScriptedModel::new(vec![text_response("Done")]));
+            let mut config = AgentConfig::default();
+            config.agent.system_prompt = Some("Answer in French".to_string());
+            config.tools.text_protocol = text_protocol;
+            let executor = executor_with_config(model.clone(), config).await;
+
+            let task = Task::new("Say hi").with_context("Greet the user").with_tool_policy(policy);
+            executor.execute_task(task).await.unwrap();
+
+            let system_prompts = model.system_prompts.lock().unwrap();
+            let system_prompt = system_prompts[0].as_deref().unwrap();
+            assert!(system_prompt.contains("Answer in French\n\nGreet the user"), "{system_prompt}");
+            assert_eq!(system_prompt.contains("<tool_call>"), text_protocol, "{system_prompt}");
+        }
+    }
+
+    fn workspace_config(workspace: &tempfile::TempDir) -> AgentConfig {
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = workspace.path().to_path_buf();
+        config
+    }
+
+    #[tokio::test]
+    async fn test_queue_survives_restart() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let model = Arc::new(ScriptedModel::new(vec![]));
+
+        let executor = executor_with_config(model.clone(), workspace_config(&workspace)).await;
+        executor.queue_task(Task::new("low"), Priority::Low).await.unwrap();
+        executor.queue_task(Task::new("high"), Priority::High).await.unwrap();
+        executor.queue_task(Task::new("normal"), Priority::Normal).await.unwrap();
+        assert!(workspace.path().join("queue.json").exists());
+        drop(executor);
+
+        let restarted = executor_with_config(model, workspace_config(&workspace)).await;
+        let mut queue = restarted.task_queue.lock().await;
+        let prompts: Vec<String> = std::iter::from_fn(|| queue.pop().map(|q| q.task.prompt)).collect();
+        assert_eq!(prompts, vec!["high", "normal", "low"]);
+    }
+
+    /// Throttled for the first `throttles` calls, then answers
+    struct ThrottledModel {
+        throttles: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ConverseApi for ThrottledModel {
+        async fn converse(
+            &self,
+            _model_id: &str,
+            _messages: Vec<Message>,
+            _system_prompt: Option<String>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ConverseResponse> {
+            let throttled = self
+                .throttles
+                .fetch_update(std::sync::atomic::Ordering::SeqCst, std::sync::atomic::Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if throttled {
+                return Err(BedrockError::RateLimitError("ThrottlingException".into()));
+            }
+            Ok(text_response("Done"))
+        }
+    }
+
+    fn retry_config(workspace: &tempfile::TempDir, max_task_retries: u32) -> AgentConfig {
+        let mut config = workspace_config(workspace);
+        config.limits.max_task_retries = max_task_retries;
+        config.limits.task_retry_backoff_ms = 0;
+        config
+    }
+
+    async fn dequeue(executor: &TaskExecutor) -> QueuedTask {
+        executor.task_queue.lock().await.pop().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_throttled_queued_task_is_retried() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let model = Arc::new(ThrottledModel { throttles: 1.into() });
+        let executor = executor_with_config(model, retry_config(&workspace, 2)).await;
+        executor.queue_task(Task::new("Summarize"), Priority::Normal).await.unwrap();
+
+        assert!(executor.run_queued(dequeue(&executor).await).await.is_none());
+        let requeued = dequeue(&executor).await;
+        assert_eq!((requeued.attempts, requeued.max_retries), (1, 2));
+
+        let result = executor.run_queued(requeued).await.unwrap().unwrap();
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.summary, "Done (attempt 2)");
+        assert!(executor.task_queue.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_backing_off_task_does_not_hold_a_slot() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let mut config = retry_config(&workspace, 2);
+        config.limits.max_concurrent_tasks = 1;
+        config.limits.task_retry_backoff_ms = 60_000;
+        config.limits.task_retry_max_backoff_ms = 60_000;
+        let model = Arc::new(ThrottledModel { throttles: 1.into() });
+        let executor = executor_with_config(model, config).await;
+        executor.queue_task(Task::new("throttled"), Priority::High).await.unwrap();
+
+        // Re-queued straight away rather than sleeping out the backoff
+        let throttled = timeout(Duration::from_secs(5), executor.run_queued(dequeue(&executor).await)).await.unwrap();
+        assert!(throttled.is_none());
+
+        // The only slot goes to other work while the retry waits
+        executor.queue_task(Task::new("next"), Priority::Low).await.unwrap();
+        assert_eq!(executor.admit_next().await.unwrap().task.prompt, "next");
+        let waiting = executor.task_queue.lock().await.peek().cloned().unwrap();
+        assert_eq!(waiting.task.prompt, "throttled");
+        assert!(waiting.not_before.unwrap() > Utc::now());
+    }
+
+    #[tokio::test]
+    async fn test_queued_task_retries_are_capped() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let model = Arc::new(ThrottledModel { throttles: usize::MAX.into() });
+        let executor = executor_with_config(model, retry_config(&workspace, 1)).await;
+        executor.queue_task(Task::new("Summarize"), Priority::Normal).await.unwrap();
+
+        assert!(executor.run_queued(dequeue(&executor).await).await.is_none());
+        let result = executor.run_queued(dequeue(&executor).await).await.unwrap();
+        assert!(matches!(result, Err(BedrockError::RateLimitError(_))));
+        assert!(executor.task_queue.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_permanent_queued_failure_is_not_retried() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let model = Arc::new(ThrottledModel { throttles: 0.into() });
+        let executor = executor_with_config(model, retry_config(&workspace, 2)).await;
+        executor.queue_task(Task::new(""), Priority::Normal).await.unwrap();
+
+        let result = executor.run_queued(dequeue(&executor).await).await.unwrap();
+        assert!(matches!(result, Err(BedrockError::TaskError(_))));
+        assert!(executor.task_queue.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_task_is_admitted_when_low_tasks_fill_the_pool() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let mut config = workspace_config(&workspace);
+        config.limits.max_concurrent_tasks = 3;
+        config.limits.reserved_high_priority_slots = 1;
+        let executor = executor_with_config(Arc::new(ScriptedModel::new(vec![])), config).await;
+        for _ in 0..3 {
+            executor.queue_task(Task::new("low"), Priority::Low).await.unwrap();
+        }
+
+        assert!(executor.admit_next().await.is_some());
+        assert!(executor.admit_next().await.is_some());
+        // The last slot is held back for High-priority work
+        assert!(executor.admit_next().await.is_none());
+        assert_eq!(executor.active_task_count().await, 2);
+
+        executor.queue_task(Task::new("urgent"), Priority::High).await.unwrap();
+        let admitted = executor.admit_next().await.unwrap();
+        assert_eq!(admitted.task.prompt, "urgent");
+        assert_eq!(executor.active_task_count().await, 3);
+        assert!(executor.admit_next().await.is_none());
+        assert_eq!(executor.task_queue.lock().await.len(), 1);
+    }
+
+    fn queued(prompt: &str, priority: Priority, queued_secs_ago: i64) -> QueuedTask {
+        QueuedTask {
+            task: Task::new(prompt),
+            priority,
+            queued_at: Utc::now() - chrono::Duration::seconds(queued_secs_ago),
+            attempts: 0,
+            max_retries: 0,
+            in_progress: false,
+            not_before: None,
+        }
+    }
+
+    #[test]
+    fn test_queue_pops_by_priority_then_oldest_first() {
+        let mut queue = BinaryHeap::new();
+        queue.push(queued("normal, newer", Priority::Normal, 10));
+        queue.push(queued("low, oldest", Priority::Low, 60));
+        queue.push(queued("normal, older", Priority::Normal, 30));
+        queue.push(queued("high, newest", Priority::High, 0));
+
+        let prompts: Vec<String> = std::iter::from_fn(|| queue.pop().map(|q| q.task.prompt)).collect();
+        assert_eq!(prompts, vec!["high, newest", "normal, older", "normal, newer", "low, oldest"]);
+    }
+
+    #[test]
+    fn test_queued_task_ordering_agrees_with_equality() {
+        let first = queued("a", Priority::Normal, 5);
+        let mut second = queued("b", Priority::Normal, 0);
+        second.queued_at = first.queued_at;
+
+        assert_ne!(first, second);
+        assert_ne!(first.cmp(&second), std::cmp::Ordering::Equal);
+        assert_eq!(first.cmp(&first.clone()), std::cmp::Ordering::Equal);
+        assert_eq!(first, first.clone());
+    }
+
+    /// Answers after a delay
+    struct SlowModel(Duration);
+
+    #[async_trait]
+    impl ConverseApi for SlowModel {
+        async fn converse(
+            &self,
+            _model_id: &str,
+            _messages: Vec<Message>,
+            _system_prompt: Option<String>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ConverseResponse> {
+            tokio::time::sleep(self.0).await;
+            Ok(text_response("Done"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_running_tasks_and_persists_the_rest() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let mut config = workspace_config(&workspace);
+        config.limits.max_concurrent_tasks = 1;
+        let model = Arc::new(SlowModel(Duration::from_millis(200)));
+        let executor = executor_with_config(model, config).await;
+        executor.queue_task(Task::new("first"), Priority::High).await.unwrap();
+        executor.queue_task(Task::new("second"), Priority::Low).await.unwrap();
+
+        let worker = executor.clone();
+        let processing = tokio::spawn(async move { worker.process_queue().await });
+        while executor.active_task_count().await == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(executor.shutdown().await.unwrap(), 0);
+        timeout(Duration::from_secs(1), processing).await.unwrap().unwrap();
+
+        let persisted = read_queue_file(&workspace.path().join("queue.json"));
+        let prompts: Vec<&str> = persisted.iter().map(|q| q.task.prompt.as_str()).collect();
+        assert_eq!(prompts, vec!["second"]);
+    }
+
+    #[tokio::test]
+    async fn test_unfinished_task_runs_again_after_restart() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let mut config = workspace_config(&workspace);
+        config.limits.shutdown_timeout_secs = 0;
+        let model = Arc::new(SlowModel(Duration::from_secs(60)));
+        let executor = executor_with_config(model, config.clone()).await;
+        let task = Task::new("long job");
+        executor.queue_task(task.clone(), Priority::Normal).await.unwrap();
+
+        let worker = executor.clone();
+        tokio::spawn(async move { worker.process_queue().await });
+        while executor.active_task_count().await == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Kept on disk while it runs, so a crash doesn't lose it
+        let persisted: Vec<QueuedTask> =
+            serde_json::from_str(&std::fs::read_to_string(workspace.path().join("queue.json")).unwrap()).unwrap();
+        assert_eq!(persisted.len(), 1);
+        assert!(persisted[0].in_progress);
+
+        assert_eq!(executor.shutdown().await.unwrap(), 1);
+
+        let restarted = executor_with_config(Arc::new(ScriptedModel::new(vec![])), config).await;
+        let restored = restarted.task_queue.lock().await.pop().unwrap();
+        assert_eq!(restored.task.task_id, task.task_id);
+        assert!(!restored.in_progress);
+    }
+
+    #[tokio::test]
+    async fn test_drain_queue_reports_every_result_and_stops() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let model = Arc::new(ThrottledModel { throttles: 0.into() });
+        let executor = executor_with_config(model, workspace_config(&workspace)).await;
+        let good = Task::new("Summarize");
+        let empty = Task::new("");
+        executor.queue_task(good.clone(), Priority::Normal).await.unwrap();
+        executor.queue_task(empty.clone(), Priority::Normal).await.unwrap();
+
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(4);
+        timeout(Duration::from_secs(5), executor.drain_queue(sender)).await.unwrap();
+
+        let mut results = Vec::new();
+        while let Some(result) = receiver.recv().await {
+            results.push(result);
+        }
+        assert_eq!(results.len(), 2);
+        let by_id = |id: Uuid| results.iter().find(|r| r.task_id == id).unwrap();
+        assert_eq!(by_id(good.task_id).status, TaskStatus::Completed);
+        let failed = by_id(empty.task_id);
+        assert_eq!(failed.status, TaskStatus::Failed);
+        assert!(failed.error.as_deref().unwrap().contains("Task prompt is empty"));
+        assert!(executor.task_queue.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_queue_starts_empty() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        std::fs::write(workspace.path().join("queue.json"), "{ not a queue").unwrap();
+
+        let executor = executor_with_config(Arc::new(ScriptedModel::new(vec![])), workspace_config(&workspace)).await;
+        assert!(executor.task_queue.lock().await.is_empty());
+        assert_eq!(executor.load_queue().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_mixed_response_prefers_tools_by_default() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            answer_with_tool_response("Probably 42", "grep"),
+            text_response("Confirmed, the answer is 42"),
+        ]));
+        let executor = executor_with(model.clone()).await;
+
+        let result = executor.execute_task(Task::new("What is the answer?")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(model.requests.lock().unwrap().len(), 2);
+        assert!(result.summary.contains("Confirmed"));
+        assert_eq!(result.result.unwrap()["mixed_response"], json!("tools"));
+    }
+
+    #[tokio::test]
+    async fn test_mixed_response_prefer_answer_returns_text() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            answer_with_tool_response("Probably 42", "grep"),
+            text_response("Confirmed, the answer is 42"),
+        ]));
+        let mut config = AgentConfig::default();
+        config.tools.mixed_response = MixedResponsePolicy::PreferAnswer;
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let result = executor.execute_task(Task::new("What is the answer?")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(model.requests.lock().unwrap().len(), 1);
+        assert!(result.summary.contains("Probably 42"));
+        assert_eq!(result.result.unwrap()["mixed_response"], json!("answer"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_budget_stops_execution() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            tool_uses_response(&["grep", "grep"]),
+            tool_uses_response(&["grep", "grep"]),
+            tool_use_response("grep"),
+            text_response("Done with what I have"),
+        ]));
+        let mut config = AgentConfig::default();
+        config.limits.max_tool_executions_per_task = Some(3);
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let result = executor.execute_task(Task::new("Search everything")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.tool_executions, 3);
+
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+
+        let refused = |message: &Message| {
+            message
+                .content()
+                .iter()
+                .filter(|block| {
+                    matches!(block, ContentBlock::ToolResult(r) if r.status() == Some(&ToolResultStatus::Error))
+                })
+                .count()
+        };
+        let has_notice = |message: &Message| {
+            message
+                .content()
+                .iter()
+                .any(|block| matches!(block, ContentBlock::Text(text) if text == TOOL_BUDGET_NOTICE))
+        };
+
+        // Second round: one call fits the budget, the other is refused
+        let second_results = requests[2].last().unwrap();
+        assert_eq!(refused(second_results), 1);
+        assert!(has_notice(second_results));
+
+        // Every later call is refused
+        let third_results = requests[3].last().unwrap();
+        assert_eq!(refused(third_results), 1);
+        assert!(has_notice(third_results));
+    }
+
+    #[tokio::test]
+    async fn test_tool_budget_applies_to_text_protocol() {
+        let call = r#"<tool_call>{"name": "grep", "input": {"pattern": "main"}}</tool_call>"#;
+        let model = Arc::new(ScriptedModel::new(vec![
+            text_response(&format!("{call}\n{call}")),
+            text_response(call),
+            text_response("Done with what I have"),
+        ]));
+        let mut config = AgentConfig::default();
+        config.tools.text_protocol = true;
+        config.limits.max_tool_executions_per_task = Some(1);
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let result = executor.execute_task(Task::new("Search everything")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.tool_executions, 1);
+
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let first_results = message_text(requests[1].last().unwrap());
+        assert_eq!(first_results.matches("status=\"error\"").count(), 1);
+        assert!(first_results.ends_with(TOOL_BUDGET_NOTICE));
+        let second_results = message_text(requests[2].last().unwrap());
+        assert_eq!(second_results.matches("status=\"error\"").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_cache_hits_accumulate_and_are_priced() {
+        let with_usage = |mut response: ConverseResponse, input: i32, read: i32, write: i32| {
+            response.usage = Some(
+                aws_sdk_bedrockruntime::types::TokenUsage::builder()
+                    .input_tokens(input)
+                    .output_tokens(10)
+                    .total_tokens(input + read + write + 10)
+                    .cache_read_input_tokens(read)
+                    .cache_write_input_tokens(write)
+                    .build()
+                    .unwrap(),
+            );
+            response
+        };
+        let model = Arc::new(ScriptedModel::new(vec![
+            with_usage(tool_use_response("grep"), 100, 0, 2000),
+            with_usage(tool_use_response("grep"), 50, 2000, 0),
+            with_usage(text_response("Done"), 50, 2000, 0),
+        ]));
+        let mut config = AgentConfig::default();
+        config.agent.enable_prompt_cache = true;
+        config.pricing.insert(
+            config.agent.model.clone(),
+            bedrock_config::ModelPricing {
+                input_per_1k: 1.0,
+                output_per_1k: 0.0,
+                currency: "USD".to_string(),
+                cache_read_per_1k: Some(0.1),
+                cache_write_per_1k: None,
+            },
+        );
+        let executor = executor_with_config(model, config).await;
+
+        let result = executor.execute_task(Task::new("Search twice")).await.unwrap();
+
+        assert_eq!(result.token_stats.input_tokens, 200);
+        assert_eq!(result.token_stats.cache_hits, 4000);
+        assert_eq!(result.token_stats.cache_write_tokens, 2000);
+        // 0.2 uncached + 0.4 read + 2.5 written at the default 1.25x rate
+        assert!((result.cost.input_cost - 3.1).abs() < 1e-9, "{}", result.cost.input_cost);
+    }
+
+    #[tokio::test]
+    async fn test_failed_summary_model_call_falls_back_to_truncation() {
+        let answer = "a".repeat(150);
+        // Only the answer is scripted, so the summary call fails
+        let model = Arc::new(ScriptedModel::new(vec![text_response(&answer)]));
+        let mut config = AgentConfig::default();
+        config.tasks.summary = bedrock_config::SummaryMode::Model;
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let result = executor.execute_task(Task::new("Write a lot")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.summary, format!("{}...", "a".repeat(97)));
+        assert_eq!(model.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_model_summary_replaces_the_answer() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            text_response(&"b".repeat(150)),
+            text_response(" Wrote a long line of b. "),
+        ]));
+        let mut config = AgentConfig::default();
+        config.tasks.summary = bedrock_config::SummaryMode::Model;
+        let executor = executor_with_config(model, config).await;
+
+        let result = executor.execute_task(Task::new("Write a lot")).await.unwrap();
+
+        assert_eq!(result.summary, "Wrote a long line of b.");
+    }
+
+    #[tokio::test]
+    async fn test_template_summary_uses_prompt_and_status() {
+        let model = Arc::new(ScriptedModel::new(vec![text_response("The answer is 42")]));
+        let mut config = AgentConfig::default();
+        config.tasks.summary = bedrock_config::SummaryMode::Template;
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let result = executor.execute_task(Task::new("What is the answer?")).await.unwrap();
+
+        assert_eq!(result.summary, "Completed: What is the answer?");
+        assert_eq!(model.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_tool_errors_stop_the_loop() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            tool_use_response("deploy"),
+            tool_use_response("grep"),
+            tool_use_response("deploy"),
+            tool_uses_response(&["deploy", "deploy"]),
+            tool_use_response("deploy"),
+            text_response("unused"),
+        ]));
+        let mut config = AgentConfig::default();
+        config.limits.max_consecutive_tool_errors = 3;
+        config.limits.max_tool_iterations = 20;
+        let executor = executor_with_config(model.clone(), config).await;
+        executor.tool_registry.register(BrokenTool).unwrap();
+
+        let result = executor.execute_task(Task::new("Deploy the service")).await.unwrap();
+
+        // The grep success resets the streak; the last call comes after the notice
+        assert_eq!(result.status, TaskStatus::Failed);
+        assert_eq!(result.tool_executions, 5);
+        assert_eq!(result.result.unwrap()["consecutive_tool_errors"], 3);
+        assert!(result.error.unwrap().contains("3 consecutive tool errors"));
+
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 5);
+        let has_notice = |message: &Message| {
+            message
+                .content()
+                .iter()
+                .any(|block| matches!(block, ContentBlock::Text(text) if text == TOOL_ERRORS_NOTICE))
+        };
+        assert!(!has_notice(requests[3].last().unwrap()));
+        assert!(has_notice(requests[4].last().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_task_request_metadata_sent_with_each_request() {
+        let model = Arc::new(ScriptedModel::new(vec![tool_use_response("grep"), text_response("Done")]));
+        let executor = executor_with(model.clone()).await;
+        let mut task = Task::new("Search");
+        task.request_metadata.insert("project".into(), "atlas".into());
+
+        executor.execute_task(task).await.unwrap();
+
+        let sent = model.request_metadata.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert!(sent.iter().all(|metadata| metadata["project"] == "atlas"));
+    }
+
+    #[tokio::test]
+    async fn test_paid_tool_calls_add_tool_cost() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            tool_uses_response(&["grep", "fs_read"]),
+            tool_use_response("grep"),
+            text_response("Found it"),
+        ]));
+        let mut config = AgentConfig::default();
+        config.tools.permissions.insert(
+            "grep".to_string(),
+            bedrock_config::ToolPermission {
+                permission: bedrock_config::Permission::Allow,
+                constraint: None,
+                timeout_secs: None,
+                strict: false,
+                cost_per_call: Some(0.25),
+                cost_per_unit: None,
+                cost_unit_path: None,
+            },
+        );
+        let executor = executor_with_config(model, config).await;
+
+        let result = executor.execute_task(Task::new("Search twice")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert!((result.cost.tool_cost - 0.5).abs() < 1e-9);
+        assert!((result.cost.total_cost - (result.cost.input_cost + result.cost.output_cost + 0.5)).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_task_stops_at_budget_limit() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            tool_use_response("grep"),
+            tool_use_response("grep"),
+            text_response("Found it"),
+        ]));
+        let mut config = AgentConfig::default();
+        config.limits.budget_limit = Some(0.2);
+        config.tools.permissions.insert(
+            "grep".to_string(),
+            bedrock_config::ToolPermission {
+                permission: bedrock_config::Permission::Allow,
+                constraint: None,
+                timeout_secs: None,
+                strict: false,
+                cost_per_call: Some(0.25),
+                cost_per_unit: None,
+                cost_unit_path: None,
+            },
+        );
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let result = executor.execute_task(Task::new("Search twice")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Failed);
+        assert!(result.summary.contains("cost budget"), "{}", result.summary);
+        assert_eq!(result.tool_executions, 1);
+        assert_eq!(model.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stop_sequence_carried_into_result() {
+        let mut response = text_response("Step one done");
+        response.stop_reason = StopReason::StopSequence;
+        response.stop_sequence = Some("###".into());
+        let model = Arc::new(ScriptedModel::new(vec![response]));
+        let executor = executor_with(model).await;
+
+        let result = executor.execute_task(Task::new("Do step one")).await.unwrap();
+
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.stop_sequence.as_deref(), Some("###"));
+    }
+
+    #[tokio::test]
+    async fn test_tags_recorded_and_grouped_by_cost_center() {
+        let workspace = tempfile::TempDir::new().unwrap();
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = workspace.path().to_path_buf();
+        let responses = [1000, 2000, 3000]
+            .into_iter()
+            .map(|output_tokens| {
+                let mut response = text_response("done");
+                response.usage = Some(
+                    aws_sdk_bedrockruntime::types::TokenUsage::builder()
+                        .input_tokens(0)
+                        .output_tokens(output_tokens)
+                        .total_tokens(output_tokens)
+                        .build()
+                        .unwrap(),
+                );
+                response
+            })
+            .collect();
+        let executor = executor_with_config(Arc::new(ScriptedModel::new(responses)), config).await;
+
+        for cost_center in ["research", "platform", "research"] {
+            let task = Task::new("Summarize").with_tag("cost_center", cost_center);
+            let result = executor.execute_task(task).await.unwrap();
+            assert_eq!(result.tags["cost_center"], cost_center);
+            executor.save_result(&result).await.unwrap();
+        }
+
+        let results = attribution::load_task_results(&workspace.path().join("results")).unwrap();
+        let costs = cost_by_tag(&results, "cost_center");
+        let totals: Vec<(&str, usize, usize)> =
+            costs.iter().map(|c| (c.value.as_str(), c.tasks, c.total_tokens)).collect();
+        assert_eq!(totals, vec![("research", 2, 4000), ("platform", 1, 2000)]);
+        // Default pricing of $0.015 per 1k output tokens
+        assert!((costs[0].total_cost - 0.06).abs() < 1e-9);
+        assert!((costs[1].total_cost - 0.03).abs() < 1e-9);
+    }
+
+    /// Answers with the model ID, but only once every expected model has called in
+    struct BarrierModel {
+        barrier: tokio::sync::Barrier,
+    }
+
+    #[async_trait]
+    impl ConverseApi for BarrierModel {
+        async fn converse(
+            &self,
+            model_id: &str,
+            _messages: Vec<Message>,
+            _system_prompt: Option<String>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ConverseResponse> {
+            self.barrier.wait().await;
+            Ok(text_response(&format!("Answer from {model_id}")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compare_models_runs_concurrently() {
+        let models: Vec<String> = vec!["model-a".into(), "model-b".into(), "model-c".into()];
+        let model = Arc::new(BarrierModel { barrier: tokio::sync::Barrier::new(models.len()) });
+        let mut config = AgentConfig::default();
+        config.limits.max_concurrent_requests = models.len();
+        let executor = executor_with_config(model, config).await;
+
+        // The barrier only opens if all three runs are in flight together
+        let comparisons = tokio::time::timeout(
+            Duration::from_secs(10),
+            executor.compare_models(Task::new("Which is best?").with_tool_policy(ToolPolicy::None), &models),
+        )
+        .await
+        .expect("runs should execute concurrently");
+
+        assert_eq!(comparisons.len(), 3);
+        for (comparison, model) in comparisons.iter().zip(&models) {
+            assert_eq!(&comparison.model, model);
+            let result = comparison.result.as_ref().unwrap();
+            assert_eq!(result.status, TaskStatus::Completed);
+            assert!(result.summary.contains(&format!("Answer from {model}")));
+            assert_eq!(&result.cost.model, model);
+        }
+    }
+
+    /// Answers each prompt after a short delay, tracking how many calls overlap
+    #[derive(Default)]
+    struct OverlapModel {
+        in_flight: std::sync::atomic::AtomicUsize,
+        max_in_flight: std::sync::atomic::AtomicUsize,
+        /// (prompt, system prompt) of each call in the order they started
+        calls: std::sync::Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait]
+    impl ConverseApi for OverlapModel {
+        async fn converse(
+            &self,
+            _model_id: &str,
+            messages: Vec<Message>,
+            system_prompt: Option<String>,
+            _tools: Option<Vec<ToolDefinition>>,
+        ) -> Result<ConverseResponse> {
+            use std::sync::atomic::Ordering;
+            let prompt = message_text(&messages[0]);
+            self.calls.lock().unwrap().push((prompt.clone(), system_prompt.unwrap_or_default()));
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(text_response(&format!("finished {prompt}")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dag_bounds_concurrency_on_wide_graph() {
+        let model = Arc::new(OverlapModel::default());
+        let mut config = AgentConfig::default();
+        config.limits.max_concurrent_dag_nodes = 3;
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let nodes: Vec<DagNode> = (0..12)
+            .map(|i| DagNode::new(Task::new(format!("node-{i}")).with_tool_policy(ToolPolicy::None)))
+            .collect();
+        let run = DagExecutor::new(executor).run(nodes).await.unwrap();
+
+        assert_eq!(run.results.len(), 12);
+        assert!(run.results.iter().all(|r| r.status == TaskStatus::Completed));
+        assert!(run.errors.is_empty() && run.skipped.is_empty());
+        let max = model.max_in_flight.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(max <= 3, "{max} nodes ran at once");
+        assert!(max > 1, "independent nodes should overlap");
+    }
+
+    #[tokio::test]
+    async fn test_dag_honors_dependencies() {
+        let model = Arc::new(OverlapModel::default());
+        let executor = executor_with_config(model.clone(), AgentConfig::default()).await;
+
+        let node = |prompt: &str| DagNode::new(Task::new(prompt).with_tool_policy(ToolPolicy::None));
+        let (a, b) = (node("fetch-a"), node("fetch-b"));
+        let merge = node("merge").after(a.task.task_id).after(b.task.task_id);
+        let report = node("report").after(merge.task.task_id);
+        let run = DagExecutor::new(executor).run(vec![report, merge, b, a]).await.unwrap();
+
+        let finished: Vec<&str> = run.results.iter().map(|r| r.summary.as_str()).collect();
+        assert_eq!(finished.len(), 4);
+        assert!(finished[..2].contains(&"finished fetch-a") && finished[..2].contains(&"finished fetch-b"));
+        assert_eq!(&finished[2..], &["finished merge", "finished report"]);
+
+        // Dependency results reach the dependent's context
+        let calls = model.calls.lock().unwrap();
+        let (_, merge_context) = calls.iter().find(|(prompt, _)| prompt == "merge").unwrap();
+        assert!(merge_context.contains("finished fetch-a") && merge_context.contains("finished fetch-b"));
+    }
+
+    #[tokio::test]
+    async fn test_dag_rejects_cycles_and_unknown_dependencies() {
+        let executor = DagExecutor::new(executor_with(Arc::new(OverlapModel::default())).await);
+        let mut a = DagNode::new(Task::new("a"));
+        let b = DagNode::new(Task::new("b")).after(a.task.task_id);
+        a = a.after(b.task.task_id);
+        assert!(matches!(executor.run(vec![a, b]).await, Err(BedrockError::TaskError(_))));
+
+        let orphan = DagNode::new(Task::new("c")).after(Uuid::new_v4());
+        assert!(matches!(executor.run(vec![orphan]).await, Err(BedrockError::TaskError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_request_reduced_to_token_limit() {
+        // ~1000 tokens of answer text per turn
+        let model = Arc::new(ScriptedModel::new(vec![
+            answer_with_tool_response(&"a ".repeat(1_000), "grep"),
+            answer_with_tool_response(&"b ".repeat(1_000), "grep"),
+            text_response("Done"),
+        ]));
+        let mut config = AgentConfig::default();
+        config.limits.max_request_tokens = Some(1_500);
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let result = executor.execute_task(Task::new("Search twice")).await.unwrap();
+        assert_eq!(result.status, TaskStatus::Completed);
+
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1].len(), 3);
+        // The first exchange was dropped; the prompt and the latest exchange remain
+        let last = &requests[2];
+        assert_eq!(last.len(), 3);
+        assert_eq!(message_text(&last[0]), "Search twice");
+        assert!(message_text(&last[1]).starts_with('b'));
+    }
+
+    #[tokio::test]
+    async fn test_irreducible_request_fails() {
+        let model = Arc::new(ScriptedModel::new(vec![text_response("unreachable")]));
+        let mut config = AgentConfig::default();
+        config.limits.max_request_tokens = Some(100);
+        let executor = executor_with_config(model.clone(), config).await;
+
+        let err = executor.execute_task(Task::new("x".repeat(5_000))).await.unwrap_err();
+
+        assert!(err.to_string().contains("max_request_tokens"));
+        assert!(model.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_task_events_report_progress() {
+        let model = Arc::new(ScriptedModel::new(vec![tool_use_response("grep"), text_response("Found it")]));
+        let executor = executor_with(model).await;
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(32);
+
+        let result = executor
+            .execute_task_with_events(Task::new("Where is main?"), Some(sender))
+            .await
+            .unwrap();
+        assert_eq!(result.status, TaskStatus::Completed);
+
+        let mut events = Vec::new();
+        while let Some(event) = receiver.recv().await {
+            events.push(event);
+        }
+        let kinds: Vec<String> = events
+            .iter()
+            .map(|event| serde_json::to_value(event).unwrap()["event"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            kinds,
+            ["iteration_started", "tool_called", "tool_completed", "iteration_started", "completed"]
+        );
+        assert!(matches!(&events[1], TaskEvent::ToolCalled { name } if name == "grep"));
+        assert!(matches!(&events[2], TaskEvent::ToolCompleted { name, ok: true } if name == "grep"));
+        assert!(matches!(&events[3], TaskEvent::IterationStarted { n: 2 }));
+        assert!(matches!(&events[4], TaskEvent::Completed { summary } if summary == "Found it"));
+    }
+
+    #[tokio::test]
+    async fn test_task_events_end_with_failure() {
+        let model = Arc::new(ScriptedModel::new(vec![]));
+        let executor = executor_with(model).await;
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(32);
+
+        let result = executor.execute_task_with_events(Task::new("Anything"), Some(sender)).await;
+        assert!(result.is_err());
+
+        let mut last = None;
+        while let Some(event) = receiver.recv().await {
+            last = Some(event);
+        }
+        assert!(matches!(last, Some(TaskEvent::Failed { error }) if error.contains("script exhausted")));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_task_runs_tools_and_returns_a_full_result() {
+        let model = Arc::new(ScriptedModel::new(vec![tool_use_response("grep"), text_response("Found it")]));
+        let executor = executor_with(model.clone()).await;
+        let mut streamed = String::new();
+
+        let result = executor
+            .execute_task_stream(Task::new("Where is main?"), &mut |text| streamed.push_str(text))
+            .await
+            .unwrap();
+
+        assert_eq!(streamed, "Found it");
+        assert_eq!(result.status, TaskStatus::Completed);
+        assert_eq!(result.tool_executions, 1);
+        let conversation = result.conversation.unwrap();
+        assert_eq!(conversation[1]["tool_calls"][0]["name"], "grep");
+        assert_eq!(model.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_counts_tool_schemas_without_calling_the_model() {
+        let model = Arc::new(ScriptedModel::new(vec![]));
+        let mut config = AgentConfig::default();
+        config.pricing.insert(
+            config.agent.model.clone(),
+            bedrock_config::ModelPricing { input_per_1k: 1.0, output_per_1k: 5.0, currency: "USD".to_string(), cache_read_per_1k: None, cache_write_per_1k: None },
+        );
+        let executor = executor_with_config(model.clone(), config).await;
+        let task = Task::new("Summarize the repository").with_context("Be brief");
+
+        let with_tools = executor.estimate(&task).unwrap();
+        let without_tools = executor.estimate(&task.clone().with_tool_policy(ToolPolicy::None)).unwrap();
+
+        assert_eq!(with_tools.tools_offered, 2);
+        assert!(with_tools.tool_schema_bytes > 0);
+        assert_eq!(without_tools.tool_schema_bytes, 0);
+        let system_prompt = format!("{}\n\nBe brief", executor.config.agent.get_system_prompt());
+        let expected = bedrock_metrics::estimate_tokens(&format!("{system_prompt}Summarize the repository"), &executor.config.agent.model);
+        assert_eq!(without_tools.input_tokens, expected);
+        assert!(with_tools.input_tokens > without_tools.input_tokens);
+        assert_eq!(with_tools.cost.input_cost, with_tools.input_tokens as f64 / 1000.0);
+        assert_eq!(with_tools.cost.output_cost, 0.0);
+        assert!(model.requests.lock().unwrap().is_empty());
+    }
+
+    fn two_turn_bundle() -> ReplayBundle {
+        serde_json::from_value(json!({
+            "metadata": {"system_prompt": "Be brief"},
+            "messages": [
+                {"timestamp": "2024-01-01T00:00:00Z", "role": "user", "content": "What is 2 + 2?"},
+                {"timestamp": "2024-01-01T00:00:01Z", "role": "assistant", "content": "4"},
+                {"timestamp": "2024-01-01T00:00:02Z", "role": "user", "content": [{"type": "text", "text": "List the primes below 6"}]},
+                {"timestamp": "2024-01-01T00:00:03Z", "role": "assistant", "content": "2\n3\n5"}
+            ],
+            "expectations": [
+                {"turn": 1, "regex": "^\\d+$"},
+                {"turn": 2, "exact": "2\n3\n5"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replay_matches_expectations() {
+        let model = Arc::new(ScriptedModel::new(vec![
+            text_response("4"),
+            tool_use_response("grep"),
+            text_response("2\n3\n5\n"),
+        ]));
+        let executor = executor_with(model.clone()).await;
+
+        let report = executor.replay(&two_turn_bundle()).await.unwrap();
+
+        assert!(report.passed());
+        assert_eq!(report.turns.len(), 2);
+        assert!(report.turns.iter().all(|turn| turn.passed == Some(true)));
+        // The second turn sees the first exchange, and its tool call was answered
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1].len(), 3);
+        assert_eq!(requests[2].len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_exported_script_replays_the_user_prompts() {
+        let mut tool_call = MessageEntry::assistant(String::new());
+        tool_call.content = json!([{"type": "tool_use", "tool_use_id": "t1", "name": "grep", "input": {}}]);
+        let mut tool_result = MessageEntry::user(String::new());
+        tool_result.content = json!([{"type": "tool_result", "tool_use_id": "t1", "content": "src/lib.rs"}]);
+        let recorded = vec![
+            MessageEntry::user("Find the entry point".to_string()),
+            tool_call,
+            tool_result,
+            MessageEntry::assistant("It's src/lib.rs".to_string()),
+            MessageEntry::user(TOOL_USE_NUDGE.to_string()),
+            MessageEntry::user("<tool_result name=\"grep\">{}</tool_result>\n".to_string()),
+            MessageEntry::user("Now list its modules".to_string()),
+            MessageEntry::assistant("replay, events".to_string()),
+        ];
+
+        let script = ReplayBundle::script(Some("Be brief".to_string()), &recorded);
+        let json = serde_json::to_string(&script).unwrap();
+        let script: ReplayBundle = serde_json::from_str(&json).unwrap();
+        assert_eq!(script.user_turns(), vec!["Find the entry point", "Now list its modules"]);
+        assert!(script.expectations.is_empty());
+
+        let model = Arc::new(ScriptedModel::new(vec![text_response("a"), text_response("b")]));
+        let executor = executor_with(model.clone()).await;
+        let report = executor.replay(&script).await.unwrap();
+
+        let prompts: Vec<&str> = report.turns.iter().map(|turn| turn.prompt.as_str()).collect();
+        assert_eq!(prompts, vec!["Find the entry point", "Now list its modules"]);
+        let requests = model.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let last_text = |request: &Vec<Message>| request.last().unwrap().content()[0].as_text().unwrap().clone();
+        assert_eq!(last_text(&requests[0]), "Find the entry point");
+        assert_eq!(last_text(&requests[1]), "Now list its modules");
+    }
+
+    #[tokio::test]
+    async fn test_replay_reports_mismatch_with_diff() {
+        let model = Arc::new(ScriptedModel::new(vec![text_response("four"), text_response("2\n4\n5")]));
+        let executor = executor_with(model).await;
+
+        let report = executor.replay(&two_turn_bundle()).await.unwrap();
+
+        assert!(!report.passed());
+        assert_eq!(report.turns[0].passed, Some(false));
+        assert!(report.turns[0].failure.as_ref().unwrap().contains("doesn't match"));
+        let diff = report.turns[1].failure.as_ref().unwrap();
+        assert!(diff.contains("  2\n- 3\n+ 4\n  5"), "{diff}");
+    }
+}
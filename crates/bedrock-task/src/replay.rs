@@ -0,0 +1,282 @@
+//! Replay a stored conversation against the current agent and check its replies
+
+use aws_sdk_bedrockruntime::types::{ContentBlock, ConversationRole, Message};
+use bedrock_client::ToolDefinition;
+use bedrock_conversation::{assemble_context, MessageEntry};
+use bedrock_core::{BedrockError, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use tracing::info;
+
+use crate::{build_tool_definitions, fit_request_tokens, resolve_tool_policy, TaskExecutor, TOOL_USE_NUDGE};
+
+/// A conversation plus what the replies to its user turns should look like.
+///
+/// The output of `conversation --export` is a bundle once `expectations` is added,
+/// and `task --export-script` writes one holding only the user's prompts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayBundle {
+    #[serde(default)]
+    pub metadata: Option<BundleMetadata>,
+    pub messages: Vec<MessageEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expectations: Vec<TurnExpectation>,
+}
+
+/// The parts of the exported conversation metadata a replay uses
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleMetadata {
+    /// Replaces the configured system prompt when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+}
+
+/// What the reply to one user turn must match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnExpectation {
+    /// User turn, counting from 1
+    pub turn: usize,
+    #[serde(flatten)]
+    pub expected: Expected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expected {
+    /// The whole reply, ignoring surrounding whitespace
+    Exact(String),
+    /// Text the reply must contain
+    Contains(String),
+    /// Pattern that must match somewhere in the reply
+    Regex(String),
+}
+
+/// How one replayed turn went
+#[derive(Debug, Clone, Serialize)]
+pub struct TurnOutcome {
+    pub turn: usize,
+    pub prompt: String,
+    pub reply: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Expected>,
+    /// `None` for turns without an expectation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passed: Option<bool>,
+    /// Why the reply didn't match; a line diff for `exact`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    pub turns: Vec<TurnOutcome>,
+}
+
+impl ReplayReport {
+    /// No turn with an expectation failed
+    pub fn passed(&self) -> bool {
+        self.turns.iter().all(|turn| turn.passed != Some(false))
+    }
+}
+
+impl ReplayBundle {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(BedrockError::IoError)?;
+        serde_json::from_str(&json)
+            .map_err(|e| BedrockError::ConfigError(format!("Invalid replay bundle {}: {e}", path.display())))
+    }
+
+    /// A re-runnable script of a recorded conversation: its system prompt and the
+    /// prompts the user wrote, without replies, tool results or the agent's own nudges
+    pub fn script(system_prompt: Option<String>, messages: &[MessageEntry]) -> Self {
+        let prompts = messages
+            .iter()
+            .filter(|entry| entry.role == "user")
+            .filter_map(|entry| turn_text(&entry.content))
+            .filter(|text| text != TOOL_USE_NUDGE && !text.starts_with("<tool_result "))
+            .map(MessageEntry::user)
+            .collect();
+        Self {
+            metadata: Some(BundleMetadata { system_prompt }),
+            messages: prompts,
+            expectations: Vec::new(),
+        }
+    }
+
+    /// The text of each user turn; tool results sent back as user messages aren't turns
+    pub fn user_turns(&self) -> Vec<String> {
+        self.messages
+            .iter()
+            .filter(|entry| entry.role == "user")
+            .filter_map(|entry| turn_text(&entry.content))
+            .collect()
+    }
+}
+
+fn turn_text(content: &Value) -> Option<String> {
+    match content {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(blocks) => {
+            let block_type = |block: &Value| block.get("type").and_then(Value::as_str).map(str::to_string);
+            if blocks.iter().any(|block| block_type(block).as_deref() == Some("tool_result")) {
+                return None;
+            }
+            let text: Vec<&str> = blocks
+                .iter()
+                .filter(|block| block_type(block).as_deref() == Some("text"))
+                .filter_map(|block| block.get("text")?.as_str())
+                .collect();
+            (!text.is_empty()).then(|| text.join("\n"))
+        }
+        _ => None,
+    }
+}
+
+impl Expected {
+    /// Why `reply` doesn't match, or `None` when it does
+    fn check(&self, reply: &str) -> Result<Option<String>> {
+        let failure = match self {
+            Expected::Exact(expected) => (reply.trim() != expected.trim()).then(|| {
+                format!("reply differs from the expected text (- expected, + actual):\n{}", line_diff(expected.trim(), reply.trim()))
+            }),
+            Expected::Contains(needle) => (!reply.contains(needle.as_str()))
+                .then(|| format!("reply doesn't contain {needle:?}; it was:\n{}", indent(reply))),
+            Expected::Regex(pattern) => {
+                let regex = Regex::new(pattern)
+                    .map_err(|e| BedrockError::ConfigError(format!("Invalid regex {pattern:?}: {e}")))?;
+                (!regex.is_match(reply)).then(|| format!("reply doesn't match /{pattern}/; it was:\n{}", indent(reply)))
+            }
+        };
+        Ok(failure)
+    }
+}
+
+fn indent(text: &str) -> String {
+    text.lines().map(|line| format!("  {line}")).collect::<Vec<_>>().join("\n")
+}
+
+/// Line diff of `expected` against `actual`: `-` lines are missing, `+` lines unexpected
+fn line_diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+
+    // Longest common subsequence of the suffixes starting at each pair of lines
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(format!("  {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(format!("- {}", old[i]));
+            i += 1;
+        } else {
+            diff.push(format!("+ {}", new[j]));
+            j += 1;
+        }
+    }
+    diff.join("\n")
+}
+
+impl TaskExecutor {
+    /// Send the bundle's user turns to the model in order, running tool calls as a
+    /// chat would, and check each reply against the bundle's expectations.
+    ///
+    /// Nothing is saved to conversation storage.
+    pub async fn replay(&self, bundle: &ReplayBundle) -> Result<ReplayReport> {
+        let prompts = bundle.user_turns();
+        if let Some(expectation) = bundle.expectations.iter().find(|e| e.turn == 0 || e.turn > prompts.len()) {
+            return Err(BedrockError::ConfigError(format!(
+                "Expectation for turn {} but the bundle has {} user turn(s)",
+                expectation.turn,
+                prompts.len()
+            )));
+        }
+
+        let system_prompt = bundle
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.system_prompt.clone())
+            .unwrap_or_else(|| self.config.agent.get_system_prompt());
+        let tools = self.tool_registry.select(&resolve_tool_policy(&self.config.tools, None));
+        let tool_definitions = (!tools.is_empty()).then(|| build_tool_definitions(&self.config, tools)).transpose()?;
+
+        let mut conversation = Vec::new();
+        let mut turns = Vec::with_capacity(prompts.len());
+        for (index, prompt) in prompts.into_iter().enumerate() {
+            let turn = index + 1;
+            conversation.push(
+                Message::builder()
+                    .role(ConversationRole::User)
+                    .content(ContentBlock::Text(prompt.clone()))
+                    .build()
+                    .map_err(|e| BedrockError::Unknown(e.to_string()))?,
+            );
+            let reply = self.replay_turn(&mut conversation, &system_prompt, tool_definitions.as_deref()).await?;
+
+            let expected = bundle.expectations.iter().find(|e| e.turn == turn).map(|e| e.expected.clone());
+            let failure = match &expected {
+                Some(expected) => expected.check(&reply)?,
+                None => None,
+            };
+            let passed = expected.as_ref().map(|_| failure.is_none());
+            info!("Replayed turn {}: {:?}", turn, passed);
+            turns.push(TurnOutcome { turn, prompt, reply, expected, passed, failure });
+        }
+        Ok(ReplayReport { turns })
+    }
+
+    /// Run one turn to its final answer, executing the tool calls on the way
+    async fn replay_turn(
+        &self,
+        conversation: &mut Vec<Message>,
+        system_prompt: &str,
+        tools: Option<&[ToolDefinition]>,
+    ) -> Result<String> {
+        for _ in 0..self.max_tool_iterations {
+            let context = fit_request_tokens(
+                &self.config,
+                assemble_context(conversation, &self.config.conversation)?,
+                Some(system_prompt),
+                tools,
+            )?;
+            let response = self.model
+                .converse(&self.config.agent.model, context, Some(system_prompt.to_string()), tools.map(<[_]>::to_vec))
+                .await?;
+            conversation.push(response.message.clone());
+
+            let tool_uses = response.get_tool_uses();
+            if tool_uses.is_empty() {
+                return Ok(response.get_text_content());
+            }
+            let results = self.bedrock_client
+                .execute_tools(&tool_uses, &self.tool_registry, &self.permissions)
+                .await?;
+            conversation.push(
+                Message::builder()
+                    .role(ConversationRole::User)
+                    .set_content(Some(results.into_iter().map(ContentBlock::ToolResult).collect()))
+                    .build()
+                    .map_err(|e| BedrockError::Unknown(e.to_string()))?,
+            );
+        }
+        Err(BedrockError::TaskError(format!(
+            "No answer within {} tool iterations",
+            self.max_tool_iterations
+        )))
+    }
+}
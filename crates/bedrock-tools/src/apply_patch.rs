@@ -0,0 +1,455 @@
+use async_trait::async_trait;
+use bedrock_core::{BedrockError, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tracing::debug;
+
+use crate::fs_tools::resolve_workspace_path;
+use crate::Tool;
+
+/// Lines of the file shown on each side of a hunk that didn't match
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Deserialize)]
+struct ApplyPatchArgs {
+    path: String,
+    patch: Option<String>,
+    #[serde(default)]
+    edits: Vec<SearchReplace>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchReplace {
+    search: String,
+    replace: String,
+}
+
+/// One hunk of a unified diff
+#[derive(Debug, Default)]
+struct Hunk {
+    /// 1-based line the diff expects the hunk at
+    line: Option<usize>,
+    old: Vec<String>,
+    new: Vec<String>,
+}
+
+/// What happened to one hunk or edit
+#[derive(Debug)]
+struct HunkOutcome {
+    /// 1-based line the change landed on, or the line the problem is reported at
+    line: Option<usize>,
+    /// Why it was rejected, with the file's lines around it
+    rejected: Option<String>,
+}
+
+/// Edit a workspace file with a unified diff or search/replace edits.
+///
+/// Either every hunk applies and the file is replaced atomically, or the file is
+/// left untouched and the error shows why each rejected hunk didn't match.
+#[derive(Debug, Clone)]
+pub struct ApplyPatchTool {
+    workspace_dir: PathBuf,
+    max_file_size: usize,
+}
+
+impl ApplyPatchTool {
+    pub fn new(workspace_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            workspace_dir: workspace_dir.into(),
+            max_file_size: 10 * 1024 * 1024, // 10MB
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> BedrockError {
+        BedrockError::ToolError {
+            tool: self.name().to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Parse the hunks of a single-file unified diff; `---`/`+++` headers are optional
+fn parse_unified_diff(patch: &str) -> std::result::Result<Vec<Hunk>, String> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+    for line in patch.lines() {
+        if let Some(header) = line.strip_prefix("@@") {
+            hunks.extend(current.take());
+            current = Some(Hunk { line: old_start(header), ..Default::default() });
+            continue;
+        }
+        // File headers before the first hunk
+        let Some(hunk) = current.as_mut() else { continue };
+        match line.chars().next() {
+            Some(' ') => {
+                hunk.old.push(line[1..].to_string());
+                hunk.new.push(line[1..].to_string());
+            }
+            // Some generators drop the space of empty context lines
+            None => {
+                hunk.old.push(String::new());
+                hunk.new.push(String::new());
+            }
+            Some('-') => hunk.old.push(line[1..].to_string()),
+            Some('+') => hunk.new.push(line[1..].to_string()),
+            // "\ No newline at end of file"
+            Some('\\') => {}
+            _ => return Err(format!("Unexpected line in hunk: {line:?}")),
+        }
+    }
+    hunks.extend(current);
+    if hunks.is_empty() {
+        return Err("No hunks found; each hunk starts with an @@ -line,count +line,count @@ header".into());
+    }
+    Ok(hunks)
+}
+
+/// The old-file start line of a `@@ -12,5 +12,6 @@` header
+fn old_start(header: &str) -> Option<usize> {
+    let range = header.split_whitespace().find_map(|part| part.strip_prefix('-'))?;
+    range.split(',').next()?.parse().ok()
+}
+
+/// The file's lines around `index`, numbered, for showing why a hunk didn't match
+fn context_around(lines: &[String], index: usize, len: usize) -> String {
+    let start = index.saturating_sub(CONTEXT_LINES).min(lines.len());
+    let end = (index + len.max(1) + CONTEXT_LINES).min(lines.len());
+    lines[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, line)| format!("{:>5} | {line}", start + i + 1))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Apply the hunks in order, each at the matching position nearest the line it names
+fn apply_hunks(lines: &mut Vec<String>, hunks: &[Hunk]) -> Vec<HunkOutcome> {
+    // How far earlier hunks moved the lines after them
+    let mut offset = 0isize;
+    hunks
+        .iter()
+        .map(|hunk| {
+            let expected = hunk
+                .line
+                .map(|line| (line.max(1) as isize - 1 + offset).clamp(0, lines.len() as isize) as usize);
+            let matches_at = |start: &usize| {
+                lines[*start..*start + hunk.old.len()]
+                    .iter()
+                    .zip(&hunk.old)
+                    .all(|(line, old)| line.trim_end() == old.trim_end())
+            };
+            let found = if hunk.old.is_empty() {
+                Some(expected.unwrap_or(lines.len()))
+            } else {
+                (0..=lines.len().saturating_sub(hunk.old.len()))
+                    .filter(|start| hunk.old.len() <= lines.len() && matches_at(start))
+                    .min_by_key(|start| expected.map_or(*start, |expected| start.abs_diff(expected)))
+            };
+
+            match found {
+                Some(start) => {
+                    lines.splice(start..start + hunk.old.len(), hunk.new.iter().cloned());
+                    offset += hunk.new.len() as isize - hunk.old.len() as isize;
+                    HunkOutcome { line: Some(start + 1), rejected: None }
+                }
+                None => {
+                    let at = expected.unwrap_or(0);
+                    HunkOutcome {
+                        line: Some(at + 1),
+                        rejected: Some(format!(
+                            "its lines weren't found. Expected:\n{}\nFile around line {}:\n{}",
+                            hunk.old.join("\n"),
+                            at + 1,
+                            context_around(lines, at, hunk.old.len())
+                        )),
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// Apply search/replace edits in order; each search text must occur exactly once
+fn apply_edits(content: &mut String, edits: &[SearchReplace]) -> Vec<HunkOutcome> {
+    let line_of = |content: &str, byte: usize| content[..byte].matches('\n').count();
+    edits
+        .iter()
+        .map(|edit| {
+            if edit.search.is_empty() {
+                return HunkOutcome { line: None, rejected: Some("the search text is empty".into()) };
+            }
+            let starts: Vec<usize> = content.match_indices(&edit.search).map(|(start, _)| start).collect();
+            match starts.as_slice() {
+                [start] => {
+                    let line = line_of(content, *start) + 1;
+                    content.replace_range(*start..*start + edit.search.len(), &edit.replace);
+                    HunkOutcome { line: Some(line), rejected: None }
+                }
+                [] => {
+                    let lines: Vec<String> = content.lines().map(str::to_string).collect();
+                    // Point at where the search text starts to diverge, if its first line is there
+                    let first = edit.search.lines().next().unwrap_or_default().trim();
+                    let near = (!first.is_empty())
+                        .then(|| lines.iter().position(|line| line.contains(first)))
+                        .flatten();
+                    let rejected = match near {
+                        Some(index) => format!(
+                            "the search text wasn't found. File around line {}:\n{}",
+                            index + 1,
+                            context_around(&lines, index, edit.search.lines().count())
+                        ),
+                        None => "the search text wasn't found".to_string(),
+                    };
+                    HunkOutcome { line: near.map(|index| index + 1), rejected: Some(rejected) }
+                }
+                many => {
+                    let lines: Vec<String> = many.iter().map(|start| (line_of(content, *start) + 1).to_string()).collect();
+                    HunkOutcome {
+                        line: None,
+                        rejected: Some(format!(
+                            "the search text occurs {} times (lines {}); include more surrounding text",
+                            many.len(),
+                            lines.join(", ")
+                        )),
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// Write through a temporary file in the same directory so the file is never half-written.
+/// The temporary name is new for each write, so concurrent patches and files already
+/// there are never written through.
+async fn write_atomically(path: &Path, content: &str) -> std::io::Result<()> {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let temp = path.with_file_name(format!(".{name}.{}.apply_patch.tmp", uuid::Uuid::new_v4().simple()));
+    let permissions = tokio::fs::metadata(path).await?.permissions();
+    let mut file = tokio::fs::OpenOptions::new().write(true).create_new(true).open(&temp).await?;
+    let renamed = async {
+        file.write_all(content.as_bytes()).await?;
+        file.flush().await?;
+        tokio::fs::set_permissions(&temp, permissions).await?;
+        tokio::fs::rename(&temp, path).await
+    };
+    if let Err(e) = renamed.await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl Tool for ApplyPatchTool {
+    fn name(&self) -> &str {
+        "apply_patch"
+    }
+
+    fn description(&self) -> &str {
+        "Edit a file in the workspace without rewriting it: give either a unified diff \
+         (@@ hunks) or search/replace edits. All changes apply or none do; rejected hunks \
+         are reported with the surrounding lines of the file."
+    }
+
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Path to the file to edit (relative to workspace)"
+                },
+                "patch": {
+                    "type": "string",
+                    "description": "Unified diff for this one file, made of @@ -line,count +line,count @@ hunks"
+                },
+                "edits": {
+                    "type": "array",
+                    "description": "Search/replace edits applied in order, as an alternative to patch",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "search": {
+                                "type": "string",
+                                "description": "Exact text to replace; must occur exactly once"
+                            },
+                            "replace": {
+                                "type": "string",
+                                "description": "Text to put in its place"
+                            }
+                        },
+                        "required": ["search", "replace"]
+                    }
+                }
+            },
+            "required": ["path"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<Value> {
+        let args: ApplyPatchArgs = serde_json::from_value(args)
+            .map_err(|e| self.error(format!("Invalid arguments: {e}")))?;
+        let path = resolve_workspace_path(self.name(), &self.workspace_dir, Path::new(&args.path))?;
+
+        let metadata = tokio::fs::metadata(&path).await.map_err(BedrockError::IoError)?;
+        if metadata.len() > self.max_file_size as u64 {
+            return Err(self.error(format!("File too large: {} bytes", metadata.len())));
+        }
+        let original = tokio::fs::read_to_string(&path).await.map_err(BedrockError::IoError)?;
+
+        let (patched, outcomes) = match (args.patch, args.edits.is_empty()) {
+            (Some(patch), true) => {
+                let hunks = parse_unified_diff(&patch).map_err(|e| self.error(e))?;
+                let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+                let outcomes = apply_hunks(&mut lines, &hunks);
+                let ending = if original.contains("\r\n") { "\r\n" } else { "\n" };
+                let mut patched = lines.join(ending);
+                if original.ends_with('\n') && !patched.is_empty() {
+                    patched.push_str(ending);
+                }
+                (patched, outcomes)
+            }
+            (None, false) => {
+                let mut patched = original.clone();
+                let outcomes = apply_edits(&mut patched, &args.edits);
+                (patched, outcomes)
+            }
+            _ => return Err(self.error("Give either 'patch' or 'edits'")),
+        };
+
+        let rejected: Vec<String> = outcomes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, outcome)| Some(format!("Hunk {}: {}", i + 1, outcome.rejected.as_ref()?)))
+            .collect();
+        if !rejected.is_empty() {
+            return Err(self.error(format!(
+                "{} of {} hunk(s) rejected; {} was not changed.\n{}",
+                rejected.len(),
+                outcomes.len(),
+                args.path,
+                rejected.join("\n\n")
+            )));
+        }
+
+        write_atomically(&path, &patched).await.map_err(BedrockError::IoError)?;
+        debug!("Applied {} hunk(s) to {:?}", outcomes.len(), path);
+
+        let hunks: Vec<Value> = outcomes
+            .iter()
+            .enumerate()
+            .map(|(i, outcome)| json!({"hunk": i + 1, "line": outcome.line}))
+            .collect();
+        Ok(json!({
+            "success": true,
+            "path": path.to_string_lossy(),
+            "hunks_applied": hunks,
+            "size": patched.len()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const ORIGINAL: &str = "fn main() {\n    let x = 1;\n    println!(\"{x}\");\n}\n\nfn helper() -> u32 {\n    41\n}\n";
+
+    fn workspace_with(content: &str) -> (TempDir, ApplyPatchTool) {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join("main.rs"), content).unwrap();
+        let tool = ApplyPatchTool::new(workspace.path());
+        (workspace, tool)
+    }
+
+    #[tokio::test]
+    async fn test_unified_diff_hunks_are_applied() {
+        let (workspace, tool) = workspace_with(ORIGINAL);
+        let patch = "--- a/main.rs\n+++ b/main.rs\n\
+                     @@ -1,3 +1,4 @@\n fn main() {\n-    let x = 1;\n+    let x = 2;\n+    let y = x * 2;\n     println!(\"{x}\");\n\
+                     @@ -6,3 +7,3 @@\n fn helper() -> u32 {\n-    41\n+    42\n }\n";
+
+        let result = tool.execute(json!({"path": "main.rs", "patch": patch})).await.unwrap();
+
+        assert_eq!(result["hunks_applied"], json!([{"hunk": 1, "line": 1}, {"hunk": 2, "line": 7}]));
+        let patched = std::fs::read_to_string(workspace.path().join("main.rs")).unwrap();
+        assert_eq!(
+            patched,
+            "fn main() {\n    let x = 2;\n    let y = x * 2;\n    println!(\"{x}\");\n}\n\nfn helper() -> u32 {\n    42\n}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_hunk_leaves_file_unchanged() {
+        let (workspace, tool) = workspace_with(ORIGINAL);
+        let patch = "@@ -1,2 +1,2 @@\n fn main() {\n-    let x = 1;\n+    let x = 3;\n\
+                     @@ -7,1 +7,1 @@\n-    40\n+    42\n";
+
+        let err = tool.execute(json!({"path": "main.rs", "patch": patch})).await.unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains("1 of 2 hunk(s) rejected"), "{message}");
+        assert!(message.contains("Hunk 2"), "{message}");
+        assert!(message.contains("    7 |     41"), "{message}");
+        assert_eq!(std::fs::read_to_string(workspace.path().join("main.rs")).unwrap(), ORIGINAL);
+    }
+
+    #[tokio::test]
+    async fn test_search_replace_edits() {
+        let (workspace, tool) = workspace_with(ORIGINAL);
+
+        let result = tool
+            .execute(json!({"path": "main.rs", "edits": [{"search": "    41", "replace": "    42"}]}))
+            .await
+            .unwrap();
+        assert_eq!(result["hunks_applied"][0]["line"], 7);
+        assert!(std::fs::read_to_string(workspace.path().join("main.rs")).unwrap().contains("    42\n"));
+
+        // Ambiguous search text is rejected, and the other edit isn't applied either
+        let err = tool
+            .execute(json!({"path": "main.rs", "edits": [
+                {"search": "let x = 1", "replace": "let x = 5"},
+                {"search": "fn ", "replace": "pub fn "}
+            ]}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("occurs 2 times (lines 1, 6)"), "{err}");
+        assert!(std::fs::read_to_string(workspace.path().join("main.rs")).unwrap().contains("let x = 1"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_write_never_goes_through_an_existing_temp_file() {
+        let (workspace, tool) = workspace_with(ORIGINAL);
+        let outside = TempDir::new().unwrap();
+        let target = outside.path().join("target.txt");
+        std::fs::write(&target, "untouched").unwrap();
+        std::os::unix::fs::symlink(&target, workspace.path().join(".main.rs.apply_patch.tmp")).unwrap();
+
+        tool.execute(json!({"path": "main.rs", "edits": [{"search": "    41", "replace": "    42"}]}))
+            .await
+            .unwrap();
+
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "untouched");
+        assert!(std::fs::read_to_string(workspace.path().join("main.rs")).unwrap().contains("    42\n"));
+        let leftovers = std::fs::read_dir(workspace.path()).unwrap().count();
+        assert_eq!(leftovers, 2, "only main.rs and the planted link remain");
+    }
+
+    #[tokio::test]
+    async fn test_paths_outside_workspace_rejected() {
+        let (workspace, _) = workspace_with(ORIGINAL);
+        let inner = workspace.path().join("inner");
+        std::fs::create_dir(&inner).unwrap();
+        let tool = ApplyPatchTool::new(&inner);
+
+        let err = tool
+            .execute(json!({"path": "../main.rs", "edits": [{"search": "41", "replace": "42"}]}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("outside workspace"), "{err}");
+    }
+}
@@ -0,0 +1,283 @@
+use async_trait::async_trait;
+use bedrock_core::{BedrockError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::Tool;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64URL_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Encoding {
+    Base64,
+    Base64url,
+    Hex,
+    Url,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Operation {
+    Encode,
+    Decode,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct EncodingArgs {
+    encoding: Encoding,
+    operation: Operation,
+    input: String,
+}
+
+/// Encode and decode strings as base64, base64url, hex or URL percent-encoding
+#[derive(Debug, Clone, Default)]
+pub struct EncodingTool;
+
+impl EncodingTool {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn error(&self, message: impl Into<String>) -> BedrockError {
+        BedrockError::ToolError {
+            tool: self.name().to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn transform(&self, args: &EncodingArgs) -> Result<String> {
+        let input = args.input.as_bytes();
+        match (args.encoding, args.operation) {
+            (Encoding::Base64, Operation::Encode) => Ok(base64_encode(input, BASE64_ALPHABET, true)),
+            (Encoding::Base64url, Operation::Encode) => Ok(base64_encode(input, BASE64URL_ALPHABET, false)),
+            (Encoding::Hex, Operation::Encode) => Ok(input.iter().map(|b| format!("{b:02x}")).collect()),
+            (Encoding::Url, Operation::Encode) => Ok(url_encode(input)),
+            (encoding, Operation::Decode) => {
+                let bytes = match encoding {
+                    Encoding::Base64 => base64_decode(&args.input, BASE64_ALPHABET, "base64"),
+                    Encoding::Base64url => base64_decode(&args.input, BASE64URL_ALPHABET, "base64url"),
+                    Encoding::Hex => hex_decode(&args.input),
+                    Encoding::Url => url_decode(&args.input),
+                }
+                .map_err(|e| self.error(e))?;
+
+                String::from_utf8(bytes).map_err(|_| {
+                    self.error("Decoded bytes are not valid UTF-8 text; decode to hex instead to inspect binary data")
+                })
+            }
+        }
+    }
+}
+
+fn base64_encode(input: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(alphabet[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else if pad {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(input: &str, alphabet: &[u8; 64], name: &str) -> std::result::Result<Vec<u8>, String> {
+    // Line breaks and padding carry no data; padding is optional
+    let data: Vec<(usize, u8)> = input
+        .bytes()
+        .enumerate()
+        .filter(|(_, b)| !b.is_ascii_whitespace())
+        .collect();
+    let padding = data.iter().rev().take_while(|(_, b)| *b == b'=').count();
+    let data = &data[..data.len() - padding];
+
+    if padding > 2 || (padding > 0 && !(data.len() + padding).is_multiple_of(4)) {
+        return Err(format!("Invalid {name} input: incorrect padding"));
+    }
+    if data.len() % 4 == 1 {
+        return Err(format!("Invalid {name} input: length {} is not a valid {name} length", data.len()));
+    }
+
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &(position, byte) in data {
+        let value = alphabet.iter().position(|&c| c == byte).ok_or_else(|| {
+            format!("Invalid {name} input: unexpected character '{}' at position {position}", byte as char)
+        })?;
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(out)
+}
+
+fn hex_decode(input: &str) -> std::result::Result<Vec<u8>, String> {
+    let input = input.trim();
+    if !input.len().is_multiple_of(2) {
+        return Err(format!("Invalid hex input: odd number of digits ({})", input.len()));
+    }
+    input
+        .as_bytes()
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| {
+                    format!("Invalid hex input: '{}' at position {} is not a hex byte", String::from_utf8_lossy(pair), i * 2)
+                })
+        })
+        .collect()
+}
+
+fn url_encode(input: &[u8]) -> String {
+    let mut out = String::with_capacity(input.len());
+    for &byte in input {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+fn url_decode(input: &str) -> std::result::Result<Vec<u8>, String> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+            let byte = hex
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!("Invalid url input: malformed escape at position {i}"))?;
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+#[async_trait]
+impl Tool for EncodingTool {
+    fn name(&self) -> &str {
+        "encode"
+    }
+
+    fn description(&self) -> &str {
+        "Encode or decode text as base64, base64url, hex or URL percent-encoding"
+    }
+
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "encoding": {
+                    "type": "string",
+                    "enum": ["base64", "base64url", "hex", "url"],
+                    "description": "Encoding to use"
+                },
+                "operation": {
+                    "type": "string",
+                    "enum": ["encode", "decode"],
+                    "description": "Whether to encode the input or decode it"
+                },
+                "input": {
+                    "type": "string",
+                    "description": "Text to encode, or encoded data to decode"
+                }
+            },
+            "required": ["encoding", "operation", "input"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<Value> {
+        let args: EncodingArgs = serde_json::from_value(args)
+            .map_err(|e| self.error(format!("Invalid arguments: {e}")))?;
+
+        let output = self.transform(&args)?;
+
+        Ok(json!({
+            "encoding": args.encoding,
+            "operation": args.operation,
+            "output": output
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn run(encoding: &str, operation: &str, input: &str) -> Result<String> {
+        let result = EncodingTool::new()
+            .execute(json!({"encoding": encoding, "operation": operation, "input": input}))
+            .await?;
+        Ok(result["output"].as_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_round_trips() {
+        let samples = ["", "f", "fo", "foo", "foob", "hello world?&=/", "naïve ✓ ~>"];
+        for encoding in ["base64", "base64url", "hex", "url"] {
+            for sample in samples {
+                let encoded = run(encoding, "encode", sample).await.unwrap();
+                let decoded = run(encoding, "decode", &encoded).await.unwrap();
+                assert_eq!(decoded, sample, "{encoding} round trip of {sample:?} via {encoded:?}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_known_encodings() {
+        assert_eq!(run("base64", "encode", "foob").await.unwrap(), "Zm9vYg==");
+        assert_eq!(run("base64url", "encode", "~~~?").await.unwrap(), "fn5-Pw");
+        assert_eq!(run("hex", "encode", "Hi").await.unwrap(), "4869");
+        assert_eq!(run("url", "encode", "a b&c").await.unwrap(), "a%20b%26c");
+        assert_eq!(run("base64", "decode", "Zm9v\nYg").await.unwrap(), "foob");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_inputs_are_descriptive() {
+        let cases = [
+            ("base64", "Zm9v!mFy", "unexpected character '!' at position 4"),
+            ("base64", "Zm9vY", "not a valid base64 length"),
+            ("base64", "Zg===", "incorrect padding"),
+            ("base64url", "Zm9v+g", "unexpected character '+'"),
+            ("hex", "abc", "odd number of digits"),
+            ("hex", "zz", "'zz' at position 0 is not a hex byte"),
+            ("url", "100%", "malformed escape at position 3"),
+            ("hex", "ff", "not valid UTF-8"),
+        ];
+        for (encoding, input, expected) in cases {
+            match run(encoding, "decode", input).await {
+                Err(BedrockError::ToolError { tool, message }) => {
+                    assert_eq!(tool, "encode");
+                    assert!(message.contains(expected), "{encoding} {input:?}: {message}");
+                }
+                other => panic!("{encoding} {input:?} should fail, got {other:?}"),
+            }
+        }
+
+        assert!(EncodingTool::new()
+            .execute(json!({"encoding": "rot13", "operation": "encode", "input": "x"}))
+            .await
+            .is_err());
+    }
+}
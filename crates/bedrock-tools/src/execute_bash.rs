@@ -9,6 +9,81 @@ use tracing::{debug, warn};
 use super::Tool;
 use crate::security::CommandValidator;
 
+/// Output of a finished process, each stream cut to the size limit
+pub(crate) struct ProcessOutput {
+    pub success: bool,
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration: Duration,
+}
+
+/// Run `cmd` with piped output and no stdin, giving up after `timeout`.
+///
+/// Errors are messages for the model: the process couldn't start or timed out.
+pub(crate) async fn run_process(
+    mut cmd: Command,
+    timeout: Duration,
+    max_output_size: usize,
+) -> std::result::Result<ProcessOutput, String> {
+    cmd.stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdin(Stdio::null());
+
+    let start_time = std::time::Instant::now();
+    let output = match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(format!("Command execution failed: {}", e)),
+        Err(_) => return Err(format!("Command timed out after {} seconds", timeout.as_secs())),
+    };
+    let duration = start_time.elapsed();
+
+    // Truncate output if too large
+    let truncate = |bytes: &[u8]| {
+        let text = String::from_utf8_lossy(bytes);
+        if text.len() > max_output_size {
+            // Cut at a character boundary; the limit may land inside a multi-byte character
+            let mut end = max_output_size;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            format!("{}... [output truncated]", &text[..end])
+        } else {
+            text.to_string()
+        }
+    };
+
+    Ok(ProcessOutput {
+        success: output.status.success(),
+        exit_code: output.status.code().unwrap_or(-1),
+        stdout: truncate(&output.stdout),
+        stderr: truncate(&output.stderr),
+        duration,
+    })
+}
+
+/// Characters that only mean something to a shell
+const SHELL_METACHARACTERS: &[char] = &['|', '>', '<', '&', ';', '$', '`', '"', '\'', '*'];
+
+/// A command as the model asked for it to be run
+struct Invocation<'a> {
+    command: &'a str,
+    /// Passed to the program as-is, one argv entry each
+    args: Vec<String>,
+    /// Run `command` through the shell instead of executing it directly
+    shell: bool,
+}
+
+impl Invocation<'_> {
+    /// The whole command on one line, for validation and logging
+    fn command_line(&self) -> String {
+        std::iter::once(self.command.to_string())
+            .chain(self.args.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
 pub struct ExecuteBashTool {
     workspace_dir: std::path::PathBuf,
     timeout_seconds: u64,
@@ -36,9 +111,10 @@ impl ExecuteBashTool {
         self
     }
 
-    async fn execute_command(&self, command: &str, working_dir: Option<&str>) -> Result<Value> {
+    async fn execute_command(&self, invocation: &Invocation<'_>, working_dir: Option<&str>) -> Result<Value> {
+        let command = invocation.command;
         // Validate command before execution
-        if let Err(e) = self.validator.validate(command) {
+        if let Err(e) = self.validator.validate(&invocation.command_line()) {
             return Ok(json!({
                 "success": false,
                 "error": format!("Command validation failed: {}", e),
@@ -46,48 +122,34 @@ impl ExecuteBashTool {
             }));
         }
         
-        debug!("Executing command: {}", command);
-        
-        // Parse the command line into command and arguments
-        // This is a simple implementation - for production, use a proper shell parser
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        if parts.is_empty() {
-            return Ok(json!({
-                "success": false,
-                "error": "Empty command",
-                "command": command
-            }));
-        }
-        
-        let cmd_name = parts[0];
-        let args = &parts[1..];
-        
-        // For complex commands with pipes, redirections etc., we need to use shell
-        // Check if the command contains shell metacharacters
-        let needs_shell = command.contains('|') || 
-                         command.contains('>') || 
-                         command.contains('<') ||
-                         command.contains('&') ||
-                         command.contains(';') ||
-                         command.contains('$') ||
-                         command.contains('`') ||
-                         command.contains('"') ||
-                         command.contains('\'');
+        debug!("Executing command: {}", invocation.command_line());
         
         // Create the command
-        let mut cmd = if cfg!(target_os = "windows") {
-            let mut cmd = Command::new("cmd");
-            cmd.args(["/C", command]);
-            cmd
-        } else if needs_shell {
-            // Use shell for complex commands
-            let mut cmd = Command::new("/bin/sh");
-            cmd.args(["-c", command]);
+        let mut cmd = if invocation.shell {
+            let (shell, flag) = if cfg!(target_os = "windows") { ("cmd", "/C") } else { ("/bin/sh", "-c") };
+            let mut cmd = Command::new(shell);
+            cmd.args([flag, command]);
             cmd
         } else {
-            // Execute directly without shell for simple commands
-            let mut cmd = Command::new(cmd_name);
-            cmd.args(args);
+            // Shell syntax in `command` would be passed along literally; make the model choose
+            if command.contains(SHELL_METACHARACTERS) {
+                return Ok(json!({
+                    "success": false,
+                    "error": "Command uses shell syntax; pass each argument in 'args', or set 'shell': true to run it through the shell",
+                    "command": command
+                }));
+            }
+            // The program and any words after it, then `args` exactly as given
+            let mut words = command.split_whitespace();
+            let Some(program) = words.next() else {
+                return Ok(json!({
+                    "success": false,
+                    "error": "Empty command",
+                    "command": command
+                }));
+            };
+            let mut cmd = Command::new(program);
+            cmd.args(words).args(&invocation.args);
             cmd
         };
 
@@ -108,67 +170,37 @@ impl ExecuteBashTool {
             cmd.current_dir(&work_dir);
         }
 
-        // Configure command
-        cmd.stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .stdin(Stdio::null());
-
-        // Execute with timeout
-        let start_time = std::time::Instant::now();
-        let timeout = Duration::from_secs(self.timeout_seconds);
-
-        let output = match tokio::time::timeout(timeout, cmd.output()).await {
-            Ok(Ok(output)) => output,
-            Ok(Err(e)) => {
+        let output = match run_process(cmd, Duration::from_secs(self.timeout_seconds), self.max_output_size).await {
+            Ok(output) => output,
+            Err(error) => {
                 return Ok(json!({
                     "success": false,
-                    "error": format!("Command execution failed: {}", e),
+                    "error": error,
                     "command": command
                 }));
             }
-            Err(_) => {
-                return Ok(json!({
-                    "success": false,
-                    "error": format!("Command timed out after {} seconds", self.timeout_seconds),
-                    "command": command
-                }));
-            }
-        };
-
-        let duration = start_time.elapsed();
-
-        // Convert output to strings
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        // Truncate output if too large
-        let stdout = if stdout.len() > self.max_output_size {
-            format!("{}... [output truncated]", &stdout[..self.max_output_size])
-        } else {
-            stdout.to_string()
-        };
-
-        let stderr = if stderr.len() > self.max_output_size {
-            format!("{}... [output truncated]", &stderr[..self.max_output_size])
-        } else {
-            stderr.to_string()
         };
 
         debug!(
             "Command completed: exit_code={}, duration={:?}",
-            output.status.code().unwrap_or(-1),
-            duration
+            output.exit_code,
+            output.duration
         );
 
-        Ok(json!({
-            "success": output.status.success(),
-            "exit_code": output.status.code().unwrap_or(-1),
-            "stdout": stdout,
-            "stderr": stderr,
-            "duration_ms": duration.as_millis(),
+        let mut result = json!({
+            "success": output.success,
+            "exit_code": output.exit_code,
+            "stdout": output.stdout,
+            "stderr": output.stderr,
+            "duration_ms": output.duration.as_millis(),
             "command": command,
+            "shell": invocation.shell,
             "working_directory": work_dir.to_string_lossy()
-        }))
+        });
+        if !invocation.args.is_empty() {
+            result["args"] = json!(invocation.args);
+        }
+        Ok(result)
     }
 }
 
@@ -184,9 +216,11 @@ impl Tool for ExecuteBashTool {
 
     fn description(&self) -> &str {
         if cfg!(target_os = "windows") {
-            "Execute Windows command prompt commands. Supports common system commands and utilities."
+            "Execute Windows commands. The program runs directly with 'args' as its arguments; \
+             set 'shell' to true to run the command line through the command prompt."
         } else {
-            "Execute bash shell commands. Supports common Unix/Linux commands and utilities."
+            "Execute Unix/Linux commands. The program runs directly with 'args' as its arguments, \
+             so nothing needs quoting; set 'shell' to true for pipes, redirection or other shell syntax."
         }
     }
 
@@ -196,10 +230,19 @@ impl Tool for ExecuteBashTool {
             "properties": {
                 "command": {
                     "type": "string",
+                    "description": "The program to run, optionally followed by plain arguments; the whole command line when 'shell' is true"
+                },
+                "args": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Arguments passed to the program exactly as given, without quoting or expansion"
+                },
+                "shell": {
+                    "type": "boolean",
                     "description": if cfg!(target_os = "windows") {
-                        "The Windows command to execute"
+                        "Run 'command' through cmd.exe (default false)"
                     } else {
-                        "The bash command to execute"
+                        "Run 'command' through /bin/sh for pipes, redirection and expansion (default false)"
                     }
                 },
                 "working_directory": {
@@ -224,6 +267,32 @@ impl Tool for ExecuteBashTool {
             .get("working_directory")
             .and_then(|v| v.as_str());
 
+        let shell = args.get("shell").and_then(|v| v.as_bool()).unwrap_or(false);
+        let argv = match args.get("args") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|item| item.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| bedrock_core::BedrockError::ToolError {
+                    tool: self.name().to_string(),
+                    message: "'args' must be an array of strings".to_string(),
+                })?,
+            Some(_) => {
+                return Err(bedrock_core::BedrockError::ToolError {
+                    tool: self.name().to_string(),
+                    message: "'args' must be an array of strings".to_string(),
+                })
+            }
+        };
+        if shell && !argv.is_empty() {
+            return Err(bedrock_core::BedrockError::ToolError {
+                tool: self.name().to_string(),
+                message: "'args' can't be used with 'shell'; put the whole command line in 'command'".to_string(),
+            });
+        }
+        let invocation = Invocation { command, args: argv, shell };
+
         // Validate command is not empty
         if command.trim().is_empty() {
             return Ok(json!({
@@ -232,7 +301,7 @@ impl Tool for ExecuteBashTool {
             }));
         }
 
-        match self.execute_command(command, working_dir).await {
+        match self.execute_command(&invocation, working_dir).await {
             Ok(result) => {
                 debug!("Command executed successfully: {:?}", result);
                 Ok(result)
@@ -247,4 +316,61 @@ impl Tool for ExecuteBashTool {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_args_are_passed_literally() {
+        let workspace = TempDir::new().unwrap();
+        let tool = ExecuteBashTool::new(workspace.path());
+        let literal = "a b; touch injected $(touch injected) `touch injected` > out";
+
+        let result = tool
+            .execute(json!({"command": "printf", "args": ["%s", literal]}))
+            .await
+            .unwrap();
+
+        assert_eq!(result["success"], true, "{result}");
+        assert_eq!(result["stdout"], literal);
+        assert_eq!(result["shell"], false);
+        assert!(!workspace.path().join("injected").exists());
+        assert!(!workspace.path().join("out").exists());
+    }
+
+    #[tokio::test]
+    async fn test_output_truncated_at_char_boundary() {
+        let mut cmd = Command::new("printf");
+        cmd.arg("aé");
+
+        // Three bytes of output, limited to two: the cut would land inside 'é'
+        let output = run_process(cmd, Duration::from_secs(5), 2).await.unwrap();
+        assert_eq!(output.stdout, "a... [output truncated]");
+    }
+
+    #[tokio::test]
+    async fn test_shell_syntax_needs_shell_mode() {
+        let workspace = TempDir::new().unwrap();
+        let tool = ExecuteBashTool::new(workspace.path());
+
+        let refused = tool.execute(json!({"command": "echo hi | tr a-z A-Z"})).await.unwrap();
+        assert_eq!(refused["success"], false);
+        assert!(refused["error"].as_str().unwrap().contains("'shell': true"));
+
+        let piped = tool
+            .execute(json!({"command": "echo hi | tr a-z A-Z", "shell": true}))
+            .await
+            .unwrap();
+        assert_eq!(piped["stdout"], "HI\n");
+
+        // Plain words after the program still work without `args`
+        let plain = tool.execute(json!({"command": "echo one two"})).await.unwrap();
+        assert_eq!(plain["stdout"], "one two\n");
+
+        let mixed = tool.execute(json!({"command": "echo", "args": ["x"], "shell": true})).await;
+        assert!(mixed.is_err());
+    }
+}
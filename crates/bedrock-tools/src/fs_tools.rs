@@ -2,11 +2,81 @@ use async_trait::async_trait;
 use bedrock_core::{BedrockError, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use tracing::debug;
 
 use crate::Tool;
 
+/// Resolve a model-supplied path against the workspace root.
+///
+/// `.` and `..` are collapsed first, then the deepest existing ancestor is
+/// canonicalized so symlinks can't point outside the workspace. Paths inside a
+/// `.git` directory are refused too, since writing hooks or config there runs
+/// code on the next git command. The returned path is the one tools must use
+/// for file access.
+pub(crate) fn resolve_workspace_path(tool: &str, workspace_dir: &Path, path: &Path) -> Result<PathBuf> {
+    let workspace = workspace_dir
+        .canonicalize()
+        .unwrap_or_else(|_| normalize(workspace_dir));
+
+    let requested = normalize(&workspace.join(path));
+
+    // Canonicalize the part that exists; whatever is left doesn't exist yet
+    // and so can't be a symlink. A dangling symlink fails to canonicalize like
+    // a missing path, but writing through it would create its target.
+    let mut existing = requested.as_path();
+    let mut remainder = Vec::new();
+    let resolved = loop {
+        match existing.canonicalize() {
+            Ok(canonical) => break canonical,
+            Err(_) if existing.symlink_metadata().is_ok() => {
+                return Err(BedrockError::ToolError {
+                    tool: tool.to_string(),
+                    message: format!("Path goes through a dangling symlink: {}", path.display()),
+                });
+            }
+            Err(_) => match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    remainder.push(name.to_os_string());
+                    existing = parent;
+                }
+                _ => break existing.to_path_buf(),
+            },
+        }
+    };
+    let resolved = remainder.into_iter().rev().fold(resolved, |acc, part| acc.join(part));
+
+    let Ok(relative) = resolved.strip_prefix(&workspace) else {
+        return Err(BedrockError::ToolError {
+            tool: tool.to_string(),
+            message: format!("Path outside workspace: {}", path.display()),
+        });
+    };
+    if relative.components().any(|c| c.as_os_str().eq_ignore_ascii_case(".git")) {
+        return Err(BedrockError::ToolError {
+            tool: tool.to_string(),
+            message: format!("Path inside a .git directory: {}", path.display()),
+        });
+    }
+
+    Ok(resolved)
+}
+
+/// Lexically collapse `.` and `..` components without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
 #[derive(Debug, Clone)]
 pub struct FileReadTool {
     workspace_dir: PathBuf,
@@ -22,29 +92,7 @@ impl FileReadTool {
     }
 
     fn validate_path(&self, path: &Path) -> Result<PathBuf> {
-        let absolute_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            self.workspace_dir.join(path)
-        };
-
-        // Try to canonicalize, but if it fails (file doesn't exist yet), 
-        // just use the absolute path
-        let canonical = absolute_path.canonicalize()
-            .unwrap_or_else(|_| absolute_path.clone());
-
-        // Ensure workspace_dir is also canonical for comparison
-        let workspace_canonical = self.workspace_dir.canonicalize()
-            .unwrap_or_else(|_| self.workspace_dir.clone());
-
-        if !canonical.starts_with(&workspace_canonical) {
-            return Err(BedrockError::ToolError {
-                tool: "fs_read".to_string(),
-                message: format!("Path outside workspace: {canonical:?}"),
-            });
-        }
-
-        Ok(canonical)
+        resolve_workspace_path("fs_read", &self.workspace_dir, path)
     }
 }
 
@@ -123,30 +171,7 @@ impl FileWriteTool {
     }
 
     fn validate_path(&self, path: &Path) -> Result<PathBuf> {
-        let absolute_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            self.workspace_dir.join(path)
-        };
-
-        // Ensure workspace_dir is canonical for comparison
-        let workspace_canonical = self.workspace_dir.canonicalize()
-            .unwrap_or_else(|_| self.workspace_dir.clone());
-
-        // For write, we need to check the parent directory
-        if let Some(parent) = absolute_path.parent() {
-            let parent_canonical = parent.canonicalize()
-                .unwrap_or_else(|_| parent.to_path_buf());
-            
-            if !parent_canonical.starts_with(&workspace_canonical) && !absolute_path.starts_with(&workspace_canonical) {
-                return Err(BedrockError::ToolError {
-                    tool: "fs_write".to_string(),
-                    message: format!("Path outside workspace: {absolute_path:?}"),
-                });
-            }
-        }
-
-        Ok(absolute_path)
+        resolve_workspace_path("fs_write", &self.workspace_dir, path)
     }
 }
 
@@ -251,27 +276,7 @@ impl FileListTool {
     }
 
     fn validate_path(&self, path: &Path) -> Result<PathBuf> {
-        let absolute_path = if path.is_absolute() {
-            path.to_path_buf()
-        } else {
-            self.workspace_dir.join(path)
-        };
-
-        let canonical = absolute_path.canonicalize()
-            .unwrap_or(absolute_path.clone());
-
-        // Ensure workspace_dir is also canonical for comparison
-        let workspace_canonical = self.workspace_dir.canonicalize()
-            .unwrap_or_else(|_| self.workspace_dir.clone());
-
-        if !canonical.starts_with(&workspace_canonical) {
-            return Err(BedrockError::ToolError {
-                tool: "fs_list".to_string(),
-                message: format!("Path outside workspace: {canonical:?}"),
-            });
-        }
-
-        Ok(canonical)
+        resolve_workspace_path("fs_list", &self.workspace_dir, path)
     }
 }
 
@@ -391,4 +396,105 @@ mod tests {
         let entries = result["entries"].as_array().unwrap();
         assert_eq!(entries.len(), 3);
     }
+
+    fn assert_outside_workspace(result: Result<Value>) {
+        match result {
+            Err(BedrockError::ToolError { message, .. }) => {
+                assert!(message.contains("outside workspace"), "unexpected message: {message}")
+            }
+            other => panic!("expected path rejection, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parent_traversal_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let workspace = temp_dir.path().join("workspace");
+        std::fs::create_dir(&workspace).unwrap();
+        std::fs::write(temp_dir.path().join("secret.txt"), "secret").unwrap();
+
+        assert_outside_workspace(FileReadTool::new(&workspace).execute(json!({"path": "../secret.txt"})).await);
+        assert_outside_workspace(
+            FileWriteTool::new(&workspace)
+                .execute(json!({"path": "sub/../../escape.txt", "content": "x"}))
+                .await,
+        );
+        assert_outside_workspace(FileListTool::new(&workspace).execute(json!({"path": ".."})).await);
+        assert!(!temp_dir.path().join("escape.txt").exists());
+
+        // `..` that stays inside the workspace is fine
+        std::fs::write(workspace.join("inside.txt"), "ok").unwrap();
+        let result = FileReadTool::new(&workspace)
+            .execute(json!({"path": "missing/../inside.txt"}))
+            .await
+            .unwrap();
+        assert_eq!(result["content"], "ok");
+    }
+
+    #[tokio::test]
+    async fn test_absolute_path_outside_workspace_rejected() {
+        let workspace = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let secret = outside.path().join("secret.txt");
+        std::fs::write(&secret, "secret").unwrap();
+
+        assert_outside_workspace(
+            FileReadTool::new(workspace.path())
+                .execute(json!({"path": secret.to_string_lossy()}))
+                .await,
+        );
+        assert_outside_workspace(
+            FileWriteTool::new(workspace.path())
+                .execute(json!({"path": outside.path().join("new.txt").to_string_lossy(), "content": "x"}))
+                .await,
+        );
+
+        // Absolute paths inside the workspace are still accepted
+        std::fs::write(workspace.path().join("inside.txt"), "ok").unwrap();
+        let inside = workspace.path().join("inside.txt");
+        let result = FileReadTool::new(workspace.path())
+            .execute(json!({"path": inside.to_string_lossy()}))
+            .await
+            .unwrap();
+        assert_eq!(result["content"], "ok");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlink_escape_rejected() {
+        let workspace = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.txt"), workspace.path().join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), workspace.path().join("linkdir")).unwrap();
+
+        assert_outside_workspace(FileReadTool::new(workspace.path()).execute(json!({"path": "link.txt"})).await);
+        assert_outside_workspace(FileListTool::new(workspace.path()).execute(json!({"path": "linkdir"})).await);
+        assert_outside_workspace(
+            FileWriteTool::new(workspace.path())
+                .execute(json!({"path": "linkdir/new/file.txt", "content": "x"}))
+                .await,
+        );
+        assert!(!outside.path().join("new").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dangling_symlink_rejected() {
+        let workspace = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let target = outside.path().join("created.txt");
+        std::os::unix::fs::symlink(&target, workspace.path().join("dangling.txt")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("missing"), workspace.path().join("dangling_dir")).unwrap();
+
+        for path in ["dangling.txt", "dangling_dir/file.txt"] {
+            let err = FileWriteTool::new(workspace.path())
+                .execute(json!({"path": path, "content": "x"}))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("dangling symlink"), "unexpected error: {err}");
+        }
+        assert!(!target.exists());
+        assert!(!outside.path().join("missing").exists());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,439 @@
+use async_trait::async_trait;
+use bedrock_core::{BedrockError, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::execute_bash::run_process;
+use crate::fs_tools::resolve_workspace_path;
+use crate::Tool;
+
+/// Separates fields in the formats asked of `git log` and `git branch`
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+/// Passed before every command so repository config can't make git run other
+/// programs through an fsmonitor, hooks or a commit signer. An empty `diff.external`
+/// would be run as a command itself, so `diff` turns external and textconv drivers
+/// off with flags instead, and filter drivers are emptied per command.
+const SAFE_CONFIG: &[&str] = &[
+    "-c",
+    "core.fsmonitor=",
+    "-c",
+    "core.hooksPath=/dev/null",
+    "-c",
+    "commit.gpgSign=false",
+    "-c",
+    "tag.gpgSign=false",
+];
+
+/// Filter driver commands, which git would run on files as they're added or checked out
+const FILTER_COMMANDS: &[&str] = &["clean", "smudge", "process"];
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum GitOperation {
+    Status,
+    Diff,
+    Log,
+    Add,
+    Commit,
+    Branch,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitArgs {
+    operation: GitOperation,
+    #[serde(default)]
+    paths: Vec<String>,
+    message: Option<String>,
+    #[serde(default)]
+    staged: bool,
+    #[serde(default = "default_log_limit")]
+    limit: usize,
+    name: Option<String>,
+}
+
+fn default_log_limit() -> usize {
+    10
+}
+
+/// Inspect and commit changes in the workspace's git repository.
+///
+/// Paths are resolved like the file tools' and must stay inside the workspace
+/// and out of `.git`; without paths, `status`, `diff` and `log` cover the whole
+/// workspace. `commit` only records changes inside the workspace and runs no hooks.
+pub struct GitTool {
+    workspace_dir: PathBuf,
+    timeout_seconds: u64,
+    max_output_size: usize,
+}
+
+impl GitTool {
+    pub fn new(workspace_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            workspace_dir: workspace_dir.into(),
+            timeout_seconds: 30,
+            max_output_size: 1024 * 1024, // 1MB
+        }
+    }
+
+    pub fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout_seconds = seconds;
+        self
+    }
+
+    fn error(&self, message: impl Into<String>) -> BedrockError {
+        BedrockError::ToolError {
+            tool: self.name().to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Run git in the workspace and return its stdout, or its stderr as the error
+    async fn git<I, S>(&self, args: I) -> Result<String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<std::ffi::OsStr>,
+    {
+        let overrides = self.filter_overrides().await?;
+        let mut cmd = Command::new("git");
+        cmd.args(SAFE_CONFIG).args(args).current_dir(&self.workspace_dir);
+        cmd.env("GIT_CONFIG_COUNT", overrides.len().to_string());
+        for (i, key) in overrides.iter().enumerate() {
+            cmd.env(format!("GIT_CONFIG_KEY_{i}"), key).env(format!("GIT_CONFIG_VALUE_{i}"), "");
+        }
+        let output = run_process(cmd, Duration::from_secs(self.timeout_seconds), self.max_output_size)
+            .await
+            .map_err(|e| self.error(e))?;
+        if !output.success {
+            return Err(self.error(format!("git failed: {}", output.stderr.trim())));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Config keys that empty every configured filter driver's commands. Driver names
+    /// are only known from config, and `-c` can't take names containing `=`, so these
+    /// are passed through `GIT_CONFIG_KEY_<n>` instead.
+    async fn filter_overrides(&self) -> Result<Vec<String>> {
+        let mut cmd = Command::new("git");
+        cmd.args(SAFE_CONFIG)
+            .args(["config", "--name-only", "--get-regexp", r"^filter\."])
+            .current_dir(&self.workspace_dir);
+        let output = run_process(cmd, Duration::from_secs(self.timeout_seconds), self.max_output_size)
+            .await
+            .map_err(|e| self.error(e))?;
+        // Exits 1 when no filter is configured
+        if !output.success {
+            return Ok(Vec::new());
+        }
+
+        let mut drivers: Vec<&str> = output
+            .stdout
+            .lines()
+            .filter_map(|key| key.strip_prefix("filter.")?.rsplit_once('.').map(|(driver, _)| driver))
+            .collect();
+        drivers.sort_unstable();
+        drivers.dedup();
+        Ok(drivers
+            .into_iter()
+            .flat_map(|driver| FILTER_COMMANDS.iter().map(move |command| format!("filter.{driver}.{command}")))
+            .collect())
+    }
+
+    /// Pathspecs for the requested paths, or the workspace itself
+    fn pathspecs(&self, paths: &[String]) -> Result<Vec<PathBuf>> {
+        if paths.is_empty() {
+            return Ok(vec![PathBuf::from(".")]);
+        }
+        paths
+            .iter()
+            .map(|path| resolve_workspace_path(self.name(), &self.workspace_dir, Path::new(path)))
+            .collect()
+    }
+
+    async fn status(&self, pathspecs: &[PathBuf]) -> Result<Value> {
+        let output = self.git(with_pathspecs(["status", "--porcelain=v1", "--branch"], pathspecs)).await?;
+        let mut branch = None;
+        let mut files = Vec::new();
+        for line in output.lines() {
+            if let Some(header) = line.strip_prefix("## ") {
+                branch = Some(header.split("...").next().unwrap_or(header).to_string());
+            } else if line.len() > 3 {
+                files.push(json!({
+                    "path": &line[3..],
+                    "index": &line[..1],
+                    "worktree": &line[1..2],
+                }));
+            }
+        }
+        Ok(json!({"branch": branch, "clean": files.is_empty(), "files": files}))
+    }
+
+    async fn diff(&self, pathspecs: &[PathBuf], staged: bool) -> Result<Value> {
+        let mut args = vec!["diff", "--no-ext-diff", "--no-textconv"];
+        if staged {
+            args.push("--cached");
+        }
+        let diff = self.git(with_pathspecs(args, pathspecs)).await?;
+        Ok(json!({"staged": staged, "diff": diff}))
+    }
+
+    async fn log(&self, pathspecs: &[PathBuf], limit: usize) -> Result<Value> {
+        let count = format!("-n{}", limit.max(1));
+        let format = format!("--format=%H{0}%an{0}%aI{0}%s", FIELD_SEPARATOR);
+        let output = self.git(with_pathspecs(["log", count.as_str(), format.as_str()], pathspecs)).await?;
+        let commits: Vec<Value> = output
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, FIELD_SEPARATOR);
+                Some(json!({
+                    "hash": fields.next()?,
+                    "author": fields.next()?,
+                    "date": fields.next()?,
+                    "subject": fields.next()?,
+                }))
+            })
+            .collect();
+        Ok(json!({"commits": commits}))
+    }
+
+    async fn add(&self, paths: &[String]) -> Result<Value> {
+        if paths.is_empty() {
+            return Err(self.error("'add' needs the paths to stage"));
+        }
+        let pathspecs = self.pathspecs(paths)?;
+        self.git(with_pathspecs(["add"], &pathspecs)).await?;
+        Ok(json!({"added": paths}))
+    }
+
+    async fn commit(&self, message: Option<&str>) -> Result<Value> {
+        let message = message
+            .filter(|message| !message.trim().is_empty())
+            .ok_or_else(|| self.error("'commit' needs a message"))?;
+        self.git(["commit", "--no-verify", "-m", message, "--", "."]).await?;
+        let hash = self.git(["rev-parse", "HEAD"]).await?;
+        Ok(json!({"commit": hash.trim(), "message": message}))
+    }
+
+    async fn branch(&self, name: Option<&str>) -> Result<Value> {
+        if let Some(name) = name {
+            if name.starts_with('-') {
+                return Err(self.error(format!("Invalid branch name '{name}'")));
+            }
+            self.git(["branch", name]).await?;
+            return Ok(json!({"created": name}));
+        }
+
+        let format = format!("--format=%(HEAD){}%(refname:short)", FIELD_SEPARATOR);
+        let output = self.git(["branch", format.as_str()]).await?;
+        let mut current = None;
+        let mut branches = Vec::new();
+        for line in output.lines() {
+            let Some((head, branch)) = line.split_once(FIELD_SEPARATOR) else { continue };
+            if head == "*" {
+                current = Some(branch.to_string());
+            }
+            branches.push(branch.to_string());
+        }
+        Ok(json!({"current": current, "branches": branches}))
+    }
+}
+
+/// `args` followed by `--` and the pathspecs, so paths are never read as options
+fn with_pathspecs<'a>(args: impl IntoIterator<Item = &'a str>, pathspecs: &'a [PathBuf]) -> Vec<&'a std::ffi::OsStr> {
+    args.into_iter()
+        .chain(["--"])
+        .map(std::ffi::OsStr::new)
+        .chain(pathspecs.iter().map(|path| path.as_os_str()))
+        .collect()
+}
+
+#[async_trait]
+impl Tool for GitTool {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn description(&self) -> &str {
+        "Run git in the workspace repository: status, diff (staged or not), log, add, \
+         commit, and list or create branches. Returns structured JSON."
+    }
+
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "operation": {
+                    "type": "string",
+                    "enum": ["status", "diff", "log", "add", "commit", "branch"],
+                    "description": "The git operation to run"
+                },
+                "paths": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Workspace paths to limit status, diff and log to; required for add"
+                },
+                "message": {
+                    "type": "string",
+                    "description": "Commit message, required for commit; commits the workspace's changes to tracked and added files"
+                },
+                "staged": {
+                    "type": "boolean",
+                    "description": "For diff: show staged changes instead of unstaged ones"
+                },
+                "limit": {
+                    "type": "integer",
+                    "description": "For log: number of commits to return (default: 10)"
+                },
+                "name": {
+                    "type": "string",
+                    "description": "For branch: create a branch with this name instead of listing them"
+                }
+            },
+            "required": ["operation"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<Value> {
+        let args: GitArgs = serde_json::from_value(args)
+            .map_err(|e| self.error(format!("Invalid arguments: {e}")))?;
+        debug!("git {:?} in {}", args.operation, self.workspace_dir.display());
+
+        match args.operation {
+            GitOperation::Status => self.status(&self.pathspecs(&args.paths)?).await,
+            GitOperation::Diff => self.diff(&self.pathspecs(&args.paths)?, args.staged).await,
+            GitOperation::Log => self.log(&self.pathspecs(&args.paths)?, args.limit).await,
+            GitOperation::Add => self.add(&args.paths).await,
+            GitOperation::Commit => self.commit(args.message.as_deref()).await,
+            GitOperation::Branch => self.branch(args.name.as_deref()).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn init_repo() -> TempDir {
+        let repo = TempDir::new().unwrap();
+        for args in [
+            vec!["init", "-q", "-b", "main"],
+            vec!["config", "user.name", "Test"],
+            vec!["config", "user.email", "test@example.com"],
+        ] {
+            let status = std::process::Command::new("git").args(args).current_dir(repo.path()).status().unwrap();
+            assert!(status.success());
+        }
+        repo
+    }
+
+    #[tokio::test]
+    async fn test_add_commit_and_inspect() {
+        let repo = init_repo();
+        std::fs::write(repo.path().join("notes.md"), "first\n").unwrap();
+        let tool = GitTool::new(repo.path());
+
+        let status = tool.execute(json!({"operation": "status"})).await.unwrap();
+        assert_eq!(status["branch"], "No commits yet on main");
+        assert_eq!(status["files"][0]["path"], "notes.md");
+        assert_eq!(status["files"][0]["worktree"], "?");
+
+        tool.execute(json!({"operation": "add", "paths": ["notes.md"]})).await.unwrap();
+        let staged = tool.execute(json!({"operation": "diff", "staged": true})).await.unwrap();
+        assert!(staged["diff"].as_str().unwrap().contains("+first"));
+
+        let commit = tool.execute(json!({"operation": "commit", "message": "Add notes"})).await.unwrap();
+        assert_eq!(commit["commit"].as_str().unwrap().len(), 40);
+
+        let log = tool.execute(json!({"operation": "log"})).await.unwrap();
+        assert_eq!(log["commits"][0]["subject"], "Add notes");
+        assert_eq!(log["commits"][0]["author"], "Test");
+
+        std::fs::write(repo.path().join("notes.md"), "second\n").unwrap();
+        let diff = tool.execute(json!({"operation": "diff", "paths": ["notes.md"]})).await.unwrap();
+        assert!(diff["diff"].as_str().unwrap().contains("+second"));
+
+        tool.execute(json!({"operation": "branch", "name": "feature"})).await.unwrap();
+        let branches = tool.execute(json!({"operation": "branch"})).await.unwrap();
+        assert_eq!(branches["current"], "main");
+        assert_eq!(branches["branches"], json!(["feature", "main"]));
+    }
+
+    #[tokio::test]
+    async fn test_paths_outside_workspace_rejected() {
+        let repo = init_repo();
+        let workspace = repo.path().join("workspace");
+        std::fs::create_dir(&workspace).unwrap();
+        std::fs::write(repo.path().join("secret.txt"), "secret").unwrap();
+        let tool = GitTool::new(&workspace);
+
+        for args in [
+            json!({"operation": "add", "paths": ["../secret.txt"]}),
+            json!({"operation": "diff", "paths": ["/etc/passwd"]}),
+        ] {
+            let err = tool.execute(args).await.unwrap_err();
+            assert!(err.to_string().contains("outside workspace"), "unexpected error: {err}");
+        }
+
+        // Without paths, status only covers the workspace
+        let status = tool.execute(json!({"operation": "status"})).await.unwrap();
+        assert_eq!(status["clean"], true);
+
+        let err = tool.execute(json!({"operation": "commit"})).await.unwrap_err();
+        assert!(err.to_string().contains("needs a message"));
+
+        // Nothing under .git can be named
+        let err = GitTool::new(repo.path())
+            .execute(json!({"operation": "add", "paths": [".git/hooks/pre-commit"]}))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(".git directory"), "unexpected error: {err}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_commit_stays_in_workspace_and_runs_no_repo_programs() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo = init_repo();
+        let workspace = repo.path().join("workspace");
+        std::fs::create_dir(&workspace).unwrap();
+        std::fs::write(workspace.join("notes.md"), "notes\n").unwrap();
+        std::fs::write(repo.path().join("outside.txt"), "outside\n").unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git").args(args).current_dir(repo.path()).status().unwrap();
+            assert!(status.success());
+        };
+        git(&["add", "outside.txt"]);
+        // Diff would fail if the configured driver ran
+        git(&["config", "diff.external", "false"]);
+        // Signing would fail without a key, and the filter would leave a marker
+        git(&["config", "commit.gpgSign", "true"]);
+        let marker = repo.path().join("filtered");
+        git(&["config", "filter.mark.clean", &format!("touch {}; cat", marker.display())]);
+        git(&["config", "filter.mark.smudge", "cat"]);
+        std::fs::write(repo.path().join(".gitattributes"), "*.md filter=mark\n").unwrap();
+
+        // A failing pre-commit hook would block the commit if it ran
+        let hook = repo.path().join(".git/hooks/pre-commit");
+        std::fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let tool = GitTool::new(&workspace);
+        tool.execute(json!({"operation": "add", "paths": ["notes.md"]})).await.unwrap();
+        tool.execute(json!({"operation": "diff", "staged": true})).await.unwrap();
+        tool.execute(json!({"operation": "commit", "message": "Add notes"})).await.unwrap();
+
+        let committed = std::process::Command::new("git")
+            .args(["show", "--name-only", "--format=", "HEAD"])
+            .current_dir(repo.path())
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&committed.stdout).trim(), "workspace/notes.md");
+        assert!(!marker.exists());
+    }
+}
@@ -0,0 +1,261 @@
+use async_trait::async_trait;
+use bedrock_core::{BedrockError, Result};
+use reqwest::{Method, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::Tool;
+
+/// Response bytes returned to the model unless configured otherwise
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Redirects followed before giving up
+const MAX_REDIRECTS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+struct HttpRequestArgs {
+    #[serde(default = "default_method")]
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    body: Option<String>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+/// Hosts the tool may contact: exact names, or `*.example.com` for any subdomain
+#[derive(Debug, Clone, Default)]
+struct HostAllowlist(Vec<String>);
+
+impl HostAllowlist {
+    fn allows(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else { return false };
+        self.0.iter().any(|pattern| match pattern.strip_prefix("*.") {
+            Some(domain) => host.strip_suffix(domain).is_some_and(|sub| sub.ends_with('.')),
+            None => host == *pattern,
+        })
+    }
+}
+
+/// Send an HTTP request to one of the allowed hosts and return the response.
+///
+/// Redirects are only followed to allowed hosts, and the body is cut off after
+/// `max_body_bytes`.
+pub struct HttpRequestTool {
+    http: reqwest::Client,
+    allowed_hosts: Arc<HostAllowlist>,
+    max_body_bytes: usize,
+}
+
+impl HttpRequestTool {
+    pub fn new(allowed_hosts: impl IntoIterator<Item = impl Into<String>>) -> Result<Self> {
+        let allowed_hosts = Arc::new(HostAllowlist(
+            allowed_hosts.into_iter().map(|host| host.into().trim().to_ascii_lowercase()).collect(),
+        ));
+        let redirect_hosts = Arc::clone(&allowed_hosts);
+        let http = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if redirect_hosts.allows(attempt.url()) {
+                    attempt.follow()
+                } else {
+                    attempt.stop()
+                }
+            }))
+            .build()
+            .map_err(|e| BedrockError::ToolError {
+                tool: "http_request".to_string(),
+                message: format!("Failed to build HTTP client: {e}"),
+            })?;
+        Ok(Self { http, allowed_hosts, max_body_bytes: DEFAULT_MAX_BODY_BYTES })
+    }
+
+    /// Allowed hosts from a comma-separated constraint, e.g. `api.github.com, *.example.com`
+    pub fn from_constraint(constraint: Option<&str>) -> Result<Self> {
+        let hosts = constraint.unwrap_or_default().split(',').filter(|host| !host.trim().is_empty());
+        Self::new(hosts.map(str::to_string))
+    }
+
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    fn error(&self, message: impl Into<String>) -> BedrockError {
+        BedrockError::ToolError {
+            tool: self.name().to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn parse_url(&self, url: &str) -> Result<Url> {
+        let url = Url::parse(url).map_err(|e| self.error(format!("Invalid url '{url}': {e}")))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(self.error("url must start with http:// or https://"));
+        }
+        if !self.allowed_hosts.allows(&url) {
+            let host = url.host_str().unwrap_or_default();
+            return Err(if self.allowed_hosts.0.is_empty() {
+                self.error("No hosts are allowed; list them in the http_request permission's constraint")
+            } else {
+                self.error(format!("Host '{host}' is not in the allowed hosts: {}", self.allowed_hosts.0.join(", ")))
+            });
+        }
+        Ok(url)
+    }
+}
+
+#[async_trait]
+impl Tool for HttpRequestTool {
+    fn name(&self) -> &str {
+        "http_request"
+    }
+
+    fn description(&self) -> &str {
+        "Send an HTTP request to an allowed host. Returns the status code, response headers \
+         and body; long bodies are truncated."
+    }
+
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "method": {
+                    "type": "string",
+                    "description": "HTTP method (default: GET)"
+                },
+                "url": {
+                    "type": "string",
+                    "description": "HTTP(S) URL to request"
+                },
+                "headers": {
+                    "type": "object",
+                    "additionalProperties": {"type": "string"},
+                    "description": "Request headers"
+                },
+                "body": {
+                    "type": "string",
+                    "description": "Request body"
+                }
+            },
+            "required": ["url"]
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<Value> {
+        let args: HttpRequestArgs = serde_json::from_value(args)
+            .map_err(|e| self.error(format!("Invalid arguments: {e}")))?;
+        let url = self.parse_url(&args.url)?;
+        let method = Method::from_bytes(args.method.to_ascii_uppercase().as_bytes())
+            .map_err(|_| self.error(format!("Invalid method '{}'", args.method)))?;
+
+        debug!("{} {}", method, url);
+        let mut request = self.http.request(method, url.clone());
+        for (name, value) in &args.headers {
+            request = request.header(name, value);
+        }
+        if let Some(body) = args.body {
+            request = request.body(body);
+        }
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| self.error(format!("Request to {url} failed: {e}")))?;
+
+        let status = response.status().as_u16();
+        let headers: HashMap<String, String> = response
+            .headers()
+            .iter()
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+            .collect();
+
+        let mut body = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| self.error(format!("Failed to read response from {url}: {e}")))?
+        {
+            let room = self.max_body_bytes - body.len();
+            if chunk.len() > room {
+                body.extend_from_slice(&chunk[..room]);
+                truncated = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(json!({
+            "status": status,
+            "headers": headers,
+            "body": String::from_utf8_lossy(&body),
+            "truncated": truncated
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one request with `body` and return the server's address
+    async fn serve_once(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 4096];
+            let _ = socket.read(&mut request).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 201 Created\r\nContent-Type: text/plain\r\nX-Job: 42\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_request_returns_status_headers_and_capped_body() {
+        let base = serve_once("0123456789").await;
+        let tool = HttpRequestTool::from_constraint(Some("127.0.0.1")).unwrap().with_max_body_bytes(4);
+
+        let result = tool
+            .execute(json!({"method": "post", "url": format!("{base}/jobs"), "body": "{}"}))
+            .await
+            .unwrap();
+
+        assert_eq!(result["status"], 201);
+        assert_eq!(result["headers"]["x-job"], "42");
+        assert_eq!(result["body"], "0123");
+        assert_eq!(result["truncated"], true);
+    }
+
+    #[tokio::test]
+    async fn test_hosts_outside_allowlist_are_refused() {
+        let tool = HttpRequestTool::from_constraint(Some("api.example.com, *.internal.dev")).unwrap();
+
+        let err = tool.execute(json!({"url": "https://evil.test/steal"})).await.unwrap_err();
+        assert!(err.to_string().contains("not in the allowed hosts"));
+        assert!(tool.execute(json!({"url": "file:///etc/passwd"})).await.is_err());
+
+        let allowlist = &tool.allowed_hosts;
+        assert!(allowlist.allows(&Url::parse("https://API.example.com/v1").unwrap()));
+        assert!(allowlist.allows(&Url::parse("https://ci.internal.dev").unwrap()));
+        assert!(!allowlist.allows(&Url::parse("https://internal.dev").unwrap()));
+        assert!(!allowlist.allows(&Url::parse("https://notinternal.dev").unwrap()));
+
+        let closed = HttpRequestTool::from_constraint(None).unwrap();
+        let err = closed.execute(json!({"url": "https://api.example.com"})).await.unwrap_err();
+        assert!(err.to_string().contains("No hosts are allowed"));
+    }
+}
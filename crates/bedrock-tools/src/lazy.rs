@@ -0,0 +1,134 @@
+//! Lazily constructed tools
+//!
+//! Some tools are expensive to build (database connections, API authentication).
+//! A `LazyTool` advertises its name, description and schema up front, and only runs
+//! the factory once the tool is first fetched from the registry or its schema is read.
+//! Construction starts in the background then; execution waits for it to finish.
+
+use async_trait::async_trait;
+use bedrock_core::{BedrockError, Result};
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::Tool;
+
+type BoxedToolFuture = Pin<Box<dyn Future<Output = Result<Arc<dyn Tool>>> + Send>>;
+type ToolFactory = Box<dyn Fn() -> BoxedToolFuture + Send + Sync>;
+
+struct LazyInit {
+    name: String,
+    factory: ToolFactory,
+    instance: OnceCell<Arc<dyn Tool>>,
+}
+
+impl LazyInit {
+    async fn instance(&self) -> Result<Arc<dyn Tool>> {
+        let tool = self
+            .instance
+            .get_or_try_init(|| async {
+                debug!("Initializing lazy tool '{}'", self.name);
+                (self.factory)().await.map_err(|e| BedrockError::ToolError {
+                    tool: self.name.clone(),
+                    message: format!("Failed to initialize tool: {e}"),
+                })
+            })
+            .await?;
+        Ok(Arc::clone(tool))
+    }
+}
+
+pub struct LazyTool {
+    description: String,
+    schema: Value,
+    init: Arc<LazyInit>,
+    started: AtomicBool,
+}
+
+impl LazyTool {
+    pub fn new<F, Fut, T>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        schema: Value,
+        factory: F,
+    ) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T>> + Send + 'static,
+        T: Tool + 'static,
+    {
+        let factory: ToolFactory = Box::new(move || -> BoxedToolFuture {
+            let fut = factory();
+            Box::pin(async move { fut.await.map(|tool| Arc::new(tool) as Arc<dyn Tool>) })
+        });
+
+        Self {
+            description: description.into(),
+            schema,
+            init: Arc::new(LazyInit {
+                name: name.into(),
+                factory,
+                instance: OnceCell::new(),
+            }),
+            started: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the underlying tool has been constructed yet
+    pub fn is_initialized(&self) -> bool {
+        self.init.instance.initialized()
+    }
+
+    /// Get the underlying tool, constructing it on first use.
+    /// A failed construction is not cached, so the next call retries.
+    pub async fn instance(&self) -> Result<Arc<dyn Tool>> {
+        self.init.instance().await
+    }
+
+    /// Start constructing the tool in the background, once. Outside a runtime
+    /// construction is left to the first execution.
+    fn start_init(&self) {
+        if self.is_initialized() || self.started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            self.started.store(false, Ordering::SeqCst);
+            return;
+        };
+        let init = Arc::clone(&self.init);
+        runtime.spawn(async move {
+            // Errors surface when the tool is executed, which retries construction
+            if let Err(e) = init.instance().await {
+                debug!("Lazy tool '{}' failed to initialize: {e}", init.name);
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl Tool for LazyTool {
+    fn name(&self) -> &str {
+        &self.init.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn schema(&self) -> Value {
+        self.start_init();
+        self.schema.clone()
+    }
+
+    fn prepare(&self) {
+        self.start_init();
+    }
+
+    async fn execute(&self, args: Value) -> Result<Value> {
+        self.instance().await?.execute(args).await
+    }
+}
@@ -1,17 +1,44 @@
 use async_trait::async_trait;
-use bedrock_core::Result;
+use bedrock_core::{Result, ToolPolicy};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
 pub mod fs_tools;
+pub mod apply_patch;
 pub mod search_tools;
 pub mod execute_bash;
 pub mod security;
+pub mod lazy;
+pub mod encoding;
+pub mod poll;
+pub mod http_request;
+pub mod git;
 
 pub use fs_tools::{FileReadTool, FileWriteTool, FileListTool};
+pub use apply_patch::ApplyPatchTool;
 pub use search_tools::{GrepTool, FindTool, RipgrepTool};
 pub use execute_bash::ExecuteBashTool;
+pub use lazy::LazyTool;
+pub use encoding::EncodingTool;
+pub use poll::PollTool;
+pub use http_request::HttpRequestTool;
+pub use git::GitTool;
+
+tokio::task_local! {
+    static TOOL_POLICY: ToolPolicy;
+}
+
+/// Run a tool call for a task limited to `policy`, so tools that call other
+/// tools, like poll, stay within the same policy
+pub async fn with_tool_policy<F: std::future::Future>(policy: ToolPolicy, call: F) -> F::Output {
+    TOOL_POLICY.scope(policy, call).await
+}
+
+/// Policy of the task making the current tool call, when it gave one
+pub fn current_tool_policy() -> Option<ToolPolicy> {
+    TOOL_POLICY.try_with(ToolPolicy::clone).ok()
+}
 
 #[async_trait]
 pub trait Tool: Send + Sync {
@@ -19,6 +46,9 @@ pub trait Tool: Send + Sync {
     fn description(&self) -> &str;
     fn schema(&self) -> Value;
     async fn execute(&self, args: Value) -> Result<Value>;
+
+    /// Called when the registry hands the tool out; lazy tools start building here
+    fn prepare(&self) {}
 }
 
 pub struct ToolRegistry {
@@ -40,6 +70,7 @@ impl ToolRegistry {
         registry.register(FileReadTool::new(&workspace)).unwrap();
         registry.register(FileWriteTool::new(&workspace)).unwrap();
         registry.register(FileListTool::new(&workspace)).unwrap();
+        registry.register(ApplyPatchTool::new(&workspace)).unwrap();
         
         // Register search tools
         registry.register(GrepTool::new(&workspace)).unwrap();
@@ -49,6 +80,9 @@ impl ToolRegistry {
         // Register execution tools
         registry.register(ExecuteBashTool::new(&workspace)).unwrap();
         
+        // Register utility tools
+        registry.register(EncodingTool::new()).unwrap();
+        
         registry
     }
 
@@ -59,6 +93,27 @@ impl ToolRegistry {
         Ok(())
     }
 
+    /// Register a tool that is only constructed once it is first fetched with `get`
+    /// or its schema is read.
+    ///
+    /// The description and schema are needed up front so the tool can be listed
+    /// without paying its setup cost. The constructed instance is cached for
+    /// subsequent calls; construction errors surface as `ToolError` on execution.
+    pub fn register_factory<F, Fut, T>(
+        &self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        schema: Value,
+        factory: F,
+    ) -> Result<()>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<T>> + Send + 'static,
+        T: Tool + 'static,
+    {
+        self.register(LazyTool::new(name, description, schema, factory))
+    }
+
     pub fn unregister(&self, name: &str) -> Result<()> {
         let mut tools = self.tools.write().unwrap();
         tools.remove(name);
@@ -67,7 +122,9 @@ impl ToolRegistry {
 
     pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
         let tools = self.tools.read().unwrap();
-        tools.get(name).cloned()
+        let tool = tools.get(name).cloned()?;
+        tool.prepare();
+        Some(tool)
     }
 
     pub fn list(&self) -> Vec<String> {
@@ -79,6 +136,16 @@ impl ToolRegistry {
         let tools = self.tools.read().unwrap();
         tools.values().cloned().collect()
     }
+
+    /// Get the registered tools permitted by a policy
+    pub fn select(&self, policy: &ToolPolicy) -> Vec<Arc<dyn Tool>> {
+        let tools = self.tools.read().unwrap();
+        tools
+            .values()
+            .filter(|tool| policy.allows(tool.name()))
+            .cloned()
+            .collect()
+    }
 }
 
 impl Default for ToolRegistry {
@@ -99,8 +166,13 @@ pub struct PermissionPolicy {
     pub permission: Permission,
 }
 
+/// Callback used to confirm a tool marked `Ask`; receives the tool name and input
+pub type AskHandler = Arc<dyn Fn(&str, &Value) -> bool + Send + Sync>;
+
 pub struct PermissionManager {
     policies: HashMap<String, PermissionPolicy>,
+    default_permission: Permission,
+    ask_handler: Option<AskHandler>,
 }
 
 impl Default for PermissionManager {
@@ -113,9 +185,23 @@ impl PermissionManager {
     pub fn new() -> Self {
         Self {
             policies: HashMap::new(),
+            default_permission: Permission::Ask,
+            ask_handler: None,
         }
     }
 
+    /// Permission used for tools without an explicit policy
+    pub fn with_default(mut self, permission: Permission) -> Self {
+        self.default_permission = permission;
+        self
+    }
+
+    /// Install the handler that confirms `Ask` tools. Without one, `Ask` is treated as deny.
+    pub fn with_ask_handler(mut self, handler: AskHandler) -> Self {
+        self.ask_handler = Some(handler);
+        self
+    }
+
     pub fn add_policy(&mut self, policy: PermissionPolicy) {
         self.policies.insert(policy.tool_name.clone(), policy);
     }
@@ -124,7 +210,20 @@ impl PermissionManager {
         self.policies
             .get(tool_name)
             .map(|p| p.permission.clone())
-            .unwrap_or(Permission::Ask)
+            .unwrap_or_else(|| self.default_permission.clone())
+    }
+
+    /// Decide whether a tool call may run, asking for confirmation when required
+    pub fn authorize(&self, tool_name: &str, input: &Value) -> bool {
+        match self.check(tool_name) {
+            Permission::Allow => true,
+            Permission::Deny => false,
+            Permission::Ask => self
+                .ask_handler
+                .as_ref()
+                .map(|ask| ask(tool_name, input))
+                .unwrap_or(false),
+        }
     }
 }
 
@@ -174,6 +273,117 @@ mod tests {
         assert!(registry.get("test_tool").is_none());
     }
     
+    #[test]
+    fn test_permission_manager_authorize() {
+        let mut manager = PermissionManager::new()
+            .with_default(Permission::Allow)
+            .with_ask_handler(Arc::new(|tool, _| tool == "approved"));
+        for (tool_name, permission) in [
+            ("blocked", Permission::Deny),
+            ("approved", Permission::Ask),
+            ("rejected", Permission::Ask),
+        ] {
+            manager.add_policy(PermissionPolicy { tool_name: tool_name.to_string(), permission });
+        }
+
+        assert!(manager.authorize("anything", &json!({})));
+        assert!(!manager.authorize("blocked", &json!({})));
+        assert!(manager.authorize("approved", &json!({})));
+        assert!(!manager.authorize("rejected", &json!({})));
+
+        // Without a handler, Ask can't be confirmed
+        let unattended = PermissionManager::new();
+        assert!(!unattended.authorize("anything", &json!({})));
+    }
+
+    #[test]
+    fn test_select_by_policy() {
+        let registry = ToolRegistry::new();
+        for name in ["a", "b", "c"] {
+            registry.register(MockTool { name: name.to_string() }).unwrap();
+        }
+
+        assert_eq!(registry.select(&ToolPolicy::All).len(), 3);
+        assert!(registry.select(&ToolPolicy::None).is_empty());
+
+        let subset = registry.select(&ToolPolicy::Subset(vec!["b".to_string(), "missing".to_string()]));
+        assert_eq!(subset.len(), 1);
+        assert_eq!(subset[0].name(), "b");
+    }
+
+    #[tokio::test]
+    async fn test_register_factory_is_lazy() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let registry = ToolRegistry::new();
+        let counters: Vec<_> = ["lazy_tool", "unused_tool"]
+            .into_iter()
+            .map(|name| {
+                let constructed = Arc::new(AtomicUsize::new(0));
+                let counter = Arc::clone(&constructed);
+                registry
+                    .register_factory(name, "A lazily built tool", json!({"type": "object"}), move || {
+                        let counter = Arc::clone(&counter);
+                        async move {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            Ok(MockTool { name: name.to_string() })
+                        }
+                    })
+                    .unwrap();
+                constructed
+            })
+            .collect();
+        let (constructed, unused) = (&counters[0], &counters[1]);
+
+        // Listing does not construct anything
+        assert_eq!(registry.list().len(), 2);
+        tokio::task::yield_now().await;
+        assert_eq!(constructed.load(Ordering::SeqCst), 0);
+
+        // The first fetch starts construction without waiting for an execution
+        let tool = registry.get("lazy_tool").unwrap();
+        assert_eq!(tool.description(), "A lazily built tool");
+        tokio::task::yield_now().await;
+        assert_eq!(constructed.load(Ordering::SeqCst), 1);
+
+        // Later uses reuse the same instance, and the tool nobody fetched is never built
+        tool.execute(json!({})).await.unwrap();
+        registry.get("lazy_tool").unwrap().execute(json!({})).await.unwrap();
+        assert_eq!(constructed.load(Ordering::SeqCst), 1);
+        assert_eq!(unused.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_lazy_tool_builds_on_schema_access() {
+        let tool = LazyTool::new("lazy_tool", "A lazily built tool", json!({"type": "object"}), || async {
+            Ok(MockTool { name: "lazy_tool".to_string() })
+        });
+        assert!(!tool.is_initialized());
+
+        assert_eq!(tool.schema(), json!({"type": "object"}));
+        tokio::task::yield_now().await;
+        assert!(tool.is_initialized());
+    }
+
+    #[tokio::test]
+    async fn test_register_factory_surfaces_errors() {
+        let registry = ToolRegistry::new();
+        registry
+            .register_factory("broken", "Fails to build", json!({"type": "object"}), || async {
+                Err::<MockTool, _>(bedrock_core::BedrockError::ConfigError("no credentials".into()))
+            })
+            .unwrap();
+
+        let err = registry.get("broken").unwrap().execute(json!({})).await.unwrap_err();
+        match err {
+            bedrock_core::BedrockError::ToolError { tool, message } => {
+                assert_eq!(tool, "broken");
+                assert!(message.contains("no credentials"));
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+    }
+
     #[test]
     fn test_default_tools() {
         let registry = ToolRegistry::with_default_tools("/tmp");
@@ -182,9 +392,11 @@ mod tests {
         assert!(tools.contains(&"fs_read".to_string()));
         assert!(tools.contains(&"fs_write".to_string()));
         assert!(tools.contains(&"fs_list".to_string()));
+        assert!(tools.contains(&"apply_patch".to_string()));
         assert!(tools.contains(&"grep".to_string()));
         assert!(tools.contains(&"find".to_string()));
         assert!(tools.contains(&"rg".to_string()));
+        assert!(tools.contains(&"encode".to_string()));
         
         // Check for execute_bash/execute_cmd based on OS
         if cfg!(target_os = "windows") {
@@ -0,0 +1,369 @@
+use async_trait::async_trait;
+use bedrock_core::{BedrockError, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::debug;
+
+use crate::{current_tool_policy, Tool, ToolRegistry};
+
+const DEFAULT_SUCCESS_STATES: &[&str] = &["done", "completed", "succeeded", "success"];
+const DEFAULT_FAILURE_STATES: &[&str] = &["failed", "failure", "error", "cancelled"];
+
+#[derive(Debug, Deserialize)]
+struct PollArgs {
+    /// Tool to call for the job's state
+    tool: Option<String>,
+    /// Input for `tool`
+    #[serde(default)]
+    input: Value,
+    /// URL to GET for the job's state
+    url: Option<String>,
+    /// JSON pointer to the state in the check's result
+    #[serde(default = "default_status_path")]
+    status_path: String,
+    success: Option<Vec<String>>,
+    failure: Option<Vec<String>>,
+    interval_secs: Option<u64>,
+    max_attempts: Option<u32>,
+}
+
+fn default_status_path() -> String {
+    "/status".to_string()
+}
+
+/// Decides whether poll may call a tool with the given input
+pub type ToolGate = Arc<dyn Fn(&str, &Value) -> bool + Send + Sync>;
+
+/// Where each attempt reads the job's state from
+enum Check {
+    Tool { tool: Arc<dyn Tool>, input: Value },
+    /// A GET through the `http_request` tool, whose body is parsed as JSON
+    Http { tool: Arc<dyn Tool>, input: Value },
+}
+
+/// Repeatedly check an asynchronous job until it succeeds, fails or runs out of attempts.
+///
+/// The check is either another registered tool or an HTTP GET returning JSON, made
+/// with the registered `http_request` tool so its host allowlist applies. Both go
+/// through the gate first. The registry is held weakly since this tool is registered in it.
+pub struct PollTool {
+    registry: Weak<ToolRegistry>,
+    gate: Option<ToolGate>,
+    interval: Duration,
+    max_attempts: u32,
+    timeout: Duration,
+}
+
+impl PollTool {
+    pub fn new(registry: Weak<ToolRegistry>) -> Self {
+        Self {
+            registry,
+            gate: None,
+            interval: Duration::from_secs(5),
+            max_attempts: 60,
+            timeout: Duration::from_secs(900),
+        }
+    }
+
+    /// Check every tool call against `gate` before making it; without one every call is allowed
+    pub fn with_gate(mut self, gate: ToolGate) -> Self {
+        self.gate = Some(gate);
+        self
+    }
+
+    /// Delay between attempts when the call doesn't give one
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Upper bound on attempts; calls may ask for fewer
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Total time a poll may take across all attempts
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn error(&self, message: impl Into<String>) -> BedrockError {
+        BedrockError::ToolError {
+            tool: self.name().to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// The registered tool `name`, once the gate lets it run with `input`
+    fn authorized_tool(&self, name: &str, input: &Value) -> Result<Arc<dyn Tool>> {
+        if name == self.name() {
+            return Err(self.error("poll can't check itself"));
+        }
+        // The task's own tool policy holds for the tools poll calls on its behalf
+        if current_tool_policy().is_some_and(|policy| !policy.allows(name)) {
+            return Err(self.error(format!("Tool '{name}' is not available to this task")));
+        }
+        let tool = self
+            .registry
+            .upgrade()
+            .and_then(|registry| registry.get(name))
+            .ok_or_else(|| self.error(format!("Unknown tool: {name}")))?;
+        if self.gate.as_ref().is_some_and(|gate| !gate(name, input)) {
+            return Err(self.error(format!("Permission denied for tool '{name}'")));
+        }
+        Ok(tool)
+    }
+
+    fn check_for(&self, args: &PollArgs) -> Result<Check> {
+        match (&args.tool, &args.url) {
+            (Some(name), None) => {
+                let input = if args.input.is_null() { json!({}) } else { args.input.clone() };
+                let tool = self.authorized_tool(name, &input)?;
+                Ok(Check::Tool { tool, input })
+            }
+            (None, Some(url)) => {
+                let input = json!({"method": "GET", "url": url});
+                let tool = self
+                    .authorized_tool("http_request", &input)
+                    .map_err(|e| self.error(format!("url checks need the http_request tool: {e}")))?;
+                Ok(Check::Http { tool, input })
+            }
+            _ => Err(self.error("Give exactly one of 'tool' or 'url'")),
+        }
+    }
+
+    async fn run_check(&self, check: &Check) -> Result<Value> {
+        match check {
+            Check::Tool { tool, input } => tool.execute(input.clone()).await,
+            Check::Http { tool, input } => {
+                let response = tool.execute(input.clone()).await?;
+                let body = response["body"].as_str().unwrap_or_default();
+                serde_json::from_str(body)
+                    .map_err(|e| self.error(format!("Response from {} is not JSON: {e}", input["url"])))
+            }
+        }
+    }
+
+    async fn poll(&self, args: &PollArgs, check: &Check, attempts: &mut u32, last: &mut Value) -> Result<&'static str> {
+        let success = states(&args.success, DEFAULT_SUCCESS_STATES);
+        let failure = states(&args.failure, DEFAULT_FAILURE_STATES);
+        let interval = args.interval_secs.map(Duration::from_secs).unwrap_or(self.interval);
+        let max_attempts = args.max_attempts.unwrap_or(self.max_attempts).clamp(1, self.max_attempts);
+
+        while *attempts < max_attempts {
+            if *attempts > 0 {
+                tokio::time::sleep(interval).await;
+            }
+            *attempts += 1;
+
+            *last = self.run_check(check).await?;
+            let state = last
+                .pointer(&args.status_path)
+                .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+                .unwrap_or_default()
+                .to_lowercase();
+            debug!("Poll attempt {}/{}: state '{}'", attempts, max_attempts, state);
+
+            if success.contains(&state) {
+                return Ok("succeeded");
+            }
+            if failure.contains(&state) {
+                return Ok("failed");
+            }
+        }
+        Ok("max_attempts_reached")
+    }
+}
+
+fn states(requested: &Option<Vec<String>>, defaults: &[&str]) -> Vec<String> {
+    match requested {
+        Some(states) => states.iter().map(|s| s.to_lowercase()).collect(),
+        None => defaults.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+#[async_trait]
+impl Tool for PollTool {
+    fn name(&self) -> &str {
+        "poll"
+    }
+
+    fn description(&self) -> &str {
+        "Wait for an asynchronous job: repeatedly call a tool or GET a URL until the job's \
+         state reports success or failure, or the attempts run out. Returns the final state."
+    }
+
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "tool": {
+                    "type": "string",
+                    "description": "Tool that reports the job's state (use this or url)"
+                },
+                "input": {
+                    "type": "object",
+                    "description": "Input passed to the tool on every attempt"
+                },
+                "url": {
+                    "type": "string",
+                    "description": "HTTP(S) URL returning the job's state as JSON, fetched with http_request (use this or tool)"
+                },
+                "status_path": {
+                    "type": "string",
+                    "description": "JSON pointer to the state in the result (default: /status)"
+                },
+                "success": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "States meaning the job succeeded (default: done, completed, succeeded, success)"
+                },
+                "failure": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "States meaning the job failed (default: failed, failure, error, cancelled)"
+                },
+                "interval_secs": {
+                    "type": "integer",
+                    "description": "Seconds between attempts"
+                },
+                "max_attempts": {
+                    "type": "integer",
+                    "description": "Maximum number of checks"
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, args: Value) -> Result<Value> {
+        let args: PollArgs = serde_json::from_value(args)
+            .map_err(|e| self.error(format!("Invalid arguments: {e}")))?;
+        let check = self.check_for(&args)?;
+
+        let mut attempts = 0;
+        let mut last = Value::Null;
+        let outcome = tokio::time::timeout(self.timeout, self.poll(&args, &check, &mut attempts, &mut last)).await;
+        let status = match outcome {
+            Ok(status) => status?,
+            Err(_) => "timed_out",
+        };
+
+        Ok(json!({
+            "status": status,
+            "attempts": attempts,
+            "state": last
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HttpRequestTool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Reports "pending" until it has been called `pending` times, then "done"
+    struct JobStatusTool {
+        calls: AtomicUsize,
+        pending: usize,
+    }
+
+    #[async_trait]
+    impl Tool for JobStatusTool {
+        fn name(&self) -> &str {
+            "job_status"
+        }
+
+        fn description(&self) -> &str {
+            "reports a job's state"
+        }
+
+        fn schema(&self) -> Value {
+            json!({"type": "object"})
+        }
+
+        async fn execute(&self, _args: Value) -> Result<Value> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let status = if call < self.pending { "pending" } else { "done" };
+            Ok(json!({"job": "42", "status": status}))
+        }
+    }
+
+    fn registry_with_job(pending: usize) -> Arc<ToolRegistry> {
+        let registry = Arc::new(ToolRegistry::new());
+        registry.register(JobStatusTool { calls: AtomicUsize::new(0), pending }).unwrap();
+        registry
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_resolves_when_job_is_done() {
+        let registry = registry_with_job(2);
+        let poll = PollTool::new(Arc::downgrade(&registry)).with_interval(Duration::from_secs(10));
+
+        let result = poll.execute(json!({"tool": "job_status", "input": {"job": "42"}})).await.unwrap();
+
+        assert_eq!(result["status"], "succeeded");
+        assert_eq!(result["attempts"], 3);
+        assert_eq!(result["state"]["status"], "done");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_respects_attempt_cap() {
+        let registry = registry_with_job(5);
+        let poll = PollTool::new(Arc::downgrade(&registry)).with_max_attempts(3);
+
+        // Asking for more attempts than configured is capped
+        let result = poll.execute(json!({"tool": "job_status", "max_attempts": 10})).await.unwrap();
+
+        assert_eq!(result["status"], "max_attempts_reached");
+        assert_eq!(result["attempts"], 3);
+        assert_eq!(result["state"]["status"], "pending");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_times_out() {
+        let registry = registry_with_job(100);
+        let poll = PollTool::new(Arc::downgrade(&registry)).with_timeout(Duration::from_secs(30));
+
+        let result = poll.execute(json!({"tool": "job_status"})).await.unwrap();
+
+        assert_eq!(result["status"], "timed_out");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_poll_checks_go_through_the_gate() {
+        let registry = registry_with_job(0);
+        let poll = PollTool::new(Arc::downgrade(&registry))
+            .with_gate(Arc::new(|tool: &str, _: &Value| tool != "job_status"));
+
+        let err = poll.execute(json!({"tool": "job_status"})).await.unwrap_err();
+        assert!(err.to_string().contains("Permission denied"));
+    }
+
+    #[tokio::test]
+    async fn test_poll_url_uses_http_request_allowlist() {
+        let registry = registry_with_job(0);
+        let poll = PollTool::new(Arc::downgrade(&registry));
+
+        // No http_request tool, no url checks
+        assert!(poll.execute(json!({"url": "http://127.0.0.1:9/jobs/42"})).await.is_err());
+
+        registry.register(HttpRequestTool::new(["api.example.com"]).unwrap()).unwrap();
+        let err = poll.execute(json!({"url": "http://127.0.0.1:9/jobs/42"})).await.unwrap_err();
+        assert!(err.to_string().contains("not in the allowed hosts"));
+    }
+
+    #[tokio::test]
+    async fn test_poll_requires_one_check() {
+        let registry = registry_with_job(0);
+        let poll = PollTool::new(Arc::downgrade(&registry));
+
+        assert!(poll.execute(json!({})).await.is_err());
+        assert!(poll.execute(json!({"tool": "job_status", "url": "http://localhost"})).await.is_err());
+        assert!(poll.execute(json!({"tool": "missing"})).await.is_err());
+    }
+}
@@ -72,7 +72,7 @@ static DANGEROUS_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
         Regex::new(r"(?i)\bkillall\b").unwrap(),
         
         // Fork bombs and resource exhaustion
-        Regex::new(r":\(\)\{.*:\|:&\}").unwrap(),
+        Regex::new(r":\(\)\s*\{.*:\|:&\s*\}").unwrap(),
         Regex::new(r"fork\s*\(\s*\)").unwrap(),
         
         // Reverse shells
@@ -185,7 +185,7 @@ impl CommandValidator {
         if self.strict_mode {
             let parts: Vec<&str> = command.split_whitespace().collect();
             if let Some(cmd) = parts.first() {
-                let base_cmd = cmd.split('/').last().unwrap_or(cmd);
+                let base_cmd = cmd.rsplit('/').next().unwrap_or(cmd);
                 
                 if !SAFE_COMMANDS.contains(base_cmd) && 
                    !self.additional_allowed.contains(base_cmd) {
@@ -214,17 +214,17 @@ impl CommandValidator {
     pub fn is_read_only(&self, command: &str) -> bool {
         let parts: Vec<&str> = command.split_whitespace().collect();
         if let Some(cmd) = parts.first() {
-            let base_cmd = cmd.split('/').last().unwrap_or(cmd);
+            let base_cmd = cmd.rsplit('/').next().unwrap_or(cmd);
             
             // Check if it's a known read-only command
-            matches!(
-                base_cmd,
+            match base_cmd {
                 "ls" | "cat" | "grep" | "find" | "echo" | "pwd" | "date" | 
                 "whoami" | "hostname" | "uname" | "which" | "wc" | "head" | 
-                "tail" | "sort" | "uniq" | "cut" | "awk" | "sed" | "tr" |
-                "git" if parts.get(1).map_or(false, |&arg| 
-                    matches!(arg, "status" | "log" | "diff" | "show" | "branch" | "remote"))
-            )
+                "tail" | "sort" | "uniq" | "cut" | "awk" | "sed" | "tr" => true,
+                "git" => parts.get(1).is_some_and(|&arg| 
+                    matches!(arg, "status" | "log" | "diff" | "show" | "branch" | "remote")),
+                _ => false,
+            }
         } else {
             false
         }
@@ -4,7 +4,6 @@ use bedrock_config::AgentConfig;
 use bedrock_core::{Agent as AgentTrait, Task};
 use std::env;
 use std::path::PathBuf;
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -87,7 +87,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("Found {} tool calls", tool_uses.len());
         
         // Execute tools
-        let tool_results = client.execute_tools(&tool_uses, &tool_registry).await?;
+        let permissions = bedrock_tools::PermissionManager::new()
+            .with_default(bedrock_tools::Permission::Allow);
+        let tool_results = client.execute_tools(&tool_uses, &tool_registry, &permissions).await?;
         println!("Executed {} tools", tool_results.len());
         
         // Create tool result message
@@ -23,6 +23,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::env::var("FIGMA_API_KEY").unwrap_or_else(|_| "your-figma-api-key".to_string()),
         )]),
         timeout: 30000,
+        handshake_timeout: 10000,
+        request_timeout: None,
         disabled: false,
         health_check: None,
         restart_policy: None,
@@ -68,6 +70,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             bedrock_mcp::types::ContentItem::Image { data, mime_type } => {
                 println!("Result {}: Image ({}), {} bytes", i + 1, mime_type, data.len());
             }
+            bedrock_mcp::types::ContentItem::Resource { resource } => {
+                println!("Result {}: Resource {}", i + 1, resource.uri);
+            }
         }
     }
 
@@ -15,7 +15,6 @@ use bedrock_tools::ToolRegistry;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{info, warn, Level};
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -51,6 +50,8 @@ async fn main() -> Result<()> {
             env
         },
         timeout: 60000,
+        handshake_timeout: 10000,
+        request_timeout: None,
         disabled: false,
         health_check: None,
         restart_policy: None,
@@ -66,6 +67,8 @@ async fn main() -> Result<()> {
         ],
         env: HashMap::new(),
         timeout: 30000,
+        handshake_timeout: 10000,
+        request_timeout: None,
         disabled: false,
         health_check: None,
         restart_policy: None,
@@ -131,15 +134,31 @@ async fn main() -> Result<()> {
             model: "anthropic.claude-3-haiku-20240307-v1:0".to_string(),
             temperature: 0.7,
             max_tokens: 2000,
+            stop_sequences: Vec::new(),
+            include_stop_sequence: false,
+            stream_mode: Default::default(),
+            stream_fallback: false,
+            request_metadata: Default::default(),
+            system_prompt: None,
+            system_prompt_file: None,
+            enable_prompt_cache: false,
         },
         aws: bedrock_config::AwsSettings {
             region: "us-east-1".to_string(),
             profile: None,
             role_arn: None,
+            role_session_name: None,
+            external_id: None,
         },
         tools: bedrock_config::ToolSettings {
             allowed: vec![],  // Allow all tools
             permissions: std::collections::HashMap::new(),
+            enabled: true,
+            tool_timeout_secs: 120,
+            max_tool_output_bytes: 100_000,
+            text_protocol: false,
+            mixed_response: Default::default(),
+            poll: Default::default(),
         },
         ..Default::default()
     };
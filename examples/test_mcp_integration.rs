@@ -13,7 +13,6 @@ use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{info, Level};
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -34,6 +33,8 @@ async fn main() -> Result<()> {
         ],
         env: HashMap::new(),
         timeout: 30000,
+        handshake_timeout: 10000,
+        request_timeout: None,
         disabled: false,
         health_check: None,
         restart_policy: None,
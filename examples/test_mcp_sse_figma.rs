@@ -18,7 +18,6 @@ use serde_json::json;
 use std::env;
 use std::sync::Arc;
 use tracing::{info, warn, Level};
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -60,6 +59,8 @@ async fn main() -> Result<()> {
                     h
                 },
                 timeout: 30000,
+                handshake_timeout: 10000,
+                request_timeout: None,
                 disabled: false,
                 health_check: Some(bedrock_mcp::HealthCheckConfig {
                     interval: 30,
@@ -16,7 +16,6 @@ use bedrock_tools::ToolRegistry;
 use serde_json::json;
 use std::sync::Arc;
 use tracing::{info, warn, Level};
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -46,6 +45,8 @@ async fn main() -> Result<()> {
                     h
                 },
                 timeout: 30000,
+                handshake_timeout: 10000,
+                request_timeout: None,
                 disabled: false,
                 health_check: Some(bedrock_mcp::HealthCheckConfig {
                     interval: 30,
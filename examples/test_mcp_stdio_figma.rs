@@ -13,7 +13,6 @@ use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tracing::{info, warn, Level};
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -50,6 +49,8 @@ async fn main() -> Result<()> {
                     env
                 },
                 timeout: 60000,
+                handshake_timeout: 10000,
+                request_timeout: None,
                 disabled: false,
                 health_check: Some(bedrock_mcp::HealthCheckConfig {
                     interval: 30,
@@ -1,34 +1,52 @@
 use anyhow::Result;
 use bedrock_agent::Agent;
-use bedrock_config::AgentConfig;
-use bedrock_conversation::{ConversationManager, ConversationStorage, MessageEntry, ConversationMetadata};
-use bedrock_core::{Agent as AgentTrait, Task, TaskStatus, TaskResult};
+use bedrock_config::{AgentConfig, AuditSettings};
+use bedrock_conversation::{conversation_to_markdown, ConversationManager, ConversationStorage, MessageEntry, ConversationMetadata, ConversationSummary};
+use bedrock_core::{Agent as AgentTrait, RotatingFileWriter, StreamResult, Task, TaskStatus, TaskResult, TokenStatistics, ToolPolicy};
+use bedrock_metrics::{render_prometheus, tool_metrics_path, CostCalculator, MetricsCollector, TokenTracker};
+use bedrock_task::{cost_by_tag, load_task_results, ReplayBundle};
 use chrono::Utc;
 use clap::{Parser, Subcommand};
 use std::fs;
-use std::io::{self, Write};
-use std::path::PathBuf;
-use tracing::info;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use tracing_subscriber::fmt::format::{DefaultFields, Format};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
 use uuid::Uuid;
 
 #[derive(Parser)]
 #[command(name = "bedrock-agent")]
 #[command(about = "AWS Bedrock LLM Agent with built-in tools", long_about = None)]
 struct Cli {
+    /// Config file; repeat to deep-merge several in order, later ones winning
     #[arg(short, long, value_name = "FILE", default_value = "config.yaml")]
-    config: PathBuf,
+    config: Vec<PathBuf>,
+
+    /// Config merged over the --config files; repeat to apply several in order
+    #[arg(long, value_name = "FILE")]
+    config_overlay: Vec<PathBuf>,
 
     #[arg(short, long, action = clap::ArgAction::SetTrue)]
     verbose: bool,
 
+    /// Print results as text or as JSON for scripting
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    output: OutputFormat,
+
+    /// Log lines as human-readable text or as one JSON object each, for log aggregators
+    #[arg(long, global = true, value_enum, env = "LOG_FORMAT", default_value = "pretty", ignore_case = true)]
+    log_format: LogFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Manage conversations (resume, summary, export, delete)
+    /// Manage conversations (resume, summary, export, delete, tag)
     Conversation {
         /// The conversation ID
         #[arg(value_name = "ID")]
@@ -42,10 +60,14 @@ enum Commands {
         #[arg(long, action = clap::ArgAction::SetTrue)]
         summary: bool,
         
-        /// Export to JSON file
+        /// Export to a file
         #[arg(long, value_name = "FILE")]
         export: Option<PathBuf>,
         
+        /// Format for --export
+        #[arg(long, value_enum, default_value = "json")]
+        format: ExportFormat,
+        
         /// Delete the conversation
         #[arg(long, action = clap::ArgAction::SetTrue)]
         delete: bool,
@@ -54,6 +76,22 @@ enum Commands {
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         force: bool,
         
+        /// Record a snapshot of the conversation under a label
+        #[arg(long, value_name = "LABEL")]
+        snapshot: Option<String>,
+        
+        /// Roll the conversation back to a snapshot
+        #[arg(long, value_name = "SNAPSHOT_ID")]
+        rollback: Option<String>,
+        
+        /// Tag the conversation (repeatable)
+        #[arg(long = "tag", value_name = "NAME")]
+        tags: Vec<String>,
+        
+        /// Remove a tag from the conversation (repeatable)
+        #[arg(long = "untag", value_name = "NAME")]
+        untags: Vec<String>,
+        
         /// Use streaming mode (for resume)
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         stream: bool,
@@ -61,9 +99,9 @@ enum Commands {
 
     /// Execute or manage tasks
     Task {
-        /// Task ID to resume or prompt to execute
+        /// Task ID to resume or prompt to execute; `-` (or omitted with piped input) reads the prompt from stdin
         #[arg(value_name = "ID_OR_PROMPT")]
-        input: String,
+        input: Option<String>,
         
         /// Resume a task by ID (auto-detected if UUID format)
         #[arg(long, action = clap::ArgAction::SetTrue)]
@@ -77,13 +115,56 @@ enum Commands {
         #[arg(short, long)]
         context: Option<String>,
         
+        /// Include the result of a previous task in the new task's context
+        #[arg(long, value_name = "TASK_ID")]
+        after: Option<String>,
+        
+        /// Run the task without offering any tools to the model
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        no_tools: bool,
+        
+        /// Estimate the input tokens and cost of the task without calling Bedrock
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "resume")]
+        dry_run: bool,
+        
         /// Export task to file
         #[arg(long, value_name = "FILE")]
         export: Option<PathBuf>,
         
+        /// Write the task's prompts as a script that `replay-test` re-runs
+        #[arg(long, value_name = "FILE", conflicts_with = "export")]
+        export_script: Option<PathBuf>,
+        
         /// Use streaming mode
         #[arg(short, long, action = clap::ArgAction::SetTrue)]
         stream: bool,
+        
+        /// Run the prompt against each of these models at once and compare the results
+        #[arg(long, value_name = "MODELS", value_delimiter = ',')]
+        compare_models: Vec<String>,
+        
+        /// Output format for --compare-models
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Tag the task for cost attribution, e.g. --tag cost_center=research (repeatable)
+        #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+        tags: Vec<(String, String)>,
+    },
+
+    /// Run every task in a JSONL file through the task queue
+    Batch {
+        /// JSONL file with one `{"prompt": "...", "context": "..."}` object per line
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Tasks run at once; defaults to limits.max_concurrent_tasks
+        #[arg(long)]
+        concurrency: Option<usize>,
+
+        /// Where each task's result is written as a line of JSON
+        #[arg(long = "results", value_name = "FILE", default_value = "batch-results.jsonl")]
+        output: PathBuf,
     },
 
     /// Import conversations or tasks from JSON
@@ -119,15 +200,38 @@ enum Commands {
         #[arg(long, action = clap::ArgAction::SetTrue)]
         stats: bool,
         
+        /// With --stats, sum task cost per value of this task tag
+        #[arg(long, value_name = "TAG", requires = "stats")]
+        group_by: Option<String>,
+        
+        /// With --stats, list each conversation's tokens and cost, most expensive first
+        #[arg(long, action = clap::ArgAction::SetTrue, requires = "stats")]
+        by_conversation: bool,
+        
         /// Show only tasks (shorthand)
         #[arg(long, action = clap::ArgAction::SetTrue)]
         tasks: bool,
         
+        /// Only conversations with this tag, applied or derived from the tools used
+        #[arg(long, value_name = "NAME")]
+        tag: Option<String>,
+        
         /// Verbose output
         #[arg(long, action = clap::ArgAction::SetTrue)]
         verbose: bool,
     },
 
+    /// Search stored conversations by content
+    Search {
+        /// Terms to find; all must match, OR separates alternatives, quotes keep phrases
+        #[arg(value_name = "QUERY")]
+        query: String,
+        
+        /// Maximum number of conversations to show
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+
     /// Interactive conversation mode
     Chat {
         /// Initial system prompt
@@ -139,11 +243,49 @@ enum Commands {
         stream: bool,
     },
 
+    /// Show token, request and cost metrics from stored conversations, and tool
+    /// execution counts, failure rates and durations
+    Metrics {
+        /// Output in Prometheus text exposition format
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        prometheus: bool,
+    },
+
     /// List available tools
-    Tools,
+    Tools {
+        /// Also print each tool's input JSON schema
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        schema: bool,
+
+        /// Show only the tool with this name
+        #[arg(long, value_name = "NAME")]
+        tool: Option<String>,
+    },
+
+    /// Replay a conversation bundle and check replies against its expectations
+    ReplayTest {
+        /// Exported conversation with an `expectations` list
+        #[arg(value_name = "BUNDLE")]
+        bundle: PathBuf,
+    },
+
+    /// Write a commented starter configuration file
+    Init {
+        /// Where to write the configuration
+        #[arg(value_name = "FILE", default_value = "config.yaml")]
+        path: PathBuf,
+        
+        /// Overwrite the file if it exists
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        force: bool,
+    },
 
     /// Test AWS credentials and connectivity
-    Test,
+    Test {
+        /// Print a human-readable summary or a JSON report
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
 }
 
 #[derive(clap::ValueEnum, Clone)]
@@ -152,6 +294,24 @@ enum ImportType {
     Task,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Json,
+    Markdown,
+}
+
 #[derive(clap::ValueEnum, Clone)]
 enum ListType {
     Conversations,
@@ -163,71 +323,145 @@ enum ListType {
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    // Initialize logging
-    init_logging(cli.verbose)?;
+    // Scaffolding a config mustn't depend on loading one
+    if let Commands::Init { path, force } = &cli.command {
+        init_logging(cli.verbose, stdout_format(&cli), cli.log_format, &AuditSettings::default())?;
+        return init_config(path, *force);
+    }
 
-    // Load configuration
-    let config = if cli.config.exists() {
-        info!("Loading configuration from: {:?}", cli.config);
-        AgentConfig::from_yaml(&cli.config)?
+    // Load configuration first, since it says where the audit log goes
+    let layers: Vec<PathBuf> = cli.config.iter().chain(&cli.config_overlay).cloned().collect();
+    let (mut config, source) = if layers.len() > 1 {
+        (AgentConfig::from_layered(&layers)?, format!("{:?}", layers))
+    } else if layers[0].exists() {
+        (AgentConfig::from_file(&layers[0])?, format!("{:?}", layers[0]))
     } else {
-        info!("Using default configuration");
-        AgentConfig::default()
+        (AgentConfig::default(), "defaults".to_string())
     };
 
+    // Initialize logging
+    init_logging(cli.verbose, stdout_format(&cli), cli.log_format, &config.audit)?;
+    info!("Loaded configuration from {}", source);
+    for warning in config.model_warnings() {
+        warn!("{warning}");
+    }
+
+    // A batch's concurrency replaces the queue limit; all its tasks share one priority
+    if let Commands::Batch { concurrency: Some(concurrency), .. } = &cli.command {
+        config.limits.max_concurrent_tasks = (*concurrency).max(1);
+        config.limits.reserved_high_priority_slots = 0;
+    }
+
     // Create agent
-    let agent = Agent::new(config).await?;
+    let agent = Agent::new(config.clone()).await?;
+    let client = agent.get_client();
+    let metrics_path = tool_metrics_path(&config);
 
-    match cli.command {
-        Commands::Conversation { id, resume, summary, export, delete, force, stream } => {
-            handle_conversation_command(agent, id, resume, summary, export, delete, force, stream).await?;
-        }
-        Commands::Task { input, resume, prompt, context, export, stream } => {
-            handle_task_command(agent, input, resume, prompt, context, export, stream).await?;
-        }
-        Commands::Import { file, import_type, resume, force, stream } => {
-            handle_import_command(agent, file, import_type, resume, force, stream).await?;
-        }
-        Commands::List { list_type, stats, tasks, verbose } => {
-            handle_list_command(list_type, stats, tasks, verbose).await?;
-        }
-        Commands::Chat { system, stream } => {
-            interactive_chat(agent, system, stream).await?;
-        }
-        Commands::Tools => {
-            list_tools(&agent);
-        }
-        Commands::Test => {
-            test_connectivity(&agent).await?;
+    let outcome = Box::pin(async move {
+        match cli.command {
+            Commands::Conversation { id, resume, summary, export, format, delete, force, snapshot, rollback, tags, untags, stream } => {
+                handle_conversation_command(agent, id, resume, summary, export, format, delete, force, snapshot, rollback, tags, untags, stream).await?;
+            }
+            Commands::Task { input, resume, prompt, context, after, no_tools, dry_run, export, export_script, stream, compare_models, format, tags } => {
+                let format = if cli.output == OutputFormat::Json { OutputFormat::Json } else { format };
+                handle_task_command(agent, input, resume, prompt, context, after, no_tools, dry_run, export, export_script, stream, compare_models, format, tags, cli.output).await?;
+            }
+            Commands::Batch { file, concurrency: _, output } => {
+                run_batch(&agent, &file, &output, cli.output).await?;
+            }
+            Commands::Import { file, import_type, resume, force, stream } => {
+                handle_import_command(agent, file, import_type, resume, force, stream).await?;
+            }
+            Commands::List { list_type, stats, group_by, by_conversation, tasks, tag, verbose } => {
+                handle_list_command(&config, list_type, stats, group_by, by_conversation, tasks, tag.as_deref(), verbose, cli.output).await?;
+            }
+            Commands::Search { query, limit } => {
+                search_conversations(&query, limit)?;
+            }
+            Commands::Chat { system, stream } => {
+                interactive_chat(agent, system, stream).await?;
+            }
+            Commands::Metrics { prometheus } => {
+                show_metrics(&config, prometheus)?;
+            }
+            Commands::Tools { schema, tool } => {
+                list_tools(&agent, schema, tool.as_deref(), cli.output)?;
+            }
+            Commands::ReplayTest { bundle } => {
+                replay_test(&agent, &bundle, cli.output).await?;
+            }
+            Commands::Init { .. } => unreachable!("handled before loading the configuration"),
+            Commands::Test { format } => {
+                let format = if cli.output == OutputFormat::Json { OutputFormat::Json } else { format };
+                test_connectivity(&agent, &config, format).await?;
+            }
         }
+        Ok::<_, anyhow::Error>(())
+    }).await;
+
+    // Keep this run's request and tool timings for the metrics command, even if the command failed
+    if let Err(e) = save_tool_metrics(&client.tool_metrics(), &metrics_path) {
+        warn!("Failed to save tool metrics to {}: {}", metrics_path.display(), e);
     }
+    outcome
+}
 
+/// Add `session`'s model requests and tool executions to those saved by earlier runs
+fn save_tool_metrics(session: &MetricsCollector, path: &Path) -> Result<()> {
+    if session.requests == 0 && session.tool_executions.is_empty() {
+        return Ok(());
+    }
+    session.merge_into_file(path)?;
     Ok(())
 }
 
+/// What the command writes to stdout, so logging can stay out of a JSON document
+fn stdout_format(cli: &Cli) -> OutputFormat {
+    match &cli.command {
+        Commands::Task { format: OutputFormat::Json, compare_models, .. } if !compare_models.is_empty() => OutputFormat::Json,
+        Commands::Test { format: OutputFormat::Json } => OutputFormat::Json,
+        _ => cli.output,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn execute_task(
     agent: Agent,
     prompt: String,
     context: Option<String>,
+    after: Option<Uuid>,
+    no_tools: bool,
     stream: bool,
+    tags: Vec<(String, String)>,
+    output: OutputFormat,
 ) -> Result<()> {
     info!("Executing task: {}", prompt);
     
-    if stream {
+    let task = agent.chain_task(new_task(&prompt, context, no_tools, tags), after).await?;
+    
+    if output == OutputFormat::Json {
+        // Streamed chunks would interleave with the document, so run the task whole
+        let result = agent.execute_task(task).await?;
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else if stream || agent.should_stream(&task) {
         println!("\n🤖 Streaming response:\n");
         
-        let result = agent.chat_stream(&prompt, |chunk| {
+        let result = agent.execute_task_stream(task, |chunk| {
             print!("{chunk}");
             std::io::stdout().flush().ok();
         }).await?;
         
         println!("\n");
+        println!("Task ID: {}", result.task_id);
+        println!("Status: {:?}", result.status);
+        if matches!(result.status, TaskStatus::Failed | TaskStatus::Blocked) {
+            if let Some(error) = &result.error {
+                println!("Error: {error}");
+            }
+        }
         
         // Display metrics after streaming
-        println!("\n📊 Token Statistics:");
-        println!("  Input tokens: {}", result.token_stats.input_tokens);
-        println!("  Output tokens: {}", result.token_stats.output_tokens);
-        println!("  Total tokens: {}", result.token_stats.total_tokens);
+        print_token_stats(&result.token_stats);
         
         println!("\n💰 Cost Details:");
         println!("  Model: {}", result.cost.model);
@@ -236,12 +470,6 @@ async fn execute_task(
         println!("  Total cost: ${:.4} {}", result.cost.total_cost, result.cost.currency);
     } else {
         // For non-streaming, use the task execution for full tracking
-        let task = if let Some(ctx) = context {
-            Task::new(&prompt).with_context(ctx)
-        } else {
-            Task::new(&prompt)
-        };
-        
         let result = agent.execute_task(task).await?;
         
         println!("\n📋 Task Result");
@@ -250,7 +478,7 @@ async fn execute_task(
         println!("Status: {:?}", result.status);
         println!("Summary: {}", result.summary);
         
-        if result.status == TaskStatus::Failed {
+        if matches!(result.status, TaskStatus::Failed | TaskStatus::Blocked) {
             if let Some(error) = &result.error {
                 println!("Error: {error}");
             }
@@ -262,16 +490,17 @@ async fn execute_task(
                 if let Some(role) = msg.get("role") {
                     if let Some(content) = msg.get("content") {
                         println!("[{role}]: {content}");
+                        for call in msg.get("tool_calls").and_then(|c| c.as_array()).into_iter().flatten() {
+                            let name = call.get("name").and_then(|n| n.as_str()).unwrap_or("tool");
+                            println!("  🔧 {name} {}", call.get("input").unwrap_or(&serde_json::Value::Null));
+                        }
                         println!();
                     }
                 }
             }
         }
         
-        println!("\n📊 Token Statistics:");
-        println!("  Input tokens: {}", result.token_stats.input_tokens);
-        println!("  Output tokens: {}", result.token_stats.output_tokens);
-        println!("  Total tokens: {}", result.token_stats.total_tokens);
+        print_token_stats(&result.token_stats);
         
         println!("\n💰 Cost Details:");
         println!("  Model: {}", result.cost.model);
@@ -283,6 +512,138 @@ async fn execute_task(
     Ok(())
 }
 
+/// Read a whole prompt from stdin, e.g. `cat spec.md | bedrock-agent task -`
+fn read_prompt_from_stdin() -> Result<String> {
+    let mut prompt = String::new();
+    io::stdin().read_to_string(&mut prompt)?;
+    if prompt.trim().is_empty() {
+        anyhow::bail!("No prompt received on stdin");
+    }
+    Ok(prompt.trim_end().to_string())
+}
+
+fn print_token_stats(stats: &TokenStatistics) {
+    println!("\n📊 Token Statistics:");
+    println!("  Input tokens: {}", stats.input_tokens);
+    println!("  Output tokens: {}", stats.output_tokens);
+    // Only shown when the model reported prompt cache use
+    if stats.cache_hits > 0 || stats.cache_write_tokens > 0 {
+        println!("  Cache read tokens: {}", stats.cache_hits);
+        println!("  Cache write tokens: {}", stats.cache_write_tokens);
+    }
+    println!("  Total tokens: {}", stats.total_tokens);
+}
+
+fn new_task(prompt: &str, context: Option<String>, no_tools: bool, tags: Vec<(String, String)>) -> Task {
+    let mut task = if let Some(ctx) = context {
+        Task::new(prompt).with_context(ctx)
+    } else {
+        Task::new(prompt)
+    };
+    task.tags.extend(tags);
+    if no_tools {
+        task.with_tool_policy(ToolPolicy::None)
+    } else {
+        task
+    }
+}
+
+/// Parse a `--tag` argument of the form `key=value`
+fn parse_tag(arg: &str) -> std::result::Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.trim().to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{arg}'")),
+    }
+}
+
+async fn estimate_task(
+    agent: Agent,
+    prompt: String,
+    context: Option<String>,
+    after: Option<Uuid>,
+    no_tools: bool,
+    tags: Vec<(String, String)>,
+    output: OutputFormat,
+) -> Result<()> {
+    let task = agent.chain_task(new_task(&prompt, context, no_tools, tags), after).await?;
+    let estimate = agent.estimate_breakdown(&task)?;
+    
+    if output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&estimate)?);
+        return Ok(());
+    }
+    
+    println!("\n🧮 Dry run (nothing sent to Bedrock)");
+    println!("═══════════════════════════════════════");
+    println!("Model:        {}", estimate.cost.model);
+    println!("Input tokens: ~{}", estimate.input_tokens);
+    println!("Tools:        {} ({} bytes of schemas)", estimate.tools_offered, estimate.tool_schema_bytes);
+    println!("Input cost:   ${:.4} {}", estimate.cost.input_cost, estimate.cost.currency);
+    println!("\nThe reply and any tool round trips are billed on top.");
+    
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn compare_task(
+    agent: Agent,
+    prompt: String,
+    context: Option<String>,
+    after: Option<Uuid>,
+    no_tools: bool,
+    models: Vec<String>,
+    format: OutputFormat,
+    tags: Vec<(String, String)>,
+) -> Result<()> {
+    info!("Comparing {} models on: {}", models.len(), prompt);
+    
+    let task = agent.chain_task(new_task(&prompt, context, no_tools, tags), after).await?;
+    let comparisons = agent.compare_models(task, &models).await;
+    
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&comparisons)?);
+        return Ok(());
+    }
+    
+    println!("\n🔀 Model Comparison");
+    println!("═══════════════════════════════════════");
+    println!("{:<50} {:<10} {:>12} {:>12} {:>10}", "Model", "Status", "Latency", "Tokens", "Cost");
+    println!("{}", "-".repeat(98));
+    for comparison in &comparisons {
+        match &comparison.result {
+            Some(result) => println!(
+                "{:<50} {:<10} {:>10}ms {:>12} {:>9.4}$",
+                comparison.model,
+                format!("{:?}", result.status),
+                comparison.latency_ms,
+                result.token_stats.total_tokens,
+                result.cost.total_cost
+            ),
+            None => println!(
+                "{:<50} {:<10} {:>10}ms {:>12} {:>10}",
+                comparison.model, "Error", comparison.latency_ms, "-", "-"
+            ),
+        }
+    }
+    
+    for comparison in &comparisons {
+        println!("\n📋 {}", comparison.model);
+        println!("───────────────────────────────────────");
+        match (&comparison.result, &comparison.error) {
+            (Some(result), _) => {
+                println!("{}", result.summary);
+                if let Some(error) = &result.error {
+                    println!("Error: {error}");
+                }
+            }
+            (None, Some(error)) => println!("Error: {error}"),
+            (None, None) => println!("No result"),
+        }
+    }
+    
+    Ok(())
+}
+
 async fn interactive_chat(
     agent: Agent,
     _system_prompt: Option<String>,
@@ -292,8 +653,11 @@ async fn interactive_chat(
     println!("🤖 Bedrock Agent Interactive Chat");
     println!("Type 'exit' or 'quit' to end the conversation");
     println!("Type 'tools' to see available tools");
+    println!("Type 'reset' to start over without earlier messages");
+    println!("Type '/help' for session commands");
     println!("═══════════════════════════════════════\n");
     
+    let mut usage = ChatUsage::new();
     loop {
         print!("You> ");
         io::stdout().flush()?;
@@ -308,7 +672,18 @@ async fn interactive_chat(
         }
         
         if input.eq_ignore_ascii_case("tools") {
-            list_tools(&agent);
+            list_tools(&agent, false, None, OutputFormat::Text)?;
+            continue;
+        }
+        
+        if input.eq_ignore_ascii_case("reset") {
+            agent.reset_conversation().await;
+            println!("Conversation reset.\n");
+            continue;
+        }
+        
+        if input.starts_with('/') {
+            run_chat_command(&agent, input, &usage).await;
             continue;
         }
         
@@ -325,68 +700,470 @@ async fn interactive_chat(
             println!("(Tokens: {} | Cost: ${:.4})", 
                 result.token_stats.total_tokens, 
                 result.cost.total_cost);
+            usage.record(&result);
         } else {
-            let response = agent.chat(input).await?;
-            println!("{response}\n");
+            let result = agent.chat_with_stats(input).await?;
+            println!("{}\n", result.response);
+            println!("(Tokens: {} | Cost: ${:.4})", 
+                result.token_stats.total_tokens, 
+                result.cost.total_cost);
+            usage.record(&result);
         }
     }
     
     Ok(())
 }
 
-fn list_tools(agent: &Agent) {
-    println!("\n🛠️  Available Tools:");
+/// Token and cost totals of an interactive chat session
+struct ChatUsage {
+    tokens: TokenTracker,
+    cost: f64,
+    currency: String,
+}
+
+impl ChatUsage {
+    fn new() -> Self {
+        Self { tokens: TokenTracker::new(), cost: 0.0, currency: "USD".to_string() }
+    }
+
+    fn record(&mut self, result: &StreamResult) {
+        self.tokens.add_input(result.token_stats.input_tokens, &result.cost.model);
+        self.tokens.add_output(result.token_stats.output_tokens, &result.cost.model);
+        self.cost += result.cost.total_cost;
+        self.currency = result.cost.currency.clone();
+    }
+}
+
+fn print_chat_commands() {
+    println!("\nSession commands:");
+    println!("  /reset          - Forget earlier messages");
+    println!("  /tokens         - Show tokens and cost so far");
+    println!("  /save <file>    - Write the session transcript to a file");
+    println!("  /model <id>     - Switch the model for later messages");
+    println!("  /system <text>  - Replace the system prompt");
+    println!("  /help           - Show this help message\n");
+}
+
+/// A `/command` typed in interactive chat
+#[derive(Debug, PartialEq)]
+enum ChatCommand<'a> {
+    Reset,
+    Tokens,
+    Save(&'a str),
+    Model(&'a str),
+    System(&'a str),
+    Help,
+    /// A command that takes an argument, given none
+    MissingArgument(&'a str),
+    Unknown(&'a str),
+}
+
+fn parse_chat_command(input: &str) -> ChatCommand<'_> {
+    let (command, argument) = match input.split_once(char::is_whitespace) {
+        Some((command, argument)) => (command, argument.trim()),
+        None => (input, ""),
+    };
+    match (command, argument) {
+        ("/reset", _) => ChatCommand::Reset,
+        ("/tokens", _) => ChatCommand::Tokens,
+        ("/save" | "/model" | "/system", "") => ChatCommand::MissingArgument(command),
+        ("/save", file) => ChatCommand::Save(file),
+        ("/model", model) => ChatCommand::Model(model),
+        ("/system", system_prompt) => ChatCommand::System(system_prompt),
+        ("/help", _) => ChatCommand::Help,
+        _ => ChatCommand::Unknown(command),
+    }
+}
+
+/// Run a `/command` typed in interactive chat; nothing is sent to the model
+async fn run_chat_command(agent: &Agent, input: &str, usage: &ChatUsage) {
+    match parse_chat_command(input) {
+        ChatCommand::Reset => {
+            agent.reset_conversation().await;
+            println!("Conversation reset.\n");
+        }
+        ChatCommand::Tokens => {
+            let stats = usage.tokens.get_stats();
+            println!(
+                "Tokens: {} ({} input, {} output) | Cost: ${:.4} {}\n",
+                stats.total_tokens, stats.input_tokens, stats.output_tokens, usage.cost, usage.currency
+            );
+        }
+        ChatCommand::MissingArgument(command) => {
+            println!("{command} needs an argument. Type /help for the available commands.\n");
+        }
+        ChatCommand::Save(file) => match fs::write(file, agent.chat_transcript().await) {
+            Ok(()) => println!("Session saved to {file}\n"),
+            Err(e) => eprintln!("❌ Failed to save the session to {file}: {e}\n"),
+        },
+        ChatCommand::Model(model) => {
+            agent.set_chat_model(model);
+            println!("Model switched to {model}\n");
+        }
+        ChatCommand::System(system_prompt) => {
+            agent.set_chat_system_prompt(system_prompt);
+            println!("System prompt updated.\n");
+        }
+        ChatCommand::Help => print_chat_commands(),
+        ChatCommand::Unknown(command) => {
+            println!("Unknown command {command}. Type /help for the available commands.\n");
+        }
+    }
+}
+
+fn show_metrics(config: &AgentConfig, prometheus: bool) -> Result<()> {
+    let storage = ConversationStorage::new()?;
+    let tokens = TokenTracker::new();
+    let mut collector = MetricsCollector::new();
+    let costs = CostCalculator::from_config(config);
+    
+    for conv in storage.list_conversations()? {
+        let metadata = storage.load_metadata(&conv.id)?;
+        let messages = storage.read_messages(&conv.id)?;
+        let mut tool_names = std::collections::HashMap::new();
+        
+        for msg in &messages {
+            // Each assistant message with token usage is one model request
+            if msg.role == "assistant" {
+                if let Some(usage) = &msg.tokens {
+                    tokens.add_input(usage.input_tokens as usize, &metadata.model_id);
+                    tokens.add_output(usage.output_tokens as usize, &metadata.model_id);
+                    collector.record_request(0, true);
+                }
+            }
+            
+            let Some(blocks) = msg.content.as_array() else { continue };
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("tool_use") => {
+                        if let (Some(id), Some(name)) = (
+                            block.get("tool_use_id").and_then(|v| v.as_str()),
+                            block.get("name").and_then(|v| v.as_str()),
+                        ) {
+                            tool_names.insert(id.to_string(), name.to_string());
+                        }
+                    }
+                    Some("tool_result") => {
+                        let name = block.get("tool_use_id")
+                            .and_then(|v| v.as_str())
+                            .and_then(|id| tool_names.get(id))
+                            .map(String::as_str)
+                            .unwrap_or("unknown");
+                        let failed = block.get("status")
+                            .and_then(|v| v.as_str())
+                            .map(|status| status.contains("Error"))
+                            .unwrap_or(false);
+                        collector.record_tool_execution(name, 0, !failed);
+                        if !failed {
+                            // Stored results are text only, so only per-call costs apply here
+                            costs.add_tool_cost(config.tools.call_cost(name, &serde_json::Value::Null));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        
+        costs.calculate(
+            &TokenStatistics {
+                input_tokens: metadata.token_usage.input_tokens as usize,
+                output_tokens: metadata.token_usage.output_tokens as usize,
+                ..Default::default()
+            },
+            &metadata.model_id,
+        );
+    }
+    
+    // Stored conversations carry no timings, so prefer the requests and executions
+    // recorded as they were made
+    let recorded = MetricsCollector::load(&tool_metrics_path(config))?;
+    if recorded.requests > 0 {
+        collector.requests = recorded.requests;
+        collector.successful_requests = recorded.successful_requests;
+        collector.failed_requests = recorded.failed_requests;
+        collector.total_latency_ms = recorded.total_latency_ms;
+    }
+    if !recorded.tool_executions.is_empty() {
+        collector.tool_executions = recorded.tool_executions;
+    }
+    
+    if prometheus {
+        print!("{}", render_prometheus(&tokens, &collector, &costs));
+        return Ok(());
+    }
+    
+    let stats = tokens.get_stats();
+    let summary = collector.get_summary();
+    println!("\n📈 Metrics for current workspace");
     println!("═══════════════════════════════════════");
+    println!("Requests: {}", summary.total_requests);
+    if collector.total_latency_ms > 0 {
+        println!("Average latency: {:.0}ms", summary.average_latency_ms);
+    }
+    println!("Tokens: {} input / {} output", stats.input_tokens, stats.output_tokens);
+    println!("Total cost: ${:.4} {}", costs.get_total_cost(), costs.currency());
+    
+    if !collector.tool_executions.is_empty() {
+        println!("\n🛠️  Tool executions:");
+        let mut tools: Vec<_> = collector.tool_executions.values().collect();
+        tools.sort_by_key(|t| std::cmp::Reverse(t.executions));
+        for tool in tools {
+            println!(
+                "  {:<24} {:>6} runs {:>6.1}% failed {:>8.0}ms avg",
+                tool.name,
+                tool.executions,
+                tool.failure_rate(),
+                tool.average_duration_ms()
+            );
+        }
+    }
     
+    Ok(())
+}
+
+/// Print the registered tools, sorted by name; `--output json` emits
+/// `{name, description, schema}` objects whether or not `schema` is set
+fn list_tools(agent: &Agent, schema: bool, only: Option<&str>, output: OutputFormat) -> Result<()> {
     let tool_registry = agent.get_tool_registry();
-    for tool_name in tool_registry.list() {
-        if let Some(tool) = tool_registry.get(&tool_name) {
-            println!("\n📦 {}", tool.name());
-            println!("   {}", tool.description());
+    let mut names = tool_registry.list();
+    names.sort();
+    if let Some(only) = only {
+        if !names.iter().any(|name| name == only) {
+            anyhow::bail!("Unknown tool '{}'; run the tools command to list them", only);
+        }
+        names.retain(|name| name == only);
+    }
+    let tools: Vec<_> = names.iter().filter_map(|name| tool_registry.get(name)).collect();
+
+    if output == OutputFormat::Json {
+        let listing: Vec<_> = tools
+            .iter()
+            .map(|tool| serde_json::json!({
+                "name": tool.name(),
+                "description": tool.description(),
+                "schema": tool.schema(),
+            }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&listing)?);
+        return Ok(());
+    }
+
+    println!("\n🛠️  Available Tools:");
+    println!("═══════════════════════════════════════");
+    
+    for tool in tools {
+        println!("\n📦 {}", tool.name());
+        println!("   {}", tool.description());
+        if schema {
+            for line in serde_json::to_string_pretty(&tool.schema())?.lines() {
+                println!("   {}", line);
+            }
         }
     }
     println!();
+    Ok(())
+}
+
+/// Write a starter config built from the defaults, asking for the region and model
+/// when run interactively
+fn init_config(path: &std::path::Path, force: bool) -> Result<()> {
+    if path.exists() && !force {
+        anyhow::bail!("{} already exists; use --force to overwrite it", path.display());
+    }
+    
+    let mut config = AgentConfig::default();
+    if io::stdin().is_terminal() {
+        config.aws.region = prompt_with_default("AWS region", &config.aws.region)?;
+        config.agent.model = prompt_with_default("Model ID", &config.agent.model)?;
+    }
+    // `tools.allowed` stays empty, which offers every tool, MCP tools included
+    
+    fs::write(path, config.to_starter_yaml()?)?;
+    println!("✅ Wrote starter configuration to {}", path.display());
+    if !config.pricing.contains_key(&config.agent.model) {
+        println!("Add a pricing entry for {} to get cost reports", config.agent.model);
+    }
+    println!("Check it with: bedrock-agent --config {} test", path.display());
+    Ok(())
+}
+
+/// Ask for a value on the terminal; an empty answer keeps `default`
+fn prompt_with_default(label: &str, default: &str) -> Result<String> {
+    print!("{label} [{default}]: ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default.to_string() } else { answer.to_string() })
 }
 
-async fn test_connectivity(agent: &Agent) -> Result<()> {
+async fn test_connectivity(agent: &Agent, config: &AgentConfig, format: OutputFormat) -> Result<()> {
+    if format == OutputFormat::Json {
+        let report = agent.get_client().probe_connectivity().await;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    
     println!("\n🔍 Testing AWS Bedrock Connectivity");
     println!("═══════════════════════════════════════");
     
     print!("\nTesting API connection... ");
     io::stdout().flush()?;
     
-    let test_task = Task::new("Hello, can you hear me?");
-    match agent.execute_task(test_task).await {
-        Ok(result) => {
-            if result.status == TaskStatus::Completed {
-                println!("✅ Success!");
-                println!("Response: {}", result.summary);
-                println!("\nToken usage: {} tokens", result.token_stats.total_tokens);
-                println!("Estimated cost: ${:.4}", result.cost.total_cost);
-            } else {
-                println!("❌ Failed");
-                println!("Error: {:?}", result.error);
-            }
+    let report = agent.get_client().probe_connectivity().await;
+    if report.is_ok() {
+        println!("✅ Success! ({}ms)", report.latency_ms);
+        println!("Response: {}", report.response.as_deref().unwrap_or_default());
+        if let Some(usage) = &report.token_usage {
+            let cost = CostCalculator::from_config(config).calculate(usage, &report.model);
+            println!("\nToken usage: {} tokens", usage.total_tokens);
+            println!("Estimated cost: ${:.4}", cost.total_cost);
         }
-        Err(e) => {
-            println!("❌ Failed");
-            println!("Error: {e}");
+    } else {
+        println!("❌ Failed");
+        println!("Credentials accepted: {}", if report.credentials_ok { "yes" } else { "no" });
+        println!("Model reachable:      {}", if report.model_reachable { "yes" } else { "no" });
+        println!("Region: {}  Model: {}", report.region, report.model);
+        if let Some(error) = &report.error {
+            println!("Error: {error}");
         }
     }
     
     Ok(())
 }
 
+async fn replay_test(agent: &Agent, bundle: &Path, output: OutputFormat) -> Result<()> {
+    let report = agent.replay(&ReplayBundle::from_file(bundle)?).await?;
+
+    if output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("\n🔁 Replaying {}", bundle.display());
+        println!("═══════════════════════════════════════");
+        for turn in &report.turns {
+            let mark = match turn.passed {
+                Some(true) => "✅",
+                Some(false) => "❌",
+                None => "➖",
+            };
+            println!("{} Turn {}: {}", mark, turn.turn, turn.prompt.lines().next().unwrap_or_default());
+            if let Some(failure) = &turn.failure {
+                for line in failure.lines() {
+                    println!("     {line}");
+                }
+            }
+        }
+        let checked = report.turns.iter().filter(|turn| turn.passed.is_some()).count();
+        let failed = report.turns.iter().filter(|turn| turn.passed == Some(false)).count();
+        println!("\n{} of {} checked turn(s) passed", checked - failed, checked);
+    }
+
+    if !report.passed() {
+        anyhow::bail!("Replay of {} didn't match its expectations", bundle.display());
+    }
+    Ok(())
+}
+
+/// Tasks from a batch file: one `{"prompt": ..., "context": ...}` object per non-blank line
+fn read_batch_file(file: &Path) -> Result<Vec<Task>> {
+    let content = fs::read_to_string(file)?;
+    let mut tasks = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: serde_json::Value = serde_json::from_str(line)
+            .map_err(|e| anyhow::anyhow!("{} line {}: {}", file.display(), index + 1, e))?;
+        let Some(prompt) = entry.get("prompt").and_then(|p| p.as_str()) else {
+            anyhow::bail!("{} line {}: missing \"prompt\"", file.display(), index + 1);
+        };
+        let context = entry.get("context").and_then(|c| c.as_str()).map(str::to_string);
+        tasks.push(new_task(prompt, context, false, Vec::new()));
+    }
+    Ok(tasks)
+}
+
+async fn run_batch(agent: &Agent, file: &Path, results_path: &Path, output: OutputFormat) -> Result<()> {
+    let tasks = read_batch_file(file)?;
+    info!("Running {} task(s) from {}", tasks.len(), file.display());
+    let mut results_file = io::BufWriter::new(fs::File::create(results_path)?);
+
+    let (sender, mut receiver) = tokio::sync::mpsc::channel::<TaskResult>(16);
+    let tokens = TokenTracker::new();
+    let mut collector = MetricsCollector::new();
+    let mut total_cost = 0.0;
+    let mut currency = "USD".to_string();
+
+    let record = async {
+        while let Some(result) = receiver.recv().await {
+            writeln!(results_file, "{}", serde_json::to_string(&result)?)?;
+            results_file.flush()?;
+            tokens.add_input(result.token_stats.input_tokens, &result.cost.model);
+            tokens.add_output(result.token_stats.output_tokens, &result.cost.model);
+            collector.record_request(result.duration_ms.unwrap_or(0), result.status == TaskStatus::Completed);
+            total_cost += result.cost.total_cost;
+            if !result.cost.currency.is_empty() {
+                currency = result.cost.currency.clone();
+            }
+            if output == OutputFormat::Text {
+                let mark = if result.status == TaskStatus::Completed { "✅" } else { "❌" };
+                println!("{} {} {}", mark, result.task_id, result.summary);
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    };
+    let (run, recorded) = tokio::join!(agent.run_batch(tasks, sender), record);
+    run?;
+    recorded?;
+
+    let stats = tokens.get_stats();
+    let summary = collector.get_summary();
+    let failed = collector.failed_requests;
+    if output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+            "tasks": summary.total_requests,
+            "succeeded": collector.successful_requests,
+            "failed": failed,
+            "input_tokens": stats.input_tokens,
+            "output_tokens": stats.output_tokens,
+            "total_tokens": stats.total_tokens,
+            "total_cost": total_cost,
+            "currency": currency,
+            "results": results_path,
+        }))?);
+    } else {
+        println!("\n📦 Batch Summary");
+        println!("═══════════════════════════════════════");
+        println!("Tasks: {} ({} succeeded, {} failed)", summary.total_requests, collector.successful_requests, failed);
+        println!("Success rate: {:.1}%", summary.success_rate);
+        println!("Average duration: {:.0}ms", summary.average_latency_ms);
+        println!("Tokens: {} ({} input, {} output)", stats.total_tokens, stats.input_tokens, stats.output_tokens);
+        println!("Total cost: ${:.4} {}", total_cost, currency);
+        println!("Results: {}", results_path.display());
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} batch task(s) failed", failed, summary.total_requests);
+    }
+    Ok(())
+}
+
 // Unified command handlers
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_conversation_command(
     agent: Agent,
     id: String,
     _resume: bool,
     summary: bool,
     export: Option<PathBuf>,
+    format: ExportFormat,
     delete: bool,
     force: bool,
+    snapshot: Option<String>,
+    rollback: Option<String>,
+    tags: Vec<String>,
+    untags: Vec<String>,
     stream: bool,
 ) -> Result<()> {
     // Parse the conversation ID
@@ -399,7 +1176,16 @@ async fn handle_conversation_command(
     } else if summary {
         generate_conversation_summary(agent, id).await?;
     } else if let Some(export_path) = export {
-        export_conversation(id, Some(export_path)).await?;
+        match format {
+            ExportFormat::Json => export_conversation(id, Some(export_path)).await?,
+            ExportFormat::Markdown => export_conversation_markdown(id, export_path)?,
+        }
+    } else if let Some(label) = snapshot {
+        snapshot_conversation(id, label).await?;
+    } else if let Some(snapshot_id) = rollback {
+        rollback_conversation(id, snapshot_id).await?;
+    } else if !tags.is_empty() || !untags.is_empty() {
+        tag_conversation(&id, &tags, &untags)?;
     } else {
         // Default action is resume
         resume_conversation(agent, id, stream).await?;
@@ -408,23 +1194,49 @@ async fn handle_conversation_command(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_task_command(
     agent: Agent,
-    input: String,
+    input: Option<String>,
     resume: bool,
     prompt: Option<String>,
     context: Option<String>,
+    after: Option<String>,
+    no_tools: bool,
+    dry_run: bool,
     export: Option<PathBuf>,
+    export_script: Option<PathBuf>,
     stream: bool,
+    compare_models: Vec<String>,
+    format: OutputFormat,
+    tags: Vec<(String, String)>,
+    output: OutputFormat,
 ) -> Result<()> {
+    // Piped content is always a prompt, even if it happens to look like a task ID
+    let (input, from_stdin) = match input {
+        Some(input) if input == "-" => (read_prompt_from_stdin()?, true),
+        Some(input) => (input, false),
+        None if !io::stdin().is_terminal() => (read_prompt_from_stdin()?, true),
+        None => anyhow::bail!("Provide a task ID or prompt, or pipe the prompt on stdin with '-'"),
+    };
+    if from_stdin && resume {
+        anyhow::bail!("--resume needs a task ID, not a prompt from stdin");
+    }
+    
     // Check if input is a UUID (task ID) or a prompt
-    let is_uuid = Uuid::parse_str(&input).is_ok();
+    let is_uuid = !from_stdin && Uuid::parse_str(&input).is_ok();
     
     if is_uuid || resume {
+        // A resumed task continues as a chat, which has nothing to estimate
+        if dry_run {
+            anyhow::bail!("--dry-run estimates a new task; '{}' is a task ID to resume", input);
+        }
         // Resume existing task
         if let Some(export_path) = export {
             // Export task
             export_task(input.clone(), export_path).await?;
+        } else if let Some(script_path) = export_script {
+            export_task_script(&input, &script_path)?;
         } else {
             // Resume task with optional prompt
             resume_task(agent, input, prompt, stream).await?;
@@ -432,7 +1244,17 @@ async fn handle_task_command(
     } else {
         // Execute new task
         let task_prompt = prompt.unwrap_or(input);
-        execute_task(agent, task_prompt, context, stream).await?;
+        let after = after
+            .map(|id| Uuid::parse_str(&id))
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Invalid task ID for --after: {}", e))?;
+        if dry_run {
+            estimate_task(agent, task_prompt, context, after, no_tools, tags, output).await?;
+        } else if compare_models.is_empty() {
+            execute_task(agent, task_prompt, context, after, no_tools, stream, tags, output).await?;
+        } else {
+            compare_task(agent, task_prompt, context, after, no_tools, compare_models, format, tags).await?;
+        }
     }
     
     Ok(())
@@ -455,7 +1277,8 @@ async fn handle_import_command(
     
     match detected_type {
         ImportType::Conversation => {
-            import_conversation(file, force).await?;
+            let default_model = agent.get_config().agent.model.clone();
+            import_conversation(file, force, &default_model).await?;
         }
         ImportType::Task => {
             import_task(agent, file, resume, stream).await?;
@@ -465,11 +1288,17 @@ async fn handle_import_command(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_list_command(
+    config: &AgentConfig,
     list_type: ListType,
     stats: bool,
+    group_by: Option<String>,
+    by_conversation: bool,
     tasks: bool,
+    tag: Option<&str>,
     verbose: bool,
+    output: OutputFormat,
 ) -> Result<()> {
     // Override list_type if tasks flag is set
     let actual_type = if tasks {
@@ -479,15 +1308,19 @@ async fn handle_list_command(
     };
     
     if stats {
-        show_conversation_stats().await?;
+        show_conversation_stats(config, group_by.as_deref(), by_conversation, output).await?;
     } else {
         match actual_type {
-            ListType::Conversations => list_conversations().await?,
-            ListType::Tasks => list_tasks(verbose).await?,
+            ListType::Conversations => list_conversations(tag, output).await?,
+            ListType::Tasks => list_tasks(verbose, output).await?,
+            ListType::All if output == OutputFormat::Json => {
+                // One document rather than two concatenated ones
+                list_all_json(tag)?;
+            }
             ListType::All => {
-                list_conversations().await?;
+                list_conversations(tag, output).await?;
                 println!(); // Separator
-                list_tasks(verbose).await?;
+                list_tasks(verbose, output).await?;
             }
         }
     }
@@ -551,19 +1384,38 @@ async fn export_task(task_id: String, output: PathBuf) -> Result<()> {
     Err(anyhow::anyhow!("Task ID not found: {}", task_id))
 }
 
-// List tasks function
-async fn list_tasks(verbose: bool) -> Result<()> {
+/// Write the user prompts of the conversation that ran `task_id` as a replay script
+fn export_task_script(task_id: &str, output: &Path) -> Result<()> {
+    Uuid::parse_str(task_id).map_err(|e| anyhow::anyhow!("Invalid task ID: {}", e))?;
+
     let storage = ConversationStorage::new()?;
-    let conversations = storage.list_conversations()?;
-    
-    let mut task_count = 0;
-    println!("\n📋 Tasks in current workspace:\n");
-    
-    if verbose {
-        println!("{:<38} {:<38} {:<20} {:<10}", "Task ID", "Conversation ID", "Created", "Status");
-        println!("{}", "-".repeat(106));
+    for conv_summary in storage.list_conversations()? {
+        if !conv_summary.has_tasks {
+            continue;
+        }
+        let messages = storage.read_messages(&conv_summary.id)?;
+        let records_task = messages
+            .iter()
+            .any(|msg| msg.role == "assistant" && msg.content.as_str().is_some_and(|text| text.contains(task_id)));
+        if records_task {
+            let metadata = storage.load_metadata(&conv_summary.id)?;
+            let script = ReplayBundle::script(metadata.system_prompt, &messages);
+            fs::write(output, serde_json::to_string_pretty(&script)?)?;
+            println!("✅ Exported {} prompt(s) to: {}", script.messages.len(), output.display());
+            println!("   Re-run with: bedrock-agent replay-test {}", output.display());
+            return Ok(());
+        }
     }
+
+    Err(anyhow::anyhow!("Task ID not found: {}", task_id))
+}
+
+// List tasks function
+/// (task ID, conversation, status) for each task recorded in a conversation
+fn collect_tasks(storage: &ConversationStorage) -> Result<Vec<(String, ConversationSummary, &'static str)>> {
+    let conversations = storage.list_conversations()?;
     
+    let mut tasks = Vec::new();
     for conv in conversations {
         if conv.has_tasks {
             let messages = storage.read_messages(&conv.id)?;
@@ -572,33 +1424,18 @@ async fn list_tasks(verbose: bool) -> Result<()> {
                 if msg.role == "assistant" {
                     if let Some(text) = msg.content.as_str() {
                         // Extract task IDs from messages
-                        if text.contains("Task ID:") {
-                            if let Some(start) = text.find("Task ID:") {
-                                let id_start = start + 9;
-                                if let Some(end) = text[id_start..].find('\n') {
-                                    let task_id = &text[id_start..id_start + end].trim();
-                                    
-                                    if verbose {
-                                        let status = if text.contains("Status: Completed") {
-                                            "✅"
-                                        } else if text.contains("Status: Failed") {
-                                            "❌"
-                                        } else {
-                                            "⏳"
-                                        };
-                                        
-                                        println!(
-                                            "{:<38} {:<38} {:<20} {:<10}",
-                                            task_id,
-                                            conv.id,
-                                            conv.created_at.format("%Y-%m-%d %H:%M"),
-                                            status
-                                        );
-                                    } else {
-                                        println!("  {}", task_id);
-                                    }
-                                    task_count += 1;
-                                }
+                        if let Some(start) = text.find("Task ID:") {
+                            let id_start = start + 9;
+                            if let Some(end) = text[id_start..].find('\n') {
+                                let task_id = text[id_start..id_start + end].trim().to_string();
+                                let status = if text.contains("Status: Completed") {
+                                    "completed"
+                                } else if text.contains("Status: Failed") {
+                                    "failed"
+                                } else {
+                                    "pending"
+                                };
+                                tasks.push((task_id, conv.clone(), status));
                             }
                         }
                     }
@@ -606,29 +1443,109 @@ async fn list_tasks(verbose: bool) -> Result<()> {
             }
         }
     }
+    Ok(tasks)
+}
+
+fn tasks_json(tasks: &[(String, ConversationSummary, &'static str)]) -> serde_json::Value {
+    tasks
+        .iter()
+        .map(|(task_id, conv, status)| serde_json::json!({
+            "task_id": task_id,
+            "conversation_id": conv.id,
+            "created_at": conv.created_at,
+            "status": status,
+        }))
+        .collect()
+}
+
+async fn list_tasks(verbose: bool, output: OutputFormat) -> Result<()> {
+    let storage = ConversationStorage::new()?;
+    let tasks = collect_tasks(&storage)?;
+    
+    if output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&tasks_json(&tasks))?);
+        return Ok(());
+    }
     
-    if task_count == 0 {
+    println!("\n📋 Tasks in current workspace:\n");
+    
+    if verbose {
+        println!("{:<38} {:<38} {:<20} {:<10}", "Task ID", "Conversation ID", "Created", "Status");
+        println!("{}", "-".repeat(106));
+    }
+    
+    for (task_id, conv, status) in &tasks {
+        if verbose {
+            let status = match *status {
+                "completed" => "✅",
+                "failed" => "❌",
+                _ => "⏳",
+            };
+            
+            println!(
+                "{:<38} {:<38} {:<20} {:<10}",
+                task_id,
+                conv.id,
+                conv.created_at.format("%Y-%m-%d %H:%M"),
+                status
+            );
+        } else {
+            println!("  {}", task_id);
+        }
+    }
+    
+    if tasks.is_empty() {
         println!("No tasks found in the current workspace.");
     } else {
-        println!("\nTotal tasks: {}", task_count);
+        println!("\nTotal tasks: {}", tasks.len());
         println!("Use 'bedrock-agent task <id> --resume' to continue a task");
     }
     
     Ok(())
 }
 
-async fn list_conversations() -> Result<()> {
+/// Conversations and tasks as a single JSON document
+fn list_all_json(tag: Option<&str>) -> Result<()> {
     let storage = ConversationStorage::new()?;
-    let conversations = storage.list_conversations()?;
+    let conversations = tagged_conversations(&storage, tag)?;
+    let tasks = collect_tasks(&storage)?;
+    let all = serde_json::json!({
+        "conversations": conversations,
+        "tasks": tasks_json(&tasks),
+    });
+    println!("{}", serde_json::to_string_pretty(&all)?);
+    Ok(())
+}
+
+/// The workspace's conversations, only those carrying `tag` if given
+fn tagged_conversations(storage: &ConversationStorage, tag: Option<&str>) -> Result<Vec<ConversationSummary>> {
+    let mut conversations = storage.list_conversations()?;
+    if let Some(tag) = tag {
+        conversations.retain(|conv| conv.has_tag(tag));
+    }
+    Ok(conversations)
+}
+
+async fn list_conversations(tag: Option<&str>, output: OutputFormat) -> Result<()> {
+    let storage = ConversationStorage::new()?;
+    let conversations = tagged_conversations(&storage, tag)?;
+    
+    if output == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&conversations)?);
+        return Ok(());
+    }
     
     if conversations.is_empty() {
-        println!("No conversations found in the current workspace.");
+        match tag {
+            Some(tag) => println!("No conversations tagged '{}' in the current workspace.", tag),
+            None => println!("No conversations found in the current workspace."),
+        }
         return Ok(());
     }
     
     println!("\n📚 Conversations in current workspace:\n");
-    println!("{:<38} {:<20} {:<10} {:<10} {:<10}", "ID", "Updated", "Messages", "Tasks", "Status");
-    println!("{}", "-".repeat(88));
+    println!("{:<38} {:<20} {:<10} {:<10} {:<10} {:<20} Tools", "ID", "Updated", "Messages", "Tasks", "Status", "Tags");
+    println!("{}", "-".repeat(120));
     
     for conv in conversations {
         let status = if conv.has_tasks {
@@ -638,12 +1555,14 @@ async fn list_conversations() -> Result<()> {
         };
         
         println!(
-            "{:<38} {:<20} {:<10} {:<10} {:<10}",
+            "{:<38} {:<20} {:<10} {:<10} {:<10} {:<20} {}",
             conv.id,
             conv.updated_at.format("%Y-%m-%d %H:%M"),
             conv.message_count,
             conv.task_count,
-            status
+            status,
+            if conv.tags.is_empty() { "-".to_string() } else { conv.tags.join(",") },
+            conv.derived_tags.join(",")
         );
     }
     
@@ -651,6 +1570,31 @@ async fn list_conversations() -> Result<()> {
     Ok(())
 }
 
+fn search_conversations(query: &str, limit: usize) -> Result<()> {
+    let storage = ConversationStorage::new()?;
+    let hits = storage.search(query)?;
+    
+    if hits.is_empty() {
+        println!("No conversations match '{}'.", query);
+        return Ok(());
+    }
+    
+    println!("\n🔎 {} conversation(s) matching '{}':\n", hits.len(), query);
+    for hit in hits.iter().take(limit) {
+        println!("{}  ({} matches)", hit.conversation_id, hit.match_count);
+        for snippet in &hit.snippets {
+            println!("    {}", snippet);
+        }
+        println!();
+    }
+    if hits.len() > limit {
+        println!("... and {} more (use --limit to show more)", hits.len() - limit);
+    }
+    
+    println!("Use 'bedrock-agent conversation <id>' to resume a conversation");
+    Ok(())
+}
+
 async fn resume_conversation(agent: Agent, conversation_id: String, stream: bool) -> Result<()> {
     // Parse the conversation ID
     let conv_id = Uuid::parse_str(&conversation_id)
@@ -659,12 +1603,12 @@ async fn resume_conversation(agent: Agent, conversation_id: String, stream: bool
     // Load the conversation
     let mut manager = ConversationManager::new()?;
     let messages = manager.resume_conversation(conv_id)?;
+    let metadata = ConversationStorage::new()?.load_metadata(&conv_id)?;
     
-    println!("\n📖 Resumed conversation: {}", conv_id);
-    println!("Found {} previous messages\n", messages.len());
+    println!("{}", metadata.resume_header(messages.len(), agent.get_config().conversation.show_usage_on_resume));
     
     // Display conversation history
-    for (_i, msg) in messages.iter().enumerate() {
+    for msg in messages.iter() {
         let role_emoji = match msg.role.as_str() {
             "user" => "👤",
             "assistant" => "🤖",
@@ -704,6 +1648,57 @@ async fn resume_conversation(agent: Agent, conversation_id: String, stream: bool
     interactive_chat_with_history(agent, conv_id, stream).await
 }
 
+async fn snapshot_conversation(conversation_id: String, label: String) -> Result<()> {
+    let conv_id = Uuid::parse_str(&conversation_id)
+        .map_err(|e| anyhow::anyhow!("Invalid conversation ID: {}", e))?;
+    
+    let mut manager = ConversationManager::new()?;
+    manager.resume_conversation(conv_id)?;
+    let snapshot = manager.snapshot(label)?;
+    
+    println!("✅ Created snapshot '{}' at message {}", snapshot.label, snapshot.message_count);
+    println!("   Roll back with: conversation {} --rollback {}", conv_id, snapshot.id);
+    Ok(())
+}
+
+fn tag_conversation(conversation_id: &str, tags: &[String], untags: &[String]) -> Result<()> {
+    let conv_id = Uuid::parse_str(conversation_id)
+        .map_err(|e| anyhow::anyhow!("Invalid conversation ID: {}", e))?;
+    
+    let mut manager = ConversationManager::new()?;
+    manager.resume_conversation(conv_id)?;
+    for tag in untags {
+        if !manager.remove_tag(tag)? {
+            println!("Conversation {} has no tag '{}'", conv_id, tag);
+        }
+    }
+    for tag in tags {
+        manager.add_tag(tag)?;
+    }
+    
+    let tags = manager.list_conversations()?
+        .into_iter()
+        .find(|conv| conv.id == conv_id)
+        .map(|conv| conv.tags)
+        .unwrap_or_default();
+    println!("✅ Tags of conversation {}: {}", conv_id, if tags.is_empty() { "none".to_string() } else { tags.join(", ") });
+    Ok(())
+}
+
+async fn rollback_conversation(conversation_id: String, snapshot_id: String) -> Result<()> {
+    let conv_id = Uuid::parse_str(&conversation_id)
+        .map_err(|e| anyhow::anyhow!("Invalid conversation ID: {}", e))?;
+    let snapshot_id = Uuid::parse_str(&snapshot_id)
+        .map_err(|e| anyhow::anyhow!("Invalid snapshot ID: {}", e))?;
+    
+    let mut manager = ConversationManager::new()?;
+    manager.resume_conversation(conv_id)?;
+    let messages = manager.rollback(snapshot_id)?;
+    
+    println!("✅ Rolled back conversation {} to {} messages", conv_id, messages.len());
+    Ok(())
+}
+
 async fn export_conversation(conversation_id: String, output: Option<PathBuf>) -> Result<()> {
     // Parse the conversation ID
     let conv_id = Uuid::parse_str(&conversation_id)
@@ -738,6 +1733,19 @@ async fn export_conversation(conversation_id: String, output: Option<PathBuf>) -
     Ok(())
 }
 
+fn export_conversation_markdown(conversation_id: String, output: PathBuf) -> Result<()> {
+    let conv_id = Uuid::parse_str(&conversation_id)
+        .map_err(|e| anyhow::anyhow!("Invalid conversation ID: {}", e))?;
+    
+    let storage = ConversationStorage::new()?;
+    let metadata = storage.load_metadata(&conv_id)?;
+    let messages = storage.read_messages(&conv_id)?;
+    
+    std::fs::write(&output, conversation_to_markdown(&metadata, &messages))?;
+    println!("✅ Exported conversation to: {}", output.display());
+    Ok(())
+}
+
 async fn delete_conversation(conversation_id: String, force: bool) -> Result<()> {
     // Parse the conversation ID
     let conv_id = Uuid::parse_str(&conversation_id)
@@ -772,12 +1780,28 @@ async fn delete_conversation(conversation_id: String, force: bool) -> Result<()>
     Ok(())
 }
 
-async fn show_conversation_stats() -> Result<()> {
+/// Tokens and cost of one conversation, for `--by-conversation`
+struct ConversationCost {
+    id: Uuid,
+    messages: usize,
+    total_tokens: u32,
+    total_cost: f64,
+}
+
+async fn show_conversation_stats(
+    config: &AgentConfig,
+    group_by: Option<&str>,
+    by_conversation: bool,
+    output: OutputFormat,
+) -> Result<()> {
     let storage = ConversationStorage::new()?;
     let conversations = storage.list_conversations()?;
     
-    if conversations.is_empty() {
+    if conversations.is_empty() && output == OutputFormat::Text {
         println!("No conversations found in the current workspace.");
+        if let Some(tag) = group_by {
+            show_cost_by_tag(config, tag)?;
+        }
         return Ok(());
     }
     
@@ -799,6 +1823,7 @@ async fn show_conversation_stats() -> Result<()> {
     // Calculate total token usage
     let mut total_tokens = 0u32;
     let mut total_cost = 0.0f64;
+    let mut conversation_costs = Vec::new();
     
     for conv in &conversations {
         if let Ok(metadata) = storage.load_metadata(&conv.id) {
@@ -806,8 +1831,51 @@ async fn show_conversation_stats() -> Result<()> {
             if let Some(cost) = metadata.token_usage.total_cost {
                 total_cost += cost;
             }
+            conversation_costs.push(ConversationCost {
+                id: conv.id,
+                messages: metadata.message_count,
+                total_tokens: metadata.token_usage.total_tokens,
+                total_cost: metadata.token_usage.total_cost.unwrap_or(0.0),
+            });
         }
     }
+    conversation_costs.sort_by(|a, b| {
+        b.total_cost.total_cmp(&a.total_cost).then(b.total_tokens.cmp(&a.total_tokens))
+    });
+    
+    if output == OutputFormat::Json {
+        let mut stats = serde_json::json!({
+            "workspace": storage.get_workspace_dir(),
+            "total_conversations": total_conversations,
+            "total_messages": total_messages,
+            "total_tasks": total_tasks,
+            "completed_tasks": completed_tasks,
+            "failed_tasks": failed_tasks,
+            "total_tokens": total_tokens,
+            "total_cost": total_cost,
+            "oldest_conversation": oldest,
+            "latest_activity": newest,
+        });
+        if by_conversation {
+            stats["by_conversation"] = conversation_costs.iter()
+                .map(|conv| serde_json::json!({
+                    "id": conv.id,
+                    "messages": conv.messages,
+                    "total_tokens": conv.total_tokens,
+                    "total_cost": conv.total_cost,
+                }))
+                .collect();
+        }
+        if let Some(tag) = group_by {
+            let results = load_task_results(&config.paths.workspace_dir.join("results"))?;
+            stats["cost_by_tag"] = serde_json::json!({
+                "tag": tag,
+                "groups": cost_by_tag(&results, tag),
+            });
+        }
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
     
     println!("\n📊 Conversation Statistics\n");
     println!("Workspace: {}", storage.get_workspace_dir().display());
@@ -836,6 +1904,43 @@ async fn show_conversation_stats() -> Result<()> {
         println!("Latest Activity:     {}", newest.format("%Y-%m-%d %H:%M:%S"));
     }
     
+    if by_conversation {
+        println!("\n💰 Cost by conversation\n");
+        println!("{:<38} {:>10} {:>14} {:>12}", "ID", "Messages", "Tokens", "Cost");
+        println!("{}", "-".repeat(77));
+        for conv in &conversation_costs {
+            println!(
+                "{:<38} {:>10} {:>14} {:>12}",
+                conv.id, conv.messages, conv.total_tokens, format!("${:.4}", conv.total_cost)
+            );
+        }
+    }
+    
+    if let Some(tag) = group_by {
+        show_cost_by_tag(config, tag)?;
+    }
+    
+    Ok(())
+}
+
+/// Sum saved task results per value of `tag`
+fn show_cost_by_tag(config: &AgentConfig, tag: &str) -> Result<()> {
+    let results = load_task_results(&config.paths.workspace_dir.join("results"))?;
+    let costs = cost_by_tag(&results, tag);
+    
+    println!("\n💰 Cost by tag '{}'\n", tag);
+    if costs.is_empty() {
+        println!("No task results found.");
+        return Ok(());
+    }
+    println!("{:<30} {:>8} {:>14} {:>12}", "Value", "Tasks", "Tokens", "Cost");
+    println!("{}", "-".repeat(67));
+    for cost in &costs {
+        println!(
+            "{:<30} {:>8} {:>14} {:>11.4}$",
+            cost.value, cost.tasks, cost.total_tokens, cost.total_cost
+        );
+    }
     Ok(())
 }
 
@@ -848,6 +1953,7 @@ async fn interactive_chat_with_history(
     println!("Entering interactive mode with resumed conversation. Type 'exit' or 'quit' to stop.");
     println!("Type 'help' for available commands.\n");
 
+    let mut usage = ChatUsage::new();
     loop {
         print!("> ");
         io::stdout().flush()?;
@@ -870,7 +1976,13 @@ async fn interactive_chat_with_history(
             println!("\nAvailable commands:");
             println!("  exit/quit - Exit the chat");
             println!("  help      - Show this help message");
-            println!("\nJust type your message to continue the conversation.\n");
+            print_chat_commands();
+            println!("Just type your message to continue the conversation.\n");
+            continue;
+        }
+
+        if input.starts_with('/') {
+            run_chat_command(&agent, input, &usage).await;
             continue;
         }
 
@@ -887,6 +1999,7 @@ async fn interactive_chat_with_history(
                     println!("\n\n📊 Token usage: {} input, {} output", 
                              result.token_stats.input_tokens, 
                              result.token_stats.output_tokens);
+                    usage.record(&result);
                 }
                 Err(e) => eprintln!("\n❌ Error: {}", e),
             }
@@ -894,8 +2007,14 @@ async fn interactive_chat_with_history(
             print!("\n🤖 Assistant: ");
             io::stdout().flush()?;
             
-            match agent.chat(input).await {
-                Ok(response) => println!("{}\n", response),
+            match agent.chat_with_stats(input).await {
+                Ok(result) => {
+                    println!("{}", result.response);
+                    println!("\n📊 Token usage: {} input, {} output\n", 
+                             result.token_stats.input_tokens, 
+                             result.token_stats.output_tokens);
+                    usage.record(&result);
+                }
                 Err(e) => eprintln!("❌ Error: {}", e),
             }
         }
@@ -1052,7 +2171,8 @@ async fn resume_task(agent: Agent, task_id: String, prompt: Option<String>, stre
     Ok(())
 }
 
-async fn import_conversation(file: PathBuf, force: bool) -> Result<()> {
+/// `default_model` is recorded for conversations whose file names no model
+async fn import_conversation(file: PathBuf, force: bool, default_model: &str) -> Result<()> {
     println!("\n📥 Importing conversation from: {}", file.display());
     
     // Read the JSON file
@@ -1105,7 +2225,7 @@ async fn import_conversation(file: PathBuf, force: bool) -> Result<()> {
         // Create new metadata
         let model_id = import_data.get("model")
             .and_then(|v| v.as_str())
-            .unwrap_or("anthropic.claude-3-5-sonnet-20241022-v2:0")
+            .unwrap_or(default_model)
             .to_string();
         
         let mut meta = ConversationMetadata::new(model_id, None);
@@ -1152,7 +2272,7 @@ async fn import_task(agent: Agent, file: PathBuf, resume: bool, stream: bool) ->
     println!("  Status: {:?}", task_result.status);
     println!("  Summary: {}", task_result.summary);
     
-    if task_result.status == TaskStatus::Failed {
+    if matches!(task_result.status, TaskStatus::Failed | TaskStatus::Blocked) {
         if let Some(error) = &task_result.error {
             println!("  Error: {}", error);
         }
@@ -1162,8 +2282,12 @@ async fn import_task(agent: Agent, file: PathBuf, resume: bool, stream: bool) ->
     let storage = ConversationStorage::new()?;
     let conv_id = Uuid::new_v4();
     
-    // Create metadata for the imported task
-    let model_id = "anthropic.claude-3-5-sonnet-20241022-v2:0".to_string();
+    // Create metadata for the imported task, with the model it ran on if recorded
+    let model_id = if task_result.cost.model.is_empty() {
+        agent.get_config().agent.model.clone()
+    } else {
+        task_result.cost.model.clone()
+    };
     let mut metadata = ConversationMetadata::new(model_id, None);
     metadata.id = conv_id;
     metadata.has_tasks = true;
@@ -1171,7 +2295,7 @@ async fn import_task(agent: Agent, file: PathBuf, resume: bool, stream: bool) ->
     
     if task_result.status == TaskStatus::Completed {
         metadata.completed_tasks = 1;
-    } else if task_result.status == TaskStatus::Failed {
+    } else if matches!(task_result.status, TaskStatus::Failed | TaskStatus::Blocked) {
         metadata.failed_tasks = 1;
     }
     
@@ -1180,17 +2304,15 @@ async fn import_task(agent: Agent, file: PathBuf, resume: bool, stream: bool) ->
     // Add task messages to conversation
     if let Some(conversation) = &task_result.conversation {
         for msg_value in conversation {
-            // Convert the JSON value to a MessageEntry
-            if let (Some(role), Some(content)) = (msg_value.get("role"), msg_value.get("content")) {
-                let msg = if role.as_str() == Some("user") {
-                    MessageEntry::user(content.as_str().unwrap_or("").to_string())
-                } else if role.as_str() == Some("assistant") {
-                    MessageEntry::assistant(content.as_str().unwrap_or("").to_string())
-                } else {
-                    continue;
-                };
-                storage.append_message(&conv_id, &msg)?;
-            }
+            // Convert the JSON value to a MessageEntry; older results spell roles "User"/"Assistant"
+            let Some(role) = msg_value.get("role").and_then(|r| r.as_str()) else { continue };
+            let mut msg = match role.to_ascii_lowercase().as_str() {
+                "user" => MessageEntry::user(String::new()),
+                "assistant" => MessageEntry::assistant(String::new()),
+                _ => continue,
+            };
+            msg.content = imported_content(msg_value);
+            storage.append_message(&conv_id, &msg)?;
         }
     }
     
@@ -1207,20 +2329,147 @@ async fn import_task(agent: Agent, file: PathBuf, resume: bool, stream: bool) ->
     Ok(())
 }
 
-fn init_logging(verbose: bool) -> Result<()> {
+/// A task result message as stored conversation content: its text, or content blocks
+/// when it carries tool calls or results
+fn imported_content(message: &serde_json::Value) -> serde_json::Value {
+    let text = message.get("content").and_then(|c| c.as_str()).unwrap_or_default();
+    let tool_calls = message.get("tool_calls").and_then(|v| v.as_array());
+    let tool_results = message.get("tool_results").and_then(|v| v.as_array());
+    if tool_calls.is_none() && tool_results.is_none() {
+        return serde_json::Value::String(text.to_string());
+    }
+    
+    // The markers in the readable content stand for the blocks added below
+    let text: Vec<&str> = text.lines()
+        .filter(|line| !line.starts_with("[Tool: ") && *line != "[Tool Result]")
+        .collect();
+    let mut blocks = Vec::new();
+    if !text.is_empty() {
+        blocks.push(serde_json::json!({"type": "text", "text": text.join("\n")}));
+    }
+    for call in tool_calls.into_iter().flatten() {
+        blocks.push(serde_json::json!({
+            "type": "tool_use",
+            "tool_use_id": call.get("id"),
+            "name": call.get("name"),
+            "input": call.get("input"),
+        }));
+    }
+    for result in tool_results.into_iter().flatten() {
+        let content = match result.get("content") {
+            Some(serde_json::Value::String(text)) => text.clone(),
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+        let failed = result.get("status").and_then(|s| s.as_str()) == Some("error");
+        blocks.push(serde_json::json!({
+            "type": "tool_result",
+            "tool_use_id": result.get("tool_use_id"),
+            "status": if failed { "Some(Error)" } else { "Some(Success)" },
+            "content": content,
+        }));
+    }
+    serde_json::Value::Array(blocks)
+}
+
+fn init_logging(verbose: bool, output: OutputFormat, log_format: LogFormat, audit: &AuditSettings) -> Result<()> {
     let filter = if verbose {
         "debug"
     } else {
         "info"
     };
     
+    // Mirror the log into a rotated file when an audit log directory is configured
+    let audit_layer = match &audit.log_dir {
+        Some(dir) => {
+            let mut writer = RotatingFileWriter::open(dir, "agent.log", audit.max_bytes, audit.max_files)?;
+            if let Some(days) = audit.max_age_days {
+                writer = writer.with_max_age(std::time::Duration::from_secs(days * 24 * 3600))?;
+            }
+            Some(formatted(
+                tracing_subscriber::fmt::layer().with_ansi(false).with_writer(std::sync::Mutex::new(writer)),
+                log_format,
+            ))
+        }
+        None => None,
+    };
+    
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(filter))
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(formatted(
+            tracing_subscriber::fmt::layer().with_writer(move || -> Box<dyn Write> {
+                // Keep stdout clean for the JSON document
+                if output == OutputFormat::Json {
+                    Box::new(io::stderr())
+                } else {
+                    Box::new(io::stdout())
+                }
+            }),
+            log_format,
+        ))
+        .with(audit_layer)
         .init();
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// `layer` as is, or writing JSON lines with the timestamp, level, target and spans
+fn formatted<S, W>(
+    layer: tracing_subscriber::fmt::Layer<S, DefaultFields, Format, W>,
+    log_format: LogFormat,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    match log_format {
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Json => layer.json().with_current_span(true).with_span_list(true).boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bedrock_agent::AgentBuilder;
+
+    #[test]
+    fn test_parse_chat_command() {
+        assert_eq!(parse_chat_command("/reset"), ChatCommand::Reset);
+        assert_eq!(parse_chat_command("/tokens now"), ChatCommand::Tokens);
+        assert_eq!(parse_chat_command("/model  anthropic.claude-3-haiku "), ChatCommand::Model("anthropic.claude-3-haiku"));
+        assert_eq!(parse_chat_command("/system Answer in French"), ChatCommand::System("Answer in French"));
+        assert_eq!(parse_chat_command("/save session.txt"), ChatCommand::Save("session.txt"));
+        assert_eq!(parse_chat_command("/save"), ChatCommand::MissingArgument("/save"));
+        assert_eq!(parse_chat_command("/model   "), ChatCommand::MissingArgument("/model"));
+        assert_eq!(parse_chat_command("/help"), ChatCommand::Help);
+        assert_eq!(parse_chat_command("/frobnicate it"), ChatCommand::Unknown("/frobnicate"));
+    }
+
+    #[tokio::test]
+    async fn test_run_chat_command_dispatch() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut config = AgentConfig::default();
+        config.paths.workspace_dir = dir.path().to_path_buf();
+        let agent = AgentBuilder::new().with_config(config).with_mcp_enabled(false).build().await.unwrap();
+        let usage = ChatUsage::new();
+
+        run_chat_command(&agent, "/model anthropic.claude-3-haiku", &usage).await;
+        assert_eq!(agent.chat_model(), "anthropic.claude-3-haiku");
+
+        // Commands without their argument and unknown commands change nothing
+        run_chat_command(&agent, "/model", &usage).await;
+        run_chat_command(&agent, "/models other", &usage).await;
+        assert_eq!(agent.chat_model(), "anthropic.claude-3-haiku");
+
+        let saved = dir.path().join("session.txt");
+        run_chat_command(&agent, &format!("/save {}", saved.display()), &usage).await;
+        assert!(saved.exists());
+
+        run_chat_command(&agent, "/reset", &usage).await;
+        assert_eq!(agent.conversation_len().await, 0);
+    }
+}